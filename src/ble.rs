@@ -1,10 +1,12 @@
 // ble.rs - Generic BLE client for ESP32-C6 using ESP-IDF NimBLE
 // This module provides a reusable BLE client that can work with any BLE device
 
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
-use embassy_time::{Duration, Timer};
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, signal::Signal};
+use embassy_time::{Duration, Instant, Timer};
 use log::{debug, error, info, warn};
-use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
 
 // ESP-IDF NimBLE bindings
 use esp_idf_svc::sys as esp_idf_sys;
@@ -16,6 +18,30 @@ pub struct BleAddress {
     pub addr_type: u8,
 }
 
+impl BleAddress {
+    /// Parse the usual `AA:BB:CC:DD:EE:FF` form (most significant byte first)
+    pub fn parse_mac(text: &str) -> Option<[u8; 6]> {
+        let mut addr = [0u8; 6];
+        let mut parts = text.trim().split(':');
+        // NimBLE stores the address least significant byte first
+        for byte in addr.iter_mut().rev() {
+            *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+        }
+        parts.next().is_none().then_some(addr)
+    }
+}
+
+impl std::fmt::Display for BleAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let a = &self.addr;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            a[5], a[4], a[3], a[2], a[1], a[0]
+        )
+    }
+}
+
 // Discovered device information
 #[derive(Debug, Clone)]
 pub struct Device {
@@ -86,6 +112,33 @@ pub struct Connection {
     pub handle: u16,
 }
 
+// Requested link timing - intervals in 1.25ms units, supervision timeout in 10ms units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConnParams {
+    pub itvl_min: u16,
+    pub itvl_max: u16,
+    pub latency: u16,
+    pub supervision_timeout: u16,
+}
+
+impl ConnParams {
+    /// 7.5-15ms - lowest weight latency while brewing
+    pub const FAST: Self = Self {
+        itvl_min: 6,
+        itvl_max: 12,
+        latency: 0,
+        supervision_timeout: 256,
+    };
+
+    /// 100-150ms - saves power while idle
+    pub const SLOW: Self = Self {
+        itvl_min: 80,
+        itvl_max: 120,
+        latency: 0,
+        supervision_timeout: 400,
+    };
+}
+
 // Device filter for scanning
 pub struct DeviceFilter {
     pub name_prefix: Option<String>,
@@ -96,20 +149,40 @@ pub struct DeviceFilter {
 pub type NotificationChannel<T> = Channel<CriticalSectionRawMutex, T, 10>;
 pub type StatusChannel = Channel<CriticalSectionRawMutex, bool, 5>;
 
-// Global scan state for C callbacks
-static FOUND_DEVICES: LazyLock<Mutex<Vec<Device>>> = LazyLock::new(|| Mutex::new(Vec::new()));
-static SCAN_COMPLETE: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
+// All state written from NimBLE callbacks, behind a single lock so updates can't be
+// lost to lock ordering or a failed try_lock
+#[derive(Default)]
+struct BleState {
+    // Scan
+    found_devices: Vec<Device>,
+    scan_complete: bool,
+    // Connection - Some(handle) while connected
+    connection_handle: Option<u16>,
+    // Interval the peer actually agreed to, in 1.25ms units
+    conn_interval: Option<u16>,
+    // GATT discovery
+    discovered_services: Vec<Service>,
+    discovered_characteristics: Vec<Characteristic>,
+}
+
+static BLE_STATE: LazyLock<Mutex<BleState>> = LazyLock::new(|| Mutex::new(BleState::default()));
+
+fn ble_state() -> MutexGuard<'static, BleState> {
+    // A panic while holding the lock must not take BLE down with it
+    BLE_STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Whether the NimBLE host is up - lets initialize/shutdown be called in any order
+static BLE_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+// How long shutdown waits for the peer to acknowledge a disconnect
+const SHUTDOWN_DISCONNECT_TIMEOUT_MS: u64 = 1000;
 
-// Global connection state
-static CONNECTION_HANDLE: LazyLock<Mutex<Option<u16>>> = LazyLock::new(|| Mutex::new(None));
-static CONNECTED: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
+// How long a normal disconnect waits before giving up on the DISCONNECT event
+const DISCONNECT_TIMEOUT_MS: u64 = 3000;
 
-// Global GATT discovery state
-static DISCOVERED_SERVICES: LazyLock<Mutex<Vec<Service>>> =
-    LazyLock::new(|| Mutex::new(Vec::new()));
-static DISCOVERED_CHARACTERISTICS: LazyLock<Mutex<Vec<Characteristic>>> =
-    LazyLock::new(|| Mutex::new(Vec::new()));
-static DISCOVERY_COMPLETE: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
+// Raised by the connection event handler with the handle of each link that goes down
+static DISCONNECT_SIGNAL: Signal<CriticalSectionRawMutex, u16> = Signal::new();
 
 // Embassy channel for GATT events
 type GattEventChannel = Channel<CriticalSectionRawMutex, GattEvent, 5>;
@@ -123,8 +196,25 @@ enum GattEvent {
     DiscoveryError(u16),
 }
 
-// Global notification data storage
-static NOTIFICATION_DATA: LazyLock<Mutex<Option<Vec<u8>>>> = LazyLock::new(|| Mutex::new(None));
+// Raw notification as received - parsing is left to the scale that owns the characteristic
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub attr_handle: u16,
+    pub data: Vec<u8>,
+}
+
+// Result of a single characteristic read - payload or ATT status
+type ReadResultChannel = Channel<CriticalSectionRawMutex, Result<Vec<u8>, u16>, 1>;
+static READ_RESULT_CHANNEL: LazyLock<ReadResultChannel> = LazyLock::new(|| Channel::new());
+
+// How long a characteristic read may take before it's abandoned
+const READ_TIMEOUT_MS: u64 = 2000;
+
+// Notification payloads pushed from the NimBLE callback - consumers await instead of polling
+const NOTIFICATION_QUEUE_DEPTH: usize = 16;
+pub type NotificationDataChannel =
+    Channel<CriticalSectionRawMutex, Notification, NOTIFICATION_QUEUE_DEPTH>;
+static NOTIFICATION_CHANNEL: LazyLock<NotificationDataChannel> = LazyLock::new(|| Channel::new());
 
 // BLE error types
 #[derive(Debug)]
@@ -134,8 +224,10 @@ pub enum BleError {
     ConnectionFailed(String),
     DiscoveryFailed(String),
     SubscriptionFailed(String),
+    ReadFailed(String),
     NotConnected,
     DeviceNotFound,
+    DisconnectTimeout,
 }
 
 impl std::fmt::Display for BleError {
@@ -146,8 +238,10 @@ impl std::fmt::Display for BleError {
             BleError::ConnectionFailed(msg) => write!(f, "BLE connection failed: {}", msg),
             BleError::DiscoveryFailed(msg) => write!(f, "BLE discovery failed: {}", msg),
             BleError::SubscriptionFailed(msg) => write!(f, "BLE subscription failed: {}", msg),
+            BleError::ReadFailed(msg) => write!(f, "BLE read failed: {}", msg),
             BleError::NotConnected => write!(f, "Not connected to device"),
             BleError::DeviceNotFound => write!(f, "Device not found"),
+            BleError::DisconnectTimeout => write!(f, "Disconnect was not confirmed in time"),
         }
     }
 }
@@ -164,8 +258,12 @@ impl BleClient {
         Self { status_channel }
     }
 
-    /// Initialize the BLE host stack (should be called once)
+    /// Initialize the BLE host stack - a no-op if it is already running
     pub fn initialize() -> Result<(), BleError> {
+        if BLE_INITIALIZED.load(Ordering::Acquire) {
+            debug!("BLE host stack already initialized");
+            return Ok(());
+        }
         info!("Initializing BLE host stack");
 
         unsafe {
@@ -191,10 +289,70 @@ impl BleClient {
             esp_idf_sys::nimble_port_freertos_init(Some(Self::host_task));
         }
 
+        BLE_INITIALIZED.store(true, Ordering::Release);
         info!("BLE host stack initialized successfully");
         Ok(())
     }
 
+    /// Tear the BLE host stack down cleanly so another BLE user (WiFi provisioning) can take the radio.
+    /// Safe to call when the stack was never initialized; `initialize` brings it back afterwards.
+    pub async fn shutdown() -> Result<(), BleError> {
+        if !BLE_INITIALIZED.load(Ordering::Acquire) {
+            info!("🔀 BLE coexistence: stack not running, nothing to shut down");
+            return Ok(());
+        }
+
+        info!("🔀 BLE coexistence 1/4: cancelling scan");
+        unsafe {
+            esp_idf_sys::ble_gap_disc_cancel();
+        }
+
+        let handle = ble_state().connection_handle;
+        if let Some(handle) = handle {
+            info!("🔀 BLE coexistence 2/4: disconnecting handle {}", handle);
+            if !Self::terminate_and_wait(handle, SHUTDOWN_DISCONNECT_TIMEOUT_MS).await {
+                warn!(
+                    "🔀 No disconnect event after {}ms - forcing teardown",
+                    SHUTDOWN_DISCONNECT_TIMEOUT_MS
+                );
+            }
+        } else {
+            info!("🔀 BLE coexistence 2/4: no connection to close");
+        }
+
+        info!("🔀 BLE coexistence 3/4: stopping NimBLE host task");
+        unsafe {
+            let ret = esp_idf_sys::nimble_port_stop();
+            if ret != 0 {
+                error!("nimble_port_stop failed: {}", ret);
+                return Err(BleError::InitializationFailed(format!(
+                    "NimBLE stop failed: {}",
+                    ret
+                )));
+            }
+        }
+
+        info!("🔀 BLE coexistence 4/4: deinitializing NimBLE");
+        unsafe {
+            let ret = esp_idf_sys::nimble_port_deinit();
+            if ret != 0 {
+                error!("nimble_port_deinit failed: {}", ret);
+                return Err(BleError::InitializationFailed(format!(
+                    "NimBLE deinit failed: {}",
+                    ret
+                )));
+            }
+        }
+
+        // Nothing from the old stack may leak into the next session
+        *ble_state() = BleState::default();
+        while NOTIFICATION_CHANNEL.try_receive().is_ok() {}
+        BLE_INITIALIZED.store(false, Ordering::Release);
+
+        info!("🔀 BLE stack shut down - radio free for provisioning");
+        Ok(())
+    }
+
     /// Scan for BLE devices with optional filtering
     pub async fn scan_for_devices(
         &self,
@@ -227,8 +385,11 @@ impl BleClient {
         info!("Starting BLE scan for {} ms", duration_ms);
 
         // Reset scan state
-        FOUND_DEVICES.lock().unwrap().clear();
-        *SCAN_COMPLETE.lock().unwrap() = false;
+        {
+            let mut state = ble_state();
+            state.found_devices.clear();
+            state.scan_complete = false;
+        }
 
         unsafe {
             // Configure scan parameters
@@ -272,11 +433,9 @@ impl BleClient {
             Timer::after(Duration::from_millis(100)).await;
             elapsed_ms += 100;
 
-            let scan_complete = *SCAN_COMPLETE.lock().unwrap();
-            let found_device = if return_first {
-                !FOUND_DEVICES.lock().unwrap().is_empty()
-            } else {
-                false
+            let (scan_complete, found_device) = {
+                let state = ble_state();
+                (state.scan_complete, return_first && !state.found_devices.is_empty())
             };
 
             if scan_complete || found_device || elapsed_ms > timeout_ms {
@@ -295,7 +454,7 @@ impl BleClient {
             esp_idf_sys::ble_gap_disc_cancel();
         }
 
-        let devices = FOUND_DEVICES.lock().unwrap().clone();
+        let devices = ble_state().found_devices.clone();
         info!("Scan completed, found {} devices", devices.len());
         Ok(devices)
     }
@@ -305,8 +464,7 @@ impl BleClient {
         info!("Connecting to device: {:?}", device.address);
 
         // Reset connection state
-        *CONNECTION_HANDLE.lock().unwrap() = None;
-        *CONNECTED.lock().unwrap() = false;
+        ble_state().connection_handle = None;
 
         unsafe {
             // Stop scanning first
@@ -361,12 +519,11 @@ impl BleClient {
             Timer::after(Duration::from_millis(50)).await;
             timeout_counter += 1;
 
-            if *CONNECTED.lock().unwrap() {
-                if let Some(handle) = *CONNECTION_HANDLE.lock().unwrap() {
-                    info!("BLE connection established successfully");
-                    self.status_channel.send(true).await;
-                    return Ok(Connection { handle });
-                }
+            let handle = ble_state().connection_handle;
+            if let Some(handle) = handle {
+                info!("BLE connection established successfully");
+                self.status_channel.send(true).await;
+                return Ok(Connection { handle });
             }
 
             if timeout_counter > 600 {
@@ -384,8 +541,7 @@ impl BleClient {
         info!("Discovering services on connection {}", connection.handle);
 
         // Reset discovery state
-        DISCOVERED_SERVICES.lock().unwrap().clear();
-        *DISCOVERY_COMPLETE.lock().unwrap() = false;
+        ble_state().discovered_services.clear();
 
         unsafe {
             let ret = esp_idf_sys::ble_gattc_disc_all_svcs(
@@ -436,7 +592,7 @@ impl BleClient {
 
         match discovery_result {
             Either::First(Ok(_)) => {
-                let services = DISCOVERED_SERVICES.lock().unwrap().clone();
+                let services = ble_state().discovered_services.clone();
                 info!("Discovered {} services", services.len());
                 Ok(services)
             }
@@ -455,7 +611,7 @@ impl BleClient {
         info!("Discovering characteristics for service {:?}", service.uuid);
 
         // Reset characteristic discovery state
-        DISCOVERED_CHARACTERISTICS.lock().unwrap().clear();
+        ble_state().discovered_characteristics.clear();
 
         unsafe {
             let ret = esp_idf_sys::ble_gattc_disc_all_chrs(
@@ -477,11 +633,55 @@ impl BleClient {
         // Wait for characteristics to be discovered
         Timer::after(Duration::from_secs(3)).await;
 
-        let characteristics = DISCOVERED_CHARACTERISTICS.lock().unwrap().clone();
+        let characteristics = ble_state().discovered_characteristics.clone();
         info!("Discovered {} characteristics", characteristics.len());
         Ok(characteristics)
     }
 
+    /// Read a characteristic value once
+    pub async fn read_characteristic(
+        &self,
+        connection: &Connection,
+        characteristic: &Characteristic,
+    ) -> Result<Vec<u8>, BleError> {
+        debug!("Reading characteristic handle {}", characteristic.handle);
+
+        // A late reply from an abandoned read must not be taken for this one
+        while READ_RESULT_CHANNEL.try_receive().is_ok() {}
+
+        let ret = unsafe {
+            esp_idf_sys::ble_gattc_read(
+                connection.handle,
+                characteristic.handle,
+                Some(Self::read_complete_handler),
+                std::ptr::null_mut(),
+            )
+        };
+        if ret != 0 {
+            return Err(BleError::ReadFailed(format!(
+                "Characteristic read failed: {}",
+                ret
+            )));
+        }
+
+        match select(
+            READ_RESULT_CHANNEL.receive(),
+            Timer::after(Duration::from_millis(READ_TIMEOUT_MS)),
+        )
+        .await
+        {
+            Either::First(Ok(data)) => Ok(data),
+            Either::First(Err(status)) => Err(BleError::ReadFailed(format!(
+                "Read of handle {} returned status {}",
+                characteristic.handle, status
+            ))),
+            Either::Second(_) => Err(BleError::ReadFailed(format!(
+                "Read of handle {} timed out",
+                characteristic.handle
+            ))),
+        }
+    }
+
     /// Subscribe to notifications from a characteristic
     pub async fn subscribe_to_notifications(
         &self,
@@ -519,14 +719,19 @@ impl BleClient {
         Ok(())
     }
 
-    /// Get the latest notification data (if any)
-    pub fn get_notification_data(&self) -> Option<Vec<u8>> {
-        NOTIFICATION_DATA.lock().unwrap().take()
+    /// Wait for the next notification, tagged with the characteristic value handle it came from
+    pub async fn next_notification(&self) -> Notification {
+        NOTIFICATION_CHANNEL.receive().await
+    }
+
+    /// Drop any queued notifications (e.g. left over from a previous connection)
+    pub fn clear_notifications(&self) {
+        while NOTIFICATION_CHANNEL.try_receive().is_ok() {}
     }
 
     /// Check if currently connected to a BLE device
     pub fn is_connected(&self) -> bool {
-        *CONNECTED.lock().unwrap()
+        ble_state().connection_handle.is_some()
     }
 
     /// Write data to a characteristic
@@ -565,26 +770,104 @@ impl BleClient {
         Ok(())
     }
 
-    /// Disconnect from device
+    /// Ask the peer to switch link timing - the agreed interval arrives later via a connection update event
+    pub fn update_conn_params(
+        &self,
+        connection: &Connection,
+        params: &ConnParams,
+    ) -> Result<(), BleError> {
+        info!(
+            "Requesting connection interval {:.1}-{:.1}ms",
+            params.itvl_min as f32 * 1.25,
+            params.itvl_max as f32 * 1.25
+        );
+
+        let upd_params = esp_idf_sys::ble_gap_upd_params {
+            itvl_min: params.itvl_min,
+            itvl_max: params.itvl_max,
+            latency: params.latency,
+            supervision_timeout: params.supervision_timeout,
+            min_ce_len: 0,
+            max_ce_len: 0,
+        };
+
+        let ret = unsafe { esp_idf_sys::ble_gap_update_params(connection.handle, &upd_params) };
+        if ret != 0 {
+            return Err(BleError::ConnectionFailed(format!(
+                "Connection parameter update failed: {}",
+                ret
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the NimBLE host stack is running - it is down while WiFi provisioning has the radio
+    pub fn is_initialized() -> bool {
+        BLE_INITIALIZED.load(Ordering::Acquire)
+    }
+
+    /// Current connection interval in milliseconds, None while disconnected
+    pub fn current_conn_interval_ms() -> Option<f32> {
+        ble_state().conn_interval.map(|itvl| itvl as f32 * 1.25)
+    }
+
+    /// Signal strength of the current link in dBm, None while disconnected
+    pub fn current_rssi() -> Option<i8> {
+        let handle = ble_state().connection_handle?;
+        let mut rssi: i8 = 0;
+        let ret = unsafe { esp_idf_sys::ble_gap_conn_rssi(handle, &mut rssi) };
+        (ret == 0).then_some(rssi)
+    }
+
+    /// Disconnect from device, waiting for the link to actually go down so a reconnect can't race it
     pub async fn disconnect(&self, connection: &Connection) -> Result<(), BleError> {
         info!("Disconnecting from device");
 
-        unsafe {
-            let ret = esp_idf_sys::ble_gap_terminate(connection.handle, 0x13);
-            if ret != 0 {
-                warn!("Failed to initiate disconnection: {}", ret);
-            }
-        }
+        let confirmed = Self::terminate_and_wait(connection.handle, DISCONNECT_TIMEOUT_MS).await;
 
-        // Reset state
-        *CONNECTION_HANDLE.lock().unwrap() = None;
-        *CONNECTED.lock().unwrap() = false;
+        // Reset state - forced if the event never came
+        {
+            let mut state = ble_state();
+            state.connection_handle = None;
+            state.conn_interval = None;
+        }
         self.status_channel.send(false).await;
 
+        if !confirmed {
+            warn!("No disconnect event after {}ms - state cleared anyway", DISCONNECT_TIMEOUT_MS);
+            return Err(BleError::DisconnectTimeout);
+        }
+
         info!("Disconnection completed");
         Ok(())
     }
 
+    /// Request termination and wait for the handler to report the link down.
+    /// Returns false if the DISCONNECT event didn't arrive within the timeout.
+    async fn terminate_and_wait(handle: u16, timeout_ms: u64) -> bool {
+        DISCONNECT_SIGNAL.reset();
+
+        let ret = unsafe { esp_idf_sys::ble_gap_terminate(handle, 0x13) };
+        if ret == esp_idf_sys::BLE_HS_ENOTCONN as i32 {
+            debug!("Handle {} already disconnected", handle);
+            return true;
+        }
+        if ret != 0 {
+            warn!("Failed to initiate disconnection: {}", ret);
+            return ble_state().connection_handle != Some(handle);
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        loop {
+            match select(DISCONNECT_SIGNAL.wait(), Timer::at(deadline)).await {
+                Either::First(down) if down == handle => return true,
+                // A stale disconnect for an older link - keep waiting for ours
+                Either::First(_) => continue,
+                Either::Second(_) => return false,
+            }
+        }
+    }
+
     // BLE stack callbacks
     extern "C" fn on_reset(reason: i32) {
         error!("BLE host reset, reason: {}", reason);
@@ -596,7 +879,9 @@ impl BleClient {
 
     extern "C" fn host_task(_param: *mut std::ffi::c_void) {
         unsafe {
+            // Returns once nimble_port_stop() is called
             esp_idf_sys::nimble_port_run();
+            esp_idf_sys::nimble_port_freertos_deinit();
         }
     }
 
@@ -619,41 +904,47 @@ impl BleClient {
                     let adv_data =
                         std::slice::from_raw_parts(disc_data.data, disc_data.length_data as usize);
 
-                    if let Some(name) = Self::parse_device_name(adv_data) {
+                    let name = Self::parse_device_name(adv_data);
+
+                    // Apply filter if provided - unnamed devices only pass a service UUID filter
+                    let should_include = if !arg.is_null() {
+                        let filter = &*(arg as *const Option<DeviceFilter>);
+                        match filter {
+                            Some(filter) => Self::filter_matches(filter, name.as_deref(), adv_data),
+                            None => name.is_some(),
+                        }
+                    } else {
+                        name.is_some()
+                    };
+
+                    if should_include {
+                        info!(
+                            "Found device: '{}' (RSSI: {})",
+                            name.as_deref().unwrap_or("<unnamed>"),
+                            disc_data.rssi
+                        );
                         let device = Device {
-                            name: Some(name.clone()),
+                            name,
                             address: BleAddress {
                                 addr: disc_data.addr.val,
                                 addr_type: disc_data.addr.type_,
                             },
                             rssi: disc_data.rssi,
                         };
-
-                        // Apply filter if provided
-                        let should_include = if !arg.is_null() {
-                            let filter = &*(arg as *const Option<DeviceFilter>);
-                            if let Some(ref filter) = filter {
-                                if let Some(ref prefix) = filter.name_prefix {
-                                    name.starts_with(prefix)
-                                } else {
-                                    true
-                                }
-                            } else {
-                                true
-                            }
-                        } else {
-                            true
-                        };
-
-                        if should_include {
-                            info!("Found device: '{}' (RSSI: {})", name, disc_data.rssi);
-                            FOUND_DEVICES.lock().unwrap().push(device);
+                        let mut state = ble_state();
+                        // Active scans report the scan response separately - keep one entry per address
+                        if !state
+                            .found_devices
+                            .iter()
+                            .any(|d| d.address.addr == device.address.addr)
+                        {
+                            state.found_devices.push(device);
                         }
                     }
                 }
                 x if x == esp_idf_sys::BLE_GAP_EVENT_DISC_COMPLETE as u8 => {
                     info!("BLE discovery completed");
-                    *SCAN_COMPLETE.lock().unwrap() = true;
+                    ble_state().scan_complete = true;
                 }
                 _ => {}
             }
@@ -681,8 +972,9 @@ impl BleClient {
                             "BLE connection established! Handle: {}",
                             conn_data.conn_handle
                         );
-                        *CONNECTION_HANDLE.lock().unwrap() = Some(conn_data.conn_handle);
-                        *CONNECTED.lock().unwrap() = true;
+                        let mut state = ble_state();
+                        state.connection_handle = Some(conn_data.conn_handle);
+                        state.conn_interval = Self::negotiated_interval(conn_data.conn_handle);
                     } else {
                         error!("BLE connection failed with status: {}", conn_data.status);
                     }
@@ -693,8 +985,27 @@ impl BleClient {
                         "BLE disconnected! Handle: {}, Reason: {}",
                         disconn_data.conn.conn_handle, disconn_data.reason
                     );
-                    *CONNECTION_HANDLE.lock().unwrap() = None;
-                    *CONNECTED.lock().unwrap() = false;
+                    {
+                        let mut state = ble_state();
+                        // Ignore stale disconnects for a handle we've already replaced
+                        if state.connection_handle == Some(disconn_data.conn.conn_handle) {
+                            state.connection_handle = None;
+                            state.conn_interval = None;
+                        }
+                    }
+                    DISCONNECT_SIGNAL.signal(disconn_data.conn.conn_handle);
+                }
+                esp_idf_sys::BLE_GAP_EVENT_CONN_UPDATE => {
+                    let update = &event_ref.__bindgen_anon_1.conn_update;
+                    if update.status == 0 {
+                        let interval = Self::negotiated_interval(update.conn_handle);
+                        if let Some(itvl) = interval {
+                            info!("BLE connection interval now {:.1}ms", itvl as f32 * 1.25);
+                        }
+                        ble_state().conn_interval = interval;
+                    } else {
+                        warn!("BLE connection update rejected with status: {}", update.status);
+                    }
                 }
                 esp_idf_sys::BLE_GAP_EVENT_NOTIFY_RX => {
                     let notify_data = &event_ref.__bindgen_anon_1.notify_rx;
@@ -703,9 +1014,25 @@ impl BleClient {
                         let om = &*notify_data.om;
                         let data_slice = std::slice::from_raw_parts(om.om_data, om.om_len as usize);
 
-                        // Store notification data
-                        *NOTIFICATION_DATA.lock().unwrap() = Some(data_slice.to_vec());
-                        debug!("Received notification: {} bytes", data_slice.len());
+                        let notification = Notification {
+                            attr_handle: notify_data.attr_handle,
+                            data: data_slice.to_vec(),
+                        };
+
+                        // Hand off unparsed to the waiting consumer - never block the NimBLE host task
+                        if NOTIFICATION_CHANNEL.try_send(notification).is_err() {
+                            warn!(
+                                "Notification queue full - dropping {} bytes from handle {}",
+                                data_slice.len(),
+                                notify_data.attr_handle
+                            );
+                        } else {
+                            debug!(
+                                "Received notification: {} bytes from handle {}",
+                                data_slice.len(),
+                                notify_data.attr_handle
+                            );
+                        }
                     }
                 }
                 _ => {}
@@ -715,6 +1042,13 @@ impl BleClient {
         0
     }
 
+    // Read the interval in effect for a connection from the host
+    fn negotiated_interval(conn_handle: u16) -> Option<u16> {
+        let mut desc: esp_idf_sys::ble_gap_conn_desc = unsafe { std::mem::zeroed() };
+        let ret = unsafe { esp_idf_sys::ble_gap_conn_find(conn_handle, &mut desc) };
+        (ret == 0).then_some(desc.conn_itvl)
+    }
+
     // GATT service discovery handler
     extern "C" fn gatt_discovery_handler(
         _conn_handle: u16,
@@ -766,7 +1100,7 @@ impl BleClient {
                 service.uuid, service.start_handle, service.end_handle
             );
 
-            DISCOVERED_SERVICES.lock().unwrap().push(service.clone());
+            ble_state().discovered_services.push(service.clone());
             GATT_EVENT_CHANNEL
                 .try_send(GattEvent::ServiceDiscovered(service))
                 .ok();
@@ -820,9 +1154,8 @@ impl BleClient {
                 characteristic.uuid, characteristic.handle, characteristic.properties
             );
 
-            DISCOVERED_CHARACTERISTICS
-                .lock()
-                .unwrap()
+            ble_state()
+                .discovered_characteristics
                 .push(characteristic.clone());
             GATT_EVENT_CHANNEL
                 .try_send(GattEvent::CharacteristicDiscovered(characteristic))
@@ -853,6 +1186,77 @@ impl BleClient {
         0
     }
 
+    // Characteristic read completion handler
+    extern "C" fn read_complete_handler(
+        _conn_handle: u16,
+        error: *const esp_idf_sys::ble_gatt_error,
+        attr: *mut esp_idf_sys::ble_gatt_attr,
+        _arg: *mut std::ffi::c_void,
+    ) -> i32 {
+        unsafe {
+            let status = if error.is_null() { 0 } else { (*error).status };
+            let result = if status == 0 && !attr.is_null() && !(*attr).om.is_null() {
+                let om = &*(*attr).om;
+                Ok(std::slice::from_raw_parts(om.om_data, om.om_len as usize).to_vec())
+            } else {
+                Err(status)
+            };
+
+            if READ_RESULT_CHANNEL.try_send(result).is_err() {
+                warn!("Dropping characteristic read result - no reader waiting");
+            }
+        }
+
+        0
+    }
+
+    // Every set criterion must match; a name prefix needs a name to match against
+    fn filter_matches(filter: &DeviceFilter, name: Option<&str>, adv_data: &[u8]) -> bool {
+        let name_ok = match (&filter.name_prefix, name) {
+            (Some(prefix), Some(name)) => name.starts_with(prefix.as_str()),
+            (Some(_), None) => false,
+            (None, _) => name.is_some() || filter.service_uuid.is_some(),
+        };
+        let service_ok = filter
+            .service_uuid
+            .as_ref()
+            .map_or(true, |uuid| Self::parse_service_uuids(adv_data).contains(uuid));
+        name_ok && service_ok
+    }
+
+    // Parse advertised service UUIDs (complete or incomplete 16- and 128-bit lists)
+    fn parse_service_uuids(adv_data: &[u8]) -> Vec<Uuid> {
+        let mut uuids = Vec::new();
+        let mut offset = 0;
+
+        while offset + 1 < adv_data.len() {
+            let length = adv_data[offset] as usize;
+            if length == 0 || offset + length >= adv_data.len() {
+                break;
+            }
+
+            let ad_type = adv_data[offset + 1];
+            let field = &adv_data[offset + 2..offset + 1 + length];
+            match ad_type {
+                0x02 | 0x03 => uuids.extend(
+                    field
+                        .chunks_exact(2)
+                        .map(|c| Uuid::from_u16(u16::from_le_bytes([c[0], c[1]]))),
+                ),
+                0x06 | 0x07 => uuids.extend(field.chunks_exact(16).map(|c| {
+                    let mut bytes = [0u8; 16];
+                    bytes.copy_from_slice(c);
+                    Uuid::from_u128_bytes(bytes)
+                })),
+                _ => {}
+            }
+
+            offset += 1 + length;
+        }
+
+        uuids
+    }
+
     // Parse device name from advertisement data
     fn parse_device_name(adv_data: &[u8]) -> Option<String> {
         let mut offset = 0;
@@ -1,7 +1,10 @@
 pub mod auto_tare;
 pub mod controller;
 pub mod overshoot;
+pub mod profile;
+pub mod recorder;
 pub mod states;
+pub mod trace;
 
 pub use auto_tare::*;
 pub use overshoot::*;
@@ -0,0 +1,261 @@
+//! Flow profiling - a piecewise target flow curve and the PID loop that steers pump power along it
+//! Needs a proportional (PWM) relay; plain on/off hardware can't follow a curve
+
+use crate::types::{
+    FLOW_PID_KD, FLOW_PID_KI, FLOW_PID_KP, MAX_FLOW_PROFILE_POINTS, MAX_PROFILE_FLOW_G_PER_S,
+};
+use embassy_time::Instant;
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+/// One corner of a profile: `time_ms` into the shot, aim for `flow_g_per_s`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProfilePoint {
+    pub time_ms: u32,
+    pub flow_g_per_s: f32,
+}
+
+/// Gains from flow error (g/s) to relay power (0.0-1.0)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+impl Default for PidGains {
+    fn default() -> Self {
+        Self {
+            kp: FLOW_PID_KP,
+            ki: FLOW_PID_KI,
+            kd: FLOW_PID_KD,
+        }
+    }
+}
+
+/// Target flow over a shot - linear between points, held flat before the first and after the last
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlowProfile {
+    pub points: Vec<ProfilePoint, MAX_FLOW_PROFILE_POINTS>,
+    #[serde(default)]
+    pub gains: PidGains,
+}
+
+impl FlowProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points.is_empty() {
+            return Err("Flow profile needs at least one point".to_string());
+        }
+        if self.points.windows(2).any(|pair| pair[1].time_ms <= pair[0].time_ms) {
+            return Err("Flow profile points must be in increasing time order".to_string());
+        }
+        let flow_range = 0.0..=MAX_PROFILE_FLOW_G_PER_S;
+        if let Some(point) = self.points.iter().find(|p| !flow_range.contains(&p.flow_g_per_s)) {
+            return Err(format!(
+                "Profile flow must be 0-{}g/s, got {}g/s at {}ms",
+                MAX_PROFILE_FLOW_G_PER_S, point.flow_g_per_s, point.time_ms
+            ));
+        }
+        let PidGains { kp, ki, kd } = self.gains;
+        if [kp, ki, kd].iter().any(|gain| !gain.is_finite() || *gain < 0.0) {
+            return Err(format!(
+                "PID gains must be non-negative, got kp={} ki={} kd={}",
+                kp, ki, kd
+            ));
+        }
+        Ok(())
+    }
+
+    /// Flow the curve asks for `elapsed_ms` into the shot
+    pub fn target_flow(&self, elapsed_ms: u32) -> f32 {
+        let Some(first) = self.points.first() else {
+            return 0.0;
+        };
+        if elapsed_ms <= first.time_ms {
+            return first.flow_g_per_s;
+        }
+        for pair in self.points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if elapsed_ms <= to.time_ms {
+                let fraction =
+                    (elapsed_ms - from.time_ms) as f32 / (to.time_ms - from.time_ms) as f32;
+                return from.flow_g_per_s + (to.flow_g_per_s - from.flow_g_per_s) * fraction;
+            }
+        }
+        self.points.last().map_or(0.0, |last| last.flow_g_per_s)
+    }
+}
+
+/// PID loop for one shot - reset between brews so no state leaks from one shot to the next
+#[derive(Debug, Clone)]
+pub struct FlowPid {
+    gains: PidGains,
+    // Already scaled by ki and kept inside the power range
+    integral: f32,
+    last: Option<(f32, Instant)>, // Previous error and when it was measured
+    output: f32,
+}
+
+impl FlowPid {
+    pub fn new(gains: PidGains) -> Self {
+        Self {
+            gains,
+            integral: 0.0,
+            last: None,
+            output: 0.0,
+        }
+    }
+
+    /// Forget the integral and the previous sample, as if the shot had just started
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last = None;
+        self.output = 0.0;
+    }
+
+    /// Relay power (0.0-1.0) that moves `measured` flow towards `target`
+    pub fn update(&mut self, target: f32, measured: f32, now: Instant) -> f32 {
+        let error = target - measured;
+        if !error.is_finite() {
+            // A garbage reading must not poison the integral - hold the last command
+            return self.output;
+        }
+
+        let dt = self.last.map_or(0.0, |(_, at)| {
+            now.saturating_duration_since(at).as_millis() as f32 / 1000.0
+        });
+        let derivative = match self.last {
+            Some((last_error, _)) if dt > 0.0 => (error - last_error) / dt,
+            _ => 0.0,
+        };
+        self.last = Some((error, now));
+
+        // Anti-windup: while the command is pinned at a limit, error pushing further into that
+        // limit is not integrated, so the loop responds as soon as the error turns around
+        let unclamped = self.gains.kp * error + self.integral + self.gains.kd * derivative;
+        let pinned_high = unclamped >= 1.0 && error > 0.0;
+        let pinned_low = unclamped <= 0.0 && error < 0.0;
+        if dt > 0.0 && !pinned_high && !pinned_low {
+            self.integral = (self.integral + self.gains.ki * error * dt).clamp(0.0, 1.0);
+        }
+
+        self.output =
+            (self.gains.kp * error + self.integral + self.gains.kd * derivative).clamp(0.0, 1.0);
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_time::Duration;
+    use crate::types::FLOW_PID_KP;
+
+    fn profile(points: &[(u32, f32)]) -> FlowProfile {
+        FlowProfile {
+            points: points
+                .iter()
+                .map(|&(time_ms, flow_g_per_s)| ProfilePoint { time_ms, flow_g_per_s })
+                .collect(),
+            gains: PidGains::default(),
+        }
+    }
+
+    #[test]
+    fn test_target_flow_follows_curve() {
+        // Pre-infusion at 1g/s, ramp to 2.5g/s, then decline to 1.5g/s
+        let profile = profile(&[(0, 1.0), (8000, 1.0), (12_000, 2.5), (30_000, 1.5)]);
+        assert!(profile.validate().is_ok());
+        assert_eq!(profile.target_flow(4000), 1.0);
+        assert!((profile.target_flow(10_000) - 1.75).abs() < 1e-4);
+        assert_eq!(profile.target_flow(12_000), 2.5);
+        assert!((profile.target_flow(21_000) - 2.0).abs() < 1e-4);
+        assert_eq!(profile.target_flow(60_000), 1.5);
+    }
+
+    #[test]
+    fn test_invalid_profiles_rejected() {
+        assert!(profile(&[]).validate().is_err());
+        assert!(profile(&[(5000, 1.0), (5000, 2.0)]).validate().is_err());
+        assert!(profile(&[(0, MAX_PROFILE_FLOW_G_PER_S + 1.0)]).validate().is_err());
+        assert!(profile(&[(0, f32::NAN)]).validate().is_err());
+
+        let mut negative_gain = profile(&[(0, 2.0)]);
+        negative_gain.gains.ki = -0.1;
+        assert!(negative_gain.validate().is_err());
+    }
+
+    #[test]
+    fn test_pid_drives_power_towards_target_flow() {
+        let mut pid = FlowPid::new(PidGains::default());
+        let at = |ms: u64| Instant::from_millis(0) + Duration::from_millis(ms);
+
+        // No flow yet - the pump is pushed straight away
+        assert!(pid.update(2.0, 0.0, at(0)) > 0.0);
+
+        // Persistently under target - the integral keeps raising power
+        let mut power = pid.update(2.0, 1.5, at(100));
+        for i in 2..20u64 {
+            let next = pid.update(2.0, 1.5, at(i * 100));
+            assert!(next > power);
+            power = next;
+        }
+
+        // Far over target - power backs off, never below zero
+        let over = pid.update(2.0, 8.0, at(2000));
+        assert!(over < power);
+        assert!((0.0..=1.0).contains(&over));
+    }
+
+    #[test]
+    fn test_pid_integrator_frozen_at_saturation() {
+        let gains = PidGains { kp: 0.15, ki: 1.0, kd: 0.0 };
+        let mut pid = FlowPid::new(gains);
+        let at = |ms: u64| Instant::from_millis(0) + Duration::from_millis(ms);
+
+        // Pump can't keep up: a minute pinned at full power against a 5g/s shortfall
+        pid.update(6.0, 1.0, at(0));
+        for i in 1..600u64 {
+            assert_eq!(pid.update(6.0, 1.0, at(i * 100)), 1.0);
+        }
+        // Only the step that reached the limit was integrated - not a minute of shortfall
+        assert!((pid.integral - 0.5).abs() < 1e-4);
+
+        // Flow overshoots - power drops at once instead of unwinding a minute of error
+        assert!(pid.update(2.0, 3.0, at(60_000)) < 1.0);
+        assert!(pid.update(2.0, 4.0, at(60_100)) < 0.5);
+
+        // Pinned at zero the other way - the integral can't run negative either
+        for i in 0..600u64 {
+            assert_eq!(pid.update(0.0, 5.0, at(60_200 + i * 100)), 0.0);
+            assert!((0.0..=1.0).contains(&pid.integral));
+        }
+        assert!(pid.update(2.0, 1.0, at(120_200)) > 0.0);
+    }
+
+    #[test]
+    fn test_pid_command_always_in_range() {
+        let mut pid = FlowPid::new(PidGains { kp: 5.0, ki: 5.0, kd: 5.0 });
+        let at = |ms: u64| Instant::from_millis(0) + Duration::from_millis(ms);
+        for (i, measured) in [0.0, 9.0, 0.0, 9.0, f32::NAN, 0.0].iter().enumerate() {
+            let power = pid.update(4.5, *measured, at(i as u64 * 100));
+            assert!((0.0..=1.0).contains(&power), "{} out of range", power);
+        }
+    }
+
+    #[test]
+    fn test_pid_reset_starts_clean() {
+        let mut pid = FlowPid::new(PidGains::default());
+        let at = |ms: u64| Instant::from_millis(0) + Duration::from_millis(ms);
+        for i in 0..50u64 {
+            pid.update(2.0, 1.5, at(i * 100));
+        }
+        assert!(pid.integral > 0.0);
+
+        pid.reset();
+        assert_eq!(pid.integral, 0.0);
+        // First sample after a reset is pure proportional - no derivative kick, no old integral
+        assert_eq!(pid.update(2.0, 1.0, at(10_000)), FLOW_PID_KP);
+    }
+}
@@ -0,0 +1,205 @@
+//! Brew event recorder and bench replay
+//! Captures the inputs that drive the brew state machine so a user's brew can be reproduced offline
+
+use crate::brewing::{BrewController, BrewInput, BrewOutput};
+use crate::system::events::{EventBus, ScaleEvent, SystemEvent, TimeEvent, TimedEvent, UserEvent};
+use crate::system::shutdown::ShutdownToken;
+use crate::types::ScaleData;
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Timer};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// ~60 seconds of 10Hz scale samples plus user commands
+pub const MAX_RECORDED_EVENTS: usize = 600;
+
+/// Matches the control loop tick so time-based transitions fire like they did live
+const REPLAY_TICK_MS: u64 = 100;
+
+/// A state machine input in serializable form - ticks are not recorded, replay regenerates them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedInput {
+    ScaleData {
+        timestamp_ms: u32,
+        /// Controller capture time in ms since boot - absent in recordings that predate it
+        #[serde(default)]
+        received_at_ms: u64,
+        weight_g: f32,
+        flow_rate_g_per_s: f32,
+        battery_percent: u8,
+        timer_running: bool,
+    },
+    ScaleConnected,
+    ScaleDisconnected,
+    User { event: UserEvent },
+    SettlingTimeout,
+}
+
+impl RecordedInput {
+    /// The recordable part of a bus event, if any
+    pub fn from_event(event: &SystemEvent) -> Option<Self> {
+        match event {
+            SystemEvent::Scale(ScaleEvent::WeightChanged { data }) => Some(Self::ScaleData {
+                timestamp_ms: data.timestamp_ms,
+                received_at_ms: data.received_at_ms(),
+                weight_g: data.weight_g,
+                flow_rate_g_per_s: data.flow_rate_g_per_s,
+                battery_percent: data.battery_percent,
+                timer_running: data.timer_running,
+            }),
+            SystemEvent::Scale(ScaleEvent::Connected { .. }) => Some(Self::ScaleConnected),
+            SystemEvent::Scale(ScaleEvent::Disconnected { .. }) => Some(Self::ScaleDisconnected),
+            SystemEvent::User(event) => Some(Self::User { event: event.clone() }),
+            SystemEvent::Time(TimeEvent::SettlingTimeout) => Some(Self::SettlingTimeout),
+            _ => None,
+        }
+    }
+
+    pub fn into_brew_input(self) -> BrewInput {
+        match self {
+            Self::ScaleData {
+                timestamp_ms,
+                weight_g,
+                flow_rate_g_per_s,
+                battery_percent,
+                timer_running,
+                ..
+            } => BrewInput::ScaleData(ScaleData {
+                timestamp_ms,
+                weight_g,
+                flow_rate_g_per_s,
+                battery_percent,
+                timer_running,
+                // Replay runs on its own clock - staleness checks must see fresh samples
+                received_at: Instant::now(),
+            }),
+            Self::ScaleConnected => BrewInput::ScaleConnected,
+            Self::ScaleDisconnected => BrewInput::ScaleDisconnected,
+            Self::User { event } => BrewInput::UserCommand(event),
+            Self::SettlingTimeout => BrewInput::SettlingTimeout,
+        }
+    }
+}
+
+/// A recorded input with its offset from the start of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub offset_ms: u64,
+    pub input: RecordedInput,
+}
+
+/// Recording buffer - idle until `start` is called
+#[derive(Debug, Default, Serialize)]
+pub struct BrewRecording {
+    #[serde(skip)]
+    started_at: Option<Instant>,
+    events: Vec<RecordedEvent>,
+    truncated: bool,
+}
+
+impl BrewRecording {
+    /// Discard any previous recording and start capturing
+    pub fn start(&mut self) {
+        self.events.clear();
+        self.truncated = false;
+        self.started_at = Some(Instant::now());
+        info!("⏺️ Brew recording started");
+    }
+
+    pub fn stop(&mut self) {
+        if self.started_at.take().is_some() {
+            info!("⏹️ Brew recording stopped - {} events captured", self.events.len());
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Capture a bus event if recording and it drives the state machine
+    pub fn record(&mut self, timed: &TimedEvent) {
+        let Some(started_at) = self.started_at else {
+            return;
+        };
+        let event = &timed.event;
+        let Some(input) = RecordedInput::from_event(event) else {
+            return;
+        };
+
+        if self.events.len() >= MAX_RECORDED_EVENTS {
+            if !self.truncated {
+                warn!("⏺️ Brew recording full at {} events - stopping capture", MAX_RECORDED_EVENTS);
+                self.truncated = true;
+            }
+            return;
+        }
+
+        // Scale samples are placed by capture time, everything else by when it was published -
+        // never by when the bus got round to delivering it
+        let at = match event {
+            SystemEvent::Scale(ScaleEvent::WeightChanged { data }) => data.received_at,
+            _ => timed.published_at,
+        };
+        self.events.push(RecordedEvent {
+            offset_ms: at.saturating_duration_since(started_at).as_millis(),
+            input,
+        });
+    }
+}
+
+pub type SharedBrewRecording = Mutex<CriticalSectionRawMutex, BrewRecording>;
+
+/// Feed a recorded stream back through the state machine with its original timing
+pub async fn replay(controller: &mut BrewController, events: &[RecordedEvent]) -> Vec<BrewOutput> {
+    info!("▶️ Replaying {} recorded events", events.len());
+    let started_at = Instant::now();
+    let mut outputs = Vec::new();
+
+    for event in events {
+        let due = started_at + Duration::from_millis(event.offset_ms);
+
+        // Tick through the gap so cooldowns and delayed stops behave as they did live
+        while Instant::now() < due {
+            let step = (due - Instant::now()).min(Duration::from_millis(REPLAY_TICK_MS));
+            Timer::after(step).await;
+            outputs.extend(controller.handle_input(BrewInput::Tick));
+        }
+
+        outputs.extend(controller.handle_input(event.input.clone().into_brew_input()));
+    }
+
+    let end = controller.snapshot();
+    info!(
+        "▶️ Replay finished - {} outputs, ended {:?} at {:.1}g",
+        outputs.len(),
+        end.state,
+        end.current_weight_g
+    );
+    outputs
+}
+
+#[embassy_executor::task]
+pub async fn recorder_task(
+    event_bus: Arc<EventBus>,
+    recording: Arc<SharedBrewRecording>,
+    shutdown: Arc<ShutdownToken>,
+) {
+    info!("⏺️ Brew recorder task started");
+    let mut subscriber = event_bus.subscriber();
+
+    loop {
+        let event = match select(subscriber.next_timed_event(), shutdown.cancelled()).await {
+            Either::First(event) => event,
+            Either::Second(_) => break,
+        };
+        recording.lock().await.record(&event);
+    }
+    info!("⏺️ Brew recorder task stopped");
+}
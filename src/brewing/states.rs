@@ -1,11 +1,35 @@
 //! Enhanced brewing state machine with killswitch functionality
 //! States: SystemDisabled, ScaleDisconnected, Idle, Brewing, Settling
 
+use crate::brewing::profile::{FlowPid, FlowProfile};
 use crate::system::events::UserEvent;
-use crate::types::{AutoTareState, ScaleData, TARE_COOLDOWN_MS, TARE_STABILITY_THRESHOLD_G, OVERSHOOT_HISTORY_SIZE};
+use crate::types::{
+    AutoTareParams, AutoTareProfile, AutoTareReason, AutoTareState, OvershootLearningSchedule,
+    OvershootModel, OvershootProfiles, ScaleData, StopMode,
+    AUTO_TARE_BREWING_COOLDOWN_SECS, AUTO_TARE_EMPTY_THRESHOLD_G, AUTO_TARE_HISTORY_SIZE,
+    AUTO_TARE_HOLD_MS, AUTO_TARE_MAX_HOLD_MS,
+    AUTO_TARE_MAX_BREWING_COOLDOWN_SECS, BREW_STARTUP_IGNORE_MS, BREW_TIME_MS,
+    FLOW_PROFILE_MIN_POWER_STEP,
+    FLOW_DROP_CONFIRM_SAMPLES, FLOW_STOPPED_THRESHOLD_G_PER_S, FLOW_STOP_HOLD_MS, MAX_BREW_TIME_MS,
+    MAX_FLOW_STOPPED_THRESHOLD_G_PER_S, MAX_FLOW_STOP_HOLD_MS,
+    MAX_OVERSHOOT_PROFILES, MAX_SCALE_RECONNECT_GRACE_MS, MAX_SETTLE_STABILITY_G, MIN_BREW_TIME_MS,
+    OVERSHOOT_HISTORY_SIZE,
+    OVERSHOOT_INITIAL_STOP_DELAY_MS, OVERSHOOT_MAX_TARGET_G, OVERSHOOT_MAX_WARMUP_BREWS,
+    OVERSHOOT_OUTLIER_MAX_STREAK,
+    OVERSHOOT_OUTLIER_MIN_SPREAD_G, OVERSHOOT_OUTLIER_STD_DEVS, OVERSHOOT_READY_BREWS,
+    OVERSHOOT_READY_CONFIDENCE, OVERSHOOT_WARMUP_BREWS,
+    OVERSHOOT_WARMUP_EXTRA_DELAY_MS, PREDICTIVE_AUTO_DISABLE_CONFIDENCE,
+    PREDICTION_MAX_MIN_FLOW_G_PER_S, PREDICTION_MAX_SAFETY_MARGIN_MS, PREDICTION_MAX_START_DELAY_MS,
+    PREDICTION_MAX_WINDOW_MULTIPLIER,
+    PREDICTION_MIN_FLOW_G_PER_S, PREDICTION_SAFETY_MARGIN_MS, PREDICTION_START_DELAY_MS,
+    PREDICTION_WINDOW_MULTIPLIER, RESUME_MAX_WEIGHT_DRIFT_G, SCALE_RECONNECT_GRACE_MS,
+    SETTLE_STABILITY_G, SETTLE_STABLE_READINGS, TARE_COOLDOWN_MS,
+    TARE_STABILITY_COUNT, TARE_STABILITY_THRESHOLD_G, TARE_VERIFY_TIMEOUT_MS,
+};
 use embassy_time::{Duration, Instant};
 use heapless::Vec;
 use log::{debug, info};
+use serde::Serialize;
 use statig::prelude::*;
 
 // Overshoot measurement for learning
@@ -49,6 +73,7 @@ pub enum BrewInput {
 
     // From system
     TargetWeightReached { weight: f32, target: f32 },
+    TareVerified, // Post-tare verification saw the scale read zero
     FlowStopped,
     SettlingTimeout,
     EmergencyStop,
@@ -80,6 +105,7 @@ pub enum BrewOutput {
     // To relay
     RelayOn,
     RelayOff,
+    RelayPower(f32), // Proportional power for flow profiling
 
     // Network connectivity outputs
     EnableBle,
@@ -102,27 +128,50 @@ pub enum BrewOutput {
     ScaleConnectionChanged { connected: bool },
     NetworkStatusChanged { ble_enabled: bool, wifi_connected: bool },
     PredictiveStopTriggered,
+    /// Predictive stop is disabled - what it would have aimed for, reported once per shot
+    PredictiveStopPreview { predicted_final_weight: f32 },
     BrewingStarted,
     BrewingFinished,
+    /// Shot abandoned by a timer reset - nothing is recorded or learned from it
+    BrewingReset,
+    /// Tare before brew never read zero - the brew started anyway
+    BrewTareTimedOut,
+    /// The scale dropped mid-brew - the pump is off, and a reconnect within the grace window
+    /// picks the shot back up
+    BrewInterrupted { grace_ms: u32 },
+    /// A reconnect picked an interrupted shot back up - the pump is back on
+    BrewResumed,
+    /// The scale came back but the interrupted shot can't safely carry on
+    BrewResumeRefused { reason: &'static str },
     DisplayUpdate,
+    /// Dose weighing toggled or the pending dose changed (see `BrewController::dose_status`)
+    DoseStatusChanged,
+    DoseCaptured { grams: f32 },
     
     // Auto-tare outputs
-    AutoTareStateChanged { from: AutoTareState, to: AutoTareState },
+    AutoTareStateChanged { from: AutoTareState, to: AutoTareState, reason: AutoTareReason },
     AutoTareExecuted,
     
     // Overshoot control outputs
     PredictiveStopScheduled { delay_ms: i32, predicted_weight: f32 },
+    /// Flow dropped away before a scheduled stop fired - the shot is no longer heading for target
+    PredictiveStopCancelled { flow_rate: f32 },
     OvershootLearningUpdated { delay_ms: i32, ewma: f32, confidence: f32 },
     OvershootControllerReset,
+    /// A measurement far outside the recent spread was left out of learning
+    OvershootOutlierRejected { overshoot: f32, mean: f32, std_dev: f32 },
+    /// Auto mode switched predictive stop because learning became ready or lost confidence
+    PredictiveStopAutoChanged { enabled: bool, confidence: f32 },
 }
 
 // System-level states - flat but with logical grouping  
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
 pub enum SystemState {
     // 🚫 Killswitch engaged - ignore all scale input
     SystemDisabled,
     
     // 🔌 Network connectivity states
+    #[default]
     BleDisabled,        // BLE not enabled
     BleEnabled,         // BLE enabled but not scanning
     BleScanning,        // BLE scanning for devices
@@ -179,11 +228,17 @@ pub struct BrewContext {
     auto_tare_enabled: bool,
     auto_tare_state: AutoTareState,
     auto_tare_stable_weight: f32,
-    auto_tare_weight_history: Vec<f32, 10>,
+    auto_tare_weight_history: Vec<f32, AUTO_TARE_HISTORY_SIZE>,
     auto_tare_last_tare_time: Option<Instant>,
     auto_tare_brewing_cooldown_time: Option<Instant>,
+    auto_tare_brewing_cooldown: Duration,
     auto_tare_empty_threshold: f32,
+    auto_tare_stability_threshold: f32,
     auto_tare_stable_readings_needed: usize,
+    auto_tare_hold: Duration,
+    // Start and weight range of the current unbroken run inside the stability band
+    auto_tare_hold_since: Option<Instant>,
+    auto_tare_hold_range: (f32, f32),
     
     // Overshoot control state
     overshoot_stop_delay_ms: i32,
@@ -194,6 +249,49 @@ pub struct BrewContext {
     overshoot_confidence_score: f32,               // Learning confidence (0.0 to 1.0)
     overshoot_brew_count: u32,                     // Total brews for confidence calculation
     overshoot_pending_stop_time: Option<Instant>,  // Scheduled delayed stop time
+    overshoot_warmup_brews: u32,                   // Brews that stop early while learning starts
+    overshoot_target_g: f32,                       // Settled weight aims this far off target
+    overshoot_outlier_streak: u32,                 // Consecutive measurements rejected as outliers
+    overshoot_learning: OvershootLearningSchedule, // Confidence -> learning rate mapping
+    predictive_stop_enabled: bool,                 // When false predictions are only previewed
+    predictive_stop_auto: bool,                    // Learning readiness switches predictive stop
+    predictive_preview_sent: bool,                 // One preview per shot
+    prediction_safety_margin_ms: u32,              // Headroom on top of the learned delay
+    prediction_window_multiplier: f32,             // Window end as a multiple of its start
+    prediction_min_flow: f32,                      // Predict only above this flow (g/s)
+    prediction_start_delay_ms: u32,                // Scale timer time before predicting
+    brew_startup_ignore_ms: u32,                   // Scale timer time before the target counts
+    flow_stopped_threshold: f32,                   // Flow below this counts as stopped (g/s)
+    flow_drop_samples: u32,                        // Low-flow samples in a row, stop scheduled
+
+    // Stop mode state
+    stop_mode: StopMode,
+    flow_stop_hold: Duration,                      // Flow stopped this long ends the shot/settling
+    brew_duration: Duration,                       // Shot length in Time mode
+    brew_started_at: Option<Instant>,
+    flow_seen: bool,                               // Flow has started this shot
+    flow_stopped_since: Option<Instant>,
+    flow_stop_detected: bool,                      // Fed back in as BrewInput::FlowStopped
+    settle_stability_g: f32,                       // Settled weight range (g)
+    settle_weights: Vec<f32, SETTLE_STABLE_READINGS>, // Latest weights seen while settling
+
+    // Flow profiling (brewing sub-mode) - off unless a profile is set
+    flow_profile: Option<FlowProfile>,
+    profiled_shot: Option<(FlowProfile, FlowPid)>, // This shot's curve - changes wait for the next
+    flow_profile_power: Option<f32>,               // Last power sent this shot
+
+    // Tare before brew (idle sub-mode) - StartBrewing tares first and waits for zero
+    tare_before_brew: bool,
+    tare_before_brew_timeout: Duration,            // Brew anyway after this, zero or not
+    brew_tare_sent_at: Option<Instant>,            // Waiting on the pre-brew tare since
+
+    // Scale reconnect grace - off unless a grace window is set
+    reconnect_grace: Duration,                     // A reconnect this soon can resume the shot
+    interrupted_brew: Option<(Instant, f32)>,      // Shot cut by a disconnect - when, what weight
+
+    // Dose weighing (idle sub-mode)
+    dose_weighing: bool,
+    dose_candidate: Option<f32>,                   // Latest stable dose awaiting confirmation
     
     // System state
     system_enabled: bool,
@@ -228,18 +326,64 @@ impl Default for BrewContext {
             auto_tare_weight_history: Vec::new(),
             auto_tare_last_tare_time: None,
             auto_tare_brewing_cooldown_time: None,
-            auto_tare_empty_threshold: 2.0,                 // From Python
-            auto_tare_stable_readings_needed: 5,            // From Python
+            auto_tare_brewing_cooldown: Duration::from_secs(AUTO_TARE_BREWING_COOLDOWN_SECS as u64),
+            auto_tare_empty_threshold: AUTO_TARE_EMPTY_THRESHOLD_G,     // From Python
+            auto_tare_stability_threshold: TARE_STABILITY_THRESHOLD_G,  // From Python
+            auto_tare_stable_readings_needed: TARE_STABILITY_COUNT,     // From Python
+            auto_tare_hold: Duration::from_millis(AUTO_TARE_HOLD_MS as u64),
+            auto_tare_hold_since: None,
+            auto_tare_hold_range: (0.0, 0.0),
             
             // Overshoot control defaults
-            overshoot_stop_delay_ms: 500,                   // Initial delay from Python
+            overshoot_stop_delay_ms: OVERSHOOT_INITIAL_STOP_DELAY_MS, // Initial delay from Python
             overshoot_history: Vec::new(),
             overshoot_pending_predicted_stop: false,
             overshoot_ewma: 0.0,                            // Exponentially weighted moving average
-            overshoot_learning_rate: 0.3,                   // 30% new data, 70% historical
+            overshoot_learning_rate: OvershootLearningSchedule::default().uncertain_rate,
             overshoot_confidence_score: 0.0,                // Learning confidence
             overshoot_brew_count: 0,                        // Total brews for confidence calculation
             overshoot_pending_stop_time: None,              // No scheduled stop initially
+            overshoot_warmup_brews: OVERSHOOT_WARMUP_BREWS,
+            overshoot_target_g: 0.0,
+            overshoot_outlier_streak: 0,
+            overshoot_learning: OvershootLearningSchedule::default(),
+            predictive_stop_enabled: true,
+            predictive_stop_auto: false,
+            predictive_preview_sent: false,
+            prediction_safety_margin_ms: PREDICTION_SAFETY_MARGIN_MS,
+            prediction_window_multiplier: PREDICTION_WINDOW_MULTIPLIER,
+            prediction_min_flow: PREDICTION_MIN_FLOW_G_PER_S,
+            prediction_start_delay_ms: PREDICTION_START_DELAY_MS,
+            brew_startup_ignore_ms: BREW_STARTUP_IGNORE_MS,
+            flow_stopped_threshold: FLOW_STOPPED_THRESHOLD_G_PER_S,
+            flow_drop_samples: 0,
+
+            // Stop mode defaults
+            stop_mode: StopMode::Weight,
+            flow_stop_hold: Duration::from_millis(FLOW_STOP_HOLD_MS as u64),
+            brew_duration: Duration::from_millis(BREW_TIME_MS as u64),
+            brew_started_at: None,
+            flow_seen: false,
+            flow_stopped_since: None,
+            flow_stop_detected: false,
+            settle_stability_g: SETTLE_STABILITY_G,
+            settle_weights: Vec::new(),
+
+            // Flow profiling defaults
+            flow_profile: None,
+            profiled_shot: None,
+            flow_profile_power: None,
+
+            // Tare before brew defaults
+            tare_before_brew: false,
+            tare_before_brew_timeout: Duration::from_millis(TARE_VERIFY_TIMEOUT_MS),
+            brew_tare_sent_at: None,
+            reconnect_grace: Duration::from_millis(SCALE_RECONNECT_GRACE_MS as u64),
+            interrupted_brew: None,
+
+            // Dose weighing defaults
+            dose_weighing: false,
+            dose_candidate: None,
             
             // System defaults
             system_enabled: true,    // Start enabled
@@ -454,6 +598,12 @@ impl BrewStateMachine {
                 // Connection failed, go back to scanning
                 Transition(State::ble_scanning())
             }
+            BrewInput::BleScanning => {
+                // Attempt failed before the link came up - the scale task is scanning again
+                context.ble_scanning = true;
+                context.outputs.push(BrewOutput::StartBleScanning);
+                Transition(State::ble_scanning())
+            }
             BrewInput::WifiConnected => {
                 context.wifi_connected = true;
                 context.outputs.push(BrewOutput::NetworkStatusChanged { 
@@ -581,10 +731,26 @@ impl BrewStateMachine {
                 context.current_weight = data.weight_g;
                 context.timer_running = data.timer_running;
                 context.outputs.push(BrewOutput::DisplayUpdate);
-                
-                // Check auto-tare logic (only in idle state when not brewing)
-                if Self::should_auto_tare(context, data.weight_g) {
-                    Self::record_auto_tare(context);
+
+                // The first sample after a reconnect decides whether an interrupted shot goes on
+                if let Some(interrupted) = context.interrupted_brew.take() {
+                    match Self::resume_refusal(context, interrupted, data) {
+                        None => return Self::resume_brew(context),
+                        Some(reason) => {
+                            info!("Interrupted brew not resumed - {}", reason);
+                            context.outputs.push(BrewOutput::BrewResumeRefused { reason });
+                        }
+                    }
+                }
+
+                if context.brew_tare_sent_at.is_some() {
+                    // Pre-brew tare in flight - the verification decides when the brew starts
+                } else if context.dose_weighing {
+                    // Weighing grounds - a stable reading is the dose, never a reason to tare
+                    Self::track_dose(context, data.weight_g);
+                } else if Self::should_auto_tare(context, data.weight_g, data.received_at) {
+                    // Check auto-tare logic (only in idle state when not brewing)
+                    Self::record_auto_tare(context, data.received_at);
                     context.outputs.push(BrewOutput::AutoTareExecuted);
                     context.outputs.push(BrewOutput::TareScale);
                 }
@@ -595,19 +761,61 @@ impl BrewStateMachine {
                 Handled
             }
             BrewInput::UserCommand(UserEvent::StartBrewing) => {
-                context.outputs.push(BrewOutput::StartTimer);
-                context.outputs.push(BrewOutput::RelayOn);
-                context.outputs.push(BrewOutput::BrewingStarted);
-                Transition(State::brewing())
+                if !context.tare_before_brew {
+                    return Self::begin_brew(context);
+                }
+                if context.brew_tare_sent_at.is_some() {
+                    debug!("Start ignored - already taring before the brew");
+                    return Handled;
+                }
+                info!("⚖️ Taring before the brew starts");
+                context.outputs.push(BrewOutput::TareScale);
+                if context.tare_before_brew_timeout.as_ticks() == 0 {
+                    // Tare verification is off - nothing will confirm, so don't wait for it
+                    return Self::begin_brew(context);
+                }
+                context.brew_tare_sent_at = Some(Instant::now());
+                Handled
+            }
+            BrewInput::TareVerified if context.brew_tare_sent_at.is_some() => {
+                info!("⚖️ Scale zeroed - starting the brew");
+                Self::begin_brew(context)
+            }
+            BrewInput::UserCommand(UserEvent::StopBrewing)
+                if context.brew_tare_sent_at.is_some() =>
+            {
+                info!("⚖️ Brew cancelled while taring");
+                context.brew_tare_sent_at = None;
+                Handled
             }
             BrewInput::UserCommand(UserEvent::TareScale) => {
                 context.outputs.push(BrewOutput::TareScale);
                 Handled
             }
             BrewInput::UserCommand(UserEvent::ResetTimer) => {
+                // Also drops a pre-brew tare wait and anything a cut-short shot left behind
+                Self::clear_shot(context);
                 context.outputs.push(BrewOutput::ResetTimer);
                 Handled
             }
+            BrewInput::UserCommand(UserEvent::SetDoseWeighing(active)) => {
+                info!("⚖️ Dose weighing {}", if *active { "started" } else { "cancelled" });
+                context.dose_weighing = *active;
+                context.dose_candidate = None;
+                context.outputs.push(BrewOutput::DoseStatusChanged);
+                Handled
+            }
+            BrewInput::UserCommand(UserEvent::ConfirmDose) => {
+                match context.dose_candidate.take() {
+                    Some(grams) => {
+                        context.dose_weighing = false;
+                        context.outputs.push(BrewOutput::DoseCaptured { grams });
+                        context.outputs.push(BrewOutput::DoseStatusChanged);
+                    }
+                    None => debug!("Confirm dose ignored - no stable dose yet"),
+                }
+                Handled
+            }
             BrewInput::AutoTareEnabled => {
                 context.auto_tare_enabled = true;
                 Handled
@@ -621,9 +829,18 @@ impl BrewStateMachine {
                 Handled
             }
             BrewInput::Tick => {
+                // A tare that never confirms must not leave the brew waiting forever
+                let waited = context
+                    .brew_tare_sent_at
+                    .map(|sent_at| Instant::now().saturating_duration_since(sent_at));
+                if waited.is_some_and(|waited| waited >= context.tare_before_brew_timeout) {
+                    context.outputs.push(BrewOutput::BrewTareTimedOut);
+                    return Self::begin_brew(context);
+                }
+
                 // Check auto-tare brewing cooldown expiration
                 if let Some(brewing_cooldown) = context.auto_tare_brewing_cooldown_time {
-                    if Instant::now().duration_since(brewing_cooldown) >= Duration::from_secs(10) {
+                    if Instant::now().duration_since(brewing_cooldown) >= context.auto_tare_brewing_cooldown {
                         debug!("⏰ Auto-tare brewing cooldown expired");
                         context.auto_tare_brewing_cooldown_time = None;
                     }
@@ -683,6 +900,13 @@ impl BrewStateMachine {
                 context.scale_connected = false;
                 context.outputs.push(BrewOutput::ScaleConnectionChanged { connected: false });
                 context.outputs.push(BrewOutput::RelayOff);
+                // Pump off either way - the shot is only kept for a reconnect inside the window
+                if context.reconnect_grace.as_ticks() > 0 {
+                    context.interrupted_brew = Some((Instant::now(), context.current_weight));
+                    context.outputs.push(BrewOutput::BrewInterrupted {
+                        grace_ms: context.reconnect_grace.as_millis() as u32,
+                    });
+                }
                 Transition(State::scale_disconnected())
             }
             BrewInput::ScaleData(data) => {
@@ -690,21 +914,46 @@ impl BrewStateMachine {
                 context.last_weight = Some(data.weight_g);
                 context.timer_running = data.timer_running;
                 context.outputs.push(BrewOutput::DisplayUpdate);
-                
-                // Record overshoot when flow stops after predicted stop
-                if data.flow_rate_g_per_s.abs() < 0.5 && context.overshoot_pending_predicted_stop {
-                    let overshoot = data.weight_g - context.target_weight;
-                    Self::record_overshoot_learning(context, overshoot);
+
+                // Always tracked so settling knows whether flow started; only ends FlowStopped shots
+                if Self::detect_flow_stop(context, data) && context.stop_mode == StopMode::FlowStopped {
+                    context.flow_stop_detected = true;
                 }
                 
-                // Check for predictive stop opportunity
-                if let Some(predicted_weight) = Self::should_trigger_predictive_stop(context, data, context.target_weight) {
-                    context.overshoot_pending_predicted_stop = true;
-                    let time_to_target = (context.target_weight - data.weight_g) / data.flow_rate_g_per_s;
-                    Self::schedule_delayed_stop(context, time_to_target);
-                    context.outputs.push(BrewOutput::PredictiveStopTriggered);
+                // Check for predictive stop opportunity - weight mode only
+                let aim = Self::settled_aim(context);
+                let prediction = if context.stop_mode == StopMode::Weight {
+                    Self::should_trigger_predictive_stop(context, data, aim)
+                } else {
+                    None
+                };
+                if let Some(predicted_weight) = prediction {
+                    if context.predictive_stop_enabled {
+                        context.overshoot_pending_predicted_stop = true;
+                        let time_to_target = (aim - data.weight_g) / data.flow_rate_g_per_s;
+                        Self::schedule_delayed_stop(context, time_to_target);
+                        context.outputs.push(BrewOutput::PredictiveStopTriggered);
+                    } else if !context.predictive_preview_sent {
+                        // Preview only - report the prediction, leave stopping to the target check
+                        context.predictive_preview_sent = true;
+                        context.outputs.push(BrewOutput::PredictiveStopPreview {
+                            predicted_final_weight: predicted_weight,
+                        });
+                    }
                 }
                 
+                // Cup lifted or the pour stalled - the prediction behind a scheduled stop no
+                // longer holds and nothing is reaching the cup, so end the shot now
+                if context.overshoot_pending_stop_time.is_some()
+                    && Self::flow_dropped(context, data)
+                {
+                    Self::cancel_delayed_stop(context, data.flow_rate_g_per_s);
+                    context.outputs.push(BrewOutput::StopTimer);
+                    context.outputs.push(BrewOutput::RelayOff);
+                    context.settle_start_time = Some(Instant::now());
+                    return Transition(State::settling());
+                }
+
                 // Check if delayed stop timeout occurred
                 if Self::check_delayed_stop_timeout(context) {
                     context.overshoot_pending_stop_time = None;
@@ -714,6 +963,14 @@ impl BrewStateMachine {
                     return Transition(State::settling());
                 }
                 
+                if Self::brew_time_up(context, data.received_at) {
+                    info!("⏱️ Brew time reached - stopping");
+                    context.outputs.push(BrewOutput::StopTimer);
+                    context.outputs.push(BrewOutput::RelayOff);
+                    context.settle_start_time = Some(Instant::now());
+                    return Transition(State::settling());
+                }
+
                 // Check if timer stopped (manual or automatic)
                 if !data.timer_running {
                     context.timer_running = false;
@@ -722,10 +979,16 @@ impl BrewStateMachine {
                     return Transition(State::settling());
                 }
 
-                // Check target weight reached
-                if data.weight_g >= context.target_weight {
-                    // Mark as predicted stop if we had a scheduled stop
-                    if context.overshoot_pending_stop_time.is_some() {
+                // Check target weight reached, offset by the overshoot setpoint
+                if context.stop_mode == StopMode::Weight
+                    && data.weight_g >= aim
+                    && !Self::in_startup_window(context, data)
+                {
+                    // Mark as predicted stop if we had a scheduled stop. Auto mode also learns from
+                    // plain target stops, or predictive stop could never become ready
+                    if context.overshoot_pending_stop_time.is_some()
+                        || (context.predictive_stop_auto && !context.predictive_stop_enabled)
+                    {
                         context.overshoot_pending_predicted_stop = true;
                     }
                     context.overshoot_pending_stop_time = None;
@@ -735,6 +998,8 @@ impl BrewStateMachine {
                     return Transition(State::settling());
                 }
 
+                Self::follow_flow_profile(context, data);
+
                 Handled
             }
             BrewInput::TargetWeightReached { .. } => {
@@ -743,12 +1008,26 @@ impl BrewStateMachine {
                 context.settle_start_time = Some(Instant::now());
                 Transition(State::settling())
             }
+            BrewInput::FlowStopped if context.stop_mode == StopMode::FlowStopped => {
+                info!("💧 Flow stopped - ending shot");
+                context.outputs.push(BrewOutput::StopTimer);
+                context.outputs.push(BrewOutput::RelayOff);
+                context.settle_start_time = Some(Instant::now());
+                // Settling waits for its own full hold before finishing
+                context.flow_stopped_since = None;
+                Transition(State::settling())
+            }
             BrewInput::UserCommand(UserEvent::StopBrewing) => {
                 context.outputs.push(BrewOutput::StopTimer);
                 context.outputs.push(BrewOutput::RelayOff);
                 context.settle_start_time = Some(Instant::now());
                 Transition(State::settling())
             }
+            BrewInput::UserCommand(UserEvent::ResetTimer) => {
+                info!("🔄 Timer reset mid-brew - abandoning the shot");
+                context.outputs.push(BrewOutput::RelayOff);
+                Self::reset_shot(context)
+            }
             BrewInput::UserCommand(UserEvent::TareScale) => {
                 context.outputs.push(BrewOutput::TareScale);
                 Handled
@@ -766,6 +1045,14 @@ impl BrewStateMachine {
                         return Transition(State::settling());
                     }
                 }
+                // Time mode must stop even if scale data stalls
+                if Self::brew_time_up(context, Instant::now()) {
+                    info!("⏱️ Brew time reached - stopping");
+                    context.outputs.push(BrewOutput::StopTimer);
+                    context.outputs.push(BrewOutput::RelayOff);
+                    context.settle_start_time = Some(Instant::now());
+                    return Transition(State::settling());
+                }
                 Handled
             }
             _ => Handled,
@@ -817,6 +1104,21 @@ impl BrewStateMachine {
                 context.current_weight = data.weight_g;
                 context.timer_running = data.timer_running;
                 context.outputs.push(BrewOutput::DisplayUpdate);
+
+                // Record overshoot once flow stops after a predicted stop - the stop itself moved
+                // us here, so waiting in Brewing would only catch the next shot's first sample
+                if Self::flow_stopped(context, data) && context.overshoot_pending_predicted_stop {
+                    // Measured against the setpoint, so learning lands the settled weight there
+                    let overshoot = data.weight_g - Self::settled_aim(context);
+                    Self::record_overshoot_learning(context, overshoot);
+                }
+
+                // Drips have stopped and the cup is still - finish without waiting out the
+                // settling timeout, so the final weight isn't taken mid-drip
+                let weight_settled = Self::track_settle_weight(context, data.weight_g);
+                if Self::detect_flow_stop(context, data) && weight_settled {
+                    context.flow_stop_detected = true;
+                }
                 
                 // Timer restart detection is handled by ScaleEventDetector -> UserEvent::StartBrewing
                 // This ensures proper debouncing and avoids false triggers from raw timer_running field
@@ -829,11 +1131,10 @@ impl BrewStateMachine {
                 Self::auto_tare_brewing_finished(context, context.current_weight);
                 Transition(State::idle())
             }
-            BrewInput::UserCommand(UserEvent::StartBrewing) => {
-                context.outputs.push(BrewOutput::StartTimer);
-                context.outputs.push(BrewOutput::RelayOn);
-                context.outputs.push(BrewOutput::BrewingStarted);
-                Transition(State::brewing())
+            BrewInput::UserCommand(UserEvent::StartBrewing) => Self::begin_brew(context),
+            BrewInput::UserCommand(UserEvent::ResetTimer) => {
+                info!("🔄 Timer reset while settling - abandoning the shot");
+                Self::reset_shot(context)
             }
             BrewInput::UserCommand(UserEvent::TareScale) => {
                 context.outputs.push(BrewOutput::TareScale);
@@ -1070,7 +1371,7 @@ impl BrewStateMachine {
 // Auto-tare helper functions
 impl BrewStateMachine {
     /// Check if auto-tare should trigger based on current weight
-    fn should_auto_tare(context: &mut BrewContext, current_weight: f32) -> bool {
+    fn should_auto_tare(context: &mut BrewContext, current_weight: f32, received_at: Instant) -> bool {
         if !context.auto_tare_enabled 
             || context.timer_running 
             || !matches!(context.system_enabled, true) {
@@ -1079,33 +1380,37 @@ impl BrewStateMachine {
 
         // Check brewing cooldown period (prevent auto-tare right after brewing)
         if let Some(brewing_cooldown) = context.auto_tare_brewing_cooldown_time {
-            if Instant::now().duration_since(brewing_cooldown) < Duration::from_secs(10) {
+            if Instant::now().duration_since(brewing_cooldown) < context.auto_tare_brewing_cooldown {
                 debug!("Auto-tare: Still in brewing cooldown period");
                 return false;
             }
         }
 
-        // Check regular tare cooldown period
+        // Check regular tare cooldown period - in sample time, like the hold
         if let Some(last_tare) = context.auto_tare_last_tare_time {
-            if Instant::now().duration_since(last_tare) < Duration::from_millis(TARE_COOLDOWN_MS) {
+            if received_at.saturating_duration_since(last_tare) < Duration::from_millis(TARE_COOLDOWN_MS) {
                 return false;
             }
         }
 
         let is_stable = Self::is_weight_stable(context, current_weight);
         let is_empty = current_weight.abs() <= context.auto_tare_empty_threshold;
+        // An object only counts as settled once it has also held still for the hold time
+        let is_held = Self::is_weight_held(context, current_weight, received_at);
+        let is_settled = is_stable && is_held;
 
         // State machine logic from Python
         match context.auto_tare_state {
             AutoTareState::Empty => {
-                if !is_empty && is_stable {
+                if !is_empty && is_settled {
                     // Object placed on empty scale - TARE IMMEDIATELY
                     let old_state = context.auto_tare_state;
                     context.auto_tare_state = AutoTareState::StableObject;
                     context.auto_tare_stable_weight = current_weight;
-                    context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                        from: old_state, 
-                        to: AutoTareState::StableObject 
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::StableObject,
+                        reason: AutoTareReason::ObjectDetected,
                     });
                     info!("AutoTare: Object detected: {:.1}g - TARING", current_weight);
                     return true;
@@ -1113,36 +1418,37 @@ impl BrewStateMachine {
                     // Weight detected but not stable yet
                     let old_state = context.auto_tare_state;
                     context.auto_tare_state = AutoTareState::Loading;
-                    context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                        from: old_state, 
-                        to: AutoTareState::Loading 
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::Loading,
+                        reason: AutoTareReason::WeightUnsettled,
                     });
                 }
             }
 
             AutoTareState::Loading => {
-                if is_stable {
-                    if is_empty {
-                        // Stabilized to empty
-                        let old_state = context.auto_tare_state;
-                        context.auto_tare_state = AutoTareState::Empty;
-                        context.auto_tare_stable_weight = 0.0;
-                        context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                            from: old_state, 
-                            to: AutoTareState::Empty 
-                        });
-                    } else {
-                        // Stabilized with object - TARE IMMEDIATELY
-                        let old_state = context.auto_tare_state;
-                        context.auto_tare_state = AutoTareState::StableObject;
-                        context.auto_tare_stable_weight = current_weight;
-                        context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                            from: old_state, 
-                            to: AutoTareState::StableObject 
-                        });
-                        info!("AutoTare: Object stabilized: {:.1}g - TARING", current_weight);
-                        return true;
-                    }
+                if is_stable && is_empty {
+                    // Stabilized to empty
+                    let old_state = context.auto_tare_state;
+                    context.auto_tare_state = AutoTareState::Empty;
+                    context.auto_tare_stable_weight = 0.0;
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::Empty,
+                        reason: AutoTareReason::SettledEmpty,
+                    });
+                } else if is_settled {
+                    // Stabilized with object - TARE IMMEDIATELY
+                    let old_state = context.auto_tare_state;
+                    context.auto_tare_state = AutoTareState::StableObject;
+                    context.auto_tare_stable_weight = current_weight;
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::StableObject,
+                        reason: AutoTareReason::ObjectStabilized,
+                    });
+                    info!("AutoTare: Object stabilized: {:.1}g - TARING", current_weight);
+                    return true;
                 }
             }
 
@@ -1152,9 +1458,10 @@ impl BrewStateMachine {
                     let old_state = context.auto_tare_state;
                     context.auto_tare_state = AutoTareState::Empty;
                     context.auto_tare_stable_weight = 0.0;
-                    context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                        from: old_state, 
-                        to: AutoTareState::Empty 
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::Empty,
+                        reason: AutoTareReason::ObjectRemoved,
                     });
                     info!("AutoTare: Object removed");
                 } else if is_stable && (current_weight - context.auto_tare_stable_weight).abs() > 10.0 {
@@ -1163,9 +1470,10 @@ impl BrewStateMachine {
                     let old_state = context.auto_tare_state;
                     context.auto_tare_state = AutoTareState::Empty;
                     context.auto_tare_stable_weight = 0.0;
-                    context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                        from: old_state, 
-                        to: AutoTareState::Empty 
+                    context.outputs.push(BrewOutput::AutoTareStateChanged {
+                        from: old_state,
+                        to: AutoTareState::Empty,
+                        reason: AutoTareReason::CupSwap,
                     });
                     info!(
                         "AutoTare: Major cup change detected: {:.1}g -> {:.1}g",
@@ -1187,9 +1495,10 @@ impl BrewStateMachine {
                     if (recent_avg - context.auto_tare_stable_weight).abs() > 5.0 {
                         let old_state = context.auto_tare_state;
                         context.auto_tare_state = AutoTareState::Unloading;
-                        context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                            from: old_state, 
-                            to: AutoTareState::Unloading 
+                        context.outputs.push(BrewOutput::AutoTareStateChanged {
+                            from: old_state,
+                            to: AutoTareState::Unloading,
+                            reason: AutoTareReason::WeightShifting,
                         });
                         info!("AutoTare: Major weight change detected, entering unloading state");
                     }
@@ -1204,20 +1513,22 @@ impl BrewStateMachine {
                         let old_state = context.auto_tare_state;
                         context.auto_tare_state = AutoTareState::Empty;
                         context.auto_tare_stable_weight = 0.0;
-                        context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                            from: old_state, 
-                            to: AutoTareState::Empty 
+                        context.outputs.push(BrewOutput::AutoTareStateChanged {
+                            from: old_state,
+                            to: AutoTareState::Empty,
+                            reason: AutoTareReason::ObjectRemoved,
                         });
                         info!("AutoTare: Object removed");
-                    } else {
+                    } else if is_held {
                         // Stabilized at new weight - TARE IMMEDIATELY
                         let old_state = context.auto_tare_state;
                         context.auto_tare_state = AutoTareState::StableObject;
                         let old_weight = context.auto_tare_stable_weight;
                         context.auto_tare_stable_weight = current_weight;
-                        context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                            from: old_state, 
-                            to: AutoTareState::StableObject 
+                        context.outputs.push(BrewOutput::AutoTareStateChanged {
+                            from: old_state,
+                            to: AutoTareState::StableObject,
+                            reason: AutoTareReason::ObjectChanged,
                         });
                         info!(
                             "AutoTare: Object changed: {:.1}g → {:.1}g - TARING",
@@ -1235,7 +1546,7 @@ impl BrewStateMachine {
     /// Check if weight is stable based on recent history
     fn is_weight_stable(context: &mut BrewContext, current_weight: f32) -> bool {
         // Add to history
-        if context.auto_tare_weight_history.len() >= 10 {
+        if context.auto_tare_weight_history.is_full() {
             context.auto_tare_weight_history.remove(0);
         }
         let _ = context.auto_tare_weight_history.push(current_weight);
@@ -1247,25 +1558,56 @@ impl BrewStateMachine {
 
         // Use Python's simple min/max approach for consistent behavior
         let recent_weights = &context.auto_tare_weight_history[context.auto_tare_weight_history.len() - context.auto_tare_stable_readings_needed..];
+        Self::weights_within(recent_weights, context.auto_tare_stability_threshold)
+    }
 
-        // Check if recent weights are within threshold of each other (Python method)
-        let max_weight = recent_weights
-            .iter()
-            .fold(f32::NEG_INFINITY, |a, &b| a.max(b));
-        let min_weight = recent_weights.iter().fold(f32::INFINITY, |a, &b| a.min(b));
+    /// Recent weights are within threshold of each other (Python method)
+    fn weights_within(weights: &[f32], threshold: f32) -> bool {
+        let max_weight = weights.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b));
+        let min_weight = weights.iter().fold(f32::INFINITY, |a, &b| a.min(b));
 
         // Consider stable if range is within threshold (exactly like Python)
-        (max_weight - min_weight) <= TARE_STABILITY_THRESHOLD_G
+        (max_weight - min_weight) <= threshold
+    }
+
+    /// Settling counterpart of `is_weight_stable`: true once the last `SETTLE_STABLE_READINGS`
+    /// weights lie within `settle_stability_g`
+    fn track_settle_weight(context: &mut BrewContext, weight: f32) -> bool {
+        if context.settle_weights.is_full() {
+            context.settle_weights.remove(0);
+        }
+        let _ = context.settle_weights.push(weight);
+        context.settle_weights.is_full()
+            && Self::weights_within(&context.settle_weights, context.settle_stability_g)
+    }
+
+    /// Check the weight has stayed within the stability band for the whole hold time
+    fn is_weight_held(context: &mut BrewContext, current_weight: f32, received_at: Instant) -> bool {
+        let (low, high) = context.auto_tare_hold_range;
+        let (low, high) = (low.min(current_weight), high.max(current_weight));
+
+        match context.auto_tare_hold_since {
+            Some(since) if high - low <= context.auto_tare_stability_threshold => {
+                context.auto_tare_hold_range = (low, high);
+                received_at.saturating_duration_since(since) >= context.auto_tare_hold
+            }
+            _ => {
+                // Left the band (or first reading) - restart the hold from here
+                context.auto_tare_hold_since = Some(received_at);
+                context.auto_tare_hold_range = (current_weight, current_weight);
+                context.auto_tare_hold.as_ticks() == 0
+            }
+        }
     }
 
     /// Record that a tare was executed
-    fn record_auto_tare(context: &mut BrewContext) {
-        context.auto_tare_last_tare_time = Some(Instant::now());
+    fn record_auto_tare(context: &mut BrewContext, received_at: Instant) {
+        context.auto_tare_last_tare_time = Some(received_at);
     }
 
     /// Called when brewing finishes to preserve current object state
     fn auto_tare_brewing_finished(context: &mut BrewContext, current_weight: f32) {
-        // Set brewing cooldown to prevent auto-tare for 10 seconds after brewing
+        // Set brewing cooldown to prevent auto-tare right after brewing
         context.auto_tare_brewing_cooldown_time = Some(Instant::now());
 
         // If we have a stable object after brewing, keep it as stable without re-taring
@@ -1273,44 +1615,83 @@ impl BrewStateMachine {
             let old_state = context.auto_tare_state;
             context.auto_tare_state = AutoTareState::StableObject;
             context.auto_tare_stable_weight = current_weight;
-            context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                from: old_state, 
-                to: AutoTareState::StableObject 
+            context.outputs.push(BrewOutput::AutoTareStateChanged {
+                from: old_state,
+                to: AutoTareState::StableObject,
+                reason: AutoTareReason::BrewFinishedWithObject,
             });
             // Clear weight history to rebuild stability for this object
             context.auto_tare_weight_history.clear();
             info!(
-                "AutoTare: Brewing finished, preserving object at {:.1}g (10s cooldown active)",
-                current_weight
+                "AutoTare: Brewing finished, preserving object at {:.1}g ({}s cooldown active)",
+                current_weight,
+                context.auto_tare_brewing_cooldown.as_secs()
             );
         } else {
             let old_state = context.auto_tare_state;
             context.auto_tare_state = AutoTareState::Empty;
             context.auto_tare_stable_weight = 0.0;
-            context.outputs.push(BrewOutput::AutoTareStateChanged { 
-                from: old_state, 
-                to: AutoTareState::Empty 
+            context.outputs.push(BrewOutput::AutoTareStateChanged {
+                from: old_state,
+                to: AutoTareState::Empty,
+                reason: AutoTareReason::BrewFinishedEmpty,
             });
             info!("AutoTare: Brewing finished, scale empty");
         }
     }
     
-    /// Calculate valid prediction time window based on learned delay
+    /// Calculate valid prediction time window based on learned delay.
+    /// The window opens at learned delay + safety margin and closes at that times the multiplier,
+    /// so as overshoot learning lengthens the delay the window both moves earlier and widens.
     fn calculate_prediction_window(context: &BrewContext) -> (f32, f32) {
-        let min_reaction_time = (context.overshoot_stop_delay_ms as f32 / 1000.0) + 0.2; // delay + safety margin
-        let max_prediction_time = min_reaction_time * 3.0; // Don't predict too far ahead
+        let min_reaction_time = (Self::effective_stop_delay_ms(context)
+            + context.prediction_safety_margin_ms as f32)
+            / 1000.0;
+        let max_prediction_time = min_reaction_time * context.prediction_window_multiplier;
         (min_reaction_time, max_prediction_time)
     }
 
     /// Get delay with overshoot compensation applied
     fn get_compensated_delay(context: &BrewContext, target_delay: f32) -> f32 {
-        (target_delay - (context.overshoot_stop_delay_ms as f32 / 1000.0)).max(0.1)
+        (target_delay - (Self::effective_stop_delay_ms(context) / 1000.0)).max(0.1)
+    }
+
+    /// Still in the conservative warmup after an overshoot reset
+    fn overshoot_warmup_active(context: &BrewContext) -> bool {
+        context.overshoot_brew_count < context.overshoot_warmup_brews
+    }
+
+    /// Extra stop delay during warmup - full on the first shot, fading with each brew and with confidence
+    fn overshoot_warmup_bias_ms(context: &BrewContext) -> f32 {
+        if !Self::overshoot_warmup_active(context) {
+            return 0.0;
+        }
+        let remaining = 1.0 - context.overshoot_brew_count as f32 / context.overshoot_warmup_brews as f32;
+        OVERSHOOT_WARMUP_EXTRA_DELAY_MS * remaining * (1.0 - context.overshoot_confidence_score)
+    }
+
+    /// Learned stop delay plus any warmup bias
+    fn effective_stop_delay_ms(context: &BrewContext) -> f32 {
+        context.overshoot_stop_delay_ms as f32 + Self::overshoot_warmup_bias_ms(context)
+    }
+
+    /// Where the settled weight should end up - the target plus the overshoot setpoint
+    fn settled_aim(context: &BrewContext) -> f32 {
+        context.target_weight + context.overshoot_target_g
+    }
+
+    /// Still inside `brew_startup_ignore_ms` of the scale timer starting - pressing the timer
+    /// button pushes on the platform, and that spike must not read as the target being reached
+    fn in_startup_window(context: &BrewContext, scale_data: &ScaleData) -> bool {
+        scale_data.timestamp_ms < context.brew_startup_ignore_ms
     }
 
     /// Check if predictive stop should trigger based on current flow and weight
     fn should_trigger_predictive_stop(context: &BrewContext, scale_data: &ScaleData, target_weight: f32) -> Option<f32> {
-        // Only in brewing state, with timer running and positive flow
-        if scale_data.flow_rate_g_per_s <= 0.0 || scale_data.timestamp_ms <= 2000 {
+        // Only in brewing state, with timer running and enough flow
+        if scale_data.flow_rate_g_per_s <= context.prediction_min_flow
+            || scale_data.timestamp_ms <= context.prediction_start_delay_ms
+        {
             return None;
         }
 
@@ -1340,12 +1721,199 @@ impl BrewStateMachine {
         }
     }
 
+    /// Flow has dropped low enough to treat the shot as finished dripping
+    fn flow_stopped(context: &BrewContext, scale_data: &ScaleData) -> bool {
+        scale_data.flow_rate_g_per_s.abs() < context.flow_stopped_threshold
+    }
+
+    /// Flow has been below the stopped threshold for `FLOW_DROP_CONFIRM_SAMPLES` samples in a
+    /// row - unlike `flow_stopped`, weight coming off the scale counts too
+    fn flow_dropped(context: &mut BrewContext, scale_data: &ScaleData) -> bool {
+        if scale_data.flow_rate_g_per_s >= context.flow_stopped_threshold {
+            context.flow_drop_samples = 0;
+            return false;
+        }
+        context.flow_drop_samples += 1;
+        context.flow_drop_samples >= FLOW_DROP_CONFIRM_SAMPLES
+    }
+
+    /// Flow-stopped detector: true once flow has stayed stopped for `flow_stop_hold`.
+    /// A pour that hasn't started yet doesn't count - flow must have been seen this shot
+    fn detect_flow_stop(context: &mut BrewContext, scale_data: &ScaleData) -> bool {
+        if !Self::flow_stopped(context, scale_data) {
+            context.flow_seen = true;
+            context.flow_stopped_since = None;
+            return false;
+        }
+        if !context.flow_seen {
+            return false;
+        }
+        let since = *context.flow_stopped_since.get_or_insert(scale_data.received_at);
+        scale_data.received_at.saturating_duration_since(since) >= context.flow_stop_hold
+    }
+
+    /// Time mode: the shot has run for `brew_duration`
+    fn brew_time_up(context: &BrewContext, now: Instant) -> bool {
+        context.stop_mode == StopMode::Time
+            && context
+                .brew_started_at
+                .is_some_and(|start| now.saturating_duration_since(start) >= context.brew_duration)
+    }
+
+    /// Flow profiling: steer relay power so measured flow tracks the shot's curve
+    fn follow_flow_profile(context: &mut BrewContext, data: &ScaleData) {
+        let (Some((profile, pid)), Some(started_at)) =
+            (&mut context.profiled_shot, context.brew_started_at)
+        else {
+            return;
+        };
+        let elapsed_ms = data.received_at.saturating_duration_since(started_at).as_millis() as u32;
+        let target = profile.target_flow(elapsed_ms);
+        let power = pid.update(target, data.flow_rate_g_per_s, data.received_at);
+
+        let changed = context
+            .flow_profile_power
+            .map_or(true, |last| (power - last).abs() >= FLOW_PROFILE_MIN_POWER_STEP);
+        if changed {
+            debug!(
+                "🎚️ Flow {:.2}g/s, profile wants {:.2}g/s - power {:.0}%",
+                data.flow_rate_g_per_s,
+                target,
+                power * 100.0
+            );
+            context.flow_profile_power = Some(power);
+            context.outputs.push(BrewOutput::RelayPower(power));
+        }
+    }
+
+    /// Drop the shot's flow loop - `start_shot` builds a fresh one for the next shot
+    fn end_profiled_shot(context: &mut BrewContext) {
+        context.profiled_shot = None;
+        context.flow_profile_power = None;
+    }
+
+    /// Start the timer and pump and go to Brewing
+    fn begin_brew(context: &mut BrewContext) -> Response<State> {
+        context.brew_tare_sent_at = None;
+        context.predictive_preview_sent = false;
+        Self::start_shot(context);
+        context.outputs.push(BrewOutput::StartTimer);
+        context.outputs.push(BrewOutput::RelayOn);
+        context.outputs.push(BrewOutput::BrewingStarted);
+        Response::Transition(State::brewing())
+    }
+
+    /// Why an interrupted shot can't carry on, None when it can: the scale must be back inside
+    /// the grace window with its timer still running and the cup where the shot left it
+    fn resume_refusal(
+        context: &BrewContext,
+        (interrupted_at, weight_g): (Instant, f32),
+        data: &ScaleData,
+    ) -> Option<&'static str> {
+        if Instant::now().saturating_duration_since(interrupted_at) > context.reconnect_grace {
+            Some("the scale was gone too long")
+        } else if !data.timer_running {
+            Some("the scale timer stopped")
+        } else if (data.weight_g - weight_g).abs() > RESUME_MAX_WEIGHT_DRIFT_G {
+            Some("the weight changed while the scale was gone")
+        } else if context.overshoot_pending_stop_time.is_some() {
+            Some("a stop was already scheduled")
+        } else if context.stop_mode == StopMode::Weight
+            && data.weight_g >= Self::settled_aim(context)
+        {
+            Some("the target was already reached")
+        } else {
+            None
+        }
+    }
+
+    /// Pick an interrupted shot back up - its start time, target and learning never left, only
+    /// the flow loop starts over
+    fn resume_brew(context: &mut BrewContext) -> Response<State> {
+        context.flow_stopped_since = None;
+        Self::arm_flow_profile(context);
+        context.outputs.push(BrewOutput::RelayOn);
+        context.outputs.push(BrewOutput::BrewResumed);
+        Response::Transition(State::brewing())
+    }
+
+    /// Reset the scale timer and go back to Idle without finishing the shot - no overshoot is
+    /// recorded, and a scheduled predictive stop can't fire into the next one
+    fn reset_shot(context: &mut BrewContext) -> Response<State> {
+        Self::clear_shot(context);
+        context.outputs.push(BrewOutput::ResetTimer);
+        context.outputs.push(BrewOutput::BrewingReset);
+        Self::auto_tare_brewing_finished(context, context.current_weight);
+        Response::Transition(State::idle())
+    }
+
+    /// Forget everything the current shot has in flight
+    fn clear_shot(context: &mut BrewContext) {
+        context.brew_tare_sent_at = None;
+        context.brew_started_at = None;
+        context.settle_start_time = None;
+        context.overshoot_pending_stop_time = None;
+        context.overshoot_pending_predicted_stop = false;
+        context.flow_seen = false;
+        context.flow_drop_samples = 0;
+        context.flow_stopped_since = None;
+        context.flow_stop_detected = false;
+        context.settle_weights.clear();
+    }
+
+    /// Reset per-shot stop detection when brewing starts
+    fn start_shot(context: &mut BrewContext) {
+        context.brew_started_at = Some(Instant::now());
+        // A previous shot that never settled doesn't get to record this one's overshoot
+        context.overshoot_pending_predicted_stop = false;
+        context.flow_seen = false;
+        context.flow_drop_samples = 0;
+        context.flow_stopped_since = None;
+        context.flow_stop_detected = false;
+        context.settle_weights.clear();
+        context.interrupted_brew = None;
+        Self::arm_flow_profile(context);
+        if context.dose_weighing {
+            // Brewing abandons an unconfirmed dose
+            context.dose_weighing = false;
+            context.dose_candidate = None;
+            context.outputs.push(BrewOutput::DoseStatusChanged);
+        }
+    }
+
+    /// Build this shot's flow loop from the current profile, if there is one
+    fn arm_flow_profile(context: &mut BrewContext) {
+        context.profiled_shot = context.flow_profile.clone().map(|profile| {
+            let pid = FlowPid::new(profile.gains);
+            (profile, pid)
+        });
+        context.flow_profile_power = None;
+    }
+
+    /// Dose weighing: a stable weight above the empty threshold becomes the pending dose
+    fn track_dose(context: &mut BrewContext, weight: f32) {
+        if !Self::is_weight_stable(context, weight) {
+            return;
+        }
+        let candidate = (weight > context.auto_tare_empty_threshold).then_some(weight);
+        let changed = match (context.dose_candidate, candidate) {
+            (Some(old), Some(new)) => (new - old).abs() > context.auto_tare_stability_threshold,
+            (None, None) => false,
+            _ => true,
+        };
+        if changed {
+            context.dose_candidate = candidate;
+            context.outputs.push(BrewOutput::DoseStatusChanged);
+        }
+    }
+
     /// Schedule a delayed stop with compensation
     fn schedule_delayed_stop(context: &mut BrewContext, delay_seconds: f32) {
         let compensated_delay = Self::get_compensated_delay(context, delay_seconds);
         let delay_duration = Duration::from_millis((compensated_delay * 1000.0) as u64);
         
         context.overshoot_pending_stop_time = Some(Instant::now() + delay_duration);
+        context.flow_drop_samples = 0;
         context.outputs.push(BrewOutput::PredictiveStopScheduled { 
             delay_ms: (compensated_delay * 1000.0) as i32,
             predicted_weight: 0.0 // Will be filled in by caller
@@ -1357,6 +1925,16 @@ impl BrewStateMachine {
         );
     }
 
+    /// Drop a scheduled stop whose prediction no longer holds. Its overshoot would say nothing
+    /// about the stop delay, so it isn't learned either
+    fn cancel_delayed_stop(context: &mut BrewContext, flow_rate: f32) {
+        context.overshoot_pending_stop_time = None;
+        context.overshoot_pending_predicted_stop = false;
+        context.flow_drop_samples = 0;
+        context.outputs.push(BrewOutput::PredictiveStopCancelled { flow_rate });
+        info!("🚫 Scheduled stop cancelled - flow dropped to {:.1}g/s, ending shot", flow_rate);
+    }
+
     /// Record overshoot and update learning using EWMA algorithm
     fn record_overshoot_learning(context: &mut BrewContext, overshoot: f32) {
         if !context.overshoot_pending_predicted_stop {
@@ -1365,7 +1943,26 @@ impl BrewStateMachine {
         }
 
         context.overshoot_pending_predicted_stop = false;
-        
+
+        // A knocked cup or a gusher says nothing about the machine - keep it out of the EWMA.
+        // Only a streak is let through, in case the setup really has changed
+        if let Some((mean, std_dev)) = Self::overshoot_outlier(context, overshoot) {
+            if context.overshoot_outlier_streak < OVERSHOOT_OUTLIER_MAX_STREAK {
+                context.overshoot_outlier_streak += 1;
+                info!(
+                    "🚫 Overshoot {:.1}g is an outlier (recent {:.1}±{:.1}g) - not learned",
+                    overshoot, mean, std_dev
+                );
+                context.outputs.push(BrewOutput::OvershootOutlierRejected {
+                    overshoot,
+                    mean,
+                    std_dev,
+                });
+                return;
+            }
+        }
+        context.overshoot_outlier_streak = 0;
+
         // Add to history
         let measurement = OvershootMeasurement {
             overshoot,
@@ -1399,6 +1996,7 @@ impl BrewStateMachine {
             ewma: context.overshoot_ewma,
             confidence: context.overshoot_confidence_score,
         });
+        Self::apply_predictive_stop_auto(context);
 
         info!(
             "📊 Overshoot learning: {:.1}g -> ewma={:.1}g, delay={}ms, confidence={:.1}%, brews={}",
@@ -1426,6 +2024,13 @@ impl BrewStateMachine {
                 "🔼 Overshooting by {:.1}g, increasing delay by {}ms: {}ms -> {}ms",
                 context.overshoot_ewma, adjustment, old_delay, context.overshoot_stop_delay_ms
             );
+        } else if context.overshoot_ewma < -0.5 && Self::overshoot_warmup_active(context) {
+            // Warmup shots stop early on purpose - don't learn a shorter delay from them
+            debug!(
+                "🐢 Warmup undershoot of {:.1}g expected, keeping delay at {}ms",
+                context.overshoot_ewma.abs(),
+                context.overshoot_stop_delay_ms
+            );
         } else if context.overshoot_ewma < -0.5 {
             // Undershooting - stop later (decrease delay)
             context.overshoot_stop_delay_ms -= adjustment;
@@ -1444,41 +2049,27 @@ impl BrewStateMachine {
 
     /// Update learning confidence based on consistency
     fn update_overshoot_confidence(context: &mut BrewContext) {
-        if context.overshoot_history.len() < 3 {
+        // Calculate consistency (lower variance = higher confidence)
+        let Some((_, std_dev)) = Self::overshoot_spread(context) else {
             context.overshoot_confidence_score = 0.0;
             return;
-        }
-
-        // Calculate consistency (lower variance = higher confidence)
-        let mut overshoots = heapless::Vec::<f32, OVERSHOOT_HISTORY_SIZE>::new();
-        for measurement in context.overshoot_history.iter() {
-            let _ = overshoots.push(measurement.overshoot);
-        }
-
-        let mean: f32 = overshoots.iter().sum::<f32>() / overshoots.len() as f32;
-        let variance: f32 = overshoots.iter()
-            .map(|&x| (x - mean).powi(2))
-            .sum::<f32>() / overshoots.len() as f32;
-
-        let std_dev = variance.sqrt();
+        };
 
         // Convert consistency to confidence (lower std_dev = higher confidence)
         let consistency_score = (3.0f32 - std_dev).max(0.0) / 2.5; // 0.0 to 1.0
 
         // Experience factor: more brews = higher confidence
-        let experience_factor = (context.overshoot_brew_count as f32 / 20.0).min(1.0);
+        let experience_factor = context
+            .overshoot_learning
+            .experience_factor(context.overshoot_brew_count);
 
         // Combined confidence
         context.overshoot_confidence_score = (consistency_score * experience_factor).min(1.0);
 
         // Update learning rate based on confidence
-        context.overshoot_learning_rate = if context.overshoot_confidence_score > 0.8 {
-            0.1 // Slow learning when confident
-        } else if context.overshoot_confidence_score > 0.5 {
-            0.2 // Medium learning
-        } else {
-            0.3 // Fast learning when uncertain
-        };
+        context.overshoot_learning_rate = context
+            .overshoot_learning
+            .learning_rate(context.overshoot_confidence_score);
 
         debug!(
             "Confidence update: consistency={:.2}, experience={:.2}, combined={:.2}, learning_rate={:.1}%",
@@ -1487,19 +2078,115 @@ impl BrewStateMachine {
         );
     }
 
+    /// Mean and standard deviation of the recent overshoots, None until there are 3
+    fn overshoot_spread(context: &BrewContext) -> Option<(f32, f32)> {
+        if context.overshoot_history.len() < 3 {
+            return None;
+        }
+
+        let mut overshoots = heapless::Vec::<f32, OVERSHOOT_HISTORY_SIZE>::new();
+        for measurement in context.overshoot_history.iter() {
+            let _ = overshoots.push(measurement.overshoot);
+        }
+
+        let mean: f32 = overshoots.iter().sum::<f32>() / overshoots.len() as f32;
+        let variance: f32 = overshoots.iter()
+            .map(|&x| (x - mean).powi(2))
+            .sum::<f32>() / overshoots.len() as f32;
+
+        Some((mean, variance.sqrt()))
+    }
+
+    /// The recent mean and spread if `overshoot` lies more than `OVERSHOOT_OUTLIER_STD_DEVS`
+    /// standard deviations from them
+    fn overshoot_outlier(context: &BrewContext, overshoot: f32) -> Option<(f32, f32)> {
+        let (mean, std_dev) = Self::overshoot_spread(context)?;
+        let limit = OVERSHOOT_OUTLIER_STD_DEVS * std_dev.max(OVERSHOOT_OUTLIER_MIN_SPREAD_G);
+        ((overshoot - mean).abs() > limit).then_some((mean, std_dev))
+    }
+
     /// Reset overshoot controller to defaults
     fn reset_overshoot_controller(context: &mut BrewContext) {
         info!("🔄 Resetting overshoot controller to defaults");
         context.overshoot_history.clear();
-        context.overshoot_stop_delay_ms = 500;
+        context.overshoot_stop_delay_ms = OVERSHOOT_INITIAL_STOP_DELAY_MS;
         context.overshoot_pending_predicted_stop = false;
         context.overshoot_ewma = 0.0;
         context.overshoot_confidence_score = 0.0;
         context.overshoot_brew_count = 0;
-        context.overshoot_learning_rate = 0.3;
+        context.overshoot_learning_rate = context.overshoot_learning.uncertain_rate;
+        context.overshoot_outlier_streak = 0;
         context.overshoot_pending_stop_time = None;
         
         context.outputs.push(BrewOutput::OvershootControllerReset);
+        Self::apply_predictive_stop_auto(context);
+    }
+
+    /// The learned model as stored in an overshoot profile
+    fn overshoot_model(context: &BrewContext) -> OvershootModel {
+        OvershootModel {
+            stop_delay_ms: context.overshoot_stop_delay_ms,
+            ewma_g: context.overshoot_ewma,
+            confidence: context.overshoot_confidence_score,
+            brew_count: context.overshoot_brew_count,
+            recent_overshoots_g: context
+                .overshoot_history
+                .iter()
+                .map(|measurement| measurement.overshoot)
+                .collect(),
+        }
+    }
+
+    /// Make a profile's model the one brews learn into
+    fn load_overshoot_model(context: &mut BrewContext, model: &OvershootModel) {
+        context.overshoot_stop_delay_ms = model.stop_delay_ms;
+        context.overshoot_ewma = model.ewma_g;
+        context.overshoot_confidence_score = model.confidence;
+        context.overshoot_brew_count = model.brew_count;
+        context.overshoot_history.clear();
+        let skip = model.recent_overshoots_g.len().saturating_sub(OVERSHOOT_HISTORY_SIZE);
+        for &overshoot in &model.recent_overshoots_g[skip..] {
+            let _ = context.overshoot_history.push(OvershootMeasurement {
+                overshoot,
+                timestamp: Instant::now(),
+            });
+        }
+        context.overshoot_learning_rate =
+            context.overshoot_learning.learning_rate(model.confidence);
+        context.overshoot_pending_predicted_stop = false;
+        context.overshoot_outlier_streak = 0;
+    }
+
+    /// Learning has enough consistent data for its predictions to be trusted
+    fn overshoot_learning_ready(context: &BrewContext) -> bool {
+        context.overshoot_brew_count >= OVERSHOOT_READY_BREWS
+            && context.overshoot_confidence_score > OVERSHOOT_READY_CONFIDENCE
+    }
+
+    /// Auto mode: switch predictive stop on once learning is ready, and off again only if
+    /// confidence falls below the lower disable bar
+    fn apply_predictive_stop_auto(context: &mut BrewContext) {
+        if !context.predictive_stop_auto {
+            return;
+        }
+        let enabled = if context.predictive_stop_enabled {
+            context.overshoot_confidence_score >= PREDICTIVE_AUTO_DISABLE_CONFIDENCE
+        } else {
+            Self::overshoot_learning_ready(context)
+        };
+        if enabled != context.predictive_stop_enabled {
+            context.predictive_stop_enabled = enabled;
+            info!(
+                "🔮 Predictive stop auto-{}: confidence {:.0}% after {} brews",
+                if enabled { "enabled" } else { "disabled" },
+                context.overshoot_confidence_score * 100.0,
+                context.overshoot_brew_count
+            );
+            context.outputs.push(BrewOutput::PredictiveStopAutoChanged {
+                enabled,
+                confidence: context.overshoot_confidence_score,
+            });
+        }
     }
 
     /// Check if delayed stop timeout has occurred
@@ -1512,10 +2199,56 @@ impl BrewStateMachine {
     }
 }
 
+/// Point-in-time view of a brew for observers outside the state machine - copied out of the
+/// context so the status API and replay don't depend on how the context is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct BrewSnapshot {
+    pub state: SystemState,
+    pub current_weight_g: f32,
+    /// Total across all cups - what the brew stops at
+    pub target_weight_g: f32,
+    /// Scale timer, which starts and ends a shot
+    pub timer_running: bool,
+    pub auto_tare_state: AutoTareState,
+    /// Learned stop delay the next predictive stop will use
+    pub stop_delay_ms: i32,
+    /// A predictive stop is scheduled and hasn't fired yet
+    pub stop_pending: bool,
+}
+
+/// Dose weighing mode as reported over the API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DoseStatus {
+    /// Idle is in the dose-weighing sub-mode - auto-tare is paused
+    pub weighing: bool,
+    /// Stable reading that `confirm_dose` would lock in
+    pub pending_g: Option<f32>,
+}
+
+/// Overshoot learner state as reported over the API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct OvershootStatus {
+    /// Smoothed overshoot past the target (negative = stopping short)
+    pub ewma_g: f32,
+    /// 0.0-1.0, grows as consistent measurements accumulate
+    pub confidence: f32,
+    pub brew_count: u32,
+    /// How far ahead of the predicted target the relay is cut
+    pub stop_delay_ms: i32,
+    /// Enough data for predictions to be trusted
+    pub ready: bool,
+    /// Early brews are stopping short on purpose (see `set_overshoot_warmup_brews`)
+    pub warmup_active: bool,
+    /// Weight the next overshoot gets in the EWMA (see `OvershootLearningSchedule`)
+    pub learning_rate: f32,
+}
+
 // Main interface for the hierarchical state machine
 pub struct BrewController {
     machine: statig::prelude::StateMachine<BrewStateMachine>,
     context: BrewContext,
+    /// Inactive profiles are kept here; the active one's entry goes stale as the context learns
+    overshoot_profiles: OvershootProfiles,
 }
 
 impl BrewController {
@@ -1523,6 +2256,7 @@ impl BrewController {
         Self {
             machine: BrewStateMachine::default().state_machine(),
             context: BrewContext::default(),
+            overshoot_profiles: OvershootProfiles::default(),
         }
     }
 
@@ -1537,9 +2271,35 @@ impl BrewController {
         // Handle the input with context
         let _ = self.machine.handle_with_context(&input, &mut self.context);
 
+        // The flow-stopped detector runs on scale data - feed its verdict back in as an input
+        if std::mem::take(&mut self.context.flow_stop_detected) {
+            let _ = self
+                .machine
+                .handle_with_context(&BrewInput::FlowStopped, &mut self.context);
+        }
+
         // Capture new state after transition
         let new_state = self.get_system_state();
 
+        // However the shot ended, its flow loop ends with it - nothing carries into the next
+        if previous_state == SystemState::Brewing && new_state != SystemState::Brewing {
+            BrewStateMachine::end_profiled_shot(&mut self.context);
+        }
+        // Stopping while the scale is away means the interrupted shot must not come back
+        if matches!(
+            input,
+            BrewInput::UserCommand(UserEvent::StopBrewing)
+                | BrewInput::UserCommand(UserEvent::ResetTimer)
+                | BrewInput::EmergencyStop
+                | BrewInput::DisableSystem
+        ) {
+            self.context.interrupted_brew = None;
+        }
+        // A pre-brew tare left behind by a disconnect or the killswitch must not start a brew later
+        if new_state != SystemState::Idle {
+            self.context.brew_tare_sent_at = None;
+        }
+
         // Only emit StateChanged if the state actually changed
         if previous_state != new_state {
             self.context.outputs.push(BrewOutput::StateChanged {
@@ -1615,21 +2375,296 @@ impl BrewController {
         }
     }
 
-    /// Check if auto-tare is enabled
-    pub fn is_auto_tare_enabled(&self) -> bool {
-        self.context.auto_tare_enabled
+    /// Enable/disable predictive stop - when disabled predictions are reported as previews only
+    pub fn set_predictive_stop_enabled(&mut self, enabled: bool) {
+        self.context.predictive_stop_enabled = enabled;
     }
 
-    /// Get current auto-tare state
-    pub fn get_auto_tare_state(&self) -> AutoTareState {
-        self.context.auto_tare_state
+    pub fn is_predictive_stop_enabled(&self) -> bool {
+        self.context.predictive_stop_enabled
     }
 
-    /// Reset auto-tare state (useful for debugging or manual reset)
+    /// Let overshoot learning readiness drive predictive stop. Returns the
+    /// `PredictiveStopAutoChanged` output if turning auto mode on flips it straight away
+    pub fn set_predictive_stop_auto(&mut self, enabled: bool) -> heapless::Vec<BrewOutput, 10> {
+        self.context.outputs.clear();
+        self.context.predictive_stop_auto = enabled;
+        BrewStateMachine::apply_predictive_stop_auto(&mut self.context);
+        std::mem::take(&mut self.context.outputs)
+    }
+
+    pub fn is_predictive_stop_auto(&self) -> bool {
+        self.context.predictive_stop_auto
+    }
+
+    /// Choose what ends a shot - applies from the next sample, even mid-brew
+    pub fn set_stop_mode(&mut self, mode: StopMode) {
+        self.context.stop_mode = mode;
+    }
+
+    pub fn get_stop_mode(&self) -> StopMode {
+        self.context.stop_mode
+    }
+
+    /// Scale timer time at the start of a shot during which reaching the target doesn't stop it
+    pub fn set_brew_startup_ignore_ms(&mut self, ignore_ms: u32) {
+        self.context.brew_startup_ignore_ms = ignore_ms;
+    }
+
+    /// Set how long flow must stay stopped before it counts (see `detect_flow_stop`)
+    pub fn set_flow_stop_hold_ms(&mut self, hold_ms: u32) -> Result<(), String> {
+        if hold_ms > MAX_FLOW_STOP_HOLD_MS {
+            return Err(format!(
+                "Flow stop hold must be at most {}ms, got {}ms",
+                MAX_FLOW_STOP_HOLD_MS, hold_ms
+            ));
+        }
+        self.context.flow_stop_hold = Duration::from_millis(hold_ms as u64);
+        Ok(())
+    }
+
+    pub fn get_flow_stop_hold_ms(&self) -> u32 {
+        self.context.flow_stop_hold.as_millis() as u32
+    }
+
+    /// Set how long after a mid-brew disconnect a reconnect may resume the shot, 0 to end it
+    pub fn set_scale_reconnect_grace_ms(&mut self, grace_ms: u32) -> Result<(), String> {
+        if grace_ms > MAX_SCALE_RECONNECT_GRACE_MS {
+            return Err(format!(
+                "Scale reconnect grace must be at most {}ms, got {}ms",
+                MAX_SCALE_RECONNECT_GRACE_MS, grace_ms
+            ));
+        }
+        self.context.reconnect_grace = Duration::from_millis(grace_ms as u64);
+        Ok(())
+    }
+
+    pub fn get_scale_reconnect_grace_ms(&self) -> u32 {
+        self.context.reconnect_grace.as_millis() as u32
+    }
+
+    /// Set how still the weight must be before settling finishes early
+    pub fn set_settle_stability_g(&mut self, stability_g: f32) -> Result<(), String> {
+        if !stability_g.is_finite() || stability_g <= 0.0 || stability_g > MAX_SETTLE_STABILITY_G {
+            return Err(format!(
+                "Settle stability must be above 0g and at most {}g, got {}g",
+                MAX_SETTLE_STABILITY_G, stability_g
+            ));
+        }
+        self.context.settle_stability_g = stability_g;
+        Ok(())
+    }
+
+    pub fn get_settle_stability_g(&self) -> f32 {
+        self.context.settle_stability_g
+    }
+
+    /// Set the shot length used in Time mode
+    pub fn set_brew_time_ms(&mut self, brew_time_ms: u32) -> Result<(), String> {
+        if !(MIN_BREW_TIME_MS..=MAX_BREW_TIME_MS).contains(&brew_time_ms) {
+            return Err(format!(
+                "Brew time must be {}-{}ms, got {}ms",
+                MIN_BREW_TIME_MS, MAX_BREW_TIME_MS, brew_time_ms
+            ));
+        }
+        self.context.brew_duration = Duration::from_millis(brew_time_ms as u64);
+        Ok(())
+    }
+
+    pub fn get_brew_time_ms(&self) -> u32 {
+        self.context.brew_duration.as_millis() as u32
+    }
+
+    /// Set the flow curve to follow from the next shot - None brews at full power
+    pub fn set_flow_profile(&mut self, profile: Option<FlowProfile>) -> Result<(), String> {
+        if let Some(profile) = &profile {
+            profile.validate()?;
+        }
+        self.context.flow_profile = profile;
+        Ok(())
+    }
+
+    pub fn get_flow_profile(&self) -> Option<&FlowProfile> {
+        self.context.flow_profile.as_ref()
+    }
+
+    /// Tare on StartBrewing and start once tare verification confirms zero, or after
+    /// `verify_timeout_ms` regardless. 0 means verification is off - tare and start at once
+    pub fn set_tare_before_brew(&mut self, enabled: bool, verify_timeout_ms: u64) {
+        self.context.tare_before_brew = enabled;
+        self.context.tare_before_brew_timeout = Duration::from_millis(verify_timeout_ms);
+        if !enabled {
+            self.context.brew_tare_sent_at = None;
+        }
+    }
+
+    /// Check if auto-tare is enabled
+    pub fn is_auto_tare_enabled(&self) -> bool {
+        self.context.auto_tare_enabled
+    }
+
+    /// Get current auto-tare state
+    pub fn get_auto_tare_state(&self) -> AutoTareState {
+        self.context.auto_tare_state
+    }
+
+    /// Replace all auto-tare detection parameters after validating them
+    pub fn set_auto_tare_params(&mut self, params: AutoTareParams) -> Result<(), String> {
+        params.validate()?;
+        self.context.auto_tare_empty_threshold = params.empty_threshold_g;
+        self.context.auto_tare_stability_threshold = params.stability_threshold_g;
+        self.context.auto_tare_stable_readings_needed = params.stable_readings;
+        Ok(())
+    }
+
+    /// Set the weight (g) at or below which the scale counts as empty
+    pub fn set_auto_tare_empty_threshold(&mut self, threshold_g: f32) -> Result<(), String> {
+        let mut params = self.get_auto_tare_params();
+        params.empty_threshold_g = threshold_g;
+        self.set_auto_tare_params(params)
+    }
+
+    /// Set how many consecutive readings must agree before weight counts as stable
+    pub fn set_auto_tare_stable_readings(&mut self, readings: usize) -> Result<(), String> {
+        let mut params = self.get_auto_tare_params();
+        params.stable_readings = readings;
+        self.set_auto_tare_params(params)
+    }
+
+    /// Apply a sensitivity preset and restart detection with the new window
+    pub fn set_auto_tare_profile(&mut self, profile: AutoTareProfile) {
+        let params = profile.params();
+        self.context.auto_tare_empty_threshold = params.empty_threshold_g;
+        self.context.auto_tare_stability_threshold = params.stability_threshold_g;
+        self.context.auto_tare_stable_readings_needed = params.stable_readings;
+        self.reset_auto_tare();
+    }
+
+    /// Current auto-tare detection parameters
+    pub fn get_auto_tare_params(&self) -> AutoTareParams {
+        AutoTareParams {
+            empty_threshold_g: self.context.auto_tare_empty_threshold,
+            stability_threshold_g: self.context.auto_tare_stability_threshold,
+            stable_readings: self.context.auto_tare_stable_readings_needed,
+        }
+    }
+
+    /// Set how long after a brew auto-tare stays suppressed
+    pub fn set_auto_tare_cooldown_secs(&mut self, secs: u32) -> Result<(), String> {
+        if secs > AUTO_TARE_MAX_BREWING_COOLDOWN_SECS {
+            return Err(format!(
+                "Auto-tare cooldown must be at most {}s, got {}s",
+                AUTO_TARE_MAX_BREWING_COOLDOWN_SECS, secs
+            ));
+        }
+        self.context.auto_tare_brewing_cooldown = Duration::from_secs(secs as u64);
+        Ok(())
+    }
+
+    /// Set how long a placed object must hold still before auto-tare fires
+    pub fn set_auto_tare_hold_ms(&mut self, hold_ms: u32) -> Result<(), String> {
+        if hold_ms > AUTO_TARE_MAX_HOLD_MS {
+            return Err(format!(
+                "Auto-tare hold must be at most {}ms, got {}ms",
+                AUTO_TARE_MAX_HOLD_MS, hold_ms
+            ));
+        }
+        self.context.auto_tare_hold = Duration::from_millis(hold_ms as u64);
+        Ok(())
+    }
+
+    /// Set the predictive stop window (see `calculate_prediction_window`)
+    pub fn set_prediction_window(&mut self, safety_margin_ms: u32, multiplier: f32) -> Result<(), String> {
+        if safety_margin_ms > PREDICTION_MAX_SAFETY_MARGIN_MS {
+            return Err(format!(
+                "Prediction safety margin must be at most {}ms, got {}ms",
+                PREDICTION_MAX_SAFETY_MARGIN_MS, safety_margin_ms
+            ));
+        }
+        if !multiplier.is_finite() || multiplier <= 1.0 || multiplier > PREDICTION_MAX_WINDOW_MULTIPLIER {
+            return Err(format!(
+                "Prediction window multiplier must be above 1 and at most {:.0}, got {}",
+                PREDICTION_MAX_WINDOW_MULTIPLIER, multiplier
+            ));
+        }
+        self.context.prediction_safety_margin_ms = safety_margin_ms;
+        self.context.prediction_window_multiplier = multiplier;
+        Ok(())
+    }
+
+    /// Set the flow and timing gates for predictive stop and overshoot recording
+    pub fn set_prediction_thresholds(
+        &mut self,
+        min_flow_for_prediction: f32,
+        flow_stopped_threshold: f32,
+        prediction_start_delay_ms: u32,
+    ) -> Result<(), String> {
+        if !min_flow_for_prediction.is_finite()
+            || min_flow_for_prediction < 0.0
+            || min_flow_for_prediction > PREDICTION_MAX_MIN_FLOW_G_PER_S
+        {
+            return Err(format!(
+                "Minimum prediction flow must be between 0 and {:.0}g/s, got {}",
+                PREDICTION_MAX_MIN_FLOW_G_PER_S, min_flow_for_prediction
+            ));
+        }
+        if !flow_stopped_threshold.is_finite()
+            || flow_stopped_threshold <= 0.0
+            || flow_stopped_threshold > MAX_FLOW_STOPPED_THRESHOLD_G_PER_S
+        {
+            return Err(format!(
+                "Flow stopped threshold must be between 0 and {:.0}g/s, got {}",
+                MAX_FLOW_STOPPED_THRESHOLD_G_PER_S, flow_stopped_threshold
+            ));
+        }
+        if prediction_start_delay_ms > PREDICTION_MAX_START_DELAY_MS {
+            return Err(format!(
+                "Prediction start delay must be at most {}ms, got {}ms",
+                PREDICTION_MAX_START_DELAY_MS, prediction_start_delay_ms
+            ));
+        }
+        self.context.prediction_min_flow = min_flow_for_prediction;
+        self.context.flow_stopped_threshold = flow_stopped_threshold;
+        self.context.prediction_start_delay_ms = prediction_start_delay_ms;
+        Ok(())
+    }
+
+    /// Current predictive stop gates (min flow g/s, flow stopped g/s, start delay ms)
+    pub fn get_prediction_thresholds(&self) -> (f32, f32, u32) {
+        (
+            self.context.prediction_min_flow,
+            self.context.flow_stopped_threshold,
+            self.context.prediction_start_delay_ms,
+        )
+    }
+
+    /// Current predictive stop window parameters (safety margin ms, multiplier)
+    pub fn get_prediction_window(&self) -> (u32, f32) {
+        (
+            self.context.prediction_safety_margin_ms,
+            self.context.prediction_window_multiplier,
+        )
+    }
+
+    pub fn get_auto_tare_cooldown_secs(&self) -> u32 {
+        self.context.auto_tare_brewing_cooldown.as_secs() as u32
+    }
+
+    pub fn get_auto_tare_hold_ms(&self) -> u32 {
+        self.context.auto_tare_hold.as_millis() as u32
+    }
+
+    /// Preset matching the current parameters, if any
+    pub fn get_auto_tare_profile(&self) -> Option<AutoTareProfile> {
+        AutoTareProfile::matching(&self.get_auto_tare_params())
+    }
+
+    /// Reset auto-tare state (useful for debugging or manual reset)
     pub fn reset_auto_tare(&mut self) {
         self.context.auto_tare_state = AutoTareState::Empty;
         self.context.auto_tare_weight_history.clear();
         self.context.auto_tare_stable_weight = 0.0;
+        self.context.auto_tare_hold_since = None;
     }
 
     /// Reset overshoot controller
@@ -1637,6 +2672,59 @@ impl BrewController {
         self.handle_input(BrewInput::OvershootReset)
     }
 
+    /// Switch the overshoot model brews learn into, starting a fresh one for a new name.
+    /// Returns the auto predictive stop switch if the new model's confidence calls for one
+    pub fn select_overshoot_profile(
+        &mut self,
+        name: &str,
+    ) -> Result<heapless::Vec<BrewOutput, 10>, String> {
+        let name = OvershootProfiles::validate_name(name)?;
+        if matches!(self.get_system_state(), SystemState::Brewing | SystemState::Settling) {
+            return Err("Overshoot profile can't change during a shot".to_string());
+        }
+        let names = self.overshoot_profiles.names();
+        if !names.iter().any(|existing| existing == name) && names.len() >= MAX_OVERSHOOT_PROFILES {
+            return Err(format!(
+                "At most {} overshoot profiles, can't add {}",
+                MAX_OVERSHOOT_PROFILES, name
+            ));
+        }
+
+        self.context.outputs.clear();
+        let current = BrewStateMachine::overshoot_model(&self.context);
+        let previous = std::mem::replace(&mut self.overshoot_profiles.active, name.to_string());
+        self.overshoot_profiles.models.insert(previous, current);
+        let model = self.overshoot_profiles.models.get(name).cloned().unwrap_or_default();
+        BrewStateMachine::load_overshoot_model(&mut self.context, &model);
+        BrewStateMachine::apply_predictive_stop_auto(&mut self.context);
+        Ok(std::mem::take(&mut self.context.outputs))
+    }
+
+    /// Restore persisted profiles and load the active one's model
+    pub fn restore_overshoot_profiles(
+        &mut self,
+        profiles: OvershootProfiles,
+    ) -> Result<(), String> {
+        OvershootProfiles::validate_name(&profiles.active)?;
+        let model = profiles.models.get(&profiles.active).cloned().unwrap_or_default();
+        BrewStateMachine::load_overshoot_model(&mut self.context, &model);
+        self.overshoot_profiles = profiles;
+        Ok(())
+    }
+
+    /// Every profile with the active one's current learning, as persisted
+    pub fn overshoot_profiles(&self) -> OvershootProfiles {
+        let mut profiles = self.overshoot_profiles.clone();
+        profiles
+            .models
+            .insert(profiles.active.clone(), BrewStateMachine::overshoot_model(&self.context));
+        profiles
+    }
+
+    pub fn active_overshoot_profile(&self) -> &str {
+        &self.overshoot_profiles.active
+    }
+
     /// Get current overshoot delay
     pub fn get_overshoot_delay_ms(&self) -> i32 {
         self.context.overshoot_stop_delay_ms
@@ -1653,18 +2741,112 @@ impl BrewController {
 
     /// Check if overshoot learning is ready (has enough data)
     pub fn is_overshoot_learning_ready(&self) -> bool {
-        self.context.overshoot_brew_count >= 3 && self.context.overshoot_confidence_score > 0.2
+        BrewStateMachine::overshoot_learning_ready(&self.context)
+    }
+
+    /// Set how many brews after an overshoot reset use the conservative warmup delay
+    pub fn set_overshoot_warmup_brews(&mut self, brews: u32) -> Result<(), String> {
+        if brews > OVERSHOOT_MAX_WARMUP_BREWS {
+            return Err(format!(
+                "Overshoot warmup must be at most {} brews, got {}",
+                OVERSHOOT_MAX_WARMUP_BREWS, brews
+            ));
+        }
+        self.context.overshoot_warmup_brews = brews;
+        Ok(())
+    }
+
+    pub fn get_overshoot_warmup_brews(&self) -> u32 {
+        self.context.overshoot_warmup_brews
+    }
+
+    /// Set where the settled weight should land relative to the target, negative to undershoot
+    pub fn set_overshoot_target_g(&mut self, grams: f32) -> Result<(), String> {
+        if !(-OVERSHOOT_MAX_TARGET_G..=OVERSHOOT_MAX_TARGET_G).contains(&grams) {
+            return Err(format!(
+                "Overshoot setpoint must be within ±{}g, got {}g",
+                OVERSHOOT_MAX_TARGET_G, grams
+            ));
+        }
+        self.context.overshoot_target_g = grams;
+        Ok(())
+    }
+
+    pub fn get_overshoot_target_g(&self) -> f32 {
+        self.context.overshoot_target_g
+    }
+
+    /// Set how aggressively overshoot learning adapts; the current rate follows immediately
+    pub fn set_overshoot_learning_schedule(
+        &mut self,
+        schedule: OvershootLearningSchedule,
+    ) -> Result<(), String> {
+        schedule.validate()?;
+        self.context.overshoot_learning = schedule;
+        self.context.overshoot_learning_rate =
+            schedule.learning_rate(self.context.overshoot_confidence_score);
+        Ok(())
+    }
+
+    pub fn get_overshoot_learning_schedule(&self) -> OvershootLearningSchedule {
+        self.context.overshoot_learning
+    }
+
+    /// Weight the next overshoot measurement gets in the EWMA
+    pub fn get_overshoot_learning_rate(&self) -> f32 {
+        self.context.overshoot_learning_rate
+    }
+
+    /// Early brews are deliberately stopping short while learning has little data
+    pub fn is_overshoot_warmup_active(&self) -> bool {
+        BrewStateMachine::overshoot_warmup_active(&self.context)
+    }
+
+    /// Copy of the brew as it stands - the way to observe the state machine from outside
+    pub fn snapshot(&self) -> BrewSnapshot {
+        BrewSnapshot {
+            state: self.get_system_state(),
+            current_weight_g: self.context.current_weight,
+            target_weight_g: self.context.target_weight,
+            timer_running: self.context.timer_running,
+            auto_tare_state: self.context.auto_tare_state,
+            stop_delay_ms: self.context.overshoot_stop_delay_ms,
+            stop_pending: self.context.overshoot_pending_stop_time.is_some(),
+        }
+    }
+
+    /// Whether dose weighing is on and the reading it would lock in
+    pub fn dose_status(&self) -> DoseStatus {
+        DoseStatus {
+            weighing: self.context.dose_weighing,
+            pending_g: self.context.dose_candidate,
+        }
+    }
+
+    /// Snapshot of the overshoot learner for the status API
+    pub fn overshoot_status(&self) -> OvershootStatus {
+        OvershootStatus {
+            ewma_g: self.context.overshoot_ewma,
+            confidence: self.context.overshoot_confidence_score,
+            brew_count: self.context.overshoot_brew_count,
+            stop_delay_ms: self.context.overshoot_stop_delay_ms,
+            ready: self.is_overshoot_learning_ready(),
+            warmup_active: self.is_overshoot_warmup_active(),
+            learning_rate: self.context.overshoot_learning_rate,
+        }
     }
 
     /// Get overshoot learning info as string for logging
     pub fn get_overshoot_learning_info(&self) -> String {
         format!(
-            "Learning: delay={}ms, ewma={:.1}g, confidence={:.1}%, brews={}, ready={}",
+            "Learning: delay={}ms (+{:.0}ms warmup), ewma={:.1}g, confidence={:.1}%, brews={}, ready={}, warmup={}",
             self.context.overshoot_stop_delay_ms,
+            BrewStateMachine::overshoot_warmup_bias_ms(&self.context),
             self.context.overshoot_ewma,
             self.context.overshoot_confidence_score * 100.0,
             self.context.overshoot_brew_count,
-            self.is_overshoot_learning_ready()
+            self.is_overshoot_learning_ready(),
+            self.is_overshoot_warmup_active()
         )
     }
 }
@@ -1701,4 +2883,1109 @@ impl BrewStateTransition {
     pub fn new(from: crate::types::BrewState, to: crate::types::BrewState) -> Self {
         Self { from, to }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{brewing_controller, idle_controller};
+    use crate::types::{DEFAULT_OVERSHOOT_PROFILE, MAX_OVERSHOOT_PROFILE_NAME_LEN};
+    use proptest::prelude::*;
+
+    fn sample(timestamp_ms: u32, weight_g: f32, flow_rate_g_per_s: f32) -> ScaleData {
+        ScaleData {
+            timestamp_ms,
+            weight_g,
+            flow_rate_g_per_s,
+            battery_percent: 100,
+            timer_running: true,
+            received_at: Instant::now(),
+        }
+    }
+
+    // Default first-shot window is [1.0s, 3.0s] (warmup included); 36g target with 1.5s to go is inside it
+    fn predicts(context: &BrewContext, data: &ScaleData) -> bool {
+        BrewStateMachine::should_trigger_predictive_stop(context, data, 36.0).is_some()
+    }
+
+    // Feeds idle readings 100ms apart and returns the weights at which auto-tare fired
+    fn auto_tare_fires(context: &mut BrewContext, weights: &[f32]) -> std::vec::Vec<f32> {
+        weights
+            .iter()
+            .enumerate()
+            .filter(|&(i, &weight)| {
+                let received_at = Instant::from_millis(i as u64 * 100);
+                BrewStateMachine::should_auto_tare(context, weight, received_at)
+            })
+            .map(|(_, &weight)| weight)
+            .collect()
+    }
+
+    // Portafilter lowered in two stages: a 400ms pause at 200g before it comes to rest at 250g
+    const SLOW_PLACEMENT: [f32; 20] = [
+        0.0, 0.0, 60.0, 140.0, 200.0, 200.1, 200.0, 200.2, 200.1, 230.0, 250.0, 250.1, 250.0,
+        250.1, 250.2, 250.1, 250.0, 250.1, 250.1, 250.0,
+    ];
+
+    #[test]
+    fn test_auto_tare_waits_out_slow_placement() {
+        let mut context = BrewContext::default();
+        assert_eq!(auto_tare_fires(&mut context, &SLOW_PLACEMENT), [250.1]);
+        assert_eq!(context.auto_tare_state, AutoTareState::StableObject);
+    }
+
+    #[test]
+    fn test_auto_tare_without_hold_tares_mid_placement() {
+        let mut context = BrewContext::default();
+        context.auto_tare_hold = Duration::from_millis(0);
+        assert_eq!(auto_tare_fires(&mut context, &SLOW_PLACEMENT), [200.1, 250.2]);
+    }
+
+    #[test]
+    fn test_auto_tare_hold_restarts_when_weight_leaves_band() {
+        let mut context = BrewContext::default();
+        // Settles for 400ms, nudged by 1g, then needs a full fresh hold
+        let weights = [
+            0.0, 0.0, 100.0, 100.0, 100.0, 100.0, 100.0, 101.0, 101.0, 101.0, 101.0, 101.0, 101.0,
+            101.0,
+        ];
+        assert_eq!(auto_tare_fires(&mut context, &weights), [101.0]);
+        assert_eq!(context.auto_tare_hold_since, Some(Instant::from_millis(700)));
+    }
+
+    // Feeds readings 100ms apart, recording each tare like the idle state does, and returns when
+    // (in ms) each one fired
+    fn auto_tare_times(context: &mut BrewContext, weights: &[f32]) -> std::vec::Vec<u64> {
+        let mut fired = std::vec::Vec::new();
+        for (i, &weight) in weights.iter().enumerate() {
+            let ms = i as u64 * 100;
+            let received_at = Instant::from_millis(ms);
+            if BrewStateMachine::should_auto_tare(context, weight, received_at) {
+                BrewStateMachine::record_auto_tare(context, received_at);
+                fired.push(ms);
+            }
+        }
+        fired
+    }
+
+    // Runs of steady readings - empty, objects of any size, and negative after a tared cup is lifted
+    fn weight_runs() -> impl Strategy<Value = std::vec::Vec<f32>> {
+        let weight = prop_oneof![-1.5f32..1.5, 3.0f32..500.0, -500.0f32..-3.0];
+        prop::collection::vec((weight, 1usize..15), 1..12).prop_map(|runs| {
+            runs.into_iter()
+                .flat_map(|(weight, len)| std::iter::repeat(weight).take(len))
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_auto_tare_never_fires_while_timer_running(weights in weight_runs()) {
+            let mut context = BrewContext::default();
+            context.timer_running = true;
+            prop_assert!(auto_tare_times(&mut context, &weights).is_empty());
+        }
+
+        #[test]
+        fn prop_auto_tare_never_fires_twice_within_cooldown(weights in weight_runs()) {
+            let mut context = BrewContext::default();
+            let fired = auto_tare_times(&mut context, &weights);
+            for pair in fired.windows(2) {
+                prop_assert!(pair[1] - pair[0] >= TARE_COOLDOWN_MS, "tares at {:?}", fired);
+            }
+        }
+
+        #[test]
+        fn prop_auto_tare_returns_to_empty_when_weight_is_back_near_zero(
+            weights in weight_runs(),
+            empty in -1.5f32..1.5,
+        ) {
+            let mut context = BrewContext::default();
+            // Long enough to outlast a tare cooldown and then fill the stability window
+            let settle = (TARE_COOLDOWN_MS / 100) as usize + TARE_STABILITY_COUNT;
+            let mut weights = weights;
+            weights.extend(std::iter::repeat(empty).take(settle));
+
+            auto_tare_times(&mut context, &weights);
+            prop_assert_eq!(context.auto_tare_state, AutoTareState::Empty);
+        }
+    }
+
+    #[test]
+    fn test_auto_tare_hold_validation() {
+        let mut controller = BrewController::new();
+        assert_eq!(controller.get_auto_tare_hold_ms(), AUTO_TARE_HOLD_MS);
+        assert!(controller.set_auto_tare_hold_ms(AUTO_TARE_MAX_HOLD_MS + 1).is_err());
+        assert!(controller.set_auto_tare_hold_ms(800).is_ok());
+        assert_eq!(controller.get_auto_tare_hold_ms(), 800);
+    }
+
+    #[test]
+    fn test_ble_connection_stages_drive_state() {
+        let mut controller = BrewController::new();
+        controller.handle_input(BrewInput::BleEnabled);
+        controller.handle_input(BrewInput::BleScanning);
+        controller.handle_input(BrewInput::BleConnecting);
+        assert_eq!(controller.get_system_state(), SystemState::BleConnecting);
+
+        // Connect timed out - back to scanning, then a second attempt succeeds
+        controller.handle_input(BrewInput::BleScanning);
+        assert_eq!(controller.get_system_state(), SystemState::BleScanning);
+        controller.handle_input(BrewInput::BleConnecting);
+        controller.handle_input(BrewInput::ScaleConnected);
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+    }
+
+    #[test]
+    fn test_reconnect_follows_connection_stages() {
+        let mut controller = idle_controller();
+        controller.handle_input(BrewInput::ScaleDisconnected);
+        assert_eq!(controller.get_system_state(), SystemState::ScaleDisconnected);
+
+        controller.handle_input(BrewInput::BleScanning);
+        controller.handle_input(BrewInput::BleConnecting);
+        // Link came up but subscribing failed - the scale task disconnects and scans again
+        controller.handle_input(BrewInput::ScaleDisconnected);
+        assert_eq!(controller.get_system_state(), SystemState::BleScanning);
+
+        controller.handle_input(BrewInput::BleConnecting);
+        let outputs = controller.handle_input(BrewInput::ScaleConnected);
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert!(outputs
+            .iter()
+            .any(|o| matches!(o, BrewOutput::ScaleConnectionChanged { connected: true })));
+    }
+
+    // Scale sample captured at a fixed time, so hold durations don't depend on the test's speed
+    fn sample_at(received_ms: u64, weight_g: f32, flow_rate_g_per_s: f32) -> BrewInput {
+        BrewInput::ScaleData(ScaleData {
+            received_at: Instant::from_millis(received_ms),
+            ..sample(received_ms as u32, weight_g, flow_rate_g_per_s)
+        })
+    }
+
+    #[test]
+    fn test_flow_stopped_mode_ignores_target_and_ends_when_flow_ceases() {
+        let mut controller = brewing_controller(StopMode::FlowStopped);
+
+        // Kettle not pouring yet - no flow seen, so nothing has stopped
+        for ms in [0, 1000, 2000, 3000] {
+            controller.handle_input(sample_at(ms, 0.0, 0.0));
+        }
+        // Well past the 36g target
+        controller.handle_input(sample_at(3500, 120.0, 8.0));
+        controller.handle_input(sample_at(4000, 250.0, 6.0));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        controller.handle_input(sample_at(4500, 252.0, 0.3));
+        controller.handle_input(sample_at(5500, 252.1, 0.1));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        let outputs = controller.handle_input(sample_at(6000, 252.1, 0.0));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+        assert!(outputs.iter().any(|o| matches!(o, BrewOutput::RelayOff)));
+    }
+
+    #[test]
+    fn test_flow_stopped_mode_survives_a_pause_in_the_pour() {
+        let mut controller = brewing_controller(StopMode::FlowStopped);
+        controller.handle_input(sample_at(0, 50.0, 5.0));
+        controller.handle_input(sample_at(1000, 52.0, 0.2));
+        // Pouring again before the hold ran out
+        controller.handle_input(sample_at(2000, 80.0, 5.0));
+        controller.handle_input(sample_at(2600, 95.0, 0.1));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+    }
+
+    #[test]
+    fn test_settling_finishes_once_drips_stop() {
+        let mut controller = brewing_controller(StopMode::Weight);
+        controller.handle_input(sample_at(10_000, 36.2, 2.0));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+
+        controller.handle_input(sample_at(10_500, 37.0, 0.4));
+        controller.handle_input(sample_at(11_500, 37.1, 0.1));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+
+        let outputs = controller.handle_input(sample_at(12_000, 37.1, 0.0));
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert!(outputs.iter().any(|o| matches!(o, BrewOutput::BrewingFinished)));
+    }
+
+    #[test]
+    fn test_settling_waits_for_still_weight() {
+        let mut controller = brewing_controller(StopMode::Weight);
+        controller.handle_input(sample_at(10_000, 36.2, 2.0));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+
+        // The scale's flow reading has dropped but the cup is still creeping up
+        controller.handle_input(sample_at(10_500, 36.6, 0.4));
+        controller.handle_input(sample_at(11_500, 37.0, 0.3));
+        controller.handle_input(sample_at(12_000, 37.4, 0.2));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+
+        controller.handle_input(sample_at(12_500, 37.5, 0.0));
+        let outputs = controller.handle_input(sample_at(13_000, 37.5, 0.0));
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert!(outputs.iter().any(|o| matches!(o, BrewOutput::BrewingFinished)));
+    }
+
+    #[test]
+    fn test_time_mode_stops_at_brew_time() {
+        let mut controller = brewing_controller(StopMode::Time);
+        controller.set_brew_time_ms(30_000).unwrap();
+        controller.context.brew_started_at = Some(Instant::from_millis(0));
+
+        controller.handle_input(sample_at(29_999, 80.0, 2.0));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+        controller.handle_input(sample_at(30_000, 80.2, 2.0));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+    }
+
+    fn relay_power(outputs: &[BrewOutput]) -> Option<f32> {
+        outputs.iter().find_map(|o| match o {
+            BrewOutput::RelayPower(power) => Some(*power),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_flow_profile_steers_relay_power() {
+        use crate::brewing::profile::{PidGains, ProfilePoint};
+
+        // Without a profile the pump just runs
+        let mut controller = brewing_controller(StopMode::Weight);
+        assert_eq!(relay_power(&controller.handle_input(sample_at(100, 0.5, 0.2))), None);
+
+        // 1g/s pre-infusion ramping to 3g/s over five seconds
+        let profile = FlowProfile {
+            points: [(0, 1.0), (5000, 3.0)]
+                .iter()
+                .map(|&(time_ms, flow_g_per_s)| ProfilePoint { time_ms, flow_g_per_s })
+                .collect(),
+            gains: PidGains::default(),
+        };
+        let mut controller = idle_controller();
+        controller.set_flow_profile(Some(profile)).unwrap();
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.context.brew_started_at = Some(Instant::from_millis(0));
+
+        let short = relay_power(&controller.handle_input(sample_at(100, 0.5, 0.2))).unwrap();
+        assert!(short > 0.0);
+        let over = relay_power(&controller.handle_input(sample_at(200, 1.0, 4.0))).unwrap();
+        assert!(over < short);
+
+        // Turning profiling off mid-shot applies from the next shot
+        controller.set_flow_profile(None).unwrap();
+        assert!(relay_power(&controller.handle_input(sample_at(300, 1.5, 0.5))).is_some());
+    }
+
+    #[test]
+    fn test_flow_loop_cleared_when_shot_ends() {
+        use crate::brewing::profile::{PidGains, ProfilePoint};
+        use crate::types::FLOW_PID_KP;
+
+        let mut points = Vec::new();
+        let _ = points.push(ProfilePoint { time_ms: 0, flow_g_per_s: 2.0 });
+        let profile = FlowProfile { points, gains: PidGains::default() };
+        let mut controller = idle_controller();
+        controller.set_flow_profile(Some(profile)).unwrap();
+
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.context.brew_started_at = Some(Instant::from_millis(0));
+        // Choked puck: half a minute short of target winds the loop up as far as it will go
+        for i in 0..300u64 {
+            controller.handle_input(sample_at(i * 100, 5.0, 0.5));
+        }
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StopBrewing));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+        assert!(controller.context.profiled_shot.is_none());
+        assert!(controller.context.flow_profile_power.is_none());
+
+        // The next shot starts from pure proportional control, nothing left from the last one
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.context.brew_started_at = Some(Instant::from_millis(40_000));
+        let outputs = controller.handle_input(sample_at(40_100, 0.0, 1.0));
+        assert_eq!(relay_power(&outputs), Some(FLOW_PID_KP));
+    }
+
+    // Grounds poured into a tared dosing cup, one reading every 200ms with the scale timer stopped
+    fn weigh_grounds(controller: &mut BrewController) -> std::vec::Vec<BrewOutput> {
+        [5.0, 12.0, 17.9, 18.1, 18.1, 18.1, 18.1, 18.1]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &weight_g)| {
+                let ms = i as u64 * 200;
+                controller.handle_input(BrewInput::ScaleData(ScaleData {
+                    timer_running: false,
+                    received_at: Instant::from_millis(ms),
+                    ..sample(ms as u32, weight_g, 0.0)
+                }))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dose_weighing_captures_dose_instead_of_taring() {
+        // Same readings outside dose weighing get auto-tared
+        let outputs = weigh_grounds(&mut idle_controller());
+        assert!(outputs.iter().any(|o| matches!(o, BrewOutput::TareScale)));
+
+        let mut controller = idle_controller();
+        controller.handle_input(BrewInput::UserCommand(UserEvent::SetDoseWeighing(true)));
+        let outputs = weigh_grounds(&mut controller);
+        assert!(!outputs.iter().any(|o| matches!(o, BrewOutput::TareScale)));
+        assert_eq!(
+            controller.dose_status(),
+            DoseStatus { weighing: true, pending_g: Some(18.1) }
+        );
+
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::ConfirmDose));
+        assert!(outputs
+            .iter()
+            .any(|o| matches!(o, BrewOutput::DoseCaptured { grams } if *grams == 18.1)));
+        assert_eq!(controller.dose_status(), DoseStatus::default());
+    }
+
+    #[test]
+    fn test_confirm_dose_needs_a_stable_reading() {
+        let mut controller = idle_controller();
+        controller.handle_input(BrewInput::UserCommand(UserEvent::SetDoseWeighing(true)));
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::ConfirmDose));
+        assert!(!outputs.iter().any(|o| matches!(o, BrewOutput::DoseCaptured { .. })));
+        assert!(controller.dose_status().weighing);
+
+        // Starting a brew abandons the unconfirmed dose
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        assert_eq!(controller.dose_status(), DoseStatus::default());
+    }
+
+    fn has_output(outputs: &[BrewOutput], wanted: fn(&BrewOutput) -> bool) -> bool {
+        outputs.iter().any(wanted)
+    }
+
+    #[test]
+    fn test_tare_before_brew_waits_for_zero() {
+        let mut controller = idle_controller();
+        controller.set_tare_before_brew(true, 3000);
+
+        // Tare goes out, the pump waits
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::TareScale)));
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::RelayOn)));
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+
+        // A second press while waiting doesn't tare again, and the cup isn't auto-tared meanwhile
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        assert!(outputs.is_empty());
+        let outputs = weigh_grounds(&mut controller);
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::TareScale)));
+
+        let outputs = controller.handle_input(BrewInput::TareVerified);
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::StartTimer)));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::RelayOn)));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        // Verifying an ordinary tare later starts nothing
+        let mut controller = idle_controller();
+        controller.set_tare_before_brew(true, 3000);
+        assert!(controller.handle_input(BrewInput::TareVerified).is_empty());
+    }
+
+    #[test]
+    fn test_tare_before_brew_never_deadlocks() {
+        let mut controller = idle_controller();
+        controller.set_tare_before_brew(true, 3000);
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        assert!(controller.handle_input(BrewInput::Tick).is_empty());
+
+        // The scale never zeroes - once the timeout passes the brew starts with a warning
+        controller.context.tare_before_brew_timeout = Duration::from_millis(0);
+        let outputs = controller.handle_input(BrewInput::Tick);
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::BrewTareTimedOut)));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::RelayOn)));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        // Verification off - tare and start straight away
+        let mut controller = idle_controller();
+        controller.set_tare_before_brew(true, 0);
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::TareScale)));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::RelayOn)));
+
+        // Stop cancels the wait, and so does losing the scale
+        let mut controller = idle_controller();
+        controller.set_tare_before_brew(true, 3000);
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StopBrewing));
+        assert!(controller.handle_input(BrewInput::TareVerified).is_empty());
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.handle_input(BrewInput::ScaleDisconnected);
+        controller.handle_input(BrewInput::ScaleConnected);
+        assert!(controller.handle_input(BrewInput::TareVerified).is_empty());
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+    }
+
+    #[test]
+    fn test_snapshot_follows_the_brew() {
+        let mut controller = idle_controller();
+        controller.set_target_weight(36.0);
+        let snapshot = controller.snapshot();
+        assert_eq!(snapshot.state, SystemState::Idle);
+        assert!(!snapshot.stop_pending);
+
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        controller.handle_input(BrewInput::ScaleData(sample(5000, 12.5, 1.8)));
+        controller.context.overshoot_pending_stop_time = Some(Instant::now());
+        let snapshot = controller.snapshot();
+        assert_eq!(snapshot.state, SystemState::Brewing);
+        assert_eq!(snapshot.current_weight_g, 12.5);
+        assert_eq!(snapshot.target_weight_g, 36.0);
+        assert!(snapshot.timer_running);
+        assert!(snapshot.stop_pending);
+
+        let json = serde_json::to_value(snapshot).unwrap();
+        assert_eq!(json["state"], "Brewing");
+        assert_eq!(json["stop_delay_ms"], snapshot.stop_delay_ms);
+    }
+
+    #[test]
+    fn test_reset_mid_brew_clears_pending_stop() {
+        let mut controller = idle_controller();
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        // A predictive stop is already due when the reset lands
+        controller.context.overshoot_pending_stop_time = Some(Instant::from_millis(0));
+        controller.context.overshoot_pending_predicted_stop = true;
+
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::ResetTimer));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::RelayOff)));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::ResetTimer)));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::BrewingReset)));
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::BrewingFinished)));
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert!(controller.context.overshoot_pending_stop_time.is_none());
+        assert!(!controller.context.overshoot_pending_predicted_stop);
+        assert!(controller.context.brew_started_at.is_none());
+
+        // Nothing left over fires while idle or into the next shot
+        assert!(controller.handle_input(BrewInput::Tick).is_empty());
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+        let outputs = controller.handle_input(BrewInput::Tick);
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::RelayOff)));
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::StopTimer)));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        // Reset while settling skips the overshoot measurement too
+        controller.handle_input(BrewInput::UserCommand(UserEvent::StopBrewing));
+        controller.context.overshoot_pending_predicted_stop = true;
+        let outputs = controller.handle_input(BrewInput::UserCommand(UserEvent::ResetTimer));
+        assert!(has_output(&outputs, |o| matches!(o, BrewOutput::BrewingReset)));
+        assert!(!has_output(&outputs, |o| matches!(o, BrewOutput::RelayOff)));
+        assert!(!controller.context.overshoot_pending_predicted_stop);
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+    }
+
+    #[test]
+    fn test_stop_mode_settings_validation() {
+        let mut controller = BrewController::new();
+        assert!(controller.set_brew_time_ms(MIN_BREW_TIME_MS - 1).is_err());
+        assert!(controller.set_brew_time_ms(MAX_BREW_TIME_MS + 1).is_err());
+        assert!(controller.set_flow_stop_hold_ms(MAX_FLOW_STOP_HOLD_MS + 1).is_err());
+        assert!(controller.set_settle_stability_g(0.0).is_err());
+        assert!(controller.set_settle_stability_g(MAX_SETTLE_STABILITY_G + 0.1).is_err());
+        assert_eq!(controller.get_settle_stability_g(), SETTLE_STABILITY_G);
+        assert_eq!(controller.get_brew_time_ms(), BREW_TIME_MS);
+        assert_eq!(controller.get_flow_stop_hold_ms(), FLOW_STOP_HOLD_MS);
+    }
+
+    #[test]
+    fn test_prediction_start_delay_boundary() {
+        let context = BrewContext::default();
+        assert!(!predicts(&context, &sample(PREDICTION_START_DELAY_MS, 30.0, 4.0)));
+        assert!(predicts(&context, &sample(PREDICTION_START_DELAY_MS + 1, 30.0, 4.0)));
+    }
+
+    #[test]
+    fn test_target_ignored_inside_startup_window() {
+        let mut controller = brewing_controller(StopMode::Weight);
+        controller.set_target_weight(36.0);
+        let spike = |timestamp_ms| BrewInput::ScaleData(sample(timestamp_ms, 40.0, 0.0));
+        controller.handle_input(spike(BREW_STARTUP_IGNORE_MS - 1));
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+        controller.handle_input(spike(BREW_STARTUP_IGNORE_MS));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+
+        // Zero disables the window entirely
+        let mut controller = brewing_controller(StopMode::Weight);
+        controller.set_target_weight(36.0);
+        controller.set_brew_startup_ignore_ms(0);
+        controller.handle_input(spike(0));
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+    }
+
+    #[test]
+    fn test_min_flow_for_prediction_boundary() {
+        let mut context = BrewContext::default();
+        assert!(!predicts(&context, &sample(5000, 36.0, 0.0)));
+
+        context.prediction_min_flow = 1.0;
+        assert!(!predicts(&context, &sample(5000, 34.5, 1.0)));
+        assert!(predicts(&context, &sample(5000, 34.4, 1.1)));
+    }
+
+    #[test]
+    fn test_flow_stopped_threshold_boundary() {
+        let mut context = BrewContext::default();
+        assert!(BrewStateMachine::flow_stopped(&context, &sample(5000, 38.0, 0.49)));
+        assert!(BrewStateMachine::flow_stopped(&context, &sample(5000, 38.0, -0.49)));
+        assert!(!BrewStateMachine::flow_stopped(&context, &sample(5000, 38.0, 0.5)));
+
+        context.flow_stopped_threshold = 1.0;
+        assert!(BrewStateMachine::flow_stopped(&context, &sample(5000, 38.0, 0.9)));
+    }
+
+    #[test]
+    fn test_overshoot_warmup_bias_fades_out() {
+        let mut context = BrewContext::default();
+        let first = BrewStateMachine::overshoot_warmup_bias_ms(&context);
+        assert_eq!(first, OVERSHOOT_WARMUP_EXTRA_DELAY_MS);
+
+        context.overshoot_brew_count = 1;
+        let second = BrewStateMachine::overshoot_warmup_bias_ms(&context);
+        assert!(second > 0.0 && second < first);
+
+        context.overshoot_confidence_score = 1.0;
+        assert_eq!(BrewStateMachine::overshoot_warmup_bias_ms(&context), 0.0);
+
+        context.overshoot_confidence_score = 0.0;
+        context.overshoot_brew_count = OVERSHOOT_WARMUP_BREWS;
+        assert!(!BrewStateMachine::overshoot_warmup_active(&context));
+        assert_eq!(BrewStateMachine::effective_stop_delay_ms(&context), 500.0);
+    }
+
+    #[test]
+    fn test_overshoot_warmup_disabled_with_zero_brews() {
+        let mut controller = BrewController::new();
+        assert!(controller.is_overshoot_warmup_active());
+        assert!(controller.set_overshoot_warmup_brews(OVERSHOOT_MAX_WARMUP_BREWS + 1).is_err());
+        assert!(controller.set_overshoot_warmup_brews(0).is_ok());
+        assert!(!controller.is_overshoot_warmup_active());
+    }
+
+    #[test]
+    fn test_overshoot_learning_schedule_validation() {
+        let mut controller = BrewController::new();
+        let defaults = OvershootLearningSchedule::default();
+        assert_eq!(controller.get_overshoot_learning_rate(), defaults.uncertain_rate);
+
+        for invalid in [
+            OvershootLearningSchedule { uncertain_rate: 0.0, ..defaults },
+            OvershootLearningSchedule { confident_rate: 1.5, ..defaults },
+            OvershootLearningSchedule { medium_confidence: 0.9, ..defaults },
+            OvershootLearningSchedule { high_confidence: 1.0, ..defaults },
+            OvershootLearningSchedule { full_experience_brews: 0, ..defaults },
+        ] {
+            assert!(controller.set_overshoot_learning_schedule(invalid).is_err());
+        }
+        assert_eq!(controller.get_overshoot_learning_schedule(), defaults);
+
+        let faster = OvershootLearningSchedule { uncertain_rate: 0.6, ..defaults };
+        assert!(controller.set_overshoot_learning_schedule(faster).is_ok());
+        assert_eq!(controller.get_overshoot_learning_schedule(), faster);
+        assert_eq!(controller.get_overshoot_learning_rate(), 0.6);
+    }
+
+    #[test]
+    fn test_overshoot_confidence_follows_learning_schedule() {
+        let mut context = BrewContext::default();
+        for _ in 0..OVERSHOOT_HISTORY_SIZE {
+            let _ = context.overshoot_history.push(OvershootMeasurement {
+                overshoot: 1.0,
+                timestamp: Instant::from_millis(0),
+            });
+        }
+        context.overshoot_brew_count = 10;
+
+        // Perfectly consistent, but only halfway to full experience
+        BrewStateMachine::update_overshoot_confidence(&mut context);
+        assert_eq!(context.overshoot_confidence_score, 0.5 * 1.2);
+        assert_eq!(
+            context.overshoot_learning_rate,
+            OvershootLearningSchedule::default().medium_rate
+        );
+
+        context.overshoot_learning = OvershootLearningSchedule {
+            confident_rate: 0.15,
+            full_experience_brews: 10,
+            ..OvershootLearningSchedule::default()
+        };
+        BrewStateMachine::update_overshoot_confidence(&mut context);
+        assert_eq!(context.overshoot_confidence_score, 1.0);
+        assert_eq!(context.overshoot_learning_rate, 0.15);
+    }
+
+    // Five consistent shots around 0.3g over, learned into a steady delay
+    fn settled_overshoot_context() -> BrewContext {
+        let mut context = BrewContext::default();
+        for overshoot in [0.2, 0.4, 0.3, 0.2, 0.4] {
+            let _ = context.overshoot_history.push(OvershootMeasurement {
+                overshoot,
+                timestamp: Instant::from_millis(0),
+            });
+        }
+        context.overshoot_ewma = 0.3;
+        context.overshoot_brew_count = 5;
+        context
+    }
+
+    fn record_overshoot(context: &mut BrewContext, overshoot: f32) {
+        context.overshoot_pending_predicted_stop = true;
+        BrewStateMachine::record_overshoot_learning(context, overshoot);
+    }
+
+    #[test]
+    fn test_overshoot_outlier_barely_moves_delay() {
+        let mut context = settled_overshoot_context();
+        let delay = context.overshoot_stop_delay_ms;
+
+        // Knocked cup
+        record_overshoot(&mut context, 15.0);
+        assert_eq!(context.overshoot_stop_delay_ms, delay);
+        assert_eq!(context.overshoot_ewma, 0.3);
+        assert_eq!(context.overshoot_brew_count, 5);
+        assert!(matches!(
+            context.outputs.last(),
+            Some(BrewOutput::OvershootOutlierRejected { overshoot, .. }) if *overshoot == 15.0
+        ));
+
+        // The next ordinary shot is learned as usual
+        record_overshoot(&mut context, 0.5);
+        assert_eq!(context.overshoot_brew_count, 6);
+        assert_eq!(context.overshoot_outlier_streak, 0);
+        assert_eq!(context.overshoot_stop_delay_ms, delay);
+    }
+
+    #[test]
+    fn test_overshoot_outlier_streak_is_learned() {
+        let mut context = settled_overshoot_context();
+        for _ in 0..OVERSHOOT_OUTLIER_MAX_STREAK {
+            record_overshoot(&mut context, 6.0);
+        }
+        assert_eq!(context.overshoot_brew_count, 5);
+
+        // The setup really has changed - stop rejecting it
+        record_overshoot(&mut context, 6.0);
+        assert_eq!(context.overshoot_brew_count, 6);
+        assert!(context.overshoot_ewma > 0.3);
+    }
+
+    #[test]
+    fn test_overshoot_profiles_keep_separate_models() {
+        let mut controller = idle_controller();
+        controller.context.overshoot_stop_delay_ms = 800;
+        controller.context.overshoot_brew_count = 7;
+        let _ = controller.context.overshoot_history.push(OvershootMeasurement {
+            overshoot: 1.5,
+            timestamp: Instant::from_millis(0),
+        });
+
+        assert!(controller.select_overshoot_profile(" filter ").is_ok());
+        assert_eq!(controller.active_overshoot_profile(), "filter");
+        assert_eq!(controller.get_overshoot_delay_ms(), OVERSHOOT_INITIAL_STOP_DELAY_MS);
+        assert_eq!(controller.context.overshoot_brew_count, 0);
+        assert!(controller.context.overshoot_history.is_empty());
+
+        controller.context.overshoot_stop_delay_ms = 300;
+        assert!(controller.select_overshoot_profile("default").is_ok());
+        assert_eq!(controller.get_overshoot_delay_ms(), 800);
+        assert_eq!(controller.context.overshoot_brew_count, 7);
+        assert_eq!(controller.context.overshoot_history.len(), 1);
+
+        let profiles = controller.overshoot_profiles();
+        assert_eq!(profiles.names(), ["default", "filter"]);
+        assert_eq!(profiles.models["filter"].stop_delay_ms, 300);
+
+        // What NVS restores after a reboot
+        let mut rebooted = BrewController::new();
+        assert!(rebooted.restore_overshoot_profiles(profiles.clone()).is_ok());
+        assert_eq!(rebooted.get_overshoot_delay_ms(), 800);
+        assert_eq!(rebooted.overshoot_profiles(), profiles);
+    }
+
+    #[test]
+    fn test_overshoot_profile_selection_validation() {
+        let mut controller = idle_controller();
+        assert!(controller.select_overshoot_profile("  ").is_err());
+        let too_long = "x".repeat(MAX_OVERSHOOT_PROFILE_NAME_LEN + 1);
+        assert!(controller.select_overshoot_profile(&too_long).is_err());
+
+        for index in 1..MAX_OVERSHOOT_PROFILES {
+            assert!(controller.select_overshoot_profile(&format!("profile {}", index)).is_ok());
+        }
+        assert!(controller.select_overshoot_profile("one too many").is_err());
+        assert!(controller.select_overshoot_profile("default").is_ok());
+
+        let mut controller = brewing_controller(StopMode::Weight);
+        assert!(controller.select_overshoot_profile("filter").is_err());
+        assert_eq!(controller.active_overshoot_profile(), DEFAULT_OVERSHOOT_PROFILE);
+    }
+
+    #[test]
+    fn test_prediction_thresholds_validation() {
+        let mut controller = BrewController::new();
+        assert!(controller.set_prediction_thresholds(-0.1, 0.5, 2000).is_err());
+        assert!(controller.set_prediction_thresholds(0.0, 0.0, 2000).is_err());
+        assert!(controller
+            .set_prediction_thresholds(0.0, 0.5, PREDICTION_MAX_START_DELAY_MS + 1)
+            .is_err());
+        assert_eq!(controller.get_prediction_thresholds(), (0.0, 0.5, 2000));
+
+        assert!(controller.set_prediction_thresholds(1.0, 0.8, 3000).is_ok());
+        assert_eq!(controller.get_prediction_thresholds(), (1.0, 0.8, 3000));
+    }
+
+    // Output variant names in order; transitions spell out both ends, float payloads are dropped
+    fn output_names(outputs: &[BrewOutput]) -> std::vec::Vec<String> {
+        outputs
+            .iter()
+            .map(|output| match output {
+                BrewOutput::StateChanged { from, to } => {
+                    format!("StateChanged({:?}->{:?})", from, to)
+                }
+                other => {
+                    let debug = format!("{:?}", other);
+                    debug.split([' ', '(', '{']).next().unwrap_or_default().to_string()
+                }
+            })
+            .collect()
+    }
+
+    // Feeds a scripted brew step by step, asserting the exact outputs of every input
+    fn run_script(controller: &mut BrewController, script: &[Step]) {
+        for (index, (input, expected)) in script.iter().enumerate() {
+            let outputs = controller.handle_input(input.clone());
+            assert_eq!(output_names(&outputs), *expected, "step {}: {:?}", index, input);
+        }
+    }
+
+    type Step = (BrewInput, &'static [&'static str]);
+
+    fn step(input: BrewInput, expected: &'static [&'static str]) -> Step {
+        (input, expected)
+    }
+
+    fn idle_sample_at(received_ms: u64, weight_g: f32) -> BrewInput {
+        BrewInput::ScaleData(ScaleData {
+            timer_running: false,
+            received_at: Instant::from_millis(received_ms),
+            ..sample(received_ms as u32, weight_g, 0.0)
+        })
+    }
+
+    // Cup already tared, timer started, pre-infusion, a 2g/s ramp to the 36g target, then drips
+    fn shot_up_to_prediction() -> std::vec::Vec<Step> {
+        vec![
+            step(idle_sample_at(0, 0.0), &["DisplayUpdate"]),
+            step(idle_sample_at(500, 0.0), &["DisplayUpdate"]),
+            step(
+                BrewInput::UserCommand(UserEvent::StartBrewing),
+                &["StartTimer", "RelayOn", "BrewingStarted", "StateChanged(Idle->Brewing)"],
+            ),
+            step(sample_at(1000, 0.0, 0.0), &["DisplayUpdate"]),
+            // Pre-infusion trickle, still inside the prediction start delay
+            step(sample_at(2000, 0.4, 0.3), &["DisplayUpdate"]),
+            step(sample_at(6000, 8.0, 2.0), &["DisplayUpdate"]),
+            step(sample_at(12_000, 20.0, 2.0), &["DisplayUpdate"]),
+            step(sample_at(15_000, 26.0, 2.0), &["DisplayUpdate"]),
+        ]
+    }
+
+    #[test]
+    fn test_full_brew_cycle_without_predictive_stop() {
+        let mut controller = idle_controller();
+        controller.set_predictive_stop_enabled(false);
+
+        let mut script = shot_up_to_prediction();
+        script.extend([
+            // 2.75s to target - inside the first-shot window, previewed once
+            step(sample_at(17_000, 30.5, 2.0), &["DisplayUpdate", "PredictiveStopPreview"]),
+            step(sample_at(18_000, 32.5, 2.0), &["DisplayUpdate"]),
+            step(
+                sample_at(20_000, 36.1, 2.0),
+                &["DisplayUpdate", "StopTimer", "RelayOff", "StateChanged(Brewing->Settling)"],
+            ),
+            step(sample_at(20_500, 36.8, 1.0), &["DisplayUpdate"]),
+            step(sample_at(21_000, 37.1, 0.3), &["DisplayUpdate"]),
+            step(sample_at(22_000, 37.2, 0.1), &["DisplayUpdate"]),
+            // Drips stopped for the full hold
+            step(
+                sample_at(22_500, 37.2, 0.0),
+                &[
+                    "DisplayUpdate",
+                    "BrewingFinished",
+                    "AutoTareStateChanged",
+                    "StateChanged(Settling->Idle)",
+                ],
+            ),
+            // Cup left on the scale is kept, not re-tared
+            step(idle_sample_at(23_000, 37.2), &["DisplayUpdate"]),
+            step(idle_sample_at(23_500, 37.2), &["DisplayUpdate"]),
+        ]);
+        run_script(&mut controller, &script);
+
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert_eq!(controller.context.auto_tare_state, AutoTareState::StableObject);
+    }
+
+    #[test]
+    fn test_full_brew_cycle_with_predictive_stop() {
+        let mut controller = idle_controller();
+
+        let mut script = shot_up_to_prediction();
+        script.extend([
+            // Stop scheduled ~2s out - a tick before then leaves the pump running
+            step(
+                sample_at(17_000, 30.5, 2.0),
+                &["DisplayUpdate", "PredictiveStopScheduled", "PredictiveStopTriggered"],
+            ),
+            step(BrewInput::Tick, &[]),
+        ]);
+        run_script(&mut controller, &script);
+        assert_eq!(controller.get_system_state(), SystemState::Brewing);
+
+        // The scheduled delay runs out - the next tick stops the pump
+        controller.context.overshoot_pending_stop_time = Some(Instant::from_millis(0));
+        run_script(
+            &mut controller,
+            &[step(BrewInput::Tick, &["RelayOff", "StopTimer", "StateChanged(Brewing->Settling)"])],
+        );
+        assert!(controller.context.overshoot_pending_stop_time.is_none());
+        assert!(controller.context.overshoot_pending_predicted_stop);
+
+        // The overshoot is learned in Settling as soon as the flow drops off, and the shot
+        // finishes once the drips stop
+        run_script(
+            &mut controller,
+            &[
+                step(BrewInput::Tick, &[]),
+                step(sample_at(20_500, 36.8, 1.0), &["DisplayUpdate"]),
+                step(sample_at(21_000, 37.1, 0.3), &["DisplayUpdate", "OvershootLearningUpdated"]),
+                step(sample_at(22_000, 37.2, 0.1), &["DisplayUpdate"]),
+                step(
+                    sample_at(22_500, 37.2, 0.0),
+                    &[
+                        "DisplayUpdate",
+                        "BrewingFinished",
+                        "AutoTareStateChanged",
+                        "StateChanged(Settling->Idle)",
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+        assert!(!controller.context.overshoot_pending_predicted_stop);
+        assert_eq!(controller.context.overshoot_brew_count, 1);
+    }
+
+    #[test]
+    fn test_overshoot_setpoint_moves_the_stop_and_the_learning() {
+        let mut controller = idle_controller();
+        assert!(controller.set_overshoot_target_g(OVERSHOOT_MAX_TARGET_G + 0.5).is_err());
+        assert!(controller.set_overshoot_target_g(f32::NAN).is_err());
+        controller.set_overshoot_target_g(-6.0).unwrap();
+
+        // Aiming at 30g the 26g sample is 2s out - unbiased, nothing is predicted until 30.5g
+        let mut script = shot_up_to_prediction();
+        script.pop();
+        script.extend([
+            step(
+                sample_at(15_000, 26.0, 2.0),
+                &["DisplayUpdate", "PredictiveStopScheduled", "PredictiveStopTriggered"],
+            ),
+            step(
+                sample_at(17_000, 30.1, 2.0),
+                &["DisplayUpdate", "StopTimer", "RelayOff", "StateChanged(Brewing->Settling)"],
+            ),
+            step(sample_at(18_000, 31.0, 0.3), &["DisplayUpdate", "OvershootLearningUpdated"]),
+        ]);
+        run_script(&mut controller, &script);
+
+        // Learned as 1g past the setpoint, not 5g short of the target
+        let learned = controller.context.overshoot_history.last().unwrap().overshoot;
+        assert!((learned - 1.0).abs() < 1e-4, "overshoot {}", learned);
+    }
+
+    #[test]
+    fn test_quick_reconnect_resumes_the_shot() {
+        let mut controller = idle_controller();
+        assert!(controller
+            .set_scale_reconnect_grace_ms(MAX_SCALE_RECONNECT_GRACE_MS + 1)
+            .is_err());
+        controller.set_scale_reconnect_grace_ms(5000).unwrap();
+
+        let mut script = shot_up_to_prediction();
+        script.pop();
+        script.extend([
+            step(
+                BrewInput::ScaleDisconnected,
+                &[
+                    "ScaleConnectionChanged",
+                    "RelayOff",
+                    "BrewInterrupted",
+                    "StateChanged(Brewing->ScaleDisconnected)",
+                ],
+            ),
+            step(
+                BrewInput::ScaleConnected,
+                &["ScaleConnectionChanged", "StateChanged(ScaleDisconnected->Idle)"],
+            ),
+            // Timer still running and the cup where it was - the pump comes back on
+            step(
+                sample_at(13_000, 20.5, 0.5),
+                &["DisplayUpdate", "RelayOn", "BrewResumed", "StateChanged(Idle->Brewing)"],
+            ),
+        ]);
+        run_script(&mut controller, &script);
+        assert!(controller.context.interrupted_brew.is_none());
+
+        // The cup moved while the scale was away the second time - the shot is over
+        run_script(
+            &mut controller,
+            &[
+                step(
+                    BrewInput::ScaleDisconnected,
+                    &[
+                        "ScaleConnectionChanged",
+                        "RelayOff",
+                        "BrewInterrupted",
+                        "StateChanged(Brewing->ScaleDisconnected)",
+                    ],
+                ),
+                step(
+                    BrewInput::ScaleConnected,
+                    &["ScaleConnectionChanged", "StateChanged(ScaleDisconnected->Idle)"],
+                ),
+                step(sample_at(14_000, 30.0, 0.0), &["DisplayUpdate", "BrewResumeRefused"]),
+                step(sample_at(14_500, 30.0, 0.0), &["DisplayUpdate"]),
+            ],
+        );
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+    }
+
+    #[test]
+    fn test_drop_without_grace_ends_the_shot() {
+        let mut controller = idle_controller();
+        let mut script = shot_up_to_prediction();
+        script.extend([
+            step(
+                BrewInput::ScaleDisconnected,
+                &["ScaleConnectionChanged", "RelayOff", "StateChanged(Brewing->ScaleDisconnected)"],
+            ),
+            step(
+                BrewInput::ScaleConnected,
+                &["ScaleConnectionChanged", "StateChanged(ScaleDisconnected->Idle)"],
+            ),
+            step(sample_at(16_000, 26.0, 0.5), &["DisplayUpdate"]),
+        ]);
+        run_script(&mut controller, &script);
+        assert_eq!(controller.get_system_state(), SystemState::Idle);
+    }
+
+    #[test]
+    fn test_predictive_stop_cancelled_when_flow_drops() {
+        let mut controller = idle_controller();
+
+        let mut script = shot_up_to_prediction();
+        script.extend([
+            step(
+                sample_at(17_000, 30.5, 2.0),
+                &["DisplayUpdate", "PredictiveStopScheduled", "PredictiveStopTriggered"],
+            ),
+            // A single low reading is noise - the stop stays scheduled
+            step(sample_at(17_200, 30.5, 0.0), &["DisplayUpdate"]),
+            step(
+                sample_at(17_400, 30.5, 2.0),
+                &["DisplayUpdate", "PredictiveStopScheduled", "PredictiveStopTriggered"],
+            ),
+            // Cup lifted off the scale before the scheduled stop fires
+            step(sample_at(17_600, 0.0, 0.0), &["DisplayUpdate"]),
+            step(sample_at(17_800, 0.0, 0.0), &["DisplayUpdate"]),
+            step(
+                sample_at(18_000, 0.0, 0.0),
+                &[
+                    "DisplayUpdate",
+                    "PredictiveStopCancelled",
+                    "StopTimer",
+                    "RelayOff",
+                    "StateChanged(Brewing->Settling)",
+                ],
+            ),
+        ]);
+        run_script(&mut controller, &script);
+
+        // The pump is off and nothing is learned from a shot that ended in the air
+        assert_eq!(controller.get_system_state(), SystemState::Settling);
+        assert!(controller.context.overshoot_pending_stop_time.is_none());
+        assert!(!controller.context.overshoot_pending_predicted_stop);
+    }
+
+    #[test]
+    fn test_predictive_stop_auto_learns_from_target_stops() {
+        let mut controller = idle_controller();
+        let outputs = controller.set_predictive_stop_auto(true);
+        assert_eq!(output_names(&outputs), ["PredictiveStopAutoChanged"]);
+        assert!(!controller.is_predictive_stop_enabled());
+
+        let mut script = shot_up_to_prediction();
+        script.extend([
+            step(sample_at(17_000, 30.5, 2.0), &["DisplayUpdate", "PredictiveStopPreview"]),
+            step(
+                sample_at(20_000, 36.1, 2.0),
+                &["DisplayUpdate", "StopTimer", "RelayOff", "StateChanged(Brewing->Settling)"],
+            ),
+            step(sample_at(21_000, 37.1, 0.3), &["DisplayUpdate", "OvershootLearningUpdated"]),
+        ]);
+        run_script(&mut controller, &script);
+
+        // One shot is not enough to trust predictions
+        assert_eq!(controller.context.overshoot_brew_count, 1);
+        assert!(!controller.is_predictive_stop_enabled());
+    }
+
+    #[test]
+    fn test_predictive_stop_auto_hysteresis() {
+        let mut controller = BrewController::new();
+        controller.set_predictive_stop_auto(true);
+        let context = &mut controller.context;
+
+        context.overshoot_brew_count = OVERSHOOT_READY_BREWS;
+        context.overshoot_confidence_score = OVERSHOOT_READY_CONFIDENCE + 0.1;
+        BrewStateMachine::apply_predictive_stop_auto(context);
+        assert!(context.predictive_stop_enabled);
+        assert!(matches!(
+            context.outputs.last(),
+            Some(BrewOutput::PredictiveStopAutoChanged { enabled: true, .. })
+        ));
+
+        // Dipping under the ready bar isn't a collapse
+        context.outputs.clear();
+        context.overshoot_confidence_score = OVERSHOOT_READY_CONFIDENCE - 0.05;
+        BrewStateMachine::apply_predictive_stop_auto(context);
+        assert!(context.predictive_stop_enabled);
+        assert!(context.outputs.is_empty());
+
+        context.overshoot_confidence_score = PREDICTIVE_AUTO_DISABLE_CONFIDENCE - 0.05;
+        BrewStateMachine::apply_predictive_stop_auto(context);
+        assert!(!context.predictive_stop_enabled);
+        assert!(matches!(
+            context.outputs.last(),
+            Some(BrewOutput::PredictiveStopAutoChanged { enabled: false, .. })
+        ));
+
+        // Leaving auto mode hands the toggle back untouched
+        controller.set_predictive_stop_auto(false);
+        controller.context.overshoot_confidence_score = 1.0;
+        BrewStateMachine::apply_predictive_stop_auto(&mut controller.context);
+        assert!(!controller.is_predictive_stop_enabled());
+    }
+}
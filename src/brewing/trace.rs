@@ -0,0 +1,136 @@
+//! Weight and flow curve of the last brew, exported as CSV for plotting extraction in a spreadsheet
+
+use crate::types::{ScaleData, BREW_TRACE_MAX_SAMPLES};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::Instant;
+use heapless::Vec;
+use log::{info, warn};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceSample {
+    /// Offset from the start of the brew
+    pub time_ms: u32,
+    pub weight_g: f32,
+    pub flow_g_s: f32,
+}
+
+/// Samples of the current or last brew - kept until the next brew starts
+#[derive(Debug, Default)]
+pub struct BrewTrace {
+    started_at: Option<Instant>,
+    samples: Vec<TraceSample, BREW_TRACE_MAX_SAMPLES>,
+    truncated: bool,
+}
+
+impl BrewTrace {
+    /// Discard the previous brew's samples and start capturing
+    pub fn start(&mut self, at: Instant) {
+        self.samples.clear();
+        self.truncated = false;
+        self.started_at = Some(at);
+    }
+
+    pub fn stop(&mut self) {
+        if self.started_at.take().is_some() {
+            info!("📈 Brew trace captured - {} samples", self.samples.len());
+        }
+    }
+
+    pub fn samples(&self) -> &[TraceSample] {
+        &self.samples
+    }
+
+    /// Capture a sample while a brew runs, at most one per `interval_ms`
+    pub fn record(&mut self, data: &ScaleData, interval_ms: u32) {
+        let Some(started_at) = self.started_at else {
+            return;
+        };
+        let time_ms = data.received_at.saturating_duration_since(started_at).as_millis() as u32;
+        if self
+            .samples
+            .last()
+            .is_some_and(|last| time_ms < last.time_ms + interval_ms)
+        {
+            return;
+        }
+
+        let sample = TraceSample {
+            time_ms,
+            weight_g: data.weight_g,
+            flow_g_s: data.flow_rate_g_per_s,
+        };
+        if self.samples.push(sample).is_err() && !self.truncated {
+            warn!("📈 Brew trace full at {} samples - stopping capture", BREW_TRACE_MAX_SAMPLES);
+            self.truncated = true;
+        }
+    }
+
+    /// `time_ms,weight_g,flow_g_s` rows under a header line
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("time_ms,weight_g,flow_g_s\n");
+        for sample in &self.samples {
+            let _ = writeln!(
+                csv,
+                "{},{:.2},{:.2}",
+                sample.time_ms, sample.weight_g, sample.flow_g_s
+            );
+        }
+        csv
+    }
+}
+
+pub type SharedBrewTrace = Mutex<CriticalSectionRawMutex, BrewTrace>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_at(ms: u64, weight_g: f32, flow_rate_g_per_s: f32) -> ScaleData {
+        ScaleData {
+            timestamp_ms: ms as u32,
+            weight_g,
+            flow_rate_g_per_s,
+            battery_percent: 80,
+            timer_running: true,
+            received_at: Instant::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn test_trace_samples_at_interval_and_exports_csv() {
+        let mut trace = BrewTrace::default();
+        // Nothing is captured before a brew starts
+        trace.record(&sample_at(0, 5.0, 0.0), 100);
+        assert!(trace.samples().is_empty());
+
+        trace.start(Instant::from_millis(1000));
+        for i in 0..6u64 {
+            trace.record(&sample_at(1000 + i * 100, i as f32 * 0.5, 5.0), 200);
+        }
+        trace.stop();
+        trace.record(&sample_at(2000, 40.0, 0.0), 200);
+
+        let times: std::vec::Vec<u32> = trace.samples().iter().map(|s| s.time_ms).collect();
+        assert_eq!(times, [0, 200, 400]);
+        assert_eq!(
+            trace.to_csv(),
+            "time_ms,weight_g,flow_g_s\n0,0.00,5.00\n200,1.00,5.00\n400,2.00,5.00\n"
+        );
+
+        // The next brew replaces the last one
+        trace.start(Instant::from_millis(5000));
+        assert_eq!(trace.to_csv(), "time_ms,weight_g,flow_g_s\n");
+    }
+
+    #[test]
+    fn test_trace_stops_when_full() {
+        let mut trace = BrewTrace::default();
+        trace.start(Instant::from_millis(0));
+        for i in 0..(BREW_TRACE_MAX_SAMPLES as u64 + 10) {
+            trace.record(&sample_at(i * 100, i as f32, 1.0), 100);
+        }
+        assert_eq!(trace.samples().len(), BREW_TRACE_MAX_SAMPLES);
+        assert_eq!(trace.samples().last().unwrap().weight_g, (BREW_TRACE_MAX_SAMPLES - 1) as f32);
+    }
+}
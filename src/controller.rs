@@ -1,27 +1,61 @@
 use crate::{
-    ble::StatusChannel,
+    ble::{BleAddress, BleClient, StatusChannel},
     brewing::{
-        BrewController, BrewInput, BrewOutput, BrewStateTransition,
+        recorder::{recorder_task, BrewRecording, SharedBrewRecording},
+        trace::{BrewTrace, SharedBrewTrace},
+        BrewController, BrewInput, BrewOutput,
+    },
+    error::GravelError,
+    hardware::{
+        button::{button_task, ButtonInput},
+        buzzer::{buzzer_allowed, buzzer_task, BuzzerChannel, BuzzerController, BuzzerPattern},
+        display::DisplayController,
+        relay::{
+            RelayBootCheck, RelayController, RelayError, DEFAULT_RELAY_MAX_ON_SECS,
+            PRIMARY_RELAY_CHANNEL,
+        },
     },
-    hardware::relay::{RelayController, RelayError},
     scales::{
         bookoo::BookooScale,
         event_detection::ScaleEventDetector,
-        traits::{ScaleCommand, ScaleCommandChannel, ScaleDataChannel},
+        traits::{
+            ConnectionProfile, ConnectionStage, ConnectionStageChannel, LinkQualitySignal,
+            NearbyDevicesSignal, ScaleCommand, ScaleCommandChannel, ScaleDataChannel,
+            ScaleInfoSignal, ScaleUnit,
+        },
     },
-    server::http::{WebSocketCommand, WebSocketCommandChannel, WebSocketServer},
+    server::http::{ws_broadcast_task, WebSocketCommand, WebSocketCommandChannel, WebSocketServer},
     state::StateManager,
-    system::{events::*, NvsStorage, SafetyController},
-    types::{BrewConfig, BrewState, ScaleData, TimerState},
+    system::{
+        events::*,
+        logging::set_log_level,
+        self_test::{CheckOutcome, SelfTestReport},
+        shutdown::ShutdownToken,
+        watchdog::{TaskWatchdog, CONTROL_LOOP_WATCHDOG_TIMEOUT_MS},
+        NvsStorage, SafetyController, SafetyStatus,
+    },
+    types::{
+        AutoTareParams, BrewConfig, BrewState, ConfirmableCommand, LogLevel, RadioPriority,
+        ScaleData, ScaleDetectorStatus, StopMode, TimerState, BLE_PRIORITY_BROADCAST_MS,
+        BREW_PROGRESS_INTERVAL_MS, BREW_TRACE_INTERVAL_MS, COMMAND_CONFIRM_WINDOW_MS,
+        DEFAULT_SCALE_NAME_PREFIX, DISPLAY_REFRESH_MS, EVENT_STATS_LOG_KINDS,
+        LINK_QUALITY_RECOVER_SCORE, LINK_QUALITY_WARN_SCORE, MAX_DEVICE_SCAN_MS, MAX_DOSE_G,
+        MAX_RELAY_TEST_MS, MIN_DEVICE_SCAN_MS, MIN_DOSE_G, MIN_RELAY_TEST_MS,
+        RELAY_MAX_ON_MARGIN_MS, RELAY_TEST_MS,
+        SCALE_DISCONNECT_TIMEOUT_MS, SCALE_KEEP_ALIVE_MS, STALE_DATA_TIMEOUT_MS,
+        WEIGHT_RATE_LIMIT_G_PER_S, WEIGHT_RATE_MIN_INTERVAL_MS,
+        WEIGHT_STREAM_MS,
+    },
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{select, Either};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_futures::select::{select, select3, select6, Either, Either3, Either6};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, signal::Signal};
 use embassy_time::{Duration, Instant, Timer};
 // BLE now handled by esp32-nimble crate
-use esp_idf_svc::hal::gpio::Gpio19;
+use esp_idf_svc::hal::gpio::{Gpio19, IOPin};
 use log::{debug, error, info, warn};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 
 // Scale command channel type imported from traits
 
@@ -33,6 +67,7 @@ pub struct ComprehensiveStatus {
     pub scale_detector_stable_weight: Option<f32>,
     pub system_enabled: bool,
     pub state_manager_state: crate::types::SystemState,
+    pub safety_status: SafetyStatus,
 }
 
 pub struct EspressoController {
@@ -55,46 +90,62 @@ pub struct EspressoController {
     ble_status_channel: Arc<StatusChannel>,
     websocket_command_channel: Arc<WebSocketCommandChannel>,
     scale_command_channel: Arc<ScaleCommandChannel>,
+    scale_info_signal: Arc<ScaleInfoSignal>,
+    nearby_devices_signal: Arc<NearbyDevicesSignal>,
+    connection_stage_channel: Arc<ConnectionStageChannel>,
+    link_quality_signal: Arc<LinkQualitySignal>,
+    scale_name_prefix: Arc<StdMutex<String>>,
+    scale_keep_alive_ms: Arc<AtomicU32>,
 
-    // Predictive stopping state (Python style)
-    pending_stop_time: Option<Instant>,
+    // Tare sent but the scale hasn't read zero yet
+    pending_tare_check: Option<Instant>,
 
-    // Timer detection state (from Python reference)
-    last_timer_ms: Option<u32>,
-    current_timer_running: bool,
+    // First press of a web start/stop that needs confirming
+    command_confirmation: CommandConfirmation,
 
-    // Scale shutdown detection to prevent false brewing triggers
-    timer_start_time: Option<Instant>,    // When timer was started
-    consecutive_disconnection_count: u32, // Count BLE disconnections after timer start
+    // When the current shot started, for the duration reported once it finishes
+    shot_started_at: Option<Instant>,
 
-    // Brewing startup delay to ignore button press artifacts
-    brew_start_time: Option<Instant>,
+    // Last brew progress event, for throttling
+    last_progress_at: Option<Instant>,
 
-}
+    // Weight sanity filter against corrupt frames
+    last_plausible_weight: Option<(f32, Instant)>,
+    suspect_weight: Option<(f32, Instant)>, // Rejected jump, accepted if the next frame repeats it
 
-impl EspressoController {
-    pub async fn new(gpio19: Gpio19) -> Result<Self, Box<dyn std::error::Error>> {
-        let scale_data_channel = Arc::new(Channel::new());
-        let ble_status_channel = Arc::new(Channel::new());
-        let websocket_command_channel = Arc::new(Channel::new());
-        let scale_command_channel = Arc::new(Channel::new());
+    // Poor link warning given - re-armed once the score recovers
+    link_quality_warned: bool,
 
-        let state_manager = StateManager::new();
-        let state_handle = state_manager.get_state_handle();
+    // Physical buttons, handed to their own tasks on start
+    buttons: Vec<ButtonInput>,
 
-        let scale_client = BookooScale::new(
-            Arc::clone(&scale_data_channel),
-            Arc::clone(&ble_status_channel),
-        );
+    // Optional piezo buzzer, driven through a pattern queue by its own task
+    buzzer: Option<BuzzerController>,
+    buzzer_channel: Arc<BuzzerChannel>,
 
-        let websocket_server = WebSocketServer::new(
-            Arc::clone(&state_handle),
-            Arc::clone(&websocket_command_channel),
-            8080,
-        );
+    // Optional OLED display, driven only by hardware events
+    display: Option<DisplayController>,
+
+    // Brew input recorder for offline replay, controlled over HTTP
+    brew_recording: Arc<SharedBrewRecording>,
 
-        let relay_controller = RelayController::new(gpio19)?;
+    // Weight and flow curve of the last brew, exported as CSV over HTTP
+    brew_trace: Arc<SharedBrewTrace>,
+
+    // Cancelled by `shutdown` - every spawned task watches it
+    shutdown_token: Arc<ShutdownToken>,
+    // Raised by the scale task once it has disconnected and returned - set while it runs
+    scale_stopped: Option<Arc<Signal<CriticalSectionRawMutex, ()>>>,
+    stopped: bool,
+}
+
+impl EspressoController {
+    pub async fn new(gpio19: Gpio19) -> Result<Self, GravelError> {
+        Self::new_with_relay(RelayController::single(gpio19.downgrade())?).await
+    }
 
+    /// Build the controller around a pre-configured relay (e.g. a PWM relay from `RelayController::new_pwm`)
+    pub async fn new_with_relay(relay_controller: RelayController) -> Result<Self, GravelError> {
         // Initialize NVS storage (optional - will use defaults if it fails)
         let nvs_storage = match NvsStorage::new().await {
             Ok(storage) => {
@@ -109,18 +160,231 @@ impl EspressoController {
                 None
             }
         };
+        Ok(Self::with_parts(relay_controller, nvs_storage).await)
+    }
 
-        // Overshoot controller is now integrated into the state machine
-        let mut brew_controller = BrewController::new();
-        // Set initial target weight from default config
-        brew_controller.set_target_weight(BrewConfig::default().target_weight_g);
+    /// Off-device controller for host tests - a simulated relay, storage over a `MemoryStore` and
+    /// no BLE. Drive it with `dispatch` and `send_command`; the scale is whatever the test feeds in
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn new_for_test(relay_controller: RelayController, storage: NvsStorage) -> Self {
+        Self::with_parts(relay_controller, Some(Arc::new(storage))).await
+    }
+
+    /// Everything past hardware bring-up: channels, shared state and the config restored from
+    /// `nvs_storage`
+    async fn with_parts(
+        mut relay_controller: RelayController,
+        nvs_storage: Option<Arc<NvsStorage>>,
+    ) -> Self {
+        let scale_data_channel = Arc::new(ScaleDataChannel::default());
+        let ble_status_channel = Arc::new(Channel::new());
+        let websocket_command_channel = Arc::new(Channel::new());
+        let scale_command_channel = Arc::new(Channel::new());
+        let scale_info_signal = Arc::new(ScaleInfoSignal::new());
+        let nearby_devices_signal = Arc::new(NearbyDevicesSignal::new());
+        let connection_stage_channel = Arc::new(ConnectionStageChannel::new());
+        let link_quality_signal = Arc::new(LinkQualitySignal::new());
+
+        let state_manager = StateManager::new();
+        let state_handle = state_manager.get_state_handle();
+
+        let scale_client = BookooScale::new(
+            Arc::clone(&scale_data_channel),
+            Arc::clone(&ble_status_channel),
+        );
+
+        let brew_recording = Arc::new(SharedBrewRecording::new(BrewRecording::default()));
+        let brew_trace = Arc::new(SharedBrewTrace::new(BrewTrace::default()));
 
         // 🚀 INITIALIZE WORLD-CLASS EVENT BUS!
         let event_bus = Arc::new(EventBus::new());
 
         info!("🌟 World-class EventBus initialized with type-safe subscriptions!");
 
-        Ok(Self {
+        let websocket_server = WebSocketServer::new(
+            Arc::clone(&state_handle),
+            Arc::clone(&websocket_command_channel),
+            Arc::clone(&brew_recording),
+            Arc::clone(&brew_trace),
+            event_bus.stats(),
+            8080,
+        );
+
+        let storage_available = nvs_storage.as_ref().is_some_and(|storage| storage.is_persistent());
+        state_manager.set_storage_available(storage_available).await;
+        state_manager.set_relay_boot_check(relay_controller.boot_check()).await;
+
+        // Relay wear counters survive reboots
+        if let Some(ref storage) = nvs_storage {
+            let stats = storage.get_statistics().await;
+            relay_controller.restore_stats(stats.relay_activations, stats.relay_on_time_ms);
+            state_manager.set_relay_stats(relay_controller.stats()).await;
+        }
+
+        // Overshoot controller is now integrated into the state machine
+        let mut brew_controller = BrewController::new();
+
+        // Restore the persisted config (defaults when nothing is stored, invalid values fall back)
+        let mut config = match nvs_storage {
+            Some(ref storage) => storage.load_config().await,
+            None => BrewConfig::default(),
+        };
+        let defaults = BrewConfig::default();
+        if let Err(e) = config.set_target_weight(config.target_weight_g) {
+            warn!("Ignoring stored target weight: {}", e);
+            config.cups = 1;
+            config.target_weight_g = defaults.target_weight_g;
+        }
+        if let Err(e) = config.set_brew_ratio(config.brew_ratio) {
+            warn!("Ignoring stored brew ratio: {}", e);
+            config.brew_ratio = None;
+        }
+        if config.dose_g.is_some_and(|dose_g| !(MIN_DOSE_G..=MAX_DOSE_G).contains(&dose_g)) {
+            warn!("Ignoring stored dose: {:?}g", config.dose_g);
+            config.dose_g = None;
+        }
+        if let Err(e) = config.set_cups(config.cups) {
+            warn!("Ignoring stored cup count: {}", e);
+            config.cups = 1;
+        }
+        brew_controller.set_target_weight(config.total_target_weight_g());
+        brew_controller.set_auto_tare_enabled(config.auto_tare);
+        brew_controller
+            .set_tare_before_brew(config.tare_before_brew, config.tare_verify_timeout_ms);
+        if let Some(ref storage) = nvs_storage {
+            let profiles = storage.load_overshoot_profiles().await;
+            if let Err(e) = brew_controller.restore_overshoot_profiles(profiles) {
+                warn!("Ignoring stored overshoot profiles: {}", e);
+            }
+        }
+        brew_controller.set_predictive_stop_enabled(config.predictive_stop);
+        // Auto mode re-derives predictive stop from the restored profile's confidence
+        brew_controller.set_predictive_stop_auto(config.predictive_stop_auto);
+        if let Err(e) = brew_controller.set_auto_tare_params(config.auto_tare_params()) {
+            warn!("Ignoring stored auto-tare parameters: {}", e);
+        }
+        if let Err(e) = brew_controller.set_auto_tare_cooldown_secs(config.auto_tare_cooldown_secs) {
+            warn!("Ignoring stored auto-tare cooldown: {}", e);
+        }
+        if let Err(e) = brew_controller.set_auto_tare_hold_ms(config.auto_tare_hold_ms) {
+            warn!("Ignoring stored auto-tare hold: {}", e);
+        }
+        if let Err(e) = brew_controller.set_prediction_window(
+            config.prediction_safety_margin_ms,
+            config.prediction_window_multiplier,
+        ) {
+            warn!("Ignoring stored prediction window: {}", e);
+        }
+        if let Err(e) = brew_controller.set_prediction_thresholds(
+            config.min_flow_for_prediction,
+            config.flow_stopped_threshold,
+            config.prediction_start_delay_ms,
+        ) {
+            warn!("Ignoring stored prediction thresholds: {}", e);
+        }
+        if let Err(e) = brew_controller.set_overshoot_warmup_brews(config.overshoot_warmup_brews) {
+            warn!("Ignoring stored overshoot warmup: {}", e);
+        }
+        if let Err(e) = brew_controller.set_overshoot_target_g(config.overshoot_target_g) {
+            warn!("Ignoring stored overshoot setpoint: {}", e);
+        }
+        if let Err(e) = brew_controller.set_overshoot_learning_schedule(config.overshoot_learning) {
+            warn!("Ignoring stored overshoot learning schedule: {}", e);
+        }
+        brew_controller.set_stop_mode(config.stop_mode);
+        brew_controller.set_brew_startup_ignore_ms(config.brew_startup_ignore_ms);
+        if let Err(e) = brew_controller.set_flow_stop_hold_ms(config.flow_stop_hold_ms) {
+            warn!("Ignoring stored flow stop hold: {}", e);
+        }
+        if let Err(e) =
+            brew_controller.set_scale_reconnect_grace_ms(config.scale_reconnect_grace_ms)
+        {
+            warn!("Ignoring stored scale reconnect grace: {}", e);
+        }
+        if let Err(e) = brew_controller.set_settle_stability_g(config.settle_stability_g) {
+            warn!("Ignoring stored settle stability: {}", e);
+        }
+        if let Err(e) = brew_controller.set_brew_time_ms(config.brew_time_ms) {
+            warn!("Ignoring stored brew time: {}", e);
+        }
+        if config.flow_profile.is_some() && !relay_controller.supports_pwm(PRIMARY_RELAY_CHANNEL) {
+            warn!("Ignoring stored flow profile: the pump relay has no PWM output");
+            config.flow_profile = None;
+        }
+        if let Err(e) = brew_controller.set_flow_profile(config.flow_profile.clone()) {
+            warn!("Ignoring stored flow profile: {}", e);
+            config.flow_profile = None;
+        }
+        config.set_auto_tare_params(brew_controller.get_auto_tare_params());
+        config.auto_tare_cooldown_secs = brew_controller.get_auto_tare_cooldown_secs();
+        config.auto_tare_hold_ms = brew_controller.get_auto_tare_hold_ms();
+        (config.prediction_safety_margin_ms, config.prediction_window_multiplier) =
+            brew_controller.get_prediction_window();
+        (
+            config.min_flow_for_prediction,
+            config.flow_stopped_threshold,
+            config.prediction_start_delay_ms,
+        ) = brew_controller.get_prediction_thresholds();
+        config.overshoot_warmup_brews = brew_controller.get_overshoot_warmup_brews();
+        config.overshoot_target_g = brew_controller.get_overshoot_target_g();
+        config.overshoot_learning = brew_controller.get_overshoot_learning_schedule();
+        config.predictive_stop = brew_controller.is_predictive_stop_enabled();
+        config.flow_stop_hold_ms = brew_controller.get_flow_stop_hold_ms();
+        config.scale_reconnect_grace_ms = brew_controller.get_scale_reconnect_grace_ms();
+        config.settle_stability_g = brew_controller.get_settle_stability_g();
+        config.brew_time_ms = brew_controller.get_brew_time_ms();
+        let stored_prefix = config.scale_name_prefix.clone();
+        if let Err(e) = config.set_scale_name_prefix(&stored_prefix) {
+            warn!("Ignoring stored scale name prefix: {}", e);
+            config.scale_name_prefix = DEFAULT_SCALE_NAME_PREFIX.to_string();
+        }
+        if let Err(e) = config.set_weight_rate_limit(config.weight_rate_limit_g_per_s) {
+            warn!("Ignoring stored weight rate limit: {}", e);
+            config.weight_rate_limit_g_per_s = WEIGHT_RATE_LIMIT_G_PER_S;
+        }
+        let scale_name_prefix = Arc::new(StdMutex::new(config.scale_name_prefix.clone()));
+        if let Err(e) = config.set_scale_keep_alive_ms(config.scale_keep_alive_ms) {
+            warn!("Ignoring stored scale keep-alive: {}", e);
+            config.scale_keep_alive_ms = SCALE_KEEP_ALIVE_MS;
+        }
+        if let Err(e) = config.set_brew_trace_interval_ms(config.brew_trace_interval_ms) {
+            warn!("Ignoring stored brew trace interval: {}", e);
+            config.brew_trace_interval_ms = BREW_TRACE_INTERVAL_MS;
+        }
+        if let Err(e) = config.set_ble_priority_broadcast_ms(config.ble_priority_broadcast_ms) {
+            warn!("Ignoring stored BLE priority broadcast interval: {}", e);
+            config.ble_priority_broadcast_ms = BLE_PRIORITY_BROADCAST_MS;
+        }
+        if let Err(e) = config.set_display_refresh_ms(config.display_refresh_ms) {
+            warn!("Ignoring stored display refresh interval: {}", e);
+            config.display_refresh_ms = DISPLAY_REFRESH_MS;
+        }
+        if let Err(e) = config.set_weight_stream_ms(config.weight_stream_ms) {
+            warn!("Ignoring stored weight stream interval: {}", e);
+            config.weight_stream_ms = WEIGHT_STREAM_MS;
+        }
+        let scale_keep_alive_ms = Arc::new(AtomicU32::new(config.scale_keep_alive_ms));
+        let mut scale_event_detector = ScaleEventDetector::new();
+        scale_event_detector.set_shutdown_thresholds(
+            config.shutdown_flow_rate_g_per_s,
+            config.shutdown_weight_jump_g,
+        );
+        info!(
+            "⚙️ Config restored: target={:.1}g x {} cup(s)",
+            config.target_weight_g, config.cups
+        );
+        state_manager.update_config(config).await;
+        state_manager
+            .set_overshoot_status(brew_controller.overshoot_status())
+            .await;
+        let profiles = brew_controller.overshoot_profiles();
+        info!("☕ Overshoot profile: {}", profiles.active);
+        let names = profiles.names();
+        state_manager
+            .set_overshoot_profiles(profiles.active, names)
+            .await;
+
+        Self {
             state_manager,
             scale_client,
             websocket_server,
@@ -133,29 +397,68 @@ impl EspressoController {
             event_bus,
 
             // 🕵️ INTELLIGENT SCALE EVENT DETECTION!
-            scale_event_detector: ScaleEventDetector::new(),
+            scale_event_detector,
 
             // Legacy channels (being phased out)
             scale_data_channel,
             ble_status_channel,
             websocket_command_channel,
             scale_command_channel,
+            scale_info_signal,
+            nearby_devices_signal,
+            connection_stage_channel,
+            link_quality_signal,
+            scale_name_prefix,
+            scale_keep_alive_ms,
 
-            // Predictive stopping
-            pending_stop_time: None,
+            // Tare verification
+            pending_tare_check: None,
+            command_confirmation: CommandConfirmation::default(),
 
-            // Timer detection state
-            last_timer_ms: None,
-            current_timer_running: false,
+            shot_started_at: None,
 
-            // Scale shutdown detection
-            timer_start_time: None,
-            consecutive_disconnection_count: 0,
+            // Brew progress throttling
+            last_progress_at: None,
 
-            // Brewing startup delay
-            brew_start_time: None,
+            // Weight sanity filter
+            last_plausible_weight: None,
+            suspect_weight: None,
 
-        })
+            // Link quality warning
+            link_quality_warned: false,
+
+            buttons: Vec::new(),
+
+            buzzer: None,
+            buzzer_channel: Arc::new(Channel::new()),
+
+            display: None,
+
+            brew_recording,
+            brew_trace,
+
+            shutdown_token: Arc::new(ShutdownToken::new()),
+            scale_stopped: None,
+            stopped: false,
+        }
+    }
+
+    /// Register a physical button - it publishes user events once the controller starts
+    pub fn add_button(&mut self, button: ButtonInput) {
+        self.buttons.push(button);
+    }
+
+    /// Register a buzzer for brew-complete and error alerts
+    pub fn set_buzzer(&mut self, buzzer: BuzzerController) {
+        self.buzzer = Some(buzzer);
+    }
+
+    /// Register an OLED display for live brew status and alerts
+    pub fn set_display(&mut self, mut display: DisplayController) {
+        if let Err(e) = display.show_boot_screen() {
+            warn!("Display boot screen failed: {:?}", e);
+        }
+        self.display = Some(display);
     }
 
     pub async fn start(
@@ -163,54 +466,145 @@ impl EspressoController {
         spawner: Spawner,
         wifi_connected: bool,
         ble_needs_reset: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), GravelError> {
         info!("Starting Espresso Controller with Embassy tasks");
 
-        // Handle BLE initialization based on WiFi provisioning status
+        // The display is registered before the stored config can reach it
+        let display_refresh_ms = self.state_manager.get_config().await.display_refresh_ms;
+        if let Some(display) = self.display.as_mut() {
+            display.set_refresh_interval_ms(display_refresh_ms);
+        }
+
+        // Our BLE stack is always down before provisioning runs (see WifiManager), so the
+        // scale stack comes up the same way whether or not provisioning happened
         if ble_needs_reset {
-            info!("🔄 BLE stack cleaned up by WiFi provisioning - reinitializing for scale");
-            // WiFi provisioning already cleaned up BLE stack, just reinitialize
-            BookooScale::initialize()
-                .map_err(|e| format!("BLE init after provisioning failed: {:?}", e))?;
-        } else if !wifi_connected {
-            info!("🔵 No WiFi provisioning conflict - initializing scale BLE");
-            BookooScale::initialize().map_err(|e| format!("BLE init failed: {:?}", e))?;
+            info!("🔀 BLE coexistence: provisioning finished - initializing scale BLE");
         } else {
-            info!("🔵 WiFi connected without provisioning - initializing scale BLE");
-            BookooScale::initialize().map_err(|e| format!("BLE init failed: {:?}", e))?;
+            info!(
+                "🔵 Initializing scale BLE (WiFi {})",
+                if wifi_connected { "connected" } else { "not connected" }
+            );
         }
+        BookooScale::initialize()?;
 
         // Clone references for the tasks
         let websocket_server = self.websocket_server.clone();
-        let _state_handle = self.state_manager.get_state_handle();
+        let state_handle = self.state_manager.get_state_handle();
 
         // Create a new scale client for the task (since tasks own their data)
         let scale_client = BookooScale::new(
             Arc::clone(&self.scale_data_channel),
             Arc::clone(&self.ble_status_channel),
-        );
+        )
+        .with_info_signal(Arc::clone(&self.scale_info_signal))
+        .with_devices_signal(Arc::clone(&self.nearby_devices_signal))
+        .with_stage_channel(Arc::clone(&self.connection_stage_channel))
+        .with_link_quality_signal(Arc::clone(&self.link_quality_signal))
+        .with_name_prefix(Arc::clone(&self.scale_name_prefix))
+        .with_keep_alive_interval(Arc::clone(&self.scale_keep_alive_ms));
 
         // Spawn scale task with command channel
+        let scale_stopped = Arc::new(Signal::new());
         spawner
             .spawn(scale_task(
                 scale_client,
                 Arc::clone(&self.scale_command_channel),
+                Arc::clone(&scale_stopped),
             ))
-            .map_err(|_| "Failed to spawn scale task")?;
+            .map_err(|_| GravelError::TaskSpawn("scale"))?;
+        self.scale_stopped = Some(scale_stopped);
 
         // Spawn WebSocket/HTTP server task (non-fatal if it fails)
-        if let Err(_) = spawner.spawn(websocket_task(websocket_server)) {
+        if let Err(_) = spawner.spawn(websocket_task(
+            websocket_server,
+            Arc::clone(&self.shutdown_token),
+        )) {
             warn!("Failed to spawn WebSocket task - continuing without HTTP server");
         }
 
+        // Spawn WebSocket broadcast task (non-fatal - clients fall back to polling)
+        if let Err(_) = spawner.spawn(ws_broadcast_task(
+            Arc::clone(&self.event_bus),
+            state_handle,
+            self.websocket_server.clients(),
+            Arc::clone(&self.shutdown_token),
+        )) {
+            warn!("Failed to spawn WebSocket broadcast task - web clients will poll only");
+        }
+
         // Spawn scale data bridge task (CRITICAL - bridges scale data to event bus)
         spawner
             .spawn(scale_data_bridge_task(
                 Arc::clone(&self.scale_data_channel),
                 Arc::clone(&self.ble_status_channel),
+                Arc::clone(&self.scale_info_signal),
+                Arc::clone(&self.nearby_devices_signal),
+                Arc::clone(&self.connection_stage_channel),
+                Arc::clone(&self.link_quality_signal),
                 Arc::clone(&self.event_bus),
+                Arc::clone(&self.shutdown_token),
             ))
-            .map_err(|_| "Failed to spawn scale data bridge task")?;
+            .map_err(|_| GravelError::TaskSpawn("scale data bridge"))?;
+
+        // Spawn brew recorder task (non-fatal - recording is a debugging aid)
+        if let Err(_) = spawner.spawn(recorder_task(
+            Arc::clone(&self.event_bus),
+            Arc::clone(&self.brew_recording),
+            Arc::clone(&self.shutdown_token),
+        )) {
+            warn!("Failed to spawn brew recorder task - recording unavailable");
+        }
+
+        // Spawn physical button tasks (non-fatal - web and scale controls still work)
+        for button in self.buttons.drain(..) {
+            let name = button.config().name;
+            let event_bus = Arc::clone(&self.event_bus);
+            let shutdown = Arc::clone(&self.shutdown_token);
+            if let Err(_) = spawner.spawn(button_task(button, event_bus, shutdown)) {
+                warn!("Failed to spawn button task for '{}'", name);
+            }
+        }
+
+        // Spawn buzzer task (non-fatal - alerts are a convenience)
+        if let Some(buzzer) = self.buzzer.take() {
+            let patterns = Arc::clone(&self.buzzer_channel);
+            let shutdown = Arc::clone(&self.shutdown_token);
+            if let Err(_) = spawner.spawn(buzzer_task(buzzer, patterns, shutdown)) {
+                warn!("Failed to spawn buzzer task - continuing without audible alerts");
+            }
+        }
+
+        // Published once the broadcast task is listening; `storage_available` keeps it in status
+        if !self.nvs_storage.as_ref().is_some_and(|storage| storage.is_persistent()) {
+            let message = "Settings storage unavailable - settings will not be saved".to_string();
+            self.state_manager
+                .add_log_with_level(LogLevel::Warn, message.clone())
+                .await;
+            self.get_event_publisher()
+                .publish(SystemEvent::Safety(SafetyEvent::SystemAlert {
+                    level: AlertLevel::Warning,
+                    message,
+                }))
+                .await;
+        }
+
+        // A pin that won't go low can't be fixed in software - make sure someone sees it
+        if let RelayBootCheck::StuckHigh { channel, name } = self.relay_controller.boot_check() {
+            let message = format!(
+                "Relay channel {} '{}' reads high with the output off - check the wiring",
+                channel, name
+            );
+            self.state_manager.set_error(Some(message.clone())).await;
+            self.state_manager
+                .add_log_with_level(LogLevel::Error, message.clone())
+                .await;
+            self.get_event_publisher()
+                .publish(SystemEvent::Safety(SafetyEvent::SystemAlert {
+                    level: AlertLevel::Critical,
+                    message,
+                }))
+                .await;
+        }
 
         // 🚀 Initialize state machine with proper startup events
         info!("🎯 Initializing state machine with startup events");
@@ -225,49 +619,57 @@ impl EspressoController {
         for output in ble_outputs {
             self.handle_brew_output(output).await;
         }
-        
-        // Tell state machine that BLE scanning is starting
-        let scanning_outputs = self.brew_controller.handle_input(BrewInput::BleScanning);
-        for output in scanning_outputs {
-            self.handle_brew_output(output).await;
-        }
+        // Scanning, connecting and connected follow from the scale task's connection stages
 
         // 🚀 Run the WORLD-CLASS event-driven control loop!
         self.event_driven_control_loop().await;
 
+        // Only returns once the shutdown token is cancelled
+        self.shutdown().await;
         Ok(())
     }
 
-    async fn main_control_loop(&mut self) {
-        info!("Starting main control loop with Embassy select");
+    /// Cancel this from outside to stop a running controller - `start` shuts down and returns
+    pub fn shutdown_token(&self) -> Arc<ShutdownToken> {
+        Arc::clone(&self.shutdown_token)
+    }
 
-        loop {
-            let scale_data_fut = self.scale_data_channel.receive();
-            let ble_status_fut = self.ble_status_channel.receive();
-            let websocket_cmd_fut = self.websocket_command_channel.receive();
-            let periodic_timer = Timer::after(Duration::from_millis(100));
+    /// Stop cleanly before a reboot or teardown: relay off, every task told to stop, the scale
+    /// disconnected and pending settings written. Running it again does nothing
+    pub async fn shutdown(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.stopped = true;
+        info!("🛑 Shutting down controller");
 
-            match select(
-                select(scale_data_fut, ble_status_fut),
-                select(websocket_cmd_fut, periodic_timer),
-            )
-            .await
-            {
-                Either::First(Either::First(scale_data)) => {
-                    self.handle_scale_data(scale_data).await;
-                }
-                Either::First(Either::Second(ble_status)) => {
-                    self.handle_ble_status_change(ble_status).await;
-                }
-                Either::Second(Either::First(command)) => {
-                    self.handle_websocket_command(command).await;
-                }
-                Either::Second(Either::Second(_)) => {
-                    // Periodic update
-                    self.periodic_update().await;
-                }
+        // Relay first - nothing below may leave the pump running
+        if let Err(e) = self.relay_controller.turn_off_immediately() {
+            error!("🚨 RELAYS FAILED OFF: {:?}", e);
+        }
+        self.shutdown_token.cancel();
+
+        // The scale task disconnects itself - cancelling it mid-operation would leave the link up
+        if let Some(scale_stopped) = self.scale_stopped.take() {
+            if self.scale_command_channel.try_send(ScaleCommand::Shutdown).is_err() {
+                warn!("Scale command channel full - scale may not disconnect cleanly");
+            }
+            let timeout = Timer::after(Duration::from_millis(SCALE_DISCONNECT_TIMEOUT_MS));
+            if let Either::Second(_) = select(scale_stopped.wait(), timeout).await {
+                warn!(
+                    "Scale didn't disconnect within {}ms - continuing shutdown",
+                    SCALE_DISCONNECT_TIMEOUT_MS
+                );
+            }
+        }
+
+        // Debounced writes would otherwise be lost
+        if let Some(ref storage) = self.nvs_storage {
+            if let Err(e) = storage.flush_all().await {
+                warn!("Failed to persist settings on shutdown: {}", e);
             }
         }
+        info!("🛑 Controller stopped");
     }
 
     /// 🚀 PURE EVENT-DRIVEN CONTROL LOOP! NO LEGACY GARBAGE!
@@ -279,24 +681,39 @@ impl EspressoController {
         let event_bus = Arc::clone(&self.event_bus);
         let mut all_events_subscriber = event_bus.subscriber();
 
+        // Hang protection: if this loop stops iterating the chip resets (relay is forced OFF at boot)
+        let watchdog = match TaskWatchdog::subscribe_current_task(CONTROL_LOOP_WATCHDOG_TIMEOUT_MS) {
+            Ok(watchdog) => Some(watchdog),
+            Err(e) => {
+                error!("🐕 Failed to arm task watchdog: {:?} - running without hang protection", e);
+                None
+            }
+        };
+
         // UNIFIED EVENT LOOP - process all events including hardware side effects!
         loop {
-            let event_fut = all_events_subscriber.next_event();
+            if let Some(ref watchdog) = watchdog {
+                watchdog.feed();
+            }
+            if self.shutdown_token.is_cancelled() {
+                info!("🛑 Control loop stopping");
+                break;
+            }
+
+            let event_fut = all_events_subscriber.next_timed_event();
+            let websocket_cmd_fut = self.websocket_command_channel.receive();
             let periodic_timer = Timer::after(Duration::from_millis(100));
 
-            match select(event_fut, periodic_timer).await {
-                Either::First(event) => {
-                    // Handle all event types including hardware side effects
-                    match &event {
-                        SystemEvent::Hardware(_) => {
-                            self.handle_hardware_side_effects(event).await;
-                        }
-                        _ => {
-                            self.handle_system_event(event).await;
-                        }
+            match select3(event_fut, websocket_cmd_fut, periodic_timer).await {
+                Either3::First(timed) => {
+                    let waited = timed.age();
+                    if waited > Duration::from_millis(EVENT_BUS_SLOW_DELIVERY_MS) {
+                        warn!("📡 {:?} waited {}ms on the bus", timed.event, waited.as_millis());
                     }
+                    self.process_event(timed.event).await
                 }
-                Either::Second(_) => {
+                Either3::Second(command) => self.handle_websocket_command(command).await,
+                Either3::Third(_) => {
                     // Periodic tick
                     let event_publisher = event_bus.publisher();
                     event_publisher
@@ -307,44 +724,148 @@ impl EspressoController {
         }
     }
 
+    /// Route one bus event - hardware side effects or system event handling
+    async fn process_event(&mut self, event: SystemEvent) {
+        match &event {
+            SystemEvent::Hardware(_) => {
+                self.handle_hardware_side_effects(event).await;
+            }
+            _ => {
+                self.handle_system_event(event).await;
+            }
+        }
+    }
+
+    /// Handle an event as the control loop would, then every event it led to (relay side
+    /// effects, state machine follow-ups) until the bus is quiet
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn dispatch(&mut self, event: SystemEvent) {
+        let event_bus = Arc::clone(&self.event_bus);
+        let mut subscriber = event_bus.subscriber();
+        self.process_event(event).await;
+        while let Some(event) = subscriber.try_next_event() {
+            self.process_event(event).await;
+        }
+    }
+
+    /// A web command as it arrives over HTTP or WebSocket, followed through like `dispatch`
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn send_command(&mut self, command: WebSocketCommand) {
+        let event_bus = Arc::clone(&self.event_bus);
+        let mut subscriber = event_bus.subscriber();
+        self.handle_websocket_command(command).await;
+        while let Some(event) = subscriber.try_next_event() {
+            self.process_event(event).await;
+        }
+    }
+
+    /// What the web UI would see right now
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn snapshot(&self) -> crate::types::SystemState {
+        self.state_manager.get_full_state().await
+    }
+
+    /// Commands the controller sent to the scale - a test's stand-in for the scale task
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn scale_commands(&self) -> Arc<ScaleCommandChannel> {
+        Arc::clone(&self.scale_command_channel)
+    }
+
     /// ⚡ PURE HARDWARE SIDE EFFECTS HANDLER - NO DIRECT HARDWARE CALLS ELSEWHERE!
     async fn handle_hardware_side_effects(&mut self, event: SystemEvent) {
         if let SystemEvent::Hardware(hardware_event) = event {
             match hardware_event {
-                HardwareEvent::RelayOn => {
-                    info!("⚡ HARDWARE: Relay ON");
-                    if let Err(e) = self.relay_controller.turn_on().await {
+                HardwareEvent::RelayOn { channel } => {
+                    info!("⚡ HARDWARE: Relay {} ON", channel);
+                    if let Err(e) = self.relay_controller.turn_on_channel(channel).await {
                         error!("🚨 RELAY FAILED ON: {:?}", e);
                         self.get_event_publisher()
                             .emergency_stop("Relay failure".to_string())
                             .await;
                     } else {
-                        self.state_manager.set_relay_enabled(true).await;
+                        self.sync_relay_state().await;
                     }
                 }
-                HardwareEvent::RelayOff => {
-                    info!("⚡ HARDWARE: Relay OFF");
-                    if let Err(e) = self.relay_controller.turn_off().await {
+                HardwareEvent::RelayOff { channel } => {
+                    info!("⚡ HARDWARE: Relay {} OFF", channel);
+                    if let Err(e) = self.relay_controller.turn_off_channel(channel).await {
                         error!("🚨 RELAY FAILED OFF: {:?}", e);
                     } else {
-                        self.state_manager.set_relay_enabled(false).await;
+                        self.sync_relay_state().await;
+                    }
+                }
+                HardwareEvent::RelayPower { channel, fraction } => {
+                    debug!("⚡ HARDWARE: Relay {} power {:.0}%", channel, fraction * 100.0);
+                    match self.relay_controller.set_channel_power(channel, fraction).await {
+                        Ok(()) => {
+                            self.sync_relay_state().await;
+                        }
+                        Err(RelayError::InvalidPower(_))
+                        | Err(RelayError::InvalidChannel(_))
+                        | Err(RelayError::PwmUnavailable) => {
+                            warn!("Relay {} power {} not applied - leaving relay unchanged", channel, fraction);
+                        }
+                        Err(e) => {
+                            error!("🚨 RELAY POWER FAILED: {:?}", e);
+                            self.get_event_publisher()
+                                .emergency_stop("Relay failure".to_string())
+                                .await;
+                        }
+                    }
+                }
+                HardwareEvent::AllRelaysOff => {
+                    info!("⚡ HARDWARE: All relays OFF");
+                    if let Err(e) = self.relay_controller.turn_off_immediately() {
+                        error!("🚨 RELAYS FAILED OFF: {:?}", e);
                     }
+                    self.sync_relay_state().await;
                 }
                 HardwareEvent::SendScaleCommand(command) => {
                     info!("⚡ HARDWARE: Scale command {:?}", command);
+                    let is_tare = matches!(command, ScaleCommand::Tare);
                     if let Err(_) = self.scale_command_channel.try_send(command) {
                         warn!("Scale command channel full");
+                    } else if is_tare
+                        && self.state_manager.get_config().await.tare_verify_timeout_ms > 0
+                    {
+                        self.pending_tare_check = Some(Instant::now());
                     }
                 }
                 HardwareEvent::DisplayUpdate {
-                    state: _display_state,
+                    state: display_state,
                 } => {
                     debug!("⚡ HARDWARE: Display update");
-                    // TODO: Update display when implemented
+                    if let Some(display) = self.display.as_mut() {
+                        if let Err(e) = display.update_state(display_state) {
+                            warn!("Display update failed: {:?}", e);
+                        }
+                    }
                 }
                 HardwareEvent::DisplayAlert { message, duration } => {
                     info!("⚡ HARDWARE: Display alert: {} for {:?}", message, duration);
-                    // TODO: Show alert on display
+                    if let Some(display) = self.display.as_mut() {
+                        if let Err(e) = display.show_alert(message, duration) {
+                            warn!("Display alert failed: {:?}", e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Mirror relay hardware state into the state manager (enabled = any channel on)
+    async fn sync_relay_state(&self) {
+        let any_on = self.relay_controller.is_any_on().await;
+        self.state_manager.set_relay_enabled(any_on).await;
+
+        let stats = self.relay_controller.stats();
+        self.state_manager.set_relay_stats(stats).await;
+
+        // Counters only move on edges - persist once the relay is back off (at most once per shot)
+        if !any_on {
+            if let Some(ref storage) = self.nvs_storage {
+                if let Err(e) = storage.update_relay_stats(stats.activations, stats.total_on_ms).await {
+                    warn!("Failed to persist relay stats: {:?}", e);
                 }
             }
         }
@@ -383,6 +904,35 @@ impl EspressoController {
         self.event_bus.publisher()
     }
 
+    /// Snapshot current status for the OLED display
+    async fn build_display_state(&self) -> DisplayState {
+        let state = self.state_manager.get_full_state().await;
+        let scale_data = state.scale_data.as_ref();
+        DisplayState {
+            weight_g: scale_data.map(|d| d.weight_g).unwrap_or(0.0),
+            target_weight_g: state.config.total_target_weight_g(),
+            flow_rate_g_per_s: scale_data.map(|d| d.flow_rate_g_per_s).unwrap_or(0.0),
+            timer_running: scale_data.map(|d| d.timer_running).unwrap_or(false),
+            timer_ms: scale_data.map(|d| d.timestamp_ms).unwrap_or(0),
+            brew_state: format!("{:?}", self.brew_controller.get_system_state()),
+            ble_connected: state.ble_connected,
+            battery_percent: scale_data.map(|d| d.battery_percent).unwrap_or(0),
+            error: state.last_error.clone(),
+        }
+    }
+
+    /// Queue an audible alert, honouring the buzzer toggle and quiet hours
+    async fn sound_alert(&self, pattern: BuzzerPattern) {
+        let config = self.state_manager.get_config().await;
+        if !buzzer_allowed(config.buzzer_enabled, config.quiet_hours.as_ref()) {
+            debug!("🔕 Buzzer muted - skipping {:?}", pattern);
+            return;
+        }
+        if let Err(_) = self.buzzer_channel.try_send(pattern) {
+            warn!("Buzzer queue full, dropping {:?}", pattern);
+        }
+    }
+
     /// Reset scale event detection (useful when reconnecting or troubleshooting)
     pub fn reset_scale_event_detection(&mut self) {
         info!("🔄 Resetting scale event detection state");
@@ -406,6 +956,7 @@ impl EspressoController {
             scale_detector_stable_weight: stable_weight,
             system_enabled: self.brew_controller.is_system_enabled(),
             state_manager_state: self.state_manager.get_full_state().await,
+            safety_status: self.safety_controller.status(),
         }
     }
 
@@ -413,7 +964,7 @@ impl EspressoController {
     async fn handle_scale_event(&mut self, scale_event: ScaleEvent) {
         match scale_event {
             ScaleEvent::WeightChanged { data } => {
-                info!(
+                debug!(
                     "📊 Scale: {:.2}g, flow: {:.2}g/s",
                     data.weight_g, data.flow_rate_g_per_s
                 );
@@ -421,6 +972,29 @@ impl EspressoController {
                 // Update safety controller with data receipt
                 self.safety_controller.update_data_received();
 
+                // A corrupt frame must not reach the detector, tare or brewing logic
+                let config = self.state_manager.get_config().await;
+                let previous = self.last_plausible_weight;
+                if !weight_plausible(
+                    config.weight_rate_limit_g_per_s,
+                    &mut self.last_plausible_weight,
+                    &mut self.suspect_weight,
+                    &data,
+                ) {
+                    let last_g = previous.map_or(0.0, |(weight_g, _)| weight_g);
+                    warn!(
+                        "🚫 Implausible weight {:.1}g after {:.1}g - frame dropped",
+                        data.weight_g, last_g
+                    );
+                    self.state_manager
+                        .add_log(format!(
+                            "Ignored implausible weight {:.1}g (last {:.1}g)",
+                            data.weight_g, last_g
+                        ))
+                        .await;
+                    return;
+                }
+
                 // 🕵️ INTELLIGENT EVENT DETECTION - Analyze raw data for patterns!
                 let detected_events = self.scale_event_detector.process_data(&data);
                 
@@ -434,31 +1008,48 @@ impl EspressoController {
                 }
 
                 // Update state manager
+                let (timer_running, stable_weight_g) = self.get_scale_detector_state();
+                self.state_manager
+                    .set_scale_detector_status(ScaleDetectorStatus {
+                        timer_running,
+                        stable_weight_g,
+                    })
+                    .await;
                 self.state_manager.update_scale_data(data.clone()).await;
 
+                self.check_tare_verification(Some(data.weight_g)).await;
+
                 // Send to brewing state machine
-                let brew_input = BrewInput::ScaleData(data);
+                let brew_input = BrewInput::ScaleData(data.clone());
                 let outputs = self.brew_controller.handle_input(brew_input);
 
                 // Process state machine outputs
                 for output in outputs {
                     self.handle_brew_output(output).await;
                 }
+
+                // After the outputs, so the frame that starts a brew is its first sample
+                self.brew_trace
+                    .lock()
+                    .await
+                    .record(&data, config.brew_trace_interval_ms);
+                self.state_manager
+                    .set_brew_snapshot(self.brew_controller.snapshot())
+                    .await;
+
+                self.publish_brew_progress(data.received_at).await;
             }
             ScaleEvent::Connected { info } => {
                 info!("🔗 Scale connected: {} {}", info.brand, info.model);
                 self.state_manager.set_ble_connected(true).await;
-                
-                // Notify state machine of scale connection
-                let brew_input = BrewInput::ScaleConnected;
-                let outputs = self.brew_controller.handle_input(brew_input);
-                for output in outputs {
-                    self.handle_brew_output(output).await;
-                }
+                self.state_manager.set_scale_info(Some(info)).await;
+                // The link is up, but the scale only counts as connected once it is subscribed
             }
             ScaleEvent::Disconnected { reason } => {
                 warn!("❌ Scale disconnected: {}", reason);
                 self.state_manager.set_ble_connected(false).await;
+                self.state_manager.set_scale_info(None).await;
+                self.state_manager.set_link_quality(None).await;
                 
                 // Notify state machine of scale disconnection
                 let brew_input = BrewInput::ScaleDisconnected;
@@ -467,6 +1058,47 @@ impl EspressoController {
                     self.handle_brew_output(output).await;
                 }
             }
+            ScaleEvent::InfoUpdated { info } => {
+                info!(
+                    "📇 Scale {} {} firmware {}",
+                    info.brand,
+                    info.model,
+                    info.version.as_deref().unwrap_or("unknown")
+                );
+                // Weights are parsed as grams - a scale set to ounces would brew to the wrong target
+                if let Some(ScaleUnit::Other(code)) = info.settings.map(|s| s.unit) {
+                    warn!("⚠️ Scale is not set to grams (unit code {:#04x})", code);
+                    self.state_manager
+                        .add_log(format!(
+                            "Scale is not set to grams (unit code {:#04x}) - switch it to grams, \
+                             weights will be wrong",
+                            code
+                        ))
+                        .await;
+                }
+                self.state_manager.set_scale_info(Some(info)).await;
+            }
+            ScaleEvent::LinkQualityUpdated { report } => {
+                if report.score < LINK_QUALITY_WARN_SCORE && !self.link_quality_warned {
+                    self.link_quality_warned = true;
+                    warn!("📶 Poor scale link: {:?}", report);
+                    self.state_manager
+                        .add_log(format!(
+                            "Scale link quality is poor ({}/100) - move the scale closer or away \
+                             from microwaves and other 2.4GHz devices",
+                            report.score
+                        ))
+                        .await;
+                } else if report.score >= LINK_QUALITY_RECOVER_SCORE && self.link_quality_warned {
+                    self.link_quality_warned = false;
+                    info!("📶 Scale link quality recovered ({}/100)", report.score);
+                }
+                self.state_manager.set_link_quality(Some(report)).await;
+            }
+            ScaleEvent::DevicesDiscovered { devices } => {
+                info!("📡 {} nearby devices available to pick from", devices.len());
+                self.state_manager.set_nearby_devices(devices).await;
+            }
             ScaleEvent::ButtonPressed(button) => {
                 info!("🔘 Scale button: {:?}", button);
                 // Convert to user event
@@ -505,27 +1137,569 @@ impl EspressoController {
         match user_event.clone() {
             UserEvent::SetTargetWeight(weight) => {
                 let mut config = self.state_manager.get_config().await;
-                config.target_weight_g = weight;
-                self.state_manager.update_config(config).await;
-                self.brew_controller.set_target_weight(weight);
+                if let Err(e) = config.set_target_weight(weight) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                let total = config.total_target_weight_g();
+                self.apply_config_change(config, |brew| {
+                    brew.set_target_weight(total);
+                    Ok(())
+                })
+                .await;
+            }
+            UserEvent::SetCups(cups) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_cups(cups) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                let total = config.total_target_weight_g();
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_target_weight(total);
+                        Ok(())
+                    })
+                    .await;
+                if applied {
+                    info!("☕ Batch brew: {} cup(s), total target {:.1}g", cups, total);
+                }
+                return;
             }
             UserEvent::SetAutoTare(enabled) => {
                 let mut config = self.state_manager.get_config().await;
                 config.auto_tare = enabled;
-                self.state_manager.update_config(config).await;
+                if !self.update_config(config).await {
+                    return;
+                }
+                let outputs = self.brew_controller.set_auto_tare_enabled(enabled);
+                for output in outputs {
+                    self.handle_brew_output(output).await;
+                }
+                return;
             }
-            UserEvent::SetPredictiveStop(enabled) => {
+            UserEvent::SetTareBeforeBrew(enabled) => {
                 let mut config = self.state_manager.get_config().await;
-                config.predictive_stop = enabled;
-                self.state_manager.update_config(config).await;
-            }
-            UserEvent::EmergencyStop => {
-                // Emergency stop bypasses state machine
-                self.get_event_publisher()
-                    .emergency_stop("User emergency stop".to_string())
+                config.tare_before_brew = enabled;
+                let timeout_ms = config.tare_verify_timeout_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_tare_before_brew(enabled, timeout_ms);
+                        Ok(())
+                    })
                     .await;
+                if applied {
+                    info!("⚖️ Tare before brew {}", if enabled { "on" } else { "off" });
+                }
+                return;
+            }
+            UserEvent::SetCommandConfirmation { command, enabled } => {
+                let mut config = self.state_manager.get_config().await;
+                config.set_requires_confirmation(command, enabled);
+                if !self.update_config(config).await {
+                    return;
+                }
+                info!(
+                    "✋ Web {} {} confirmation",
+                    command.as_str(),
+                    if enabled { "needs" } else { "no longer needs" }
+                );
+                return;
+            }
+            UserEvent::SetPredictiveStop(enabled) => {
+                let mut config = self.state_manager.get_config().await;
+                config.predictive_stop = enabled;
+                // Setting it by hand takes over from auto mode
+                let leave_auto = config.predictive_stop_auto;
+                config.predictive_stop_auto = false;
+                if !self.update_config(config).await {
+                    return;
+                }
+                if leave_auto {
+                    info!("🔮 Predictive stop set manually - leaving auto mode");
+                    self.brew_controller.set_predictive_stop_auto(false);
+                }
+                self.brew_controller.set_predictive_stop_enabled(enabled);
+            }
+            UserEvent::SetPredictiveStopAuto(enabled) => {
+                let mut config = self.state_manager.get_config().await;
+                config.predictive_stop_auto = enabled;
+                if !self.update_config(config).await {
+                    return;
+                }
+                info!("🔮 Predictive stop auto mode: {}", enabled);
+                let outputs = self.brew_controller.set_predictive_stop_auto(enabled);
+                for output in outputs {
+                    self.handle_brew_output(output).await;
+                }
+                return;
+            }
+            UserEvent::SetAutoTareEmptyThreshold(threshold_g) => {
+                let mut params = self.brew_controller.get_auto_tare_params();
+                params.empty_threshold_g = threshold_g;
+                self.apply_auto_tare_params(params, |brew| brew.set_auto_tare_params(params))
+                    .await;
+                return;
+            }
+            UserEvent::SetAutoTareStableReadings(readings) => {
+                let mut params = self.brew_controller.get_auto_tare_params();
+                params.stable_readings = readings;
+                self.apply_auto_tare_params(params, |brew| brew.set_auto_tare_params(params))
+                    .await;
+                return;
+            }
+            UserEvent::SetAutoTareProfile(profile) => {
+                self.apply_auto_tare_params(profile.params(), |brew| {
+                    brew.set_auto_tare_profile(profile);
+                    Ok(())
+                })
+                .await;
+                return;
+            }
+            UserEvent::SetAutoTareCooldown(secs) => {
+                let mut config = self.state_manager.get_config().await;
+                config.auto_tare_cooldown_secs = secs;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_auto_tare_cooldown_secs(secs))
+                    .await;
+                if applied {
+                    info!("⚖️ Auto-tare cooldown after brewing: {}s", secs);
+                }
+                return;
+            }
+            UserEvent::SetAutoTareHold(hold_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                config.auto_tare_hold_ms = hold_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_auto_tare_hold_ms(hold_ms))
+                    .await;
+                if applied {
+                    info!("⚖️ Auto-tare hold before taring: {}ms", hold_ms);
+                }
+                return;
+            }
+            UserEvent::SetPredictionWindow {
+                safety_margin_ms,
+                multiplier,
+            } => {
+                let mut config = self.state_manager.get_config().await;
+                config.prediction_safety_margin_ms = safety_margin_ms;
+                config.prediction_window_multiplier = multiplier;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_prediction_window(safety_margin_ms, multiplier)
+                    })
+                    .await;
+                if !applied {
+                    return;
+                }
+                info!(
+                    "🔮 Prediction window: learned delay +{}ms, x{:.1}",
+                    safety_margin_ms, multiplier
+                );
+                return;
+            }
+            UserEvent::SetOvershootWarmup(brews) => {
+                let mut config = self.state_manager.get_config().await;
+                config.overshoot_warmup_brews = brews;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_overshoot_warmup_brews(brews))
+                    .await;
+                if !applied {
+                    return;
+                }
+                self.state_manager
+                    .set_overshoot_status(self.brew_controller.overshoot_status())
+                    .await;
+                info!("🐢 Overshoot warmup: {} brews", brews);
+                return;
+            }
+            UserEvent::SetOvershootTarget(grams) => {
+                let mut config = self.state_manager.get_config().await;
+                config.overshoot_target_g = grams;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_overshoot_target_g(grams))
+                    .await;
+                if applied {
+                    info!("🎯 Overshoot setpoint: {:+.1}g", grams);
+                }
+                return;
+            }
+            UserEvent::SetOvershootLearning(schedule) => {
+                let mut config = self.state_manager.get_config().await;
+                config.overshoot_learning = schedule;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_overshoot_learning_schedule(schedule)
+                    })
+                    .await;
+                if !applied {
+                    return;
+                }
+                self.state_manager
+                    .set_overshoot_status(self.brew_controller.overshoot_status())
+                    .await;
+                info!("📈 Overshoot learning schedule: {:?}", schedule);
+                return;
+            }
+            UserEvent::SetStopMode(mode) => {
+                let mut config = self.state_manager.get_config().await;
+                config.stop_mode = mode;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_stop_mode(mode);
+                        Ok(())
+                    })
+                    .await;
+                if applied {
+                    info!("🛑 Stop mode: {:?}", mode);
+                }
+                return;
+            }
+            UserEvent::SetFlowStopHold(hold_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                config.flow_stop_hold_ms = hold_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_flow_stop_hold_ms(hold_ms))
+                    .await;
+                if applied {
+                    info!("💧 Flow counts as stopped after {}ms", hold_ms);
+                }
+                return;
+            }
+            UserEvent::SetScaleReconnectGrace(grace_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                config.scale_reconnect_grace_ms = grace_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_scale_reconnect_grace_ms(grace_ms)
+                    })
+                    .await;
+                if applied {
+                    info!("📶 Scale reconnect grace: {}ms", grace_ms);
+                }
+                return;
+            }
+            UserEvent::SetSettleStability(stability_g) => {
+                let mut config = self.state_manager.get_config().await;
+                config.settle_stability_g = stability_g;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_settle_stability_g(stability_g))
+                    .await;
+                if applied {
+                    info!("⚖️ Settled once weight holds within {:.1}g", stability_g);
+                }
                 return;
             }
+            UserEvent::SetBrewTime(brew_time_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                config.brew_time_ms = brew_time_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_brew_time_ms(brew_time_ms))
+                    .await;
+                if applied {
+                    info!("⏱️ Time mode brew length: {}ms", brew_time_ms);
+                }
+                return;
+            }
+            UserEvent::SetFlowProfile(profile) => {
+                if profile.is_some() && !self.relay_controller.supports_pwm(PRIMARY_RELAY_CHANNEL) {
+                    self.reject_setting("Flow profiling needs a PWM pump relay".to_string())
+                        .await;
+                    return;
+                }
+                let mut config = self.state_manager.get_config().await;
+                config.flow_profile = profile.clone();
+                let applied = self
+                    .apply_config_change(config, |brew| brew.set_flow_profile(profile.clone()))
+                    .await;
+                if !applied {
+                    return;
+                }
+                match &profile {
+                    Some(p) => info!("🎚️ {}-point flow profile from the next shot", p.points.len()),
+                    None => info!("🎚️ Flow profiling off - brewing at full power"),
+                }
+                return;
+            }
+            UserEvent::SetPredictionThresholds {
+                min_flow_for_prediction,
+                flow_stopped_threshold,
+                prediction_start_delay_ms,
+            } => {
+                let mut config = self.state_manager.get_config().await;
+                config.min_flow_for_prediction = min_flow_for_prediction;
+                config.flow_stopped_threshold = flow_stopped_threshold;
+                config.prediction_start_delay_ms = prediction_start_delay_ms;
+                let applied = self
+                    .apply_config_change(config, |brew| {
+                        brew.set_prediction_thresholds(
+                            min_flow_for_prediction,
+                            flow_stopped_threshold,
+                            prediction_start_delay_ms,
+                        )
+                    })
+                    .await;
+                if !applied {
+                    return;
+                }
+                info!(
+                    "🔮 Prediction gates: flow >{:.1}g/s after {}ms, stopped <{:.1}g/s",
+                    min_flow_for_prediction, prediction_start_delay_ms, flow_stopped_threshold
+                );
+                return;
+            }
+            UserEvent::SetLogLevel(level) => {
+                set_log_level(level);
+                self.state_manager
+                    .add_log_with_level(LogLevel::Warn, format!("Log level set to {:?}", level))
+                    .await;
+                return;
+            }
+            UserEvent::SetScaleNamePrefix(prefix) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_scale_name_prefix(&prefix) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                let prefix = config.scale_name_prefix.clone();
+                if !self.update_config(config).await {
+                    return;
+                }
+                info!("📡 Scale name prefix set to {}* - used from the next scan", prefix);
+                *self.scale_name_prefix.lock().unwrap_or_else(|e| e.into_inner()) = prefix;
+                return;
+            }
+            UserEvent::SetScaleKeepAlive(interval_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_scale_keep_alive_ms(interval_ms) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                self.scale_keep_alive_ms.store(interval_ms, Ordering::Relaxed);
+                info!("📡 Scale keep-alive every {}ms (scales with auto-off only)", interval_ms);
+                return;
+            }
+            UserEvent::SetBrewTraceInterval(interval_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_brew_trace_interval_ms(interval_ms) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                info!("📈 Brew trace keeps a sample every {}ms", interval_ms);
+                return;
+            }
+            UserEvent::SetBlePriorityBroadcast(interval_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_ble_priority_broadcast_ms(interval_ms) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                // A shot already running picks up the new setting straight away
+                self.sync_radio_priority().await;
+                if interval_ms == 0 {
+                    info!("📶 BLE priority off - UI updates at full rate while brewing");
+                } else {
+                    info!("📶 UI updates every {}ms while brewing", interval_ms);
+                }
+                return;
+            }
+            UserEvent::SetDisplayRefresh(interval_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_display_refresh_ms(interval_ms) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                if let Some(display) = self.display.as_mut() {
+                    display.set_refresh_interval_ms(interval_ms);
+                }
+                info!("🖥️ Display redraws at most every {}ms", interval_ms);
+                return;
+            }
+            UserEvent::SetWeightStream(interval_ms) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_weight_stream_ms(interval_ms) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                if interval_ms == 0 {
+                    info!("📡 UI gets every weight sample");
+                } else {
+                    info!("📡 UI gets new weight at most every {}ms", interval_ms);
+                }
+                return;
+            }
+            UserEvent::SetWeightRateLimit(limit_g_per_s) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_weight_rate_limit(limit_g_per_s) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                info!("🚫 Weight moving faster than {:.0}g/s is dropped as corrupt", limit_g_per_s);
+                return;
+            }
+            UserEvent::SetBrewRatio(ratio) => {
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.set_brew_ratio(ratio) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if !self.update_config(config).await {
+                    return;
+                }
+                match ratio {
+                    Some(ratio) => info!("☕ Brew ratio 1:{} - confirmed doses set the target", ratio),
+                    None => info!("☕ Brew ratio cleared - fixed target"),
+                }
+                return;
+            }
+            UserEvent::SetWeightUnit(unit) => {
+                let mut config = self.state_manager.get_config().await;
+                config.weight_unit = unit;
+                info!("⚖️ Web UI weight unit set to {:?}", unit);
+                self.update_config(config).await;
+                return;
+            }
+            UserEvent::SetConfig(config) => {
+                if let Err(e) = self.replace_config(*config).await {
+                    self.reject_setting(e).await;
+                }
+                return;
+            }
+            UserEvent::ScanDevices { duration_ms } => {
+                if !(MIN_DEVICE_SCAN_MS..=MAX_DEVICE_SCAN_MS).contains(&duration_ms) {
+                    self.reject_setting(format!(
+                        "Scan duration {}ms outside {}-{}ms",
+                        duration_ms, MIN_DEVICE_SCAN_MS, MAX_DEVICE_SCAN_MS
+                    ))
+                    .await;
+                    return;
+                }
+                self.get_event_publisher()
+                    .publish(SystemEvent::Hardware(HardwareEvent::SendScaleCommand(
+                        ScaleCommand::ScanDevices { duration_ms },
+                    )))
+                    .await;
+                return;
+            }
+            UserEvent::ConnectToDevice { address } => {
+                let Some(addr) = BleAddress::parse_mac(&address) else {
+                    self.reject_setting(format!("'{}' is not a BLE address", address))
+                        .await;
+                    return;
+                };
+                info!("📡 User picked scale {}", address);
+                self.get_event_publisher()
+                    .publish(SystemEvent::Hardware(HardwareEvent::SendScaleCommand(
+                        ScaleCommand::ConnectToDevice { address: addr },
+                    )))
+                    .await;
+                return;
+            }
+            UserEvent::TestRelay { duration_ms } => {
+                // The test drives the GPIO directly - it must never fight a running shot
+                if self.state_manager.get_brew_state().await != BrewState::Idle {
+                    self.state_manager
+                        .add_log("Relay test refused - a brew is in progress".to_string())
+                        .await;
+                    return;
+                }
+                let duration_ms = duration_ms.clamp(MIN_RELAY_TEST_MS, MAX_RELAY_TEST_MS);
+                let duration = Duration::from_millis(duration_ms as u64);
+                if let Err(e) = self.relay_controller.test_relay(duration).await {
+                    warn!("Relay test failed: {:?}", e);
+                    self.state_manager
+                        .add_log(format!("Relay test failed: {:?}", e))
+                        .await;
+                } else {
+                    self.state_manager
+                        .add_log(format!("Relay test completed - on for {}ms", duration_ms))
+                        .await;
+                }
+                return;
+            }
+            UserEvent::RunSelfTest => {
+                self.run_self_test().await;
+                return;
+            }
+            UserEvent::LogEventStats => {
+                let window = self.event_bus.stats().take_window(Instant::now());
+                let summary = window.summary(EVENT_STATS_LOG_KINDS);
+                info!("📡 {}", summary);
+                self.state_manager.add_log(summary).await;
+                return;
+            }
+            UserEvent::ResetOvershoot => {
+                info!("🔄 User requested overshoot reset");
+                let outputs = self.brew_controller.reset_overshoot();
+                for output in outputs {
+                    self.handle_brew_output(output).await;
+                }
+                return;
+            }
+            UserEvent::SelectProfile(name) => {
+                let outputs = match self.brew_controller.select_overshoot_profile(&name) {
+                    Ok(outputs) => outputs,
+                    Err(e) => {
+                        self.reject_setting(e).await;
+                        return;
+                    }
+                };
+                let active = self.brew_controller.active_overshoot_profile().to_string();
+                info!("☕ Overshoot profile: {}", active);
+                self.state_manager
+                    .add_log(format!("Overshoot profile: {}", active))
+                    .await;
+                self.persist_overshoot_profiles().await;
+                self.state_manager
+                    .set_overshoot_status(self.brew_controller.overshoot_status())
+                    .await;
+                for output in outputs {
+                    self.handle_brew_output(output).await;
+                }
+                return;
+            }
+            UserEvent::SetSystemEnabled(enabled) => {
+                warn!(
+                    "🔌 User {} the system",
+                    if enabled { "enabled" } else { "disabled" }
+                );
+                let outputs = self.brew_controller.set_system_enabled(enabled);
+                for output in outputs {
+                    self.handle_brew_output(output).await;
+                }
+                return;
+            }
+            UserEvent::EmergencyStop => {
+                // Emergency stop bypasses state machine
+                self.get_event_publisher()
+                    .emergency_stop("User emergency stop".to_string())
+                    .await;
+                return;
+            }
+            UserEvent::RebootSystem => {
+                warn!("🔁 User requested reboot");
+                self.shutdown().await;
+                esp_idf_svc::hal::reset::restart();
+            }
             _ => {}
         }
 
@@ -539,6 +1713,258 @@ impl EspressoController {
         }
     }
 
+    /// Check new auto-tare parameters as part of the config, then hand them to the state machine
+    /// through `apply` and persist them
+    async fn apply_auto_tare_params(
+        &mut self,
+        params: AutoTareParams,
+        apply: impl FnOnce(&mut BrewController) -> Result<(), String>,
+    ) {
+        let mut config = self.state_manager.get_config().await;
+        config.set_auto_tare_params(params);
+        let profile = config.auto_tare_profile;
+        if !self.apply_config_change(config, apply).await {
+            return;
+        }
+
+        info!(
+            "⚖️ Auto-tare ({}): empty ≤{:.1}g, stable within {:.1}g over {} readings",
+            profile.map(|p| format!("{:?}", p)).unwrap_or_else(|| "Custom".to_string()),
+            params.empty_threshold_g,
+            params.stability_threshold_g,
+            params.stable_readings
+        );
+    }
+
+    /// Swap in a whole new config - validated as a unit, so either every field applies or none.
+    /// The brew controller checks its own fields - one it rejects rolls it back to the old config
+    async fn replace_config(&mut self, config: BrewConfig) -> Result<(), String> {
+        let current = self.state_manager.get_config().await;
+        let config = config.validate_replacement(&current)?;
+        let pwm_pump = self.relay_controller.supports_pwm(PRIMARY_RELAY_CHANNEL);
+        if config.flow_profile.is_some() && !pwm_pump {
+            return Err("Flow profiling needs a PWM pump relay".to_string());
+        }
+        if let Err(e) = self.apply_brew_settings(&config) {
+            if let Err(restore) = self.apply_brew_settings(&current) {
+                error!("Failed to restore brew settings after a rejected config: {}", restore);
+            }
+            return Err(e);
+        }
+
+        *self
+            .scale_name_prefix
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = config.scale_name_prefix.clone();
+        self.scale_keep_alive_ms.store(config.scale_keep_alive_ms, Ordering::Relaxed);
+        if let Some(display) = self.display.as_mut() {
+            display.set_refresh_interval_ms(config.display_refresh_ms);
+        }
+        let auto_tare_changed = config.auto_tare != current.auto_tare;
+        let predictive_auto_changed = config.predictive_stop_auto != current.predictive_stop_auto;
+        let (auto_tare, predictive_stop_auto) = (config.auto_tare, config.predictive_stop_auto);
+        self.update_config(config).await;
+
+        // Mode switches go through the state machine - only the ones that actually changed
+        if auto_tare_changed {
+            for output in self.brew_controller.set_auto_tare_enabled(auto_tare) {
+                self.handle_brew_output(output).await;
+            }
+        }
+        if predictive_auto_changed {
+            for output in self.brew_controller.set_predictive_stop_auto(predictive_stop_auto) {
+                self.handle_brew_output(output).await;
+            }
+        }
+        self.state_manager
+            .set_overshoot_status(self.brew_controller.overshoot_status())
+            .await;
+        self.sync_radio_priority().await;
+        info!("⚙️ Config replaced in one update");
+        Ok(())
+    }
+
+    /// Push every brew controller setting in `config`, stopping at the first one it rejects
+    fn apply_brew_settings(&mut self, config: &BrewConfig) -> Result<(), String> {
+        let brew = &mut self.brew_controller;
+        brew.set_auto_tare_params(config.auto_tare_params())?;
+        brew.set_auto_tare_cooldown_secs(config.auto_tare_cooldown_secs)?;
+        brew.set_auto_tare_hold_ms(config.auto_tare_hold_ms)?;
+        brew.set_prediction_window(
+            config.prediction_safety_margin_ms,
+            config.prediction_window_multiplier,
+        )?;
+        brew.set_prediction_thresholds(
+            config.min_flow_for_prediction,
+            config.flow_stopped_threshold,
+            config.prediction_start_delay_ms,
+        )?;
+        brew.set_overshoot_warmup_brews(config.overshoot_warmup_brews)?;
+        brew.set_overshoot_target_g(config.overshoot_target_g)?;
+        brew.set_overshoot_learning_schedule(config.overshoot_learning)?;
+        brew.set_flow_stop_hold_ms(config.flow_stop_hold_ms)?;
+        brew.set_scale_reconnect_grace_ms(config.scale_reconnect_grace_ms)?;
+        brew.set_settle_stability_g(config.settle_stability_g)?;
+        brew.set_brew_time_ms(config.brew_time_ms)?;
+        brew.set_flow_profile(config.flow_profile.clone())?;
+        brew.set_target_weight(config.total_target_weight_g());
+        brew.set_stop_mode(config.stop_mode);
+        brew.set_tare_before_brew(config.tare_before_brew, config.tare_verify_timeout_ms);
+        // In auto mode learning decides predictive stop, not the client
+        if !config.predictive_stop_auto {
+            brew.set_predictive_stop_enabled(config.predictive_stop);
+        }
+        Ok(())
+    }
+
+    /// Publish a config change to the UI state and queue it for (debounced) persistence.
+    /// Handlers check their own input first - this catches anything that slipped past them.
+    /// False when the config was rejected and nothing was stored
+    async fn update_config(&mut self, config: BrewConfig) -> bool {
+        if let Err(e) = config.validate() {
+            error!("Refusing to store an invalid config: {}", e);
+            self.reject_setting(e).await;
+            return false;
+        }
+        if let Some(ref storage) = self.nvs_storage {
+            storage.save_config(&config).await;
+        }
+        self.state_manager.update_config(config).await;
+        true
+    }
+
+    /// Validate a changed config before anything else, then hand the change to the state machine
+    /// through `apply` and store it - a rejected change reaches neither
+    async fn apply_config_change(
+        &mut self,
+        config: BrewConfig,
+        apply: impl FnOnce(&mut BrewController) -> Result<(), String>,
+    ) -> bool {
+        if let Err(e) = config.validate().and_then(|()| apply(&mut self.brew_controller)) {
+            self.reject_setting(e).await;
+            return false;
+        }
+        self.update_config(config).await
+    }
+
+    /// Queue the overshoot profiles for NVS and mirror their names into the shared state
+    async fn persist_overshoot_profiles(&mut self) {
+        let profiles = self.brew_controller.overshoot_profiles();
+        if let Some(ref storage) = self.nvs_storage {
+            storage.save_overshoot_profiles(&profiles).await;
+        }
+        let names = profiles.names();
+        self.state_manager
+            .set_overshoot_profiles(profiles.active, names)
+            .await;
+    }
+
+    /// Give BLE the airtime while a shot runs, WiFi gets it back once the brew is over
+    async fn sync_radio_priority(&mut self) {
+        let brewing = self.state_manager.get_brew_state().await == BrewState::Brewing;
+        let config = self.state_manager.get_config().await;
+        let priority = if brewing && config.ble_priority_broadcast_ms > 0 {
+            RadioPriority::Ble
+        } else {
+            RadioPriority::Balanced
+        };
+        self.state_manager.set_radio_priority(priority).await;
+    }
+
+    /// Support snapshot - every check runs, one failing doesn't stop the rest
+    async fn run_self_test(&mut self) {
+        info!("🩺 Running self-test");
+        let mut report = SelfTestReport::new(Instant::now().as_millis());
+
+        report.check(
+            "ble_stack",
+            if BleClient::is_initialized() {
+                Ok("NimBLE host running".to_string())
+            } else {
+                Err("NimBLE host not running")
+            },
+        );
+
+        let nvs = match &self.nvs_storage {
+            Some(nvs) => nvs.self_test().await,
+            None => Err("no NVS storage".to_string()),
+        };
+        report.check("nvs", nvs);
+
+        // Toggling the relay mid-shot would cut or restart the pump
+        if self.state_manager.get_brew_state().await != BrewState::Idle {
+            report.record("relay", CheckOutcome::Skipped, "brew in progress");
+        } else {
+            let toggled = self
+                .relay_controller
+                .test_relay(Duration::from_millis(RELAY_TEST_MS as u64))
+                .await
+                .map(|_| format!("toggled on for {}ms", RELAY_TEST_MS));
+            report.check("relay", toggled);
+        }
+
+        // Only the boot read-back sees the pin level - the toggle test can't tell a stuck pin
+        match self.relay_controller.boot_check() {
+            RelayBootCheck::Low => {
+                report.record("relay_boot", CheckOutcome::Pass, "pins read back low at boot")
+            }
+            RelayBootCheck::NotChecked => report.record(
+                "relay_boot",
+                CheckOutcome::Skipped,
+                "relay output can't be read back",
+            ),
+            RelayBootCheck::StuckHigh { channel, name } => report.record(
+                "relay_boot",
+                CheckOutcome::Fail,
+                format!("channel {} '{}' read high at boot", channel, name),
+            ),
+        }
+
+        let state = self.state_manager.get_full_state().await;
+        report.check(
+            "wifi",
+            if state.wifi_connected {
+                Ok("connected".to_string())
+            } else {
+                Err("not connected")
+            },
+        );
+
+        let data_age_ms = state
+            .scale_data
+            .as_ref()
+            .map(|data| Instant::now().saturating_duration_since(data.received_at).as_millis());
+        let scale = match (state.ble_connected, data_age_ms) {
+            (false, _) => Err("not connected".to_string()),
+            (true, None) => Err("connected but no weight data yet".to_string()),
+            (true, Some(age)) if age > STALE_DATA_TIMEOUT_MS => {
+                Err(format!("connected but the last weight is {}ms old", age))
+            }
+            (true, Some(age)) => Ok(match &state.scale_info {
+                Some(info) => format!("{} {}, weight {}ms old", info.brand, info.model, age),
+                None => format!("weight {}ms old", age),
+            }),
+        };
+        report.check("scale", scale);
+
+        let summary = report.summary();
+        if report.passed {
+            info!("🩺 {}", summary);
+        } else {
+            warn!("🩺 {}", summary);
+        }
+        self.state_manager.add_log(summary).await;
+        self.state_manager.set_self_test(report).await;
+    }
+
+    /// Report an invalid setting back to the user without changing anything
+    async fn reject_setting(&mut self, reason: String) {
+        warn!("Rejected setting: {}", reason);
+        self.state_manager
+            .add_log(format!("Rejected setting: {}", reason))
+            .await;
+    }
+
     /// ☕ Handle brew events - state changes, milestones
     async fn handle_brew_event(&mut self, brew_event: BrewEvent) {
         match brew_event {
@@ -563,6 +1989,27 @@ impl EspressoController {
                     predicted_overshoot
                 );
             }
+            BrewEvent::PredictiveStopPreview { predicted } => {
+                info!("👁️ Predictive stop preview: would stop for {:.1}g", predicted);
+                self.state_manager
+                    .add_log(format!("Predictive stop preview: predicted {:.1}g", predicted))
+                    .await;
+            }
+            BrewEvent::PredictiveStopAutoChanged { enabled, confidence } => {
+                let message = if enabled {
+                    format!(
+                        "Predictive stop auto-enabled: overshoot learning is ready ({:.0}% confidence)",
+                        confidence * 100.0
+                    )
+                } else {
+                    format!(
+                        "Predictive stop auto-disabled: overshoot learning confidence dropped to {:.0}%",
+                        confidence * 100.0
+                    )
+                };
+                info!("🔮 {}", message);
+                self.state_manager.add_log(message).await;
+            }
             BrewEvent::Finished {
                 final_weight,
                 duration_ms,
@@ -574,9 +2021,17 @@ impl EspressoController {
                 self.state_manager
                     .add_log("Brewing finished".to_string())
                     .await;
+                self.sound_alert(BuzzerPattern::DoubleBeep).await;
+            }
+            BrewEvent::DoseCaptured { grams } => {
+                info!("⚖️ Dose captured: {:.1}g", grams);
+                self.state_manager
+                    .add_log(format!("Dose captured: {:.1}g", grams))
+                    .await;
             }
             BrewEvent::AutoTareTriggered { reason } => {
                 info!("⚖️ Auto-tare: {}", reason);
+                self.state_manager.set_last_auto_tare_reason(reason).await;
             }
             _ => {}
         }
@@ -586,19 +2041,60 @@ impl EspressoController {
     async fn handle_time_event(&mut self, time_event: TimeEvent) {
         match time_event {
             TimeEvent::Tick => {
+                // Write settled config, learning and statistics changes to flash
+                if let Some(ref storage) = self.nvs_storage {
+                    if let Err(e) = storage.flush().await {
+                        warn!("Failed to persist settings: {:?}", e);
+                    }
+                    self.state_manager.set_nvs_writes(storage.write_count()).await;
+                }
+
+                // Clear expired display alerts and draw any update the refresh limit held back
+                if let Some(display) = self.display.as_mut() {
+                    if let Err(e) = display.expire_alert() {
+                        warn!("Display refresh failed: {:?}", e);
+                    }
+                    if let Err(e) = display.refresh_pending() {
+                        warn!("Display refresh failed: {:?}", e);
+                    }
+                }
+
                 // Periodic safety checks
                 let current_state = self.state_manager.get_full_state().await;
-                if self.safety_controller.should_emergency_stop(&current_state) {
+                self.safety_controller
+                    .update_relay_state(current_state.relay_enabled);
+                let machine_state = self.brew_controller.get_state();
+                if self.safety_controller.should_emergency_stop(&current_state, machine_state) {
+                    let reason = self
+                        .safety_controller
+                        .last_trip_reason()
+                        .unwrap_or("Safety check failed")
+                        .to_string();
+                    self.get_event_publisher().emergency_stop(reason).await;
+                }
+                self.state_manager
+                    .set_safety_status(self.safety_controller.status())
+                    .await;
+
+                // Relay-level on-time cap, independent of the state machine
+                if let Err(e) = self.relay_controller.enforce_max_on_time() {
                     self.get_event_publisher()
-                        .emergency_stop("Safety check failed".to_string())
+                        .emergency_stop(e.to_string())
                         .await;
                 }
 
+                // Catch a tare that never zeroed even if scale data has stopped
+                self.check_tare_verification(None).await;
+
                 // Send tick to brewing state machine for time-based logic
                 let tick_outputs = self.brew_controller.handle_input(BrewInput::Tick);
                 for output in tick_outputs {
                     self.handle_brew_output(output).await;
                 }
+                // Catches what samples don't move - target changes, a stop scheduled then cancelled
+                self.state_manager
+                    .set_brew_snapshot(self.brew_controller.snapshot())
+                    .await;
 
                 // Check settling timeout (legacy - now handled by state machine)
                 let settling_outputs = self.brew_controller.check_settling_timeout();
@@ -619,22 +2115,46 @@ impl EspressoController {
         }
     }
 
+    /// Clear a pending tare check once the scale reads zero, or alert if it never does
+    async fn check_tare_verification(&mut self, weight_g: Option<f32>) {
+        let Some(sent_at) = self.pending_tare_check else {
+            return;
+        };
+        let config = self.state_manager.get_config().await;
+
+        if weight_g.is_some_and(|w| w.abs() < config.tare_zero_threshold_g) {
+            debug!("⚖️ Tare verified after {}ms", sent_at.elapsed().as_millis());
+            self.pending_tare_check = None;
+            // A brew waiting on this tare can start now
+            let outputs = self.brew_controller.handle_input(BrewInput::TareVerified);
+            for output in outputs {
+                self.handle_brew_output(output).await;
+            }
+            return;
+        }
+
+        if sent_at.elapsed() >= Duration::from_millis(config.tare_verify_timeout_ms) {
+            self.pending_tare_check = None;
+            let message = format!(
+                "Scale did not zero within {}ms of tare (reading {})",
+                config.tare_verify_timeout_ms,
+                weight_g.map_or("unavailable".to_string(), |w| format!("{:.1}g", w))
+            );
+            self.state_manager.add_log(message.clone()).await;
+            self.get_event_publisher()
+                .publish(SystemEvent::Safety(SafetyEvent::SystemAlert {
+                    level: AlertLevel::Warning,
+                    message,
+                }))
+                .await;
+        }
+    }
+
     /// 🚨 Handle safety events - emergency stops, alerts
     async fn handle_safety_event(&mut self, safety_event: SafetyEvent) {
         match safety_event {
             SafetyEvent::EmergencyStop { reason } => {
-                error!("🚨 EMERGENCY STOP: {}", reason);
-
-                // Force relay off immediately
-                self.get_event_publisher().relay_off().await;
-
-                // Force state machine to idle
-                let outputs = self.brew_controller.emergency_stop();
-                for output in outputs {
-                    self.handle_brew_output(output).await;
-                }
-
-                self.state_manager.set_error(Some(reason)).await;
+                self.emergency_stop(reason).await;
             }
             SafetyEvent::SystemAlert { level, message } => match level {
                 AlertLevel::Critical | AlertLevel::Error => {
@@ -654,465 +2174,132 @@ impl EspressoController {
                 info!("📶 WiFi connected: {}", ssid);
                 self.state_manager.set_wifi_connected(true).await;
             }
-            NetworkEvent::WifiDisconnected => {
-                warn!("📶 WiFi disconnected");
-                self.state_manager.set_wifi_connected(false).await;
-            }
-            NetworkEvent::BleConnected { device_name } => {
-                info!("🔵 BLE connected: {}", device_name);
-                self.state_manager.set_ble_connected(true).await;
-            }
-            NetworkEvent::BleDisconnected => {
-                warn!("🔵 BLE disconnected");
-                self.state_manager.set_ble_connected(false).await;
-            }
-            _ => {}
-        }
-    }
-
-    /// 🔄 Convert legacy WebSocket commands to user events
-    fn websocket_to_user_event(&self, command: WebSocketCommand) -> Option<UserEvent> {
-        match command {
-            WebSocketCommand::SetTargetWeight { weight } => {
-                Some(UserEvent::SetTargetWeight(weight))
-            }
-            WebSocketCommand::SetAutoTare { enabled } => Some(UserEvent::SetAutoTare(enabled)),
-            WebSocketCommand::SetPredictiveStop { enabled } => {
-                Some(UserEvent::SetPredictiveStop(enabled))
-            }
-            WebSocketCommand::TareScale => Some(UserEvent::TareScale),
-            WebSocketCommand::StartTimer => Some(UserEvent::StartBrewing),
-            WebSocketCommand::StopTimer => Some(UserEvent::StopBrewing),
-            WebSocketCommand::ResetTimer => Some(UserEvent::ResetTimer),
-            WebSocketCommand::TestRelay => Some(UserEvent::TestRelay),
-            WebSocketCommand::ResetOvershoot => Some(UserEvent::ResetOvershoot),
-        }
-    }
-
-    async fn handle_scale_data(&mut self, scale_data: ScaleData) {
-        debug!(
-            "Received scale data: {:.2}g, {:.2}g/s, timestamp: {}ms",
-            scale_data.weight_g, scale_data.flow_rate_g_per_s, scale_data.timestamp_ms
-        );
-
-        self.safety_controller.update_data_received();
-        self.state_manager
-            .update_scale_data(scale_data.clone())
-            .await;
-
-        // Handle timer detection using Python reference logic
-        self.handle_timer_detection(&scale_data).await;
-
-        // Handle brewing state transitions through the new BrewController
-        let brew_input = BrewInput::ScaleData(scale_data.clone());
-        let outputs = self.brew_controller.handle_input(brew_input);
-
-        // Process outputs from state machine
-        for output in outputs {
-            self.handle_brew_output(output).await;
-        }
-
-        // Handle auto-tare logic - call on every weight reading like Python
-        if self.state_manager.is_auto_tare_enabled().await {
-            let brew_state = self.state_manager.get_brew_state().await;
-            let timer_state = self.state_manager.get_timer_state().await;
-            let timer_running = timer_state == TimerState::Running;
-
-            // Auto-tare logic is now handled inside the state machine
-        }
-
-        if self.state_manager.get_timer_state().await == TimerState::Running {
-            self.handle_brewing_logic(&scale_data).await;
-        }
-    }
-
-    async fn handle_brewing_logic(&mut self, scale_data: &ScaleData) {
-        if !self.state_manager.is_predictive_stop_enabled().await {
-            return;
-        }
-
-        // Skip predictive logic during startup delay (button press artifacts)
-        if let Some(brew_start) = self.brew_start_time {
-            let elapsed = Instant::now().duration_since(brew_start);
-            if elapsed < Duration::from_millis(2000) {
-                // 2 second delay
-                debug!(
-                    "Ignoring weight measurement during startup delay: {:.2}g ({}ms elapsed)",
-                    scale_data.weight_g,
-                    elapsed.as_millis()
-                );
-                return;
-            }
-        }
-
-        let target_weight = self.state_manager.get_target_weight().await;
-
-        // Handle auto-stop logic like Python
-        self.handle_auto_stop(scale_data, target_weight).await;
-
-        // Overshoot recording is now handled inside the state machine
-    }
-
-    /// Handle automatic stopping logic (from Python)
-    async fn handle_auto_stop(&mut self, scale_data: &ScaleData, target_weight: f32) {
-        // Target reached (with startup delay)
-        if scale_data.weight_g >= target_weight && scale_data.timestamp_ms > 1000 {
-            info!(
-                "🎯 Target reached: {:.1}g >= {:.1}g at {}ms",
-                scale_data.weight_g, target_weight, scale_data.timestamp_ms
-            );
-            // LEGACY: Direct brewing control replaced by state machine
-            self.stop_brewing_with_reason("target_reached").await;
-            return;
-        }
-
-        // Predictive stopping is now handled by the state machine
-        debug!("Legacy predictive stop logic - state machine handles this now");
-    }
-
-    /// Schedule a delayed stop (Python equivalent of asyncio.create_task)
-    async fn schedule_delayed_stop(&mut self, delay_seconds: f32) {
-        // Delayed stops are now handled by the state machine with tick events
-        debug!("Legacy delayed stop called - state machine handles this now");
-    }
-
-    async fn handle_brew_state_transition(&mut self, transition: BrewStateTransition) {
-        match (transition.from, transition.to) {
-            (BrewState::Idle, BrewState::Brewing) => {
-                info!("🔥 Brewing started - activating relay immediately (robust timer detection)");
-                self.brew_start_time = Some(Instant::now());
-
-                // Activate relay immediately - no delay needed with proper timer detection
-                if let Err(e) = self.relay_controller.turn_on().await {
-                    error!("Failed to turn on relay: {:?}", e);
-                    self.emergency_stop().await;
-                } else {
-                    self.state_manager.set_relay_enabled(true).await;
-                }
-            }
-            (BrewState::Brewing, BrewState::BrewSettling) => {
-                info!("Brewing finished, settling");
-                self.brew_start_time = None; // Clear startup delay
-                if let Err(e) = self.relay_controller.turn_off().await {
-                    error!("Failed to turn off relay: {:?}", e);
-                } else {
-                    self.state_manager.set_relay_enabled(false).await;
-                }
-            }
-            (BrewState::BrewSettling, BrewState::Idle) => {
-                info!("Returned to idle state");
-            }
-            _ => {}
-        }
-    }
-
-    async fn handle_ble_status_change(&mut self, connected: bool) {
-        self.state_manager.set_ble_connected(connected).await;
-
-        if !connected {
-            // Check if disconnection happened shortly after timer start - indicates scale shutdown
-            if let Some(timer_start) = self.timer_start_time {
-                let elapsed = Instant::now().duration_since(timer_start);
-                if elapsed < Duration::from_secs(3) {
-                    // 3 second window
-                    self.consecutive_disconnection_count += 1;
-                    info!("BLE disconnected {}ms after timer start - potential scale shutdown (count: {})",
-                         elapsed.as_millis(), self.consecutive_disconnection_count);
-
-                    // If timer is running and we disconnect quickly, likely a shutdown - stop the timer
-                    if self.current_timer_running {
-                        info!("Scale shutdown detected - stopping timer");
-                        self.current_timer_running = false;
-                        self.state_manager
-                            .update_timer_state(TimerState::Idle)
-                            .await;
-
-                        // Emergency stop if brewing was triggered
-                        if self.state_manager.get_brew_state().await != BrewState::Idle {
-                            info!("Emergency stop due to scale shutdown during brewing");
-                            self.emergency_stop().await;
-                        }
-                    }
-                } else {
-                    // Reset disconnection count if enough time has passed
-                    self.consecutive_disconnection_count = 0;
-                }
-            }
-
-            self.state_manager
-                .set_error(Some("BLE disconnected".to_string()))
-                .await;
-        } else {
-            // Reset disconnection tracking on successful connection
-            self.consecutive_disconnection_count = 0;
-            self.timer_start_time = None;
-            self.state_manager.set_error(None).await;
-        }
-    }
-
-    async fn handle_websocket_command(&mut self, command: WebSocketCommand) {
-        debug!("Received WebSocket command: {:?}", command);
-
-        match command {
-            WebSocketCommand::SetTargetWeight { weight } => {
-                let mut config = self.state_manager.get_config().await;
-                config.target_weight_g = weight;
-                self.state_manager.update_config(config).await;
-
-                // Update target weight in the brewing state machine
-                self.brew_controller.set_target_weight(weight);
-
-                info!("Target weight set to {:.1}g", weight);
-            }
-
-            WebSocketCommand::SetAutoTare { enabled } => {
-                let mut config = self.state_manager.get_config().await;
-                config.auto_tare = enabled;
-                self.state_manager.update_config(config).await;
-                info!(
-                    "Auto-tare: {}",
-                    if enabled { "enabled" } else { "disabled" }
-                );
-            }
-
-            WebSocketCommand::SetPredictiveStop { enabled } => {
-                let mut config = self.state_manager.get_config().await;
-                config.predictive_stop = enabled;
-                self.state_manager.update_config(config).await;
-                info!(
-                    "Predictive stop: {}",
-                    if enabled { "enabled" } else { "disabled" }
-                );
-            }
-
-            WebSocketCommand::TestRelay => {
-                if let Err(e) = self.relay_controller.test_relay().await {
-                    warn!("Relay test failed: {:?}", e);
-                    self.state_manager
-                        .add_log("Relay test failed".to_string())
-                        .await;
-                } else {
-                    self.state_manager
-                        .add_log("Relay test completed successfully".to_string())
-                        .await;
-                }
+            NetworkEvent::WifiDisconnected => {
+                warn!("📶 WiFi disconnected");
+                self.state_manager.set_wifi_connected(false).await;
             }
-
-            WebSocketCommand::TareScale => {
-                // Route through state machine instead of direct command
-                let outputs = self.brew_controller.handle_input(BrewInput::UserCommand(UserEvent::TareScale));
+            NetworkEvent::BleScanning => {
+                info!("🔵 BLE scanning for scale");
+                self.state_manager.set_ble_stage(Some("scanning")).await;
+                let outputs = self.brew_controller.handle_input(BrewInput::BleScanning);
                 for output in outputs {
                     self.handle_brew_output(output).await;
                 }
-                self.state_manager
-                    .add_log("Tare command routed through state machine".to_string())
-                    .await;
             }
-
-            WebSocketCommand::StartTimer => {
-                // Route through state machine instead of direct command
-                let outputs = self.brew_controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+            NetworkEvent::BleConnecting => {
+                info!("🔵 BLE connecting to scale");
+                self.state_manager.set_ble_stage(Some("connecting")).await;
+                let outputs = self.brew_controller.handle_input(BrewInput::BleConnecting);
                 for output in outputs {
                     self.handle_brew_output(output).await;
                 }
-                self.state_manager
-                    .add_log("Start brewing command routed through state machine".to_string())
-                    .await;
             }
-
-            WebSocketCommand::StopTimer => {
-                // Route through state machine instead of direct command
-                let outputs = self.brew_controller.handle_input(BrewInput::UserCommand(UserEvent::StopBrewing));
-                for output in outputs {
-                    self.handle_brew_output(output).await;
-                }
-                self.state_manager
-                    .add_log("Stop brewing command routed through state machine".to_string())
-                    .await;
+            NetworkEvent::BleConnected { device_name } => {
+                info!("🔵 BLE connected: {}", device_name);
+                self.state_manager.set_ble_connected(true).await;
+                self.state_manager.set_ble_stage(Some("connected")).await;
             }
-
-            WebSocketCommand::ResetTimer => {
-                // Route through state machine instead of direct command
-                let outputs = self.brew_controller.handle_input(BrewInput::UserCommand(UserEvent::ResetTimer));
-                for output in outputs {
-                    self.handle_brew_output(output).await;
-                }
-                self.state_manager
-                    .add_log("Reset timer command routed through state machine".to_string())
-                    .await;
+            NetworkEvent::BleDiscovering => {
+                info!("🔵 BLE discovering scale services");
+                self.state_manager.set_ble_stage(Some("discovering")).await;
             }
-
-            WebSocketCommand::ResetOvershoot => {
-                info!("🔄 User requested overshoot reset - forwarding to state machine");
-                let user_event = UserEvent::ResetOvershoot;
-                let brew_input = BrewInput::UserCommand(user_event);
-                let outputs = self.brew_controller.handle_input(brew_input);
+            NetworkEvent::BleSubscribed => {
+                info!("🔵 BLE subscribed to weight notifications");
+                self.state_manager.set_ble_stage(Some("subscribed")).await;
+                // Weight data flows from here - only now is the scale usable for brewing
+                let outputs = self.brew_controller.handle_input(BrewInput::ScaleConnected);
                 for output in outputs {
                     self.handle_brew_output(output).await;
                 }
             }
+            NetworkEvent::BleDisconnected => {
+                warn!("🔵 BLE disconnected");
+                self.state_manager.set_ble_connected(false).await;
+            }
+            _ => {}
         }
     }
 
-    async fn periodic_update(&mut self) {
-        let current_state = self.state_manager.get_full_state().await;
-
-        if self.safety_controller.should_emergency_stop(&current_state) {
-            self.emergency_stop().await;
+    /// Publish progress towards the target while brewing, at most every `BREW_PROGRESS_INTERVAL_MS`
+    async fn publish_brew_progress(&mut self, now: Instant) {
+        let Some(progress) = self.state_manager.get_brew_progress().await else {
+            self.last_progress_at = None;
+            return;
+        };
+        if !progress_due(self.last_progress_at, now) {
+            return;
         }
+        self.last_progress_at = Some(now);
+        self.get_event_publisher()
+            .publish(SystemEvent::Brew(BrewEvent::Progress {
+                percent: progress.percent(),
+                eta_ms: progress.eta_ms,
+            }))
+            .await;
+    }
 
-        self.safety_controller
-            .update_relay_state(current_state.relay_enabled);
+    /// The one way in for web commands - the control loop and `send_command` both land here
+    async fn handle_websocket_command(&mut self, command: WebSocketCommand) {
+        debug!("Received WebSocket command: {:?}", command);
 
-        // Check for pending predictive stop (like Python's delayed task)
-        if let Some(stop_time) = self.pending_stop_time {
-            if Instant::now() >= stop_time {
-                info!(
-                    "⏰ EXECUTING DELAYED PREDICTIVE STOP (scheduled for {:?})",
-                    stop_time
+        // A touch screen by the machine is easy to brush - start and stop can need a second press
+        let confirmable = match command {
+            WebSocketCommand::StartTimer => Some(ConfirmableCommand::Start),
+            WebSocketCommand::StopTimer => Some(ConfirmableCommand::Stop),
+            _ => None,
+        };
+        if let Some(confirmable) = confirmable {
+            let config = self.state_manager.get_config().await;
+            if config.requires_confirmation(confirmable)
+                && !self.command_confirmation.press(confirmable, Instant::now())
+            {
+                let message = format!(
+                    "Send {} again within {}s to confirm",
+                    confirmable.as_str(),
+                    COMMAND_CONFIRM_WINDOW_MS / 1000
                 );
-                self.pending_stop_time = None;
-
-                if self.state_manager.get_timer_state().await == TimerState::Running {
-                    // LEGACY: Direct brewing control replaced by state machine
-                    self.stop_brewing_with_reason("predicted").await;
-                } else {
-                    info!("Predictive stop cancelled - timer no longer running");
-                }
+                info!("✋ {}", message);
+                self.state_manager.add_log(message).await;
+                return;
             }
         }
-    }
-
-    async fn stop_brewing(&mut self) {
-        self.stop_brewing_with_reason("manual").await
-    }
-
-    async fn stop_brewing_with_reason(&mut self, reason: &str) {
-        info!("Stopping brewing ({})", reason);
 
-        // Cancel pending stop task
-        if self.pending_stop_time.is_some() {
-            self.pending_stop_time = None;
+        // Web commands enter the bus as user events like every other input
+        match command.clone().into_user_event() {
+            Some(user_event) => self.get_event_publisher().user_command(user_event).await,
+            None => debug!("{:?} is served by the HTTP handler - ignoring", command),
         }
-
-        // Overshoot learning is now handled by the state machine
-        debug!("Overshoot learning now handled by state machine");
-
-        // LEGACY: Direct scale commands removed - now handled by state machine
-        // The state machine now handles all scale commands as side effects
-        // if reason == "target_reached" || reason == "predicted" {
-        //     if let Err(_) = self.scale_command_channel.try_send(ScaleCommand::StopTimer) {
-        //         warn!("Failed to send stop timer command - channel full");
-        //     }
-        // }
-
-        // LEGACY: Direct relay control removed - now handled by state machine
-        // self.relay_controller.turn_off().await?;
-        // self.state_manager.set_relay_enabled(false).await;
-        self.state_manager
-            .add_log(format!("Brewing stopped ({})", reason))
-            .await;
     }
 
-    async fn emergency_stop(&mut self) {
-        error!("EMERGENCY STOP ACTIVATED");
-
-        self.brew_start_time = None; // Clear startup delay
-        self.pending_stop_time = None; // Cancel any pending predictive stops
+    /// The one emergency stop path - every trigger publishes `SafetyEvent::EmergencyStop` and lands here.
+    /// Every stop turns the relays off and resets the state machine; only the alerts are
+    /// debounced, so a repeat inside the window doesn't sound or log twice.
+    async fn emergency_stop(&mut self, reason: String) {
+        // 1. Every relay channel off through the hardware side-effect handler
+        self.get_event_publisher().all_relays_off().await;
 
-        self.safety_controller
-            .handle_emergency_stop(&mut self.relay_controller);
-        self.state_manager.set_relay_enabled(false).await;
-        self.state_manager
-            .set_error(Some("Emergency stop activated".to_string()))
-            .await;
-        self.state_manager
-            .add_log("EMERGENCY STOP".to_string())
-            .await;
-
-        // TODO: Replace with proper BrewController emergency stop
-        // self.brew_controller.emergency_stop();
-        self.state_manager.update_brew_state(BrewState::Idle).await;
-        self.state_manager
-            .update_timer_state(TimerState::Idle)
-            .await;
-    }
+        // 2. State machine to idle
+        let outputs = self.brew_controller.emergency_stop();
+        for output in outputs {
+            self.handle_brew_output(output).await;
+        }
 
-    /// Handle timer state detection from scale data (Python reference implementation)
-    async fn handle_timer_detection(&mut self, scale_data: &ScaleData) {
-        if self.last_timer_ms.is_none() {
-            self.last_timer_ms = Some(scale_data.timestamp_ms);
+        if !self.safety_controller.begin_emergency_stop(Instant::now()) {
+            warn!("🚨 Emergency stop repeated - relays forced off again ({})", reason);
             return;
         }
+        error!("🚨 EMERGENCY STOP: {}", reason);
 
-        let last_timer_ms = self.last_timer_ms.unwrap();
-
-        // Timer started: not running + timestamp > 0 + timestamp > last_timestamp
-        if !self.current_timer_running
-            && scale_data.timestamp_ms > 0
-            && scale_data.timestamp_ms > last_timer_ms
-        {
-            // Check if this could be a scale shutdown (timer start followed by immediate disconnection)
-            // If we've had recent disconnections after a timer start, ignore this timer start
-            if self.consecutive_disconnection_count > 0 {
-                info!("Ignoring timer start - likely scale shutdown sequence (disconnection count: {})",
-                     self.consecutive_disconnection_count);
-                self.consecutive_disconnection_count = 0;
-                return;
-            }
-
-            // Additional shutdown detection: if weight jumps dramatically with very high flow rate,
-            // this is likely the shutdown sequence where scale readings go crazy before disconnecting
-            if scale_data.flow_rate_g_per_s > 50.0 {
-                info!("Ignoring timer start - extreme flow rate {:.1}g/s indicates scale shutdown sequence",
-                     scale_data.flow_rate_g_per_s);
-                // Don't set timer running, just wait for disconnect
-                return;
-            }
-
-            // Also check for rapid weight increases that are physically impossible during normal brewing
-            // Lower threshold to catch more shutdown sequences
-            if scale_data.flow_rate_g_per_s > 25.0 {
-                info!("Ignoring timer start - high flow rate {:.1}g/s indicates scale shutdown sequence",
-                     scale_data.flow_rate_g_per_s);
-                return;
-            }
-
-            info!(
-                "Timer started detected: {}ms -> {}ms",
-                last_timer_ms, scale_data.timestamp_ms
-            );
-            self.current_timer_running = true;
-            self.timer_start_time = Some(Instant::now());
-            self.state_manager
-                .update_timer_state(TimerState::Running)
-                .await;
-        }
-        // Timer stopped manually - IMMEDIATE DETECTION LIKE PYTHON
-        else if self.current_timer_running
-            && scale_data.timestamp_ms == last_timer_ms
-            && scale_data.timestamp_ms > 0
-        {
-            info!(
-                "⏹️ Timer stopped manually: timestamp frozen at {}ms (IMMEDIATE DETECTION)",
-                scale_data.timestamp_ms
-            );
-            self.current_timer_running = false;
-            self.state_manager
-                .update_timer_state(TimerState::Idle)
-                .await;
-        }
-        // Timer reset
-        else if self.current_timer_running && scale_data.timestamp_ms == 0 {
-            info!("Timer reset detected: timestamp -> 0");
-            self.current_timer_running = false;
-            self.state_manager
-                .update_timer_state(TimerState::Idle)
-                .await;
-        }
+        // 3. Error state for the UI
+        self.state_manager.set_error(Some(reason.clone())).await;
 
-        // Update last timestamp - AFTER detection logic like Python
-        self.last_timer_ms = Some(scale_data.timestamp_ms);
+        self.sound_alert(BuzzerPattern::ErrorTone).await;
+        self.get_event_publisher()
+            .publish(SystemEvent::Hardware(HardwareEvent::DisplayAlert {
+                message: reason,
+                duration: Duration::from_secs(5),
+            }))
+            .await;
     }
 
     /// 🚀 Handle outputs from the brewing state machine - PURE SIDE EFFECTS!
@@ -1129,6 +2316,15 @@ impl EspressoController {
                 self.get_event_publisher().relay_off().await;
                 self.state_manager.set_relay_enabled(false).await;
             }
+            BrewOutput::RelayPower(fraction) => {
+                debug!("🎚️ State machine output: RelayPower({:.2}) -> Publishing hardware event", fraction);
+                self.get_event_publisher()
+                    .publish(SystemEvent::Hardware(HardwareEvent::RelayPower {
+                        channel: PRIMARY_RELAY_CHANNEL,
+                        fraction,
+                    }))
+                    .await;
+            }
             BrewOutput::StateChanged { from, to } => {
                 info!("🔄 Brew state transition: {:?} -> {:?}", from, to);
                 // Convert SystemState to BrewState for legacy state manager
@@ -1143,6 +2339,26 @@ impl EspressoController {
                     _ => crate::types::BrewState::Idle,
                 };
                 self.state_manager.update_brew_state(brew_state).await;
+                self.state_manager.set_machine_state(to).await;
+                // Settling is part of the curve - the trace ends once the brew is over
+                if brew_state == crate::types::BrewState::Idle {
+                    self.brew_trace.lock().await.stop();
+                }
+                self.sync_radio_priority().await;
+
+                // Fast link while a shot is pulling, slow link to save power otherwise
+                let profile = match to {
+                    crate::brewing::states::SystemState::Brewing => Some(ConnectionProfile::Fast),
+                    crate::brewing::states::SystemState::Idle => Some(ConnectionProfile::Slow),
+                    _ => None,
+                };
+                if let Some(profile) = profile {
+                    self.get_event_publisher()
+                        .publish(SystemEvent::Hardware(HardwareEvent::SendScaleCommand(
+                            ScaleCommand::SetConnectionProfile(profile),
+                        )))
+                        .await;
+                }
             }
             BrewOutput::TareScale => {
                 info!("⚖️ State machine output: TareScale -> Publishing hardware event");
@@ -1178,14 +2394,67 @@ impl EspressoController {
             }
             BrewOutput::BrewingStarted => {
                 info!("☕ Brewing started");
+                self.shot_started_at = Some(Instant::now());
+                let config = self.state_manager.get_config().await;
+                self.relay_controller.set_max_on_duration(Some(relay_max_on_for(&config)));
+                self.brew_trace.lock().await.start(Instant::now());
                 self.state_manager
                     .add_log("Brewing started".to_string())
                     .await;
             }
-            BrewOutput::BrewingFinished => {
-                info!("✅ Brewing finished");
+            BrewOutput::BrewingReset => {
+                // No Finished event - an abandoned shot has no final weight worth reporting
+                info!("🔄 Brewing reset");
+                self.shot_started_at = None;
+                self.last_progress_at = None;
                 self.state_manager
-                    .add_log("Brewing finished".to_string())
+                    .add_log("Brewing reset - shot discarded".to_string())
+                    .await;
+            }
+            BrewOutput::BrewInterrupted { grace_ms } => {
+                warn!("📶 Scale lost mid-brew - pump off, waiting {}ms for it", grace_ms);
+                self.state_manager
+                    .add_log(format!(
+                        "Scale lost mid-brew - the shot resumes if it reconnects within {:.1}s",
+                        grace_ms as f32 / 1000.0
+                    ))
+                    .await;
+            }
+            BrewOutput::BrewResumed => {
+                info!("📶 Scale back - brew resumed");
+                self.state_manager
+                    .add_log("Scale reconnected - brew resumed".to_string())
+                    .await;
+            }
+            BrewOutput::BrewResumeRefused { reason } => {
+                warn!("📶 Scale back but the brew was not resumed: {}", reason);
+                self.state_manager
+                    .add_log(format!("Scale reconnected - brew not resumed, {}", reason))
+                    .await;
+            }
+            BrewOutput::BrewTareTimedOut => {
+                warn!("⚖️ Tare before brew never read zero - brewing anyway");
+                self.state_manager
+                    .add_log(
+                        "Scale did not zero before the brew - started anyway, the shot weight \
+                         may include the cup"
+                            .to_string(),
+                    )
+                    .await;
+            }
+            BrewOutput::BrewingFinished => {
+                // Logging and alerts happen in handle_brew_event
+                let final_weight = self.state_manager.get_current_weight().await.unwrap_or(0.0);
+                let duration_ms = self
+                    .shot_started_at
+                    .take()
+                    .map(|start| Instant::now().duration_since(start).as_millis() as u32)
+                    .unwrap_or(0);
+                self.get_event_publisher()
+                    .publish(SystemEvent::Brew(BrewEvent::Finished {
+                        final_weight,
+                        duration_ms,
+                    }))
                     .await;
             }
             BrewOutput::PredictiveStopTriggered => {
@@ -1194,17 +2463,31 @@ impl EspressoController {
                     .add_log("Predictive stop triggered".to_string())
                     .await;
             }
+            BrewOutput::PredictiveStopPreview { predicted_final_weight } => {
+                self.get_event_publisher()
+                    .publish(SystemEvent::Brew(BrewEvent::PredictiveStopPreview {
+                        predicted: predicted_final_weight,
+                    }))
+                    .await;
+            }
             BrewOutput::DisplayUpdate => {
-                // Display updates handled elsewhere for now
-                debug!("Display update requested");
+                // Only build and publish display state when a panel is fitted
+                if self.display.is_some() {
+                    let display_state = self.build_display_state().await;
+                    self.get_event_publisher()
+                        .publish(SystemEvent::Hardware(HardwareEvent::DisplayUpdate {
+                            state: display_state,
+                        }))
+                        .await;
+                }
             }
             BrewOutput::SystemEnabled => {
                 info!("✅ System enabled - killswitch OFF");
-                // Could publish system status event if needed
+                self.state_manager.set_system_enabled(true).await;
             }
             BrewOutput::SystemDisabled => {
                 info!("🚫 System disabled - killswitch ON");
-                // Could publish system status event if needed
+                self.state_manager.set_system_enabled(false).await;
             }
             BrewOutput::ScaleConnectionChanged { connected } => {
                 info!(
@@ -1274,9 +2557,38 @@ impl EspressoController {
                     .add_log(format!("Network status: BLE={}, WiFi={}", ble_enabled, wifi_connected))
                     .await;
             }
-            BrewOutput::AutoTareStateChanged { from, to } => {
-                info!("🔄 Auto-tare state transition: {:?} -> {:?}", from, to);
+            BrewOutput::AutoTareStateChanged { from, to, reason } => {
+                info!("🔄 Auto-tare state transition: {:?} -> {:?} ({})", from, to, reason);
                 self.state_manager.update_auto_tare_state(to).await;
+                if reason.triggers_tare() {
+                    self.get_event_publisher()
+                        .publish(SystemEvent::Brew(BrewEvent::AutoTareTriggered { reason }))
+                        .await;
+                }
+            }
+            BrewOutput::DoseStatusChanged => {
+                self.state_manager
+                    .set_dose_status(self.brew_controller.dose_status())
+                    .await;
+            }
+            BrewOutput::DoseCaptured { grams } => {
+                self.get_event_publisher()
+                    .publish(SystemEvent::Brew(BrewEvent::DoseCaptured { grams }))
+                    .await;
+                let mut config = self.state_manager.get_config().await;
+                if let Err(e) = config.apply_dose(grams) {
+                    self.reject_setting(e).await;
+                    return;
+                }
+                if config.brew_ratio.is_some() {
+                    info!("🎯 Target from dose: {:.1}g per cup", config.target_weight_g);
+                }
+                let total = config.total_target_weight_g();
+                self.apply_config_change(config, |brew| {
+                    brew.set_target_weight(total);
+                    Ok(())
+                })
+                .await;
             }
             BrewOutput::AutoTareExecuted => {
                 info!("⚖️ Auto-tare executed by state machine");
@@ -1290,18 +2602,53 @@ impl EspressoController {
                     .add_log(format!("Predictive stop scheduled: {}ms delay", delay_ms))
                     .await;
             }
+            BrewOutput::PredictiveStopCancelled { flow_rate } => {
+                self.state_manager
+                    .add_log(format!(
+                        "Predictive stop cancelled - flow dropped to {:.1}g/s, shot ended",
+                        flow_rate
+                    ))
+                    .await;
+            }
             BrewOutput::OvershootLearningUpdated { delay_ms, ewma, confidence } => {
                 info!("📊 Overshoot learning updated: delay={}ms, ewma={:.1}g, confidence={:.1}%", 
                       delay_ms, ewma, confidence * 100.0);
                 self.state_manager
                     .add_log(format!("Overshoot learning: delay={}ms, ewma={:.1}g", delay_ms, ewma))
                     .await;
+                self.state_manager
+                    .set_overshoot_status(self.brew_controller.overshoot_status())
+                    .await;
+                self.persist_overshoot_profiles().await;
             }
             BrewOutput::OvershootControllerReset => {
                 info!("🔄 Overshoot controller reset");
                 self.state_manager
                     .add_log("Overshoot controller reset".to_string())
                     .await;
+                self.state_manager
+                    .set_overshoot_status(self.brew_controller.overshoot_status())
+                    .await;
+                self.persist_overshoot_profiles().await;
+            }
+            BrewOutput::OvershootOutlierRejected { overshoot, mean, std_dev } => {
+                self.state_manager
+                    .add_log(format!(
+                        "Overshoot {:.1}g ignored as an outlier (recent {:.1}±{:.1}g)",
+                        overshoot, mean, std_dev
+                    ))
+                    .await;
+            }
+            BrewOutput::PredictiveStopAutoChanged { enabled, confidence } => {
+                let mut config = self.state_manager.get_config().await;
+                config.predictive_stop = enabled;
+                self.update_config(config).await;
+                self.get_event_publisher()
+                    .publish(SystemEvent::Brew(BrewEvent::PredictiveStopAutoChanged {
+                        enabled,
+                        confidence,
+                    }))
+                    .await;
             }
             BrewOutput::StartWifiProvisioning => {
                 info!("📱 State machine output: StartWifiProvisioning -> Starting WiFi provisioning");
@@ -1331,22 +2678,49 @@ impl EspressoController {
     }
 }
 
+/// Backstop for a controller dropped without `shutdown` - nothing async can run here, but the
+/// relay still goes off and the tasks are told to stop
+impl Drop for EspressoController {
+    fn drop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        if let Err(e) = self.relay_controller.turn_off_immediately() {
+            error!("🚨 RELAYS FAILED OFF: {:?}", e);
+        }
+        self.shutdown_token.cancel();
+        if self.scale_stopped.is_some() {
+            let _ = self.scale_command_channel.try_send(ScaleCommand::Shutdown);
+        }
+    }
+}
+
 // Embassy task functions
 #[embassy_executor::task]
-async fn scale_task(mut scale_client: BookooScale, command_channel: Arc<ScaleCommandChannel>) {
+async fn scale_task(
+    mut scale_client: BookooScale,
+    command_channel: Arc<ScaleCommandChannel>,
+    stopped: Arc<Signal<CriticalSectionRawMutex, ()>>,
+) {
     info!("Scale task started with command channel");
 
-    // Start scale client with command channel support
+    // Start scale client with command channel support - returns on `ScaleCommand::Shutdown`
     if let Err(e) = scale_client.start_with_commands(command_channel).await {
         error!("Scale task error: {:?}", e);
     }
+    stopped.signal(());
 }
 
 #[embassy_executor::task]
 async fn scale_data_bridge_task(
     scale_data_channel: Arc<ScaleDataChannel>,
     ble_status_channel: Arc<StatusChannel>,
+    scale_info_signal: Arc<ScaleInfoSignal>,
+    nearby_devices_signal: Arc<NearbyDevicesSignal>,
+    stage_channel: Arc<ConnectionStageChannel>,
+    link_quality_signal: Arc<LinkQualitySignal>,
     event_bus: Arc<EventBus>,
+    shutdown: Arc<ShutdownToken>,
 ) {
     info!("🌉 Scale data bridge task started - connecting scale data to event bus");
     
@@ -1355,24 +2729,35 @@ async fn scale_data_bridge_task(
     loop {
         let scale_data_fut = scale_data_channel.receive();
         let ble_status_fut = ble_status_channel.receive();
-        
-        match select(scale_data_fut, ble_status_fut).await {
-            Either::First(scale_data) => {
+        let scale_info_fut = scale_info_signal.wait();
+        let nearby_devices_fut = nearby_devices_signal.wait();
+        let stage_fut = stage_channel.receive();
+        let link_quality_fut = link_quality_signal.wait();
+        let bridged = select6(
+            scale_data_fut,
+            ble_status_fut,
+            scale_info_fut,
+            nearby_devices_fut,
+            stage_fut,
+            link_quality_fut,
+        );
+
+        let event = match select(bridged, shutdown.cancelled()).await {
+            Either::First(event) => event,
+            Either::Second(_) => break,
+        };
+        match event {
+            Either6::First(scale_data) => {
                 // Convert scale data to scale event and publish
                 event_publisher
                     .publish(SystemEvent::Scale(ScaleEvent::WeightChanged { data: scale_data }))
                     .await;
             }
-            Either::Second(ble_connected) => {
+            Either6::Second(ble_connected) => {
                 // Convert BLE status to both network and scale events
                 if ble_connected {
-                    event_publisher
-                        .publish(SystemEvent::Network(NetworkEvent::BleConnected { 
-                            device_name: "Bookoo Scale".to_string() 
-                        }))
-                        .await;
-                        
-                    // Also publish scale connection event with scale info
+                    // NetworkEvent::BleConnected comes from the connection stages, with the name
+                    // Publish scale connection event with scale info
                     let scale_info = crate::scales::traits::ScaleInfo {
                         brand: "Bookoo".to_string(),
                         model: "Themis Mini".to_string(),
@@ -1384,6 +2769,7 @@ async fn scale_data_bridge_task(
                             supports_tare: true,
                             supports_auto_off: false,
                         },
+                        settings: None,
                     };
                     event_publisher
                         .publish(SystemEvent::Scale(ScaleEvent::Connected { info: scale_info }))
@@ -1401,23 +2787,481 @@ async fn scale_data_bridge_task(
                         .await;
                 }
             }
+            Either6::Third(info) => {
+                event_publisher
+                    .publish(SystemEvent::Scale(ScaleEvent::InfoUpdated { info }))
+                    .await;
+            }
+            Either6::Fourth(devices) => {
+                event_publisher
+                    .publish(SystemEvent::Scale(ScaleEvent::DevicesDiscovered { devices }))
+                    .await;
+            }
+            Either6::Fifth(stage) => {
+                let network_event = match stage {
+                    ConnectionStage::Scanning => NetworkEvent::BleScanning,
+                    ConnectionStage::Connecting => NetworkEvent::BleConnecting,
+                    ConnectionStage::Connected { device_name } => {
+                        NetworkEvent::BleConnected { device_name }
+                    }
+                    ConnectionStage::Discovering => NetworkEvent::BleDiscovering,
+                    ConnectionStage::Subscribed => NetworkEvent::BleSubscribed,
+                };
+                event_publisher.publish(SystemEvent::Network(network_event)).await;
+            }
+            Either6::Sixth(report) => {
+                event_publisher
+                    .publish(SystemEvent::Scale(ScaleEvent::LinkQualityUpdated { report }))
+                    .await;
+            }
+        }
+    }
+    info!("🌉 Scale data bridge task stopped");
+}
+
+/// Second-press tracking for web commands that need confirming
+#[derive(Debug, Default)]
+struct CommandConfirmation {
+    pending: Option<(ConfirmableCommand, Instant)>,
+}
+
+impl CommandConfirmation {
+    /// True when this press confirms one of the same command inside the window - otherwise it
+    /// becomes the press waiting for confirmation
+    fn press(&mut self, command: ConfirmableCommand, now: Instant) -> bool {
+        let window = Duration::from_millis(COMMAND_CONFIRM_WINDOW_MS);
+        match self.pending.take() {
+            Some((pending, at))
+                if pending == command && now.saturating_duration_since(at) <= window =>
+            {
+                true
+            }
+            _ => {
+                self.pending = Some((command, now));
+                false
+            }
         }
     }
 }
 
+/// Relay on-time cap for a shot - a time mode brew longer than the default cap gets its length
+/// plus `RELAY_MAX_ON_MARGIN_MS`, so the cap only catches a stop that never came
+fn relay_max_on_for(config: &BrewConfig) -> Duration {
+    let default = Duration::from_secs(DEFAULT_RELAY_MAX_ON_SECS);
+    if config.stop_mode != StopMode::Time {
+        return default;
+    }
+    let shot = Duration::from_millis((config.brew_time_ms + RELAY_MAX_ON_MARGIN_MS) as u64);
+    default.max(shot)
+}
+
+/// Rate limit for brew progress events - the first sample of a brew always goes out
+fn progress_due(last_progress_at: Option<Instant>, now: Instant) -> bool {
+    last_progress_at.map_or(true, |last| {
+        now.saturating_duration_since(last) >= Duration::from_millis(BREW_PROGRESS_INTERVAL_MS)
+    })
+}
+
+/// A weight that moved faster than `rate_limit_g_per_s` since the last accepted frame is a
+/// corrupt frame the checksum let through. A jump the very next frame repeats is real - a cup
+/// set down - and is accepted on that frame
+fn weight_plausible(
+    rate_limit_g_per_s: f32,
+    last_plausible: &mut Option<(f32, Instant)>,
+    suspect: &mut Option<(f32, Instant)>,
+    data: &ScaleData,
+) -> bool {
+    let within_rate = |(weight_g, at): (f32, Instant)| {
+        let elapsed_ms = data
+            .received_at
+            .saturating_duration_since(at)
+            .as_millis()
+            .max(WEIGHT_RATE_MIN_INTERVAL_MS);
+        (data.weight_g - weight_g).abs() <= rate_limit_g_per_s * elapsed_ms as f32 / 1000.0
+    };
+    let plausible = last_plausible.map_or(true, within_rate) || suspect.is_some_and(within_rate);
+    if plausible {
+        *last_plausible = Some((data.weight_g, data.received_at));
+        *suspect = None;
+    } else {
+        *suspect = Some((data.weight_g, data.received_at));
+    }
+    plausible
+}
+
 #[embassy_executor::task]
-async fn websocket_task(websocket_server: WebSocketServer) {
+async fn websocket_task(websocket_server: WebSocketServer, shutdown: Arc<ShutdownToken>) {
     info!("WebSocket/HTTP task started");
-    if let Err(e) = websocket_server.start().await {
-        warn!(
-            "WebSocket/HTTP server failed to start: {:?} - continuing without web interface",
-            e
-        );
-        // Return instead of panicking - BLE functionality can continue
-    } else {
-        info!("WebSocket/HTTP server started successfully");
+    // Dropping the server future on shutdown drops the ESP HTTP server with it
+    match select(websocket_server.start(), shutdown.cancelled()).await {
+        Either::First(Err(e)) => {
+            warn!(
+                "WebSocket/HTTP server failed to start: {:?} - continuing without web interface",
+                e
+            );
+            // Return instead of panicking - BLE functionality can continue
+        }
+        Either::First(Ok(())) => info!("WebSocket/HTTP server started successfully"),
+        Either::Second(_) => info!("WebSocket/HTTP server stopped"),
     }
 }
 
 // NOTE: Hardware side effects and tick events are now processed directly
 // in the main event loop to avoid embassy task lifetime and generic issues
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::nvs_store::{MemoryStore, MEMORY_STORE_CAPACITY_BYTES};
+    use crate::test_helpers::{brewing_controller, idle_controller};
+    use crate::types::LogEntry;
+
+    #[test]
+    fn test_second_press_inside_the_window_confirms() {
+        let mut confirmation = CommandConfirmation::default();
+        let at = |ms: u64| Instant::from_millis(ms);
+        assert!(!confirmation.press(ConfirmableCommand::Stop, at(0)));
+        assert!(confirmation.press(ConfirmableCommand::Stop, at(COMMAND_CONFIRM_WINDOW_MS)));
+
+        // Used up - the next press starts over
+        assert!(!confirmation.press(ConfirmableCommand::Stop, at(10_000)));
+        // Too late, or a different command, re-arms rather than confirms
+        let late = 10_000 + COMMAND_CONFIRM_WINDOW_MS + 1;
+        assert!(!confirmation.press(ConfirmableCommand::Stop, at(late)));
+        assert!(!confirmation.press(ConfirmableCommand::Start, at(late + 100)));
+        assert!(confirmation.press(ConfirmableCommand::Start, at(late + 200)));
+    }
+
+    #[test]
+    fn test_unconfirmed_web_stop_is_held() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let confirm_stop = UserEvent::SetCommandConfirmation {
+                command: ConfirmableCommand::Stop,
+                enabled: true,
+            };
+            controller.dispatch(SystemEvent::User(confirm_stop)).await;
+            assert!(controller.snapshot().await.config.confirm_stop);
+
+            // The control loop's path - nothing reaches the bus until the second press
+            let event_bus = Arc::clone(&controller.event_bus);
+            let mut bus = event_bus.subscriber();
+            fn stop_published(bus: &mut EventSubscriber) -> bool {
+                std::iter::from_fn(|| bus.try_next_event())
+                    .any(|event| matches!(event, SystemEvent::User(UserEvent::StopBrewing)))
+            }
+            controller.send_command(WebSocketCommand::StopTimer).await;
+            let state = controller.snapshot().await;
+            let held = |entry: &LogEntry| entry.message.starts_with("Send stop again");
+            assert!(state.log_entries.iter().any(held));
+            assert!(!stop_published(&mut bus));
+
+            controller.send_command(WebSocketCommand::StopTimer).await;
+            assert!(stop_published(&mut bus));
+        });
+    }
+
+    #[test]
+    fn test_three_minute_time_mode_brew_fits_under_relay_cap() {
+        let config = BrewConfig {
+            stop_mode: StopMode::Time,
+            brew_time_ms: 180_000,
+            ..BrewConfig::default()
+        };
+        let cap = relay_max_on_for(&config);
+        assert!(cap > Duration::from_millis(config.brew_time_ms as u64));
+        assert_eq!(cap.as_millis(), (180_000 + RELAY_MAX_ON_MARGIN_MS) as u64);
+
+        // Weight mode and short time mode shots keep the default cap
+        let default = Duration::from_secs(DEFAULT_RELAY_MAX_ON_SECS);
+        assert_eq!(relay_max_on_for(&BrewConfig::default()), default);
+        let short = BrewConfig {
+            stop_mode: StopMode::Time,
+            ..BrewConfig::default()
+        };
+        assert_eq!(relay_max_on_for(&short), default);
+    }
+
+    #[test]
+    fn test_progress_throttled_to_five_hz() {
+        // A second of 10Hz samples yields five progress events
+        let mut last_progress_at = None;
+        let published = (0..10)
+            .map(|i| Instant::from_millis(1000 + i * 100))
+            .filter(|&now| {
+                let due = progress_due(last_progress_at, now);
+                if due {
+                    last_progress_at = Some(now);
+                }
+                due
+            })
+            .count();
+        assert_eq!(published, 5);
+    }
+
+    // Replays weights 100ms apart and returns the indices the sanity filter drops
+    fn rejected_weights(weights: &[f32]) -> Vec<usize> {
+        let mut last_plausible = None;
+        let mut suspect = None;
+        weights
+            .iter()
+            .enumerate()
+            .filter(|&(i, &weight_g)| {
+                let data = ScaleData {
+                    timestamp_ms: i as u32 * 100,
+                    weight_g,
+                    flow_rate_g_per_s: 0.0,
+                    battery_percent: 80,
+                    timer_running: true,
+                    received_at: Instant::from_millis(i as u64 * 100),
+                };
+                let limit = WEIGHT_RATE_LIMIT_G_PER_S;
+                !weight_plausible(limit, &mut last_plausible, &mut suspect, &data)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_corrupt_weight_frame_dropped() {
+        assert_eq!(rejected_weights(&[20.0, 22.0, 424.0, 24.0, 26.0]), [2]);
+        // Corruption the other way
+        assert_eq!(rejected_weights(&[20.0, 22.0, -380.0, 24.0]), [2]);
+        // Two different bad frames in a row don't confirm each other
+        assert_eq!(rejected_weights(&[20.0, 412.0, -150.0, 21.0]), [1, 2]);
+    }
+
+    #[test]
+    fn test_weight_jump_confirmed_by_next_frame() {
+        // Cup set down: the first frame is held back, the repeat goes through
+        assert_eq!(rejected_weights(&[0.0, 0.0, 310.0, 310.2, 310.2]), [2]);
+        // Fast pour stays under the limit
+        let pour: Vec<f32> = (0..30).map(|i| i as f32 * 1.5).collect();
+        assert!(rejected_weights(&pour).is_empty());
+    }
+
+    async fn test_controller() -> EspressoController {
+        let store = MemoryStore::new(MEMORY_STORE_CAPACITY_BYTES);
+        let storage = NvsStorage::with_store(Box::new(store)).await;
+        EspressoController::new_for_test(RelayController::new_simulated(), storage).await
+    }
+
+    // A live frame from the scale - a brew ends on the first frame with the timer stopped
+    fn weight_sample(weight_g: f32, timer_running: bool) -> SystemEvent {
+        SystemEvent::Scale(ScaleEvent::WeightChanged {
+            data: ScaleData {
+                timestamp_ms: 0,
+                weight_g,
+                flow_rate_g_per_s: 0.0,
+                battery_percent: 80,
+                timer_running,
+                received_at: Instant::now(),
+            },
+        })
+    }
+
+    #[test]
+    fn test_weight_sample_mid_brew_publishes_progress() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            controller.brew_controller = idle_controller();
+            controller.dispatch(SystemEvent::User(UserEvent::StartBrewing)).await;
+            assert_eq!(controller.snapshot().await.brew_state, BrewState::Brewing);
+
+            let event_bus = Arc::clone(&controller.event_bus);
+            let mut bus = event_bus.subscriber();
+            controller.dispatch(weight_sample(9.0, true)).await;
+            let percent = std::iter::from_fn(|| bus.try_next_event()).find_map(|event| match event {
+                SystemEvent::Brew(BrewEvent::Progress { percent, .. }) => Some(percent),
+                _ => None,
+            });
+            // 9g of the default 36g target
+            assert_eq!(percent, Some(25.0));
+        });
+    }
+
+    #[test]
+    fn test_zeroed_weight_sample_starts_a_brew_waiting_on_tare() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            controller.brew_controller = idle_controller();
+            let tare_first = SystemEvent::User(UserEvent::SetTareBeforeBrew(true));
+            controller.dispatch(tare_first).await;
+            controller.dispatch(SystemEvent::User(UserEvent::StartBrewing)).await;
+            assert_eq!(controller.snapshot().await.brew_state, BrewState::Idle);
+
+            // The first reading after the tare is zero - that confirms it
+            controller.dispatch(weight_sample(0.0, true)).await;
+            assert_eq!(controller.snapshot().await.brew_state, BrewState::Brewing);
+        });
+    }
+
+    #[test]
+    fn test_weight_sample_mid_brew_is_traced() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            controller.brew_controller = idle_controller();
+            controller.dispatch(SystemEvent::User(UserEvent::StartBrewing)).await;
+
+            controller.dispatch(weight_sample(9.0, true)).await;
+            let trace = controller.brew_trace.lock().await;
+            let weights: Vec<f32> = trace.samples().iter().map(|s| s.weight_g).collect();
+            assert_eq!(weights, [9.0]);
+        });
+    }
+
+    #[test]
+    fn test_implausible_weight_sample_is_dropped() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            controller.dispatch(weight_sample(20.0, false)).await;
+
+            // 400g inside one frame interval - far past the default rate limit
+            controller.dispatch(weight_sample(420.0, false)).await;
+            let state = controller.snapshot().await;
+            assert_eq!(state.scale_data.map(|data| data.weight_g), Some(20.0));
+            let dropped = |entry: &LogEntry| entry.message.starts_with("Ignored implausible");
+            assert!(state.log_entries.iter().any(dropped));
+        });
+    }
+
+    #[test]
+    fn test_web_command_updates_state_off_device() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let command = serde_json::from_str(r#"{"type":"set_target_weight","weight":40}"#);
+            controller.send_command(command.unwrap()).await;
+
+            assert_eq!(controller.snapshot().await.config.target_weight_g, 40.0);
+        });
+    }
+
+    #[test]
+    fn test_out_of_range_target_is_rejected() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            for weight in [-5.0, 0.0, 1000.0] {
+                let user = SystemEvent::User(UserEvent::SetTargetWeight(weight));
+                controller.dispatch(user).await;
+            }
+
+            let state = controller.snapshot().await;
+            assert_eq!(state.config.target_weight_g, BrewConfig::default().target_weight_g);
+            let rejected = |entry: &LogEntry| entry.message.contains("Rejected setting");
+            assert!(state.log_entries.iter().any(rejected));
+        });
+    }
+
+    #[test]
+    fn test_emergency_stop_dispatch_turns_relay_off() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let relay_on = HardwareEvent::RelayOn {
+                channel: PRIMARY_RELAY_CHANNEL,
+            };
+            controller.dispatch(SystemEvent::Hardware(relay_on)).await;
+            assert!(controller.snapshot().await.relay_enabled);
+
+            // The stop only publishes AllRelaysOff - the relay goes off when that is handled
+            controller.dispatch(SystemEvent::User(UserEvent::EmergencyStop)).await;
+            let state = controller.snapshot().await;
+            assert!(!state.relay_enabled);
+            assert!(state.last_error.is_some());
+        });
+    }
+
+    #[test]
+    fn test_repeated_emergency_stop_still_turns_relay_off() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let relay_on = HardwareEvent::RelayOn {
+                channel: PRIMARY_RELAY_CHANNEL,
+            };
+
+            // The second stop lands inside the alert debounce - the relay goes off regardless
+            for _ in 0..2 {
+                controller.dispatch(SystemEvent::Hardware(relay_on.clone())).await;
+                assert!(controller.snapshot().await.relay_enabled);
+
+                controller.dispatch(SystemEvent::User(UserEvent::EmergencyStop)).await;
+                assert!(!controller.snapshot().await.relay_enabled);
+                let machine_state = controller.brew_controller.get_state();
+                assert_eq!(machine_state, crate::brewing::BrewState::Idle);
+            }
+        });
+    }
+
+    #[test]
+    fn test_relay_on_outside_a_brew_is_stopped_on_tick() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let relay_on = HardwareEvent::RelayOn {
+                channel: PRIMARY_RELAY_CHANNEL,
+            };
+            controller.dispatch(SystemEvent::Hardware(relay_on)).await;
+            assert!(controller.snapshot().await.relay_enabled);
+
+            controller.dispatch(SystemEvent::Time(TimeEvent::Tick)).await;
+            let state = controller.snapshot().await;
+            assert!(!state.relay_enabled);
+            assert!(state
+                .last_error
+                .is_some_and(|error| error.starts_with("Relay on with no brew active")));
+        });
+    }
+
+    #[test]
+    fn test_safety_trip_mid_brew_turns_relay_off() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            controller.brew_controller = brewing_controller(StopMode::Weight);
+            let relay_on = HardwareEvent::RelayOn {
+                channel: PRIMARY_RELAY_CHANNEL,
+            };
+            controller.dispatch(SystemEvent::Hardware(relay_on)).await;
+            assert!(controller.snapshot().await.relay_enabled);
+
+            // The scale has never sent data, so the stale data check trips on the next tick
+            controller.dispatch(SystemEvent::Time(TimeEvent::Tick)).await;
+            let state = controller.snapshot().await;
+            assert!(!state.relay_enabled);
+            assert!(state
+                .last_error
+                .is_some_and(|error| error.starts_with("No scale data")));
+            let machine_state = controller.brew_controller.get_state();
+            assert_eq!(machine_state, crate::brewing::BrewState::Idle);
+        });
+    }
+
+    #[test]
+    fn test_set_config_applies_as_a_unit() {
+        embassy_futures::block_on(async {
+            let mut controller = test_controller().await;
+            let mut config = controller.snapshot().await.config;
+            config.target_weight_g = 36.0;
+            config.brew_time_ms = 30_000;
+            let set = |config: &BrewConfig| WebSocketCommand::SetConfig {
+                config: Box::new(config.clone()),
+            };
+            controller.send_command(set(&config)).await;
+            let applied = controller.snapshot().await.config;
+            assert_eq!(applied.target_weight_g, 36.0);
+            assert_eq!(applied.brew_time_ms, 30_000);
+
+            // The brew time is out of range - the valid target must not land either
+            let mut bad = applied.clone();
+            bad.target_weight_g = 50.0;
+            bad.brew_time_ms = 10;
+            controller.send_command(set(&bad)).await;
+            let config = controller.snapshot().await.config;
+            assert_eq!(config.target_weight_g, 36.0);
+            assert_eq!(config.brew_time_ms, 30_000);
+
+            let mut unsafe_limits = applied;
+            unsafe_limits.max_weight_g *= 2.0;
+            controller.send_command(set(&unsafe_limits)).await;
+            let config = controller.snapshot().await.config;
+            assert_eq!(config.max_weight_g, BrewConfig::default().max_weight_g);
+        });
+    }
+}
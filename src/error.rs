@@ -0,0 +1,47 @@
+//! Crate-level error for the top-level APIs - callers branch on the failure kind, not on strings
+
+use crate::ble::BleError;
+use crate::hardware::relay::RelayError;
+use crate::scales::bookoo::ScaleError;
+use crate::system::nvs_store::StoreError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GravelError {
+    #[error("BLE: {0}")]
+    Ble(#[from] BleError),
+    #[error("Scale: {0}")]
+    Scale(#[from] ScaleError),
+    #[error("Relay: {0}")]
+    Relay(#[from] RelayError),
+    #[error("Storage: {0}")]
+    Storage(#[from] StoreError),
+    /// The executor had no room left for one of the controller's tasks
+    #[error("Failed to spawn the {0} task")]
+    TaskSpawn(&'static str),
+}
+
+impl GravelError {
+    /// BLE trouble is often transient (the radio settling after WiFi provisioning), so another
+    /// attempt can succeed. A relay, storage or executor failure won't fix itself
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, GravelError::Ble(_) | GravelError::Scale(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ble_failures_retry_and_gpio_failures_abort() {
+        let ble: GravelError = ScaleError::from(BleError::NotConnected).into();
+        assert!(matches!(ble, GravelError::Scale(ScaleError::BleError(BleError::NotConnected))));
+        assert!(ble.is_retryable());
+
+        let gpio: GravelError = RelayError::GpioError("pin 19 busy".to_string()).into();
+        assert!(!gpio.is_retryable());
+        assert_eq!(gpio.to_string(), "Relay: GPIO error: pin 19 busy");
+        assert!(!GravelError::TaskSpawn("scale").is_retryable());
+    }
+}
@@ -0,0 +1,270 @@
+//! Physical push-button input with debouncing and short/long press detection
+//! Buttons publish plain UserEvents so they behave exactly like web commands
+
+use crate::system::events::{EventBus, UserEvent};
+use crate::system::shutdown::ShutdownToken;
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use esp_idf_svc::hal::gpio::{AnyIOPin, Input, PinDriver, Pull};
+use log::{debug, info, warn};
+use std::sync::Arc;
+
+/// How often the button pin is sampled
+const BUTTON_POLL_INTERVAL_MS: u64 = 10;
+
+/// Classified button press
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonPress {
+    Short,
+    Long,
+}
+
+/// Per-button configuration - which user event each press type emits
+#[derive(Debug, Clone)]
+pub struct ButtonConfig {
+    pub name: &'static str,
+    pub short_press: Option<UserEvent>,
+    pub long_press: Option<UserEvent>,
+    pub debounce_ms: u64,
+    pub long_press_ms: u64,
+    pub active_low: bool,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self {
+            name: "button",
+            short_press: None,
+            long_press: None,
+            debounce_ms: 30,
+            long_press_ms: 1000,
+            active_low: true, // Internal pull-up, button shorts to GND
+        }
+    }
+}
+
+impl ButtonConfig {
+    /// Short press tares, long press stops brewing (fits the on-board BOOT button)
+    pub fn tare() -> Self {
+        Self {
+            name: "tare",
+            short_press: Some(UserEvent::TareScale),
+            long_press: Some(UserEvent::StopBrewing),
+            ..Default::default()
+        }
+    }
+
+    /// Short press starts brewing, long press stops brewing
+    pub fn brew() -> Self {
+        Self {
+            name: "brew",
+            short_press: Some(UserEvent::StartBrewing),
+            long_press: Some(UserEvent::StopBrewing),
+            ..Default::default()
+        }
+    }
+
+    /// User event for a classified press
+    pub fn action_for(&self, press: ButtonPress) -> Option<UserEvent> {
+        match press {
+            ButtonPress::Short => self.short_press.clone(),
+            ButtonPress::Long => self.long_press.clone(),
+        }
+    }
+}
+
+/// Pure debounce + press classification, fed with raw samples and a millisecond clock
+#[derive(Debug)]
+pub struct ButtonDebouncer {
+    debounce_ms: u64,
+    long_press_ms: u64,
+    stable_pressed: bool,
+    last_raw: bool,
+    last_raw_change_ms: u64,
+    pressed_since_ms: Option<u64>,
+    long_press_fired: bool,
+}
+
+impl ButtonDebouncer {
+    pub fn new(debounce_ms: u64, long_press_ms: u64) -> Self {
+        Self {
+            debounce_ms,
+            long_press_ms,
+            stable_pressed: false,
+            last_raw: false,
+            last_raw_change_ms: 0,
+            pressed_since_ms: None,
+            long_press_fired: false,
+        }
+    }
+
+    /// Feed a raw sample; returns a press once it has been classified
+    pub fn update(&mut self, raw_pressed: bool, now_ms: u64) -> Option<ButtonPress> {
+        if raw_pressed != self.last_raw {
+            self.last_raw = raw_pressed;
+            self.last_raw_change_ms = now_ms;
+        }
+
+        // Accept a new level only once it has been steady for the debounce window
+        if self.last_raw != self.stable_pressed
+            && now_ms.saturating_sub(self.last_raw_change_ms) >= self.debounce_ms
+        {
+            self.stable_pressed = self.last_raw;
+
+            if self.stable_pressed {
+                self.pressed_since_ms = Some(now_ms);
+                self.long_press_fired = false;
+            } else {
+                let was_long = self.long_press_fired;
+                self.pressed_since_ms = None;
+                self.long_press_fired = false;
+                if !was_long {
+                    return Some(ButtonPress::Short);
+                }
+            }
+        }
+
+        // Long press fires while still held so the user gets immediate feedback
+        if let Some(pressed_since) = self.pressed_since_ms {
+            if !self.long_press_fired && now_ms.saturating_sub(pressed_since) >= self.long_press_ms {
+                self.long_press_fired = true;
+                return Some(ButtonPress::Long);
+            }
+        }
+
+        None
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.stable_pressed
+    }
+}
+
+/// A GPIO button bound to its configuration
+pub struct ButtonInput {
+    pin: PinDriver<'static, AnyIOPin, Input>,
+    config: ButtonConfig,
+}
+
+impl ButtonInput {
+    pub fn new(pin: AnyIOPin, config: ButtonConfig) -> Result<Self, ButtonError> {
+        let mut pin = PinDriver::input(pin).map_err(|e| {
+            ButtonError::GpioError(format!("Failed to configure {} button: {:?}", config.name, e))
+        })?;
+
+        let pull = if config.active_low { Pull::Up } else { Pull::Down };
+        pin.set_pull(pull).map_err(|e| {
+            ButtonError::GpioError(format!("Failed to set pull on {} button: {:?}", config.name, e))
+        })?;
+
+        info!(
+            "🔘 Button '{}' initialized (short: {:?}, long: {:?} after {}ms)",
+            config.name, config.short_press, config.long_press, config.long_press_ms
+        );
+
+        Ok(Self { pin, config })
+    }
+
+    pub fn config(&self) -> &ButtonConfig {
+        &self.config
+    }
+
+    fn is_pressed_raw(&self) -> bool {
+        if self.config.active_low {
+            self.pin.is_low()
+        } else {
+            self.pin.is_high()
+        }
+    }
+
+    /// Sample the button forever, publishing the configured user events
+    pub async fn run(self, event_bus: Arc<EventBus>) {
+        let mut debouncer = ButtonDebouncer::new(self.config.debounce_ms, self.config.long_press_ms);
+        let started = Instant::now();
+
+        loop {
+            let now_ms = Instant::now().duration_since(started).as_millis();
+
+            if let Some(press) = debouncer.update(self.is_pressed_raw(), now_ms) {
+                match self.config.action_for(press) {
+                    Some(user_event) => {
+                        info!("🔘 Button '{}' {:?} press -> {:?}", self.config.name, press, user_event);
+                        event_bus.publisher().user_command(user_event).await;
+                    }
+                    None => {
+                        debug!("🔘 Button '{}' {:?} press has no action", self.config.name, press);
+                    }
+                }
+            }
+
+            Timer::after(Duration::from_millis(BUTTON_POLL_INTERVAL_MS)).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ButtonError {
+    GpioError(String),
+}
+
+impl std::fmt::Display for ButtonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ButtonError::GpioError(msg) => write!(f, "GPIO error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ButtonError {}
+
+#[embassy_executor::task(pool_size = 4)]
+pub async fn button_task(
+    button: ButtonInput,
+    event_bus: Arc<EventBus>,
+    shutdown: Arc<ShutdownToken>,
+) {
+    let name = button.config().name;
+    info!("🔘 Button task started for '{}'", name);
+    match select(button.run(event_bus), shutdown.cancelled()).await {
+        Either::First(_) => warn!("Button task exited unexpectedly"),
+        Either::Second(_) => info!("🔘 Button task stopped for '{}'", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(debouncer: &mut ButtonDebouncer, pressed: bool, from_ms: u64, to_ms: u64) -> Vec<ButtonPress> {
+        (from_ms..to_ms)
+            .step_by(10)
+            .filter_map(|t| debouncer.update(pressed, t))
+            .collect()
+    }
+
+    #[test]
+    fn test_short_press() {
+        let mut debouncer = ButtonDebouncer::new(30, 1000);
+        assert!(feed(&mut debouncer, true, 0, 200).is_empty());
+        assert_eq!(feed(&mut debouncer, false, 200, 300), vec![ButtonPress::Short]);
+    }
+
+    #[test]
+    fn test_long_press_fires_once_without_short() {
+        let mut debouncer = ButtonDebouncer::new(30, 1000);
+        assert_eq!(feed(&mut debouncer, true, 0, 2000), vec![ButtonPress::Long]);
+        assert!(feed(&mut debouncer, false, 2000, 2100).is_empty());
+    }
+
+    #[test]
+    fn test_bounce_is_ignored() {
+        let mut debouncer = ButtonDebouncer::new(30, 1000);
+        // Contact bounce shorter than the debounce window
+        assert_eq!(debouncer.update(true, 0), None);
+        assert_eq!(debouncer.update(false, 10), None);
+        assert_eq!(debouncer.update(true, 20), None);
+        assert_eq!(debouncer.update(false, 30), None);
+        assert!(feed(&mut debouncer, false, 40, 200).is_empty());
+        assert!(!debouncer.is_pressed());
+    }
+}
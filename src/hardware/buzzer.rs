@@ -0,0 +1,184 @@
+//! Piezo buzzer driven by LEDC PWM for audible brew-complete and error cues
+//! Patterns are queued on a channel and played by a dedicated task so the control loop never blocks
+
+use crate::system::shutdown::ShutdownToken;
+use crate::types::QuietHours;
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Timer};
+use esp_idf_svc::hal::gpio::OutputPin;
+use esp_idf_svc::hal::ledc::{config::TimerConfig, LedcChannel, LedcDriver, LedcTimer, LedcTimerDriver};
+use esp_idf_svc::hal::peripheral::Peripheral;
+use esp_idf_svc::hal::prelude::*;
+use log::{debug, info, warn};
+use std::sync::Arc;
+
+pub type BuzzerChannel = Channel<CriticalSectionRawMutex, BuzzerPattern, 4>;
+
+/// Tone frequency - close to the resonance of common 12mm piezo discs
+const BUZZER_FREQUENCY_HZ: u32 = 2700;
+
+/// Audible cues the buzzer can play
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuzzerPattern {
+    ShortBeep,
+    DoubleBeep,
+    ErrorTone,
+}
+
+impl BuzzerPattern {
+    /// (on_ms, off_ms) segments making up the pattern
+    pub fn segments(&self) -> &'static [(u64, u64)] {
+        match self {
+            BuzzerPattern::ShortBeep => &[(120, 0)],
+            BuzzerPattern::DoubleBeep => &[(100, 80), (100, 0)],
+            BuzzerPattern::ErrorTone => &[(400, 150), (400, 150), (400, 0)],
+        }
+    }
+}
+
+/// Whether the buzzer may sound right now given the enable flag and quiet hours
+pub fn buzzer_allowed(enabled: bool, quiet_hours: Option<&QuietHours>) -> bool {
+    if !enabled {
+        return false;
+    }
+    match quiet_hours {
+        Some(quiet) => !quiet.is_active_now(),
+        None => true,
+    }
+}
+
+pub struct BuzzerController {
+    driver: LedcDriver<'static>,
+}
+
+impl BuzzerController {
+    pub fn new<C, T>(
+        channel: impl Peripheral<P = C> + 'static,
+        timer: impl Peripheral<P = T> + 'static,
+        pin: impl Peripheral<P = impl OutputPin> + 'static,
+    ) -> Result<Self, BuzzerError>
+    where
+        C: LedcChannel<SpeedMode = <T as LedcTimer>::SpeedMode>,
+        T: LedcTimer + 'static,
+    {
+        let timer_config = TimerConfig::new().frequency(BUZZER_FREQUENCY_HZ.Hz().into());
+        let timer_driver = LedcTimerDriver::new(timer, &timer_config)
+            .map_err(|e| BuzzerError::PwmError(format!("Failed to configure LEDC timer: {:?}", e)))?;
+
+        let mut driver = LedcDriver::new(channel, timer_driver, pin)
+            .map_err(|e| BuzzerError::PwmError(format!("Failed to configure LEDC channel: {:?}", e)))?;
+
+        // Start silent
+        driver
+            .set_duty(0)
+            .map_err(|e| BuzzerError::PwmError(format!("Failed to silence buzzer: {:?}", e)))?;
+
+        info!("🔔 Buzzer initialized at {}Hz", BUZZER_FREQUENCY_HZ);
+        Ok(Self { driver })
+    }
+
+    fn set_tone(&mut self, on: bool) -> Result<(), BuzzerError> {
+        // 50% duty gives the loudest square wave on a piezo
+        let duty = if on { self.driver.get_max_duty() / 2 } else { 0 };
+        self.driver
+            .set_duty(duty)
+            .map_err(|e| BuzzerError::PwmError(format!("Failed to set duty: {:?}", e)))
+    }
+
+    pub async fn play(&mut self, pattern: BuzzerPattern) -> Result<(), BuzzerError> {
+        debug!("🔔 Playing {:?}", pattern);
+        for &(on_ms, off_ms) in pattern.segments() {
+            self.set_tone(true)?;
+            Timer::after(Duration::from_millis(on_ms)).await;
+            self.set_tone(false)?;
+            if off_ms > 0 {
+                Timer::after(Duration::from_millis(off_ms)).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn short_beep(&mut self) -> Result<(), BuzzerError> {
+        self.play(BuzzerPattern::ShortBeep).await
+    }
+
+    pub async fn double_beep(&mut self) -> Result<(), BuzzerError> {
+        self.play(BuzzerPattern::DoubleBeep).await
+    }
+
+    pub async fn error_tone(&mut self) -> Result<(), BuzzerError> {
+        self.play(BuzzerPattern::ErrorTone).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BuzzerError {
+    PwmError(String),
+}
+
+impl std::fmt::Display for BuzzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuzzerError::PwmError(msg) => write!(f, "PWM error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BuzzerError {}
+
+#[embassy_executor::task]
+pub async fn buzzer_task(
+    mut buzzer: BuzzerController,
+    patterns: Arc<BuzzerChannel>,
+    shutdown: Arc<ShutdownToken>,
+) {
+    info!("🔔 Buzzer task started");
+    loop {
+        // Checked between patterns only - a pattern cut short could leave the tone on
+        let pattern = match select(patterns.receive(), shutdown.cancelled()).await {
+            Either::First(pattern) => pattern,
+            Either::Second(_) => break,
+        };
+        if let Err(e) = buzzer.play(pattern).await {
+            warn!("Buzzer failed to play {:?}: {}", pattern, e);
+        }
+    }
+    info!("🔔 Buzzer task stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet(start_hour: u8, end_hour: u8) -> QuietHours {
+        QuietHours {
+            start_hour,
+            end_hour,
+            utc_offset_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours_wrap_past_midnight() {
+        let night = quiet(22, 6);
+        for hour in [22, 23, 0, 3, 5] {
+            assert!(night.contains_hour(hour), "{}:00 should be quiet", hour);
+        }
+        for hour in [6, 12, 21] {
+            assert!(!night.contains_hour(hour), "{}:00 should not be quiet", hour);
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours_with_equal_ends_are_empty() {
+        let empty = quiet(7, 7);
+        assert!((0..24).all(|hour| !empty.contains_hour(hour)));
+    }
+
+    #[test]
+    fn test_disabled_buzzer_stays_silent() {
+        assert!(!buzzer_allowed(false, None));
+        assert!(buzzer_allowed(true, None));
+    }
+}
@@ -1,4 +1,4 @@
-//! SH1106 OLED Display support for espresso scale controller
+//! I2C OLED display support (SH1106 and SSD1306/SSD1309) for espresso scale controller
 //! Using embedded-graphics for clean, efficient rendering
 
 use embedded_graphics::{
@@ -8,107 +8,285 @@ use embedded_graphics::{
     primitives::{PrimitiveStyle, Rectangle},
     text::{Baseline, Text},
 };
+use embassy_time::{Duration, Instant};
 use esp_idf_svc::hal::{
     gpio::{InputPin, OutputPin},
-    i2c::{I2cConfig, I2cDriver},
+    i2c::{I2c, I2cConfig, I2cDriver},
     peripheral::Peripheral,
     prelude::*,
 };
+use crate::types::DISPLAY_REFRESH_MS;
 use log::{debug, info};
 use sh1106::Builder;
+use ssd1306::{
+    mode::BufferedGraphicsMode, prelude::*, size::DisplaySize128x64, I2CDisplayInterface,
+    Ssd1306,
+};
+
+// Display state is shared with the event bus (HardwareEvent::DisplayUpdate)
+pub use crate::system::events::DisplayState;
 
 const DISPLAY_WIDTH: u32 = 128;
 const DISPLAY_HEIGHT: u32 = 64;
 
-// UI state for the display
-#[derive(Debug, Clone)]
-pub struct DisplayState {
-    pub weight_g: f32,
-    pub target_weight_g: f32,
-    pub flow_rate_g_per_s: f32,
-    pub timer_running: bool,
-    pub brew_state: String,
-    pub ble_connected: bool,
-    pub battery_percent: u8,
-    pub error: Option<String>,
+/// Supported OLED controller chips
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayDriver {
+    Sh1106,
+    /// Also drives SSD1309 panels, which share the SSD1306 command set
+    Ssd1306,
+}
+
+type Sh1106Display = sh1106::mode::GraphicsMode<sh1106::interface::I2cInterface<I2cDriver<'static>>>;
+type Ssd1306Display = Ssd1306<
+    I2CInterface<I2cDriver<'static>>,
+    DisplaySize128x64,
+    BufferedGraphicsMode<DisplaySize128x64>,
+>;
+
+/// Concrete panel behind a single draw target, so the renderer doesn't care which chip is fitted
+pub enum OledPanel {
+    Sh1106(Sh1106Display),
+    Ssd1306(Ssd1306Display),
+}
+
+impl OledPanel {
+    fn clear_buffer(&mut self) {
+        match self {
+            OledPanel::Sh1106(display) => display.clear(),
+            OledPanel::Ssd1306(display) => {
+                let _ = DrawTarget::clear(display, BinaryColor::Off);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        match self {
+            OledPanel::Sh1106(display) => display.flush().map_err(|e| format!("{:?}", e)),
+            OledPanel::Ssd1306(display) => display.flush().map_err(|e| format!("{:?}", e)),
+        }
+    }
+}
+
+impl OriginDimensions for OledPanel {
+    fn size(&self) -> Size {
+        Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    }
+}
+
+impl DrawTarget for OledPanel {
+    type Color = BinaryColor;
+    type Error = String;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        match self {
+            OledPanel::Sh1106(display) => display.draw_iter(pixels).map_err(|e| format!("{:?}", e)),
+            OledPanel::Ssd1306(display) => display.draw_iter(pixels).map_err(|e| format!("{:?}", e)),
+        }
+    }
 }
 
-impl Default for DisplayState {
-    fn default() -> Self {
+// Transient message shown over the normal layout until it expires
+struct DisplayAlert {
+    message: String,
+    expires_at: Instant,
+}
+
+/// Keeps full redraws at least an interval apart - a change that comes sooner is held until
+/// `poll` finds the interval up, so the last state is always drawn, just not every one
+#[derive(Debug)]
+pub struct RefreshLimiter {
+    interval: Duration,
+    last_draw: Option<Instant>,
+    pending: bool,
+}
+
+impl RefreshLimiter {
+    pub fn new(interval_ms: u32) -> Self {
         Self {
-            weight_g: 0.0,
-            target_weight_g: 36.0,
-            flow_rate_g_per_s: 0.0,
-            timer_running: false,
-            brew_state: "Idle".to_string(),
-            ble_connected: false,
-            battery_percent: 0,
-            error: None,
+            interval: Duration::from_millis(interval_ms as u64),
+            last_draw: None,
+            pending: false,
         }
     }
+
+    pub fn set_interval_ms(&mut self, interval_ms: u32) {
+        self.interval = Duration::from_millis(interval_ms as u64);
+    }
+
+    /// The state changed - true when it may be drawn right away
+    pub fn request(&mut self, now: Instant) -> bool {
+        if self.due(now) {
+            self.drawn(now);
+            true
+        } else {
+            self.pending = true;
+            false
+        }
+    }
+
+    /// True when a held-back change is now due
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.pending && self.due(now) {
+            self.drawn(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A frame went out - it shows any change that was being held back
+    pub fn drawn(&mut self, now: Instant) {
+        self.last_draw = Some(now);
+        self.pending = false;
+    }
+
+    fn due(&self, now: Instant) -> bool {
+        self.last_draw
+            .map_or(true, |last| now.saturating_duration_since(last) >= self.interval)
+    }
 }
 
-pub struct DisplayController<I2C>
-where
-    I2C: embedded_hal::blocking::i2c::Write + embedded_hal::blocking::i2c::WriteRead,
-{
-    display: sh1106::mode::GraphicsMode<sh1106::interface::I2cInterface<I2C>>,
+pub struct DisplayController {
+    display: OledPanel,
     state: DisplayState,
+    alert: Option<DisplayAlert>,
+    limiter: RefreshLimiter,
 }
 
-impl<I2C> DisplayController<I2C>
-where
-    I2C: embedded_hal::blocking::i2c::Write + embedded_hal::blocking::i2c::WriteRead,
-    <I2C as embedded_hal::blocking::i2c::Write>::Error: std::fmt::Debug,
-    <I2C as embedded_hal::blocking::i2c::WriteRead>::Error: std::fmt::Debug,
-{
-    pub fn new(i2c: I2C) -> Result<Self, Box<dyn std::error::Error>> {
-        info!("Initializing SH1106 OLED display");
-
-        let mut display: sh1106::mode::GraphicsMode<_> = Builder::new().connect_i2c(i2c).into();
-
-        display
-            .init()
-            .map_err(|e| format!("Display init failed: {:?}", e))?;
-        display.clear();
-        display
+impl DisplayController {
+    pub fn new(i2c: I2cDriver<'static>, driver: DisplayDriver) -> Result<Self, Box<dyn std::error::Error>> {
+        info!("Initializing {:?} OLED display", driver);
+
+        let display = match driver {
+            DisplayDriver::Sh1106 => {
+                let mut display: Sh1106Display = Builder::new().connect_i2c(i2c).into();
+                display
+                    .init()
+                    .map_err(|e| format!("Display init failed: {:?}", e))?;
+                OledPanel::Sh1106(display)
+            }
+            DisplayDriver::Ssd1306 => {
+                let interface = I2CDisplayInterface::new(i2c);
+                let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+                    .into_buffered_graphics_mode();
+                display
+                    .init()
+                    .map_err(|e| format!("Display init failed: {:?}", e))?;
+                OledPanel::Ssd1306(display)
+            }
+        };
+
+        let mut controller = Self {
+            display,
+            state: DisplayState::default(),
+            alert: None,
+            limiter: RefreshLimiter::new(DISPLAY_REFRESH_MS),
+        };
+
+        controller.display.clear_buffer();
+        controller
+            .display
             .flush()
-            .map_err(|e| format!("Display flush failed: {:?}", e))?;
+            .map_err(|e| format!("Display flush failed: {}", e))?;
 
-        info!("✅ SH1106 display initialized successfully");
+        info!("✅ {:?} display initialized successfully", driver);
+        Ok(controller)
+    }
 
-        Ok(Self {
-            display,
-            state: DisplayState::default(),
-        })
+    /// Shortest gap between redraws (see `BrewConfig::set_display_refresh_ms`)
+    pub fn set_refresh_interval_ms(&mut self, interval_ms: u32) {
+        self.limiter.set_interval_ms(interval_ms);
     }
 
+    /// Take a new state - drawn now if the refresh interval is up, otherwise by
+    /// `refresh_pending`. A new error skips the wait
     pub fn update_state(
         &mut self,
         new_state: DisplayState,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        let new_error = new_state.error.is_some() && new_state.error != self.state.error;
         self.state = new_state;
+        if new_error {
+            self.limiter.drawn(now);
+            return self.refresh_display();
+        }
+        if self.limiter.request(now) {
+            self.refresh_display()?;
+        }
+        Ok(())
+    }
+
+    /// Draw a state `update_state` held back, once the interval is up - call periodically
+    pub fn refresh_pending(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.limiter.poll(Instant::now()) {
+            self.refresh_display()?;
+        }
+        Ok(())
+    }
+
+    /// Show a transient alert for the given duration - drawn at once, never throttled
+    pub fn show_alert(
+        &mut self,
+        message: String,
+        duration: Duration,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        self.alert = Some(DisplayAlert {
+            message,
+            expires_at: now + duration,
+        });
+        self.limiter.drawn(now);
         self.refresh_display()
     }
 
+    /// Drop an expired alert and redraw - call periodically
+    pub fn expire_alert(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let expired = self
+            .alert
+            .as_ref()
+            .map(|alert| Instant::now() >= alert.expires_at)
+            .unwrap_or(false);
+
+        if expired {
+            self.alert = None;
+            self.limiter.drawn(Instant::now());
+            self.refresh_display()?;
+        }
+        Ok(())
+    }
+
     pub fn refresh_display(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         debug!("Refreshing display with current state");
 
         // Clear display
-        self.display.clear();
+        self.display.clear_buffer();
 
         // Define text styles
         let title_style = MonoTextStyle::new(&FONT_9X15, BinaryColor::On);
         let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
 
-        let mut y_pos = 15;
+        let mut y_pos = 0;
+
+        // Alerts and errors take priority over the normal layout
+        if let Some(ref alert) = self.alert {
+            Text::with_baseline("ALERT", Point::new(0, y_pos), title_style, Baseline::Top)
+                .draw(&mut self.display)
+                .map_err(|e| format!("Display draw error: {:?}", e))?;
+            y_pos += 18;
 
-        // Error display takes priority
-        if let Some(ref error) = self.state.error {
+            Text::with_baseline(&alert.message, Point::new(0, y_pos), text_style, Baseline::Top)
+                .draw(&mut self.display)
+                .map_err(|e| format!("Display draw error: {:?}", e))?;
+        } else if let Some(ref error) = self.state.error {
             Text::with_baseline("ERROR:", Point::new(0, y_pos), title_style, Baseline::Top)
                 .draw(&mut self.display)
                 .map_err(|e| format!("Display draw error: {:?}", e))?;
-            y_pos += 16;
+            y_pos += 18;
 
             Text::with_baseline(error, Point::new(0, y_pos), text_style, Baseline::Top)
                 .draw(&mut self.display)
@@ -128,10 +306,10 @@ where
             .map_err(|e| format!("Display draw error: {:?}", e))?;
 
             // Target weight (smaller, right side)
-            let target_text = format!("→{:.0}g", self.state.target_weight_g);
+            let target_text = format!(">{:.0}g", self.state.target_weight_g);
             Text::with_baseline(
                 &target_text,
-                Point::new(80, y_pos),
+                Point::new(86, y_pos + 4),
                 text_style,
                 Baseline::Top,
             )
@@ -139,18 +317,23 @@ where
             .map_err(|e| format!("Display draw error: {:?}", e))?;
             y_pos += 18;
 
-            // Line 2: Flow rate
-            let flow_text = format!("Flow: {:.1}g/s", self.state.flow_rate_g_per_s);
+            // Line 2: Flow rate and timer
+            let flow_text = format!(
+                "{:.1}g/s  {:>3}.{}s",
+                self.state.flow_rate_g_per_s,
+                self.state.timer_ms / 1000,
+                (self.state.timer_ms % 1000) / 100
+            );
             Text::with_baseline(&flow_text, Point::new(0, y_pos), text_style, Baseline::Top)
                 .draw(&mut self.display)
                 .map_err(|e| format!("Display draw error: {:?}", e))?;
             y_pos += 12;
 
-            // Line 3: State and timer
+            // Line 3: State
             let state_text = format!(
-                "{} {}",
+                "{}{}",
                 self.state.brew_state,
-                if self.state.timer_running { "⏱" } else { "" }
+                if self.state.timer_running { " *" } else { "" }
             );
             Text::with_baseline(&state_text, Point::new(0, y_pos), text_style, Baseline::Top)
                 .draw(&mut self.display)
@@ -160,11 +343,7 @@ where
             // Line 4: Status indicators
             let status_text = format!(
                 "BLE:{} Bat:{}%",
-                if self.state.ble_connected {
-                    "✓"
-                } else {
-                    "✗"
-                },
+                if self.state.ble_connected { "OK" } else { "--" },
                 self.state.battery_percent
             );
             Text::with_baseline(
@@ -180,7 +359,7 @@ where
         // Flush to display
         self.display
             .flush()
-            .map_err(|e| format!("Display flush failed: {:?}", e))?;
+            .map_err(|e| format!("Display flush failed: {}", e))?;
 
         debug!("Display refresh completed");
         Ok(())
@@ -189,7 +368,7 @@ where
     pub fn show_boot_screen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         info!("Showing boot screen");
 
-        self.display.clear();
+        self.display.clear_buffer();
 
         let title_style = MonoTextStyle::new(&FONT_9X15, BinaryColor::On);
         let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
@@ -214,7 +393,7 @@ where
 
         self.display
             .flush()
-            .map_err(|e| format!("Display flush failed: {:?}", e))?;
+            .map_err(|e| format!("Display flush failed: {}", e))?;
 
         Ok(())
     }
@@ -224,7 +403,7 @@ where
         message: &str,
         progress: f32,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        self.display.clear();
+        self.display.clear_buffer();
 
         let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
 
@@ -252,26 +431,48 @@ where
 
         self.display
             .flush()
-            .map_err(|e| format!("Display flush failed: {:?}", e))?;
+            .map_err(|e| format!("Display flush failed: {}", e))?;
 
         Ok(())
     }
 }
 
 // Helper function to create display controller from ESP32 I2C pins
-pub fn create_display_controller(
+pub fn create_display_controller<I: I2c>(
+    i2c: impl Peripheral<P = I> + 'static,
     sda: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
     scl: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
-) -> Result<DisplayController<I2cDriver<'static>>, Box<dyn std::error::Error>> {
-    info!("Setting up I2C for SH1106 display");
+    driver: DisplayDriver,
+) -> Result<DisplayController, Box<dyn std::error::Error>> {
+    info!("Setting up I2C for {:?} display", driver);
 
     let config = I2cConfig::new().baudrate(400.kHz().into());
-    let i2c = I2cDriver::new(
-        unsafe { esp_idf_svc::hal::i2c::I2C0::new() },
-        sda,
-        scl,
-        &config,
-    )?;
-
-    DisplayController::new(i2c)
+    let i2c = I2cDriver::new(i2c, sda, scl, &config)?;
+
+    DisplayController::new(i2c, driver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_inside_the_interval_are_coalesced() {
+        let mut limiter = RefreshLimiter::new(250);
+        let at = |ms: u64| Instant::from_millis(ms);
+        assert!(limiter.request(at(0)));
+        // 10Hz samples - only the first gets drawn straight away
+        assert!(!limiter.request(at(100)));
+        assert!(!limiter.request(at(200)));
+        assert!(!limiter.poll(at(240)));
+        // The latest one goes out once the interval is up, and only once
+        assert!(limiter.poll(at(250)));
+        assert!(!limiter.poll(at(600)));
+
+        // An alert drawn in between covers the held-back change
+        assert!(!limiter.request(at(300)));
+        limiter.drawn(at(320));
+        assert!(!limiter.poll(at(1000)));
+        assert!(limiter.request(at(1000)));
+    }
 }
@@ -1,5 +1,9 @@
+pub mod button;
+pub mod buzzer;
 pub mod display;
 pub mod relay;
 
+pub use button::*;
+pub use buzzer::*;
 pub use display::*;
 pub use relay::*;
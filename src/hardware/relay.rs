@@ -1,121 +1,619 @@
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
 use embassy_time::{Duration, Instant, Timer};
-use esp_idf_svc::hal::gpio::{Gpio19, Output, PinDriver};
+use esp_idf_svc::hal::gpio::{AnyIOPin, InputOutput, OutputPin, PinDriver};
+use esp_idf_svc::hal::ledc::{
+    config::TimerConfig, LedcChannel, LedcDriver, LedcTimer, LedcTimerDriver, Resolution,
+};
+use esp_idf_svc::hal::peripheral::Peripheral;
+use esp_idf_svc::hal::prelude::*;
 use log::{error, info, warn};
+use serde::Serialize;
 use std::sync::Arc;
 
-pub struct RelayController {
-    gpio_pin: PinDriver<'static, Gpio19, Output>,
-    current_state: Arc<Mutex<CriticalSectionRawMutex, bool>>,
+/// Default PWM frequency for SSR-driven pumps - slow enough for zero-cross SSRs to burst-fire
+pub const DEFAULT_RELAY_PWM_FREQUENCY_HZ: u32 = 10;
+
+/// Channel used by single-relay setups and by the brewing state machine (the pump)
+pub const PRIMARY_RELAY_CHANNEL: usize = 0;
+
+/// Longest any channel may stay on - no shot or flush takes this long
+pub const DEFAULT_RELAY_MAX_ON_SECS: u64 = 120;
+
+// Raw ESP-IDF calls - usable before peripherals are taken and from inside a panic hook
+fn force_relay_gpio_low(gpio_num: i32) -> i32 {
+    use esp_idf_svc::sys::{
+        gpio_mode_t_GPIO_MODE_OUTPUT, gpio_reset_pin, gpio_set_direction, gpio_set_level,
+    };
+
+    unsafe {
+        gpio_reset_pin(gpio_num);
+        gpio_set_direction(gpio_num, gpio_mode_t_GPIO_MODE_OUTPUT);
+        gpio_set_level(gpio_num, 0)
+    }
+}
+
+/// Drive a relay GPIO low straight after boot, before peripherals are handed out.
+/// A watchdog-, panic- or brownout-induced reboot must never leave the pump running while WiFi starts.
+pub fn force_relay_gpio_low_at_boot(gpio_num: i32) {
+    let result = force_relay_gpio_low(gpio_num);
+
+    if result == 0 {
+        info!("Relay GPIO{} forced LOW at boot", gpio_num);
+    } else {
+        error!(
+            "CRITICAL: Failed to force relay GPIO{} LOW at boot (err {})",
+            gpio_num, result
+        );
+    }
+}
+
+/// Install a panic hook that drives the relay GPIO low before the default panic handling runs
+pub fn install_relay_panic_hook(gpio_num: i32) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // Relay first - logging or the default hook could fail in a broken runtime
+        force_relay_gpio_low(gpio_num);
+        error!("PANIC: relay GPIO{} forced LOW", gpio_num);
+        default_hook(panic_info);
+    }));
+    info!("Relay panic hook installed for GPIO{}", gpio_num);
+}
+
+/// A named output pin to manage as a relay channel
+pub struct RelayChannelPin {
+    pub name: &'static str,
+    /// Input-capable too, so the level can be read back at boot
+    pub pin: AnyIOPin,
+}
+
+/// Physical switch behind a relay channel - implemented by GPIO/LEDC outputs and by test mocks
+pub trait RelaySwitch {
+    fn set_level(&mut self, on: bool) -> Result<(), RelayError>;
+    fn set_fraction(&mut self, fraction: f32) -> Result<(), RelayError>;
+    fn supports_pwm(&self) -> bool;
+    /// Level read back from the pin, None when the output can't be read
+    fn read_back(&self) -> Option<bool>;
+}
+
+/// Physical output driving a relay/SSR
+pub enum RelayOutput {
+    /// Input-output so the pad can be read without reconfiguring the pin
+    Digital(PinDriver<'static, AnyIOPin, InputOutput>),
+    Pwm(LedcDriver<'static>),
+    /// Dry run - logs intended transitions, never touches a GPIO
+    Simulated,
+}
+
+impl RelaySwitch for RelayOutput {
+    fn set_level(&mut self, on: bool) -> Result<(), RelayError> {
+        match self {
+            RelayOutput::Digital(pin) => {
+                if on {
+                    pin.set_high().map_err(|e| {
+                        RelayError::GpioError(format!("Failed to set GPIO high: {:?}", e))
+                    })
+                } else {
+                    pin.set_low().map_err(|e| {
+                        RelayError::GpioError(format!("Failed to set GPIO low: {:?}", e))
+                    })
+                }
+            }
+            RelayOutput::Pwm(_) => self.set_fraction(if on { 1.0 } else { 0.0 }),
+            RelayOutput::Simulated => {
+                info!(
+                    "🧪 [SIMULATED] Relay would turn {}",
+                    if on { "ON" } else { "OFF" }
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn set_fraction(&mut self, fraction: f32) -> Result<(), RelayError> {
+        match self {
+            RelayOutput::Digital(_) => {
+                if fraction == 0.0 {
+                    self.set_level(false)
+                } else if fraction == 1.0 {
+                    self.set_level(true)
+                } else {
+                    Err(RelayError::PwmUnavailable)
+                }
+            }
+            RelayOutput::Pwm(driver) => {
+                let duty = (driver.get_max_duty() as f32 * fraction).round() as u32;
+                driver
+                    .set_duty(duty)
+                    .map_err(|e| RelayError::GpioError(format!("Failed to set PWM duty: {:?}", e)))
+            }
+            RelayOutput::Simulated => {
+                info!("🧪 [SIMULATED] Relay would run at {:.0}%", fraction * 100.0);
+                Ok(())
+            }
+        }
+    }
+
+    fn supports_pwm(&self) -> bool {
+        matches!(self, RelayOutput::Pwm(_) | RelayOutput::Simulated)
+    }
+
+    fn read_back(&self) -> Option<bool> {
+        match self {
+            // Output latch first, then the pad - a pad held high means a short or a miswired pin
+            RelayOutput::Digital(pin) => Some(pin.is_set_high() || pin.is_high()),
+            // The LEDC peripheral owns the pin, and a dry run has none
+            RelayOutput::Pwm(_) | RelayOutput::Simulated => None,
+        }
+    }
+}
+
+/// What reading the relay pins back at boot found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum RelayBootCheck {
+    /// Every readable pin is low after being driven off
+    Low,
+    /// No pin could be read back - PWM and simulated relays
+    #[default]
+    NotChecked,
+    /// A pin still reads high after being driven low - shorted to 3.3V or wired to the wrong
+    /// pin, and the pump may be running
+    StuckHigh { channel: usize, name: &'static str },
+}
+
+struct RelayChannel<O> {
+    name: &'static str,
+    output: O,
+    power: f32,
+    on_since: Option<Instant>,
+}
+
+/// Whether a power change switched a channel on or off
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PowerEdge {
+    Unchanged,
+    Rising,
+    Falling { on_for: Duration },
+}
+
+impl<O: RelaySwitch> RelayChannel<O> {
+    /// Record a power change, starting or clearing the on-time clock
+    fn track_power(&mut self, fraction: f32, now: Instant) -> PowerEdge {
+        let edge = match (self.on_since, fraction > 0.0) {
+            (None, true) => {
+                self.on_since = Some(now);
+                PowerEdge::Rising
+            }
+            (Some(on_since), false) => {
+                self.on_since = None;
+                PowerEdge::Falling {
+                    on_for: now.saturating_duration_since(on_since),
+                }
+            }
+            _ => PowerEdge::Unchanged,
+        };
+        self.power = fraction;
+        edge
+    }
+}
+
+/// Wear and duty statistics across all channels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RelayStats {
+    /// Off -> on transitions, cumulative across reboots once restored from NVS
+    pub activations: u64,
+    /// On-time of completed activations, cumulative across reboots once restored from NVS
+    pub total_on_ms: u64,
+    /// Uptime at the most recent activation (this boot only)
+    pub last_activation_uptime_ms: Option<u64>,
+}
+
+impl RelayStats {
+    fn record(&mut self, edge: PowerEdge, now: Instant) {
+        match edge {
+            PowerEdge::Rising => {
+                self.activations += 1;
+                self.last_activation_uptime_ms = Some(now.as_millis());
+            }
+            PowerEdge::Falling { on_for } => self.total_on_ms += on_for.as_millis(),
+            PowerEdge::Unchanged => {}
+        }
+    }
+}
+
+pub struct RelayController<O: RelaySwitch = RelayOutput> {
+    channels: Vec<RelayChannel<O>>,
     last_command_time: Arc<Mutex<CriticalSectionRawMutex, Option<Instant>>>,
+    max_on_duration: Option<Duration>,
+    stats: RelayStats,
+    boot_check: RelayBootCheck,
 }
 
 impl RelayController {
-    pub fn new(gpio19: Gpio19) -> Result<Self, RelayError> {
-        let mut pin = PinDriver::output(gpio19)
-            .map_err(|e| RelayError::GpioError(format!("Failed to configure GPIO19: {:?}", e)))?;
+    /// Manage N independent digital relay channels (active high), indexed in the given order
+    pub fn new(channels: Vec<RelayChannelPin>) -> Result<Self, RelayError> {
+        if channels.is_empty() {
+            return Err(RelayError::GpioError(
+                "No relay channels configured".to_string(),
+            ));
+        }
 
-        // Ensure relay starts in OFF state (safety)
-        pin.set_low().map_err(|e| {
-            RelayError::GpioError(format!("Failed to set initial low state: {:?}", e))
+        let mut relay_channels = Vec::with_capacity(channels.len());
+        for (index, channel) in channels.into_iter().enumerate() {
+            let mut pin = PinDriver::input_output(channel.pin).map_err(|e| {
+                RelayError::GpioError(format!(
+                    "Failed to configure relay '{}': {:?}",
+                    channel.name, e
+                ))
+            })?;
+
+            // Ensure relay starts in OFF state (safety)
+            pin.set_low().map_err(|e| {
+                RelayError::GpioError(format!("Failed to set initial low state: {:?}", e))
+            })?;
+
+            info!(
+                "Relay channel {} '{}' initialized (active high)",
+                index, channel.name
+            );
+            relay_channels.push(RelayChannel {
+                name: channel.name,
+                output: RelayOutput::Digital(pin),
+                power: 0.0,
+                on_since: None,
+            });
+        }
+
+        let mut relay = Self::from_channels(relay_channels);
+        relay.boot_check = relay.verify_off();
+        Ok(relay)
+    }
+
+    /// Single digital relay - the classic pump-only setup
+    pub fn single(pin: AnyIOPin) -> Result<Self, RelayError> {
+        Self::new(vec![RelayChannelPin { name: "pump", pin }])
+    }
+
+    /// Single relay driven by LEDC PWM so `set_power` can run the pump/valve proportionally
+    pub fn new_pwm<C, T>(
+        channel: impl Peripheral<P = C> + 'static,
+        timer: impl Peripheral<P = T> + 'static,
+        pin: impl Peripheral<P = impl OutputPin> + 'static,
+        frequency_hz: u32,
+    ) -> Result<Self, RelayError>
+    where
+        C: LedcChannel<SpeedMode = <T as LedcTimer>::SpeedMode>,
+        T: LedcTimer + 'static,
+    {
+        // High resolution is required to reach the low frequencies SSRs need
+        let timer_config = TimerConfig::new()
+            .frequency(frequency_hz.Hz().into())
+            .resolution(Resolution::Bits14);
+        let timer_driver = LedcTimerDriver::new(timer, &timer_config).map_err(|e| {
+            RelayError::GpioError(format!("Failed to configure LEDC timer: {:?}", e))
         })?;
 
-        info!("Relay controller initialized on GPIO19 (active high)");
+        let mut driver = LedcDriver::new(channel, timer_driver, pin).map_err(|e| {
+            RelayError::GpioError(format!("Failed to configure LEDC channel: {:?}", e))
+        })?;
+
+        // Ensure relay starts in OFF state (safety)
+        driver
+            .set_duty(0)
+            .map_err(|e| RelayError::GpioError(format!("Failed to set initial duty: {:?}", e)))?;
+
+        info!(
+            "Relay channel 0 'pump' initialized (PWM {}Hz)",
+            frequency_hz
+        );
+
+        Ok(Self::from_channels(vec![RelayChannel {
+            name: "pump",
+            output: RelayOutput::Pwm(driver),
+            power: 0.0,
+            on_since: None,
+        }]))
+    }
+
+    /// Dry-run pump relay: full state tracking and logging, but no GPIO is ever driven.
+    /// Lets the state machine and tuning be validated on a real machine without arming the pump.
+    pub fn new_simulated() -> Self {
+        warn!("🧪 Relay channel 0 'pump' is SIMULATED - the pump will not be driven");
+        Self::from_channels(vec![RelayChannel {
+            name: "pump",
+            output: RelayOutput::Simulated,
+            power: 0.0,
+            on_since: None,
+        }])
+    }
+
+    /// Whether every channel is a dry-run output
+    pub fn is_simulated(&self) -> bool {
+        self.channels
+            .iter()
+            .all(|c| matches!(c.output, RelayOutput::Simulated))
+    }
+}
 
-        Ok(Self {
-            gpio_pin: pin,
-            current_state: Arc::new(Mutex::new(false)),
+impl<O: RelaySwitch> RelayController<O> {
+    fn from_channels(channels: Vec<RelayChannel<O>>) -> Self {
+        Self {
+            channels,
             last_command_time: Arc::new(Mutex::new(None)),
-        })
+            max_on_duration: Some(Duration::from_secs(DEFAULT_RELAY_MAX_ON_SECS)),
+            stats: RelayStats::default(),
+            boot_check: RelayBootCheck::NotChecked,
+        }
     }
 
-    pub async fn turn_on(&mut self) -> Result<(), RelayError> {
-        let mut state = self.current_state.lock().await;
-        if *state {
-            return Ok(()); // Already on
+    /// Read every channel back after it was driven off - the first one still high is reported
+    fn verify_off(&self) -> RelayBootCheck {
+        let mut check = RelayBootCheck::NotChecked;
+        for (index, channel) in self.channels.iter().enumerate() {
+            match channel.output.read_back() {
+                Some(true) => {
+                    error!(
+                        "CRITICAL: Relay channel {} '{}' reads HIGH after being driven low",
+                        index, channel.name
+                    );
+                    return RelayBootCheck::StuckHigh {
+                        channel: index,
+                        name: channel.name,
+                    };
+                }
+                Some(false) => check = RelayBootCheck::Low,
+                None => {}
+            }
+        }
+        if check == RelayBootCheck::Low {
+            info!("Relay pins read back LOW");
         }
+        check
+    }
 
-        self.gpio_pin
-            .set_high()
-            .map_err(|e| RelayError::GpioError(format!("Failed to set GPIO high: {:?}", e)))?;
+    /// Result of the read-back `new` does after driving every pin off
+    pub fn boot_check(&self) -> RelayBootCheck {
+        self.boot_check
+    }
 
-        *state = true;
-        *self.last_command_time.lock().await = Some(Instant::now());
+    /// Activation count and cumulative on-time
+    pub fn stats(&self) -> RelayStats {
+        self.stats
+    }
 
-        info!("Relay turned ON (GPIO19 HIGH)");
-        Ok(())
+    /// Seed the cumulative counters with values persisted before this boot
+    pub fn restore_stats(&mut self, activations: u64, total_on_ms: u64) {
+        self.stats.activations = activations;
+        self.stats.total_on_ms = total_on_ms;
+        info!(
+            "Relay stats restored: {} activations, {}s on",
+            activations,
+            total_on_ms / 1000
+        );
     }
 
-    pub async fn turn_off(&mut self) -> Result<(), RelayError> {
-        let mut state = self.current_state.lock().await;
-        if !*state {
-            return Ok(()); // Already off
+    /// Cap how long any channel may stay on (None disables the cap)
+    pub fn set_max_on_duration(&mut self, max_on_duration: Option<Duration>) {
+        info!("Relay max on-time: {:?}", max_on_duration);
+        self.max_on_duration = max_on_duration;
+    }
+
+    pub fn max_on_duration(&self) -> Option<Duration> {
+        self.max_on_duration
+    }
+
+    /// Force off any channel on longer than the max on-time - call periodically from the control loop.
+    /// Independent of the brewing state machine, so a bug higher up cannot leave the pump energized.
+    pub fn enforce_max_on_time(&mut self) -> Result<(), RelayError> {
+        self.enforce_max_on_time_at(Instant::now())
+    }
+
+    fn enforce_max_on_time_at(&mut self, now: Instant) -> Result<(), RelayError> {
+        let Some(max_on) = self.max_on_duration else {
+            return Ok(());
+        };
+
+        let mut result = Ok(());
+        for (index, relay) in self.channels.iter_mut().enumerate() {
+            let Some(on_since) = relay.on_since else {
+                continue;
+            };
+            let on_for = now.saturating_duration_since(on_since);
+            if on_for <= max_on {
+                continue;
+            }
+
+            error!(
+                "SAFETY: Relay '{}' on for {}ms (max {}ms) - forcing OFF",
+                relay.name,
+                on_for.as_millis(),
+                max_on.as_millis()
+            );
+            // On failure the clock keeps running, so the next check retries
+            if let Err(e) = relay.output.set_level(false) {
+                error!(
+                    "CRITICAL: Failed to force relay '{}' off: {:?}",
+                    relay.name, e
+                );
+            } else {
+                self.stats.record(relay.track_power(0.0, now), now);
+            }
+            result = Err(RelayError::MaxOnTimeExceeded {
+                channel: index,
+                on_for_ms: on_for.as_millis(),
+            });
         }
 
-        self.gpio_pin
-            .set_low()
-            .map_err(|e| RelayError::GpioError(format!("Failed to set GPIO low: {:?}", e)))?;
+        result
+    }
+
+    fn channel_mut(&mut self, channel: usize) -> Result<&mut RelayChannel<O>, RelayError> {
+        self.channels
+            .get_mut(channel)
+            .ok_or(RelayError::InvalidChannel(channel))
+    }
+
+    /// Number of configured channels
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Name of a channel, if it exists
+    pub fn channel_name(&self, channel: usize) -> Option<&'static str> {
+        self.channels.get(channel).map(|c| c.name)
+    }
+
+    /// Whether proportional power is available on a channel (PWM output)
+    pub fn supports_pwm(&self, channel: usize) -> bool {
+        self.channels
+            .get(channel)
+            .map(|c| c.output.supports_pwm())
+            .unwrap_or(false)
+    }
+
+    /// Set a channel's output power as a fraction 0.0 (off) ..= 1.0 (fully on)
+    pub async fn set_channel_power(
+        &mut self,
+        channel: usize,
+        fraction: f32,
+    ) -> Result<(), RelayError> {
+        if !fraction.is_finite() || !(0.0..=1.0).contains(&fraction) {
+            warn!("Rejecting invalid relay power fraction: {}", fraction);
+            return Err(RelayError::InvalidPower(fraction));
+        }
+
+        let now = Instant::now();
+        let relay = self.channel_mut(channel)?;
+        if relay.power == fraction {
+            return Ok(()); // Already at this power
+        }
+
+        relay.output.set_fraction(fraction)?;
+        let edge = relay.track_power(fraction, now);
+        let name = relay.name;
+        self.stats.record(edge, now);
 
-        *state = false;
         *self.last_command_time.lock().await = Some(Instant::now());
 
-        info!("Relay turned OFF (GPIO19 LOW)");
+        info!("Relay '{}' power set to {:.0}%", name, fraction * 100.0);
         Ok(())
     }
 
+    pub async fn turn_on_channel(&mut self, channel: usize) -> Result<(), RelayError> {
+        self.set_channel_power(channel, 1.0).await
+    }
+
+    pub async fn turn_off_channel(&mut self, channel: usize) -> Result<(), RelayError> {
+        self.set_channel_power(channel, 0.0).await
+    }
+
+    /// Set primary channel power as a fraction 0.0 (off) ..= 1.0 (fully on)
+    pub async fn set_power(&mut self, fraction: f32) -> Result<(), RelayError> {
+        self.set_channel_power(PRIMARY_RELAY_CHANNEL, fraction)
+            .await
+    }
+
+    pub async fn turn_on(&mut self) -> Result<(), RelayError> {
+        self.turn_on_channel(PRIMARY_RELAY_CHANNEL).await
+    }
+
+    pub async fn turn_off(&mut self) -> Result<(), RelayError> {
+        self.turn_off_channel(PRIMARY_RELAY_CHANNEL).await
+    }
+
+    /// Emergency stop - every channel OFF, bypassing async
     pub fn turn_off_immediately(&mut self) -> Result<(), RelayError> {
-        // Emergency stop - bypass async and set GPIO directly
-        match self.gpio_pin.set_low() {
-            Ok(_) => {
-                // Update state synchronously for safety
-                // Note: In emergency situations, we prioritize immediate GPIO control
-                // State tracking will be updated when the async runtime is available
-                error!("EMERGENCY: Relay turned OFF immediately (GPIO19 LOW)");
-                Ok(())
-            }
-            Err(e) => {
-                error!(
-                    "CRITICAL: Failed to turn off relay immediately: GPIO error: {:?}",
-                    e
-                );
-                Err(RelayError::GpioError(format!(
-                    "Emergency stop failed: {:?}",
-                    e
-                )))
+        let mut result = Ok(());
+
+        for relay in self.channels.iter_mut() {
+            match relay.output.set_level(false) {
+                Ok(_) => {
+                    let now = Instant::now();
+                    self.stats.record(relay.track_power(0.0, now), now);
+                    error!("EMERGENCY: Relay '{}' turned OFF immediately", relay.name);
+                }
+                Err(e) => {
+                    error!(
+                        "CRITICAL: Failed to turn off relay '{}' immediately: {:?}",
+                        relay.name, e
+                    );
+                    // Keep going so the remaining channels still get switched off
+                    result = Err(RelayError::GpioError(format!(
+                        "Emergency stop failed on '{}': {:?}",
+                        relay.name, e
+                    )));
+                }
             }
         }
+
+        result
     }
 
+    /// Whether the primary channel is on
     pub async fn is_on(&self) -> bool {
-        *self.current_state.lock().await
+        self.is_channel_on(PRIMARY_RELAY_CHANNEL).await
+    }
+
+    pub async fn is_channel_on(&self, channel: usize) -> bool {
+        self.channels
+            .get(channel)
+            .map(|c| c.power > 0.0)
+            .unwrap_or(false)
+    }
+
+    /// Whether any channel is on
+    pub async fn is_any_on(&self) -> bool {
+        self.channels.iter().any(|c| c.power > 0.0)
+    }
+
+    /// Current primary channel power fraction (0.0 ..= 1.0)
+    pub async fn get_power(&self) -> f32 {
+        self.channels
+            .get(PRIMARY_RELAY_CHANNEL)
+            .map(|c| c.power)
+            .unwrap_or(0.0)
     }
 
     pub async fn get_last_command_time(&self) -> Option<Instant> {
         *self.last_command_time.lock().await
     }
 
-    pub async fn test_relay(&mut self) -> Result<(), RelayError> {
-        info!("Testing relay GPIO functionality");
+    /// Wiring check: OFF -> ON for `on_for` -> OFF on the primary channel
+    pub async fn test_relay(&mut self, on_for: Duration) -> Result<(), RelayError> {
+        info!(
+            "Testing relay GPIO functionality for {}ms",
+            on_for.as_millis()
+        );
 
-        // Test sequence: OFF -> ON -> OFF
-        self.gpio_pin
-            .set_low()
+        let relay = self.channel_mut(PRIMARY_RELAY_CHANNEL)?;
+
+        relay
+            .output
+            .set_level(false)
             .map_err(|e| RelayError::GpioError(format!("Test: Failed to set low: {:?}", e)))?;
 
         Timer::after(Duration::from_millis(100)).await;
 
-        self.gpio_pin
-            .set_high()
-            .map_err(|e| RelayError::GpioError(format!("Test: Failed to set high: {:?}", e)))?;
-
-        Timer::after(Duration::from_millis(100)).await;
+        let switched_on = relay
+            .output
+            .set_level(true)
+            .map_err(|e| RelayError::GpioError(format!("Test: Failed to set high: {:?}", e)));
+        if switched_on.is_ok() {
+            Timer::after(on_for).await;
+        }
 
-        self.gpio_pin
-            .set_low()
-            .map_err(|e| RelayError::GpioError(format!("Test: Failed to set low: {:?}", e)))?;
+        // Always end low, even when switching on failed half way
+        let switched_off = relay
+            .output
+            .set_level(false)
+            .map_err(|e| RelayError::GpioError(format!("Test: Failed to set low: {:?}", e)));
 
         // Reset state tracking
-        *self.current_state.lock().await = false;
+        let now = Instant::now();
+        let edge = relay.track_power(0.0, now);
+        self.stats.record(edge, now);
+        switched_on?;
+        switched_off?;
 
         info!("Relay GPIO test completed successfully");
         Ok(())
@@ -124,34 +622,254 @@ impl RelayController {
     pub async fn force_state(&mut self, on: bool) -> Result<(), RelayError> {
         warn!("Force setting relay state to: {}", on);
 
-        if on {
-            self.gpio_pin
-                .set_high()
-                .map_err(|e| RelayError::GpioError(format!("Force ON failed: {:?}", e)))?;
-        } else {
-            self.gpio_pin
-                .set_low()
-                .map_err(|e| RelayError::GpioError(format!("Force OFF failed: {:?}", e)))?;
-        }
+        let relay = self.channel_mut(PRIMARY_RELAY_CHANNEL)?;
+        relay.output.set_level(on).map_err(|e| {
+            RelayError::GpioError(format!(
+                "Force {} failed: {:?}",
+                if on { "ON" } else { "OFF" },
+                e
+            ))
+        })?;
+        let now = Instant::now();
+        let edge = relay.track_power(if on { 1.0 } else { 0.0 }, now);
+        self.stats.record(edge, now);
 
-        *self.current_state.lock().await = on;
         *self.last_command_time.lock().await = Some(Instant::now());
 
         Ok(())
     }
 }
 
+impl<O: RelaySwitch> Drop for RelayController<O> {
+    fn drop(&mut self) {
+        // Safety: a controller going away (including unwinding) must never leave the pump energized
+        if self.channels.iter().any(|c| c.power > 0.0) {
+            warn!("RelayController dropped with relay ON - forcing OFF");
+        }
+        let _ = self.turn_off_immediately();
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum RelayError {
     GpioError(String),
+    InvalidPower(f32),
+    InvalidChannel(usize),
+    PwmUnavailable,
+    MaxOnTimeExceeded { channel: usize, on_for_ms: u64 },
 }
 
 impl std::fmt::Display for RelayError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RelayError::GpioError(msg) => write!(f, "GPIO error: {}", msg),
+            RelayError::InvalidPower(fraction) => {
+                write!(
+                    f,
+                    "Invalid power fraction {} (expected 0.0..=1.0)",
+                    fraction
+                )
+            }
+            RelayError::InvalidChannel(channel) => write!(f, "No relay channel {}", channel),
+            RelayError::PwmUnavailable => {
+                write!(f, "Partial power requires a PWM relay output")
+            }
+            RelayError::MaxOnTimeExceeded { channel, on_for_ms } => {
+                write!(
+                    f,
+                    "Relay channel {} exceeded max on-time ({}ms) and was forced off",
+                    channel, on_for_ms
+                )
+            }
         }
     }
 }
 
 impl std::error::Error for RelayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Records every level written so tests can assert on the off path
+    struct MockSwitch {
+        levels: Rc<RefCell<Vec<bool>>>,
+    }
+
+    impl RelaySwitch for MockSwitch {
+        fn set_level(&mut self, on: bool) -> Result<(), RelayError> {
+            self.levels.borrow_mut().push(on);
+            Ok(())
+        }
+
+        fn set_fraction(&mut self, fraction: f32) -> Result<(), RelayError> {
+            self.set_level(fraction > 0.0)
+        }
+
+        fn supports_pwm(&self) -> bool {
+            false
+        }
+
+        fn read_back(&self) -> Option<bool> {
+            self.levels.borrow().last().copied().or(Some(false))
+        }
+    }
+
+    // A pin shorted high - whatever is written, it reads back on
+    struct StuckSwitch;
+
+    impl RelaySwitch for StuckSwitch {
+        fn set_level(&mut self, _on: bool) -> Result<(), RelayError> {
+            Ok(())
+        }
+
+        fn set_fraction(&mut self, _fraction: f32) -> Result<(), RelayError> {
+            Ok(())
+        }
+
+        fn supports_pwm(&self) -> bool {
+            false
+        }
+
+        fn read_back(&self) -> Option<bool> {
+            Some(true)
+        }
+    }
+
+    fn mock_channel(
+        name: &'static str,
+        power: f32,
+        levels: &Rc<RefCell<Vec<bool>>>,
+    ) -> RelayChannel<MockSwitch> {
+        RelayChannel {
+            name,
+            output: MockSwitch {
+                levels: levels.clone(),
+            },
+            power,
+            on_since: (power > 0.0).then(|| Instant::from_millis(0)),
+        }
+    }
+
+    #[test]
+    fn test_max_on_time_forces_off() {
+        let levels = Rc::new(RefCell::new(Vec::new()));
+        let mut relay = RelayController::from_channels(vec![mock_channel("pump", 1.0, &levels)]);
+        relay.set_max_on_duration(Some(Duration::from_secs(60)));
+
+        assert!(relay
+            .enforce_max_on_time_at(Instant::from_millis(60_000))
+            .is_ok());
+        assert!(levels.borrow().is_empty());
+
+        let result = relay.enforce_max_on_time_at(Instant::from_millis(60_001));
+        assert!(matches!(
+            result,
+            Err(RelayError::MaxOnTimeExceeded { channel: 0, .. })
+        ));
+        assert_eq!(*levels.borrow(), vec![false]);
+
+        // Already off - nothing further to enforce
+        assert!(relay
+            .enforce_max_on_time_at(Instant::from_millis(120_000))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_simulated_relay_tracks_state() {
+        let mut relay = RelayController::new_simulated();
+        assert!(relay.is_simulated());
+
+        embassy_futures::block_on(async {
+            relay.turn_on().await.unwrap();
+            assert!(relay.is_on().await);
+            relay.set_power(0.5).await.unwrap();
+            assert_eq!(relay.get_power().await, 0.5);
+            relay.turn_off().await.unwrap();
+            assert!(!relay.is_on().await);
+        });
+    }
+
+    #[test]
+    fn test_stats_count_activations_not_commands() {
+        let mut relay = RelayController::new_simulated();
+
+        embassy_futures::block_on(async {
+            relay.turn_on().await.unwrap();
+            relay.set_power(0.5).await.unwrap();
+            relay.turn_off().await.unwrap();
+            relay.turn_off().await.unwrap();
+            relay.turn_on().await.unwrap();
+        });
+        relay.turn_off_immediately().unwrap();
+
+        let stats = relay.stats();
+        assert_eq!(stats.activations, 2);
+        assert!(stats.last_activation_uptime_ms.is_some());
+    }
+
+    #[test]
+    fn test_boot_check_finds_a_stuck_pin() {
+        let levels = Rc::new(RefCell::new(Vec::new()));
+        let relay = RelayController::from_channels(vec![mock_channel("pump", 0.0, &levels)]);
+        assert_eq!(relay.verify_off(), RelayBootCheck::Low);
+
+        let stuck = RelayController::from_channels(vec![RelayChannel {
+            name: "valve",
+            output: StuckSwitch,
+            power: 0.0,
+            on_since: None,
+        }]);
+        assert_eq!(
+            stuck.verify_off(),
+            RelayBootCheck::StuckHigh {
+                channel: 0,
+                name: "valve"
+            }
+        );
+
+        let simulated = RelayController::new_simulated();
+        assert_eq!(simulated.boot_check(), RelayBootCheck::NotChecked);
+        let json = serde_json::to_value(stuck.verify_off()).unwrap();
+        assert_eq!(json["result"], "stuck_high");
+    }
+
+    #[test]
+    fn test_max_on_time_disabled() {
+        let levels = Rc::new(RefCell::new(Vec::new()));
+        let mut relay = RelayController::from_channels(vec![mock_channel("pump", 1.0, &levels)]);
+        relay.set_max_on_duration(None);
+
+        assert!(relay
+            .enforce_max_on_time_at(Instant::from_millis(u32::MAX as u64))
+            .is_ok());
+        assert!(levels.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_drop_turns_relay_off() {
+        let levels = Rc::new(RefCell::new(Vec::new()));
+        let relay = RelayController::from_channels(vec![mock_channel("pump", 1.0, &levels)]);
+
+        drop(relay);
+
+        assert_eq!(*levels.borrow(), vec![false]);
+    }
+
+    #[test]
+    fn test_drop_turns_every_channel_off() {
+        let pump = Rc::new(RefCell::new(Vec::new()));
+        let valve = Rc::new(RefCell::new(Vec::new()));
+        let relay = RelayController::from_channels(vec![
+            mock_channel("pump", 1.0, &pump),
+            mock_channel("valve", 0.0, &valve),
+        ]);
+
+        drop(relay);
+
+        assert_eq!(*pump.borrow(), vec![false]);
+        assert_eq!(*valve.borrow(), vec![false]);
+    }
+}
@@ -1,6 +1,7 @@
 // Core modules
 pub mod ble;
 pub mod brewing;
+pub mod error;
 pub mod hardware;
 pub mod scales;
 pub mod server;
@@ -12,5 +13,9 @@ pub mod controller;
 pub mod state;
 pub mod types;
 
+#[cfg(test)]
+mod test_helpers;
+
 pub use controller::*;
+pub use error::GravelError;
 pub use types::*;
@@ -1,11 +1,28 @@
 use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
 use esp_idf_svc::eventloop::EspSystemEventLoop;
 use esp_idf_svc::hal::prelude::Peripherals;
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_svc::hal::gpio::IOPin;
+use esp_idf_svc::hal::reset::ResetReason;
 use gravel_rs::controller::EspressoController;
+use gravel_rs::GravelError;
+use esp_idf_svc::sntp::EspSntp;
+use gravel_rs::hardware::button::{ButtonConfig, ButtonInput};
+use gravel_rs::hardware::buzzer::BuzzerController;
+use gravel_rs::hardware::display::{create_display_controller, DisplayDriver};
+use gravel_rs::hardware::relay::{force_relay_gpio_low_at_boot, install_relay_panic_hook};
+#[cfg(feature = "simulate-relay")]
+use gravel_rs::hardware::relay::RelayController;
 use gravel_rs::wifi::manager::WifiManager;
 use log::info;
 
+/// Pump relay pin (GPIO19) - raw number for the boot/panic fail-safes
+const RELAY_GPIO: i32 = 19;
+
+/// Pause before the one retry of a controller start that failed on BLE
+const START_RETRY_DELAY_MS: u64 = 2000;
+
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     // It is necessary to call this function once. Otherwise some patches to the runtime
@@ -15,8 +32,26 @@ async fn main(spawner: Spawner) {
     // Bind the log crate to the ESP Logging facilities
     esp_idf_svc::log::EspLogger::initialize_default();
 
+    // Fail safe first: relay OFF before anything else runs (covers watchdog and brownout resets),
+    // and again on panic so a crash mid-brew can't leave the pump energized
+    force_relay_gpio_low_at_boot(RELAY_GPIO);
+    install_relay_panic_hook(RELAY_GPIO);
+
     info!("Starting Espresso Scale Controller");
 
+    // Tells a field report of "it resets during brewing" apart: brownout, watchdog or panic
+    let reset_reason = ResetReason::get();
+    match reset_reason {
+        ResetReason::Brownout => log::warn!(
+            "⚠️ Last reset was a brownout - supply sagged (pump inrush?), relay was forced off"
+        ),
+        ResetReason::Panic
+        | ResetReason::InterruptWatchdog
+        | ResetReason::TaskWatchdog
+        | ResetReason::Watchdog => log::warn!("⚠️ Last reset was abnormal: {:?}", reset_reason),
+        _ => info!("Last reset: {:?}", reset_reason),
+    }
+
     // Initialize peripherals
     let peripherals = Peripherals::take().unwrap();
 
@@ -58,23 +93,74 @@ async fn main(spawner: Spawner) {
         (false, false)
     };
 
-    // Create and start the controller
-    let mut controller = match EspressoController::new(peripherals.pins.gpio19).await {
+    // Wall-clock time is only needed for buzzer quiet hours - keep the handle alive for main's lifetime
+    let _sntp = if wifi_connected {
+        EspSntp::new_default()
+            .map_err(|e| log::warn!("SNTP start failed: {:?} - quiet hours disabled", e))
+            .ok()
+    } else {
+        None
+    };
+
+    // Create and start the controller (dry-run relay with the `simulate-relay` feature)
+    #[cfg(not(feature = "simulate-relay"))]
+    let controller = EspressoController::new(peripherals.pins.gpio19).await;
+    #[cfg(feature = "simulate-relay")]
+    let controller = EspressoController::new_with_relay(RelayController::new_simulated()).await;
+
+    let mut controller = match controller {
         Ok(controller) => controller,
+        Err(GravelError::Relay(e)) => {
+            log::error!("Relay GPIO unavailable - refusing to run without a safe relay: {}", e);
+            return;
+        }
         Err(e) => {
-            log::error!("Failed to create controller: {:?}", e);
+            log::error!("Failed to create controller: {}", e);
             return;
         }
     };
 
+    // On-board BOOT button (GPIO9) as a fallback control when WiFi/phone is unavailable
+    match ButtonInput::new(peripherals.pins.gpio9.downgrade(), ButtonConfig::tare()) {
+        Ok(button) => controller.add_button(button),
+        Err(e) => log::warn!("Button init failed: {:?} - continuing without physical button", e),
+    }
+
+    // Piezo buzzer on GPIO18 via LEDC for brew-complete and error alerts
+    match BuzzerController::new(
+        peripherals.ledc.channel0,
+        peripherals.ledc.timer0,
+        peripherals.pins.gpio18,
+    ) {
+        Ok(buzzer) => controller.set_buzzer(buzzer),
+        Err(e) => log::warn!("Buzzer init failed: {:?} - continuing without audible alerts", e),
+    }
+
+    // SSD1306/SSD1309 OLED on I2C0 (SDA=GPIO6, SCL=GPIO7) - optional, absent panels just log a warning
+    match create_display_controller(
+        peripherals.i2c0,
+        peripherals.pins.gpio6,
+        peripherals.pins.gpio7,
+        DisplayDriver::Ssd1306,
+    ) {
+        Ok(display) => controller.set_display(display),
+        Err(e) => log::warn!("Display init failed: {:?} - continuing without display", e),
+    }
+
     info!("Controller created successfully, starting...");
 
     // Start the controller with Embassy executor
     // Pass WiFi status and BLE reset flag
-    if let Err(e) = controller
-        .start(spawner, wifi_connected, ble_needs_reset)
-        .await
-    {
-        log::error!("Controller start failed: {:?}", e);
+    let mut result = controller.start(spawner, wifi_connected, ble_needs_reset).await;
+    // The BLE stack can fail to come up while the radio is still settling - one more try
+    if let Err(ref e) = result {
+        if e.is_retryable() {
+            log::warn!("Controller start failed: {} - retrying in {}ms", e, START_RETRY_DELAY_MS);
+            Timer::after(Duration::from_millis(START_RETRY_DELAY_MS)).await;
+            result = controller.start(spawner, wifi_connected, ble_needs_reset).await;
+        }
+    }
+    if let Err(e) = result {
+        log::error!("Controller start failed: {}", e);
     }
 }
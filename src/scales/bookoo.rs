@@ -2,23 +2,41 @@
 // This module provides high-level interface for the Bookoo scale using the generic BLE client
 
 use crate::ble::{
-    BleClient, BleError, Characteristic, Connection, Device, DeviceFilter, StatusChannel, Uuid,
+    BleClient, BleError, Characteristic, ConnParams, Connection, Device, DeviceFilter,
+    Notification, Service, StatusChannel, Uuid,
 };
-use crate::scales::protocol::parse_scale_data;
+use crate::scales::link_quality::LinkQualityTracker;
+use crate::scales::protocol::{parse_scale_data, parse_scale_settings};
 use crate::scales::traits::{
-    BleScale, ScaleCapabilities, ScaleCommand, ScaleCommandChannel, ScaleDataChannel, ScaleInfo,
-    SmartScale,
+    BleScale, ConnectionProfile, ConnectionStage, ConnectionStageChannel, LinkQualitySignal,
+    NearbyDevicesSignal, ScaleCapabilities, ScaleCommand, ScaleCommandChannel, ScaleDataChannel,
+    ScaleInfo, ScaleInfoSignal, SmartScale,
 };
-use crate::types::ScaleData;
-use embassy_time::{Duration, Timer};
+use crate::types::{
+    ScaleData, DEFAULT_SCALE_NAME_PREFIX, LINK_QUALITY_REPORT_INTERVAL_MS, SCALE_KEEP_ALIVE_MS,
+};
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_time::{Duration, Instant, Timer};
 use log::{debug, error, info, warn};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 
 // Bookoo scale UUIDs - scale uses 16-bit UUIDs, not 128-bit
 const BOOKOO_SERVICE_UUID_16: u16 = 0x0FFE; // Service UUID as 16-bit (discovered from hardware)
 const WEIGHT_CHAR_UUID_16: u16 = 0xFF11; // Weight characteristic UUID as 16-bit
 const COMMAND_CHAR_UUID_16: u16 = 0xFF12; // Command characteristic UUID as 16-bit
 
+// Standard Device Information Service and the characteristics read from it
+const DEVICE_INFO_SERVICE_UUID_16: u16 = 0x180A;
+const MANUFACTURER_NAME_UUID_16: u16 = 0x2A29;
+const MODEL_NUMBER_UUID_16: u16 = 0x2A24;
+const FIRMWARE_REVISION_UUID_16: u16 = 0x2A26;
+
+// Notification monitoring
+const CONNECTION_CHECK_INTERVAL_MS: u64 = 1000; // Max lag before noticing a dropped link
+const WAITING_LOG_INTERVAL_SECS: u64 = 30;
+const NO_DATA_TIMEOUT_SECS: u64 = 300; // 5 minutes without data
+
 // Fallback 128-bit UUIDs (in case some scales use full UUIDs)
 const BOOKOO_SERVICE_UUID_128: [u8; 16] = [
     0xfb, 0x34, 0x9b, 0x5f, 0x80, 0x00, 0x00, 0x80, 0x00, 0x10, 0x00, 0x00, 0xe0, 0xff, 0x00, 0x00,
@@ -71,7 +89,22 @@ pub struct BookooScale {
     connection: Option<Connection>,
     weight_characteristic: Option<Characteristic>,
     command_characteristic: Option<Characteristic>,
+    device_info_service: Option<Service>,
     info: ScaleInfo,
+    info_signal: Option<Arc<ScaleInfoSignal>>,
+    name_prefix: Arc<StdMutex<String>>,
+    // Device picker - last scan results and the device the user chose from them
+    nearby_devices: Vec<Device>,
+    preferred_device: Option<Device>,
+    devices_signal: Option<Arc<NearbyDevicesSignal>>,
+    // Keep-alive interval for scales that power off when idle (0 disables)
+    keep_alive_ms: Arc<AtomicU32>,
+    stage_channel: Option<Arc<ConnectionStageChannel>>,
+    // Kept across connections so reconnect frequency counts
+    link_quality: LinkQualityTracker,
+    link_quality_signal: Option<Arc<LinkQualitySignal>>,
+    // Set by `ScaleCommand::Shutdown` - no reconnect after the current cycle
+    stopping: bool,
 }
 
 impl BookooScale {
@@ -89,6 +122,7 @@ impl BookooScale {
                 supports_tare: true,
                 supports_auto_off: false,
             },
+            settings: None,
         };
 
         Self {
@@ -97,49 +131,67 @@ impl BookooScale {
             connection: None,
             weight_characteristic: None,
             command_characteristic: None,
+            device_info_service: None,
             info,
+            info_signal: None,
+            name_prefix: Arc::new(StdMutex::new(DEFAULT_SCALE_NAME_PREFIX.to_string())),
+            nearby_devices: Vec::new(),
+            preferred_device: None,
+            devices_signal: None,
+            keep_alive_ms: Arc::new(AtomicU32::new(SCALE_KEEP_ALIVE_MS)),
+            stage_channel: None,
+            link_quality: LinkQualityTracker::default(),
+            link_quality_signal: None,
+            stopping: false,
         }
     }
 
-    /// Initialize the BLE stack (call once at startup)
-    pub fn initialize() -> Result<(), ScaleError> {
-        BleClient::initialize().map_err(ScaleError::from)
+    /// Publish the results of user-requested device scans here
+    pub fn with_devices_signal(mut self, devices_signal: Arc<NearbyDevicesSignal>) -> Self {
+        self.devices_signal = Some(devices_signal);
+        self
     }
 
-    /// Reset the BLE stack (for use after WiFi provisioning)
-    pub fn reset_ble_stack() -> Result<(), ScaleError> {
-        info!("🔄 Resetting BLE stack after WiFi provisioning");
-
-        // WiFi provisioning already cleaned up BLE - we just need to reinitialize
-        unsafe {
-            use esp_idf_svc::sys::*;
+    /// Scan for this name prefix instead of the default - read before every scan so changes apply live
+    pub fn with_name_prefix(mut self, name_prefix: Arc<StdMutex<String>>) -> Self {
+        self.name_prefix = name_prefix;
+        self
+    }
 
-            // Wait for any pending operations to complete
-            embassy_time::block_for(embassy_time::Duration::from_millis(1000));
+    /// Keep-alive interval shared with the controller - read on every check so changes apply live
+    pub fn with_keep_alive_interval(mut self, keep_alive_ms: Arc<AtomicU32>) -> Self {
+        self.keep_alive_ms = keep_alive_ms;
+        self
+    }
 
-            // Check if NimBLE is already stopped by provisioning
-            // If so, we just need to reinitialize
-            let ret = nimble_port_init();
-            if ret != ESP_OK {
-                warn!("NimBLE init failed: {}, trying full reset", ret);
+    /// Report each step of a connection attempt here
+    pub fn with_stage_channel(mut self, stage_channel: Arc<ConnectionStageChannel>) -> Self {
+        self.stage_channel = Some(stage_channel);
+        self
+    }
 
-                // Try a gentle reset - deinit first
-                nimble_port_deinit();
-                embassy_time::block_for(embassy_time::Duration::from_millis(500));
+    /// Publish the scale info here each time it is read from the device
+    pub fn with_info_signal(mut self, info_signal: Arc<ScaleInfoSignal>) -> Self {
+        self.info_signal = Some(info_signal);
+        self
+    }
 
-                // Then reinitialize
-                let ret = nimble_port_init();
-                if ret != ESP_OK {
-                    return Err(ScaleError::BleError(BleError::InitializationFailed(
-                        format!("NimBLE reinit failed: {}", ret),
-                    )));
-                }
-            }
+    /// Publish the link quality score here while connected
+    pub fn with_link_quality_signal(mut self, link_quality_signal: Arc<LinkQualitySignal>) -> Self {
+        self.link_quality_signal = Some(link_quality_signal);
+        self
+    }
 
-            // Start NimBLE host task
-            nimble_port_freertos_init(None);
-        }
+    /// Initialize the BLE stack (call once at startup)
+    pub fn initialize() -> Result<(), ScaleError> {
+        BleClient::initialize().map_err(ScaleError::from)
+    }
 
+    /// Shut the BLE stack down and bring it back up - deterministic recovery after WiFi provisioning
+    pub async fn reset_ble_stack() -> Result<(), ScaleError> {
+        info!("🔄 Resetting BLE stack");
+        BleClient::shutdown().await.map_err(ScaleError::from)?;
+        BleClient::initialize().map_err(ScaleError::from)?;
         info!("✅ BLE stack reset complete");
         Ok(())
     }
@@ -155,6 +207,7 @@ impl BookooScale {
                 }
                 Err(e) => {
                     error!("Scale connection error: {:?}", e);
+                    self.note_connection_lost();
                     self.cleanup_connection().await;
                 }
             }
@@ -178,38 +231,124 @@ impl BookooScale {
             {
                 Ok(_) => {
                     info!("Scale connection cycle completed");
+                    self.cleanup_connection().await;
                 }
                 Err(e) => {
                     error!("Scale connection error: {:?}", e);
+                    self.note_connection_lost();
                     self.cleanup_connection().await;
                 }
             }
+            if self.stopping {
+                break;
+            }
 
             info!("Waiting 5 seconds before retrying scale connection...");
-            Timer::after(Duration::from_secs(5)).await;
+            self.wait_before_retry(&command_channel, Duration::from_secs(5))
+                .await;
+            if self.stopping {
+                break;
+            }
         }
+
+        info!("Scale client stopped");
+        Ok(())
+    }
+
+    /// Sleep between connection attempts while still serving device picker commands
+    async fn wait_before_retry(&mut self, command_channel: &ScaleCommandChannel, delay: Duration) {
+        let deadline = Instant::now() + delay;
+        loop {
+            let event = select(command_channel.receive(), Timer::at(deadline)).await;
+            match event {
+                Either::First(ScaleCommand::ScanDevices { duration_ms }) => {
+                    self.scan_nearby_devices(duration_ms).await;
+                }
+                Either::First(ScaleCommand::ConnectToDevice { address }) => {
+                    if self.select_device(address) {
+                        return;
+                    }
+                }
+                Either::First(ScaleCommand::Shutdown) => {
+                    self.stopping = true;
+                    return;
+                }
+                Either::First(command) => {
+                    warn!("Scale not connected - dropping {:?}", command);
+                }
+                Either::Second(_) => return,
+            }
+        }
+    }
+
+    /// Scan for every named device nearby and publish the list for the user to pick from
+    async fn scan_nearby_devices(&mut self, duration_ms: u32) {
+        info!("📡 Scanning for nearby devices for {}ms", duration_ms);
+        match self.ble_client.scan_for_devices(None, duration_ms).await {
+            Ok(devices) => {
+                info!("📡 Found {} nearby devices", devices.len());
+                self.nearby_devices = devices.clone();
+                if let Some(signal) = &self.devices_signal {
+                    signal.signal(devices);
+                }
+            }
+            Err(e) => warn!("📡 Device scan failed: {}", e),
+        }
+    }
+
+    /// Remember a device from the last scan as the one to connect to. False if it wasn't in the scan.
+    fn select_device(&mut self, address: [u8; 6]) -> bool {
+        let Some(device) = self
+            .nearby_devices
+            .iter()
+            .find(|d| d.address.addr == address)
+            .cloned()
+        else {
+            warn!("📡 Selected device is not in the last scan - scan again first");
+            return false;
+        };
+
+        info!(
+            "📡 Switching to {} ({})",
+            device.name.as_deref().unwrap_or("<unnamed>"),
+            device.address
+        );
+        self.preferred_device = Some(device);
+        true
     }
 
     /// Connect to scale and monitor for data
     async fn connect_and_monitor(&mut self) -> Result<(), ScaleError> {
         // Step 1: Scan for Bookoo scale
+        self.report_stage(ConnectionStage::Scanning);
         let scale_device = self.find_scale().await?;
         info!("Found Bookoo scale: {:?}", scale_device.name);
 
         // Step 2: Connect to the scale
+        self.report_stage(ConnectionStage::Connecting);
         let connection = self.ble_client.connect(&scale_device).await?;
         self.connection = Some(connection.clone());
         info!("Connected to Bookoo scale");
+        let device_name = scale_device
+            .name
+            .clone()
+            .unwrap_or_else(|| scale_device.address.to_string());
+        self.report_stage(ConnectionStage::Connected { device_name });
 
         // Step 3: Discover services and characteristics
+        self.report_stage(ConnectionStage::Discovering);
         self.discover_scale_services(&connection).await?;
         info!("Discovered scale services and characteristics");
 
+        // Best effort - a scale without the service still works
+        self.read_device_information(&connection).await;
+
         // Step 4: Subscribe to weight notifications with retry logic
         if let Some(ref weight_char) = self.weight_characteristic {
             self.subscribe_to_notifications_resilient(&connection, weight_char)
                 .await?;
             info!("Subscribed to weight notifications");
+            self.report_stage(ConnectionStage::Subscribed);
         } else {
             return Err(ScaleError::CharacteristicNotFound);
         }
@@ -226,23 +365,35 @@ impl BookooScale {
         command_channel: Arc<ScaleCommandChannel>,
     ) -> Result<(), ScaleError> {
         // Step 1: Scan for Bookoo scale
+        self.report_stage(ConnectionStage::Scanning);
         let scale_device = self.find_scale().await?;
         info!("Found Bookoo scale: {:?}", scale_device.name);
 
         // Step 2: Connect to the scale
+        self.report_stage(ConnectionStage::Connecting);
         let connection = self.ble_client.connect(&scale_device).await?;
         self.connection = Some(connection.clone());
         info!("Connected to Bookoo scale");
+        let device_name = scale_device
+            .name
+            .clone()
+            .unwrap_or_else(|| scale_device.address.to_string());
+        self.report_stage(ConnectionStage::Connected { device_name });
 
         // Step 3: Discover services and characteristics
+        self.report_stage(ConnectionStage::Discovering);
         self.discover_scale_services(&connection).await?;
         info!("Discovered scale services and characteristics");
 
+        // Best effort - a scale without the service still works
+        self.read_device_information(&connection).await;
+
         // Step 4: Subscribe to weight notifications with retry logic
         if let Some(ref weight_char) = self.weight_characteristic {
             self.subscribe_to_notifications_resilient(&connection, weight_char)
                 .await?;
             info!("Subscribed to weight notifications");
+            self.report_stage(ConnectionStage::Subscribed);
         } else {
             return Err(ScaleError::CharacteristicNotFound);
         }
@@ -254,12 +405,33 @@ impl BookooScale {
         Ok(())
     }
 
+    /// Report connection progress - never waits, a full channel only costs the UI a step
+    fn report_stage(&self, stage: ConnectionStage) {
+        debug!("Scale connection stage: {:?}", stage);
+        if let Some(channel) = &self.stage_channel {
+            if channel.try_send(stage).is_err() {
+                debug!("Connection stage channel full - dropping stage");
+            }
+        }
+    }
+
     /// Scan for Bookoo scale devices - connect immediately when found
     async fn find_scale(&self) -> Result<Device, ScaleError> {
-        info!("Scanning for Bookoo scale...");
+        // A device the user picked wins over name matching
+        if let Some(device) = &self.preferred_device {
+            info!("Using selected device {}", device.address);
+            return Ok(device.clone());
+        }
+
+        let name_prefix = self
+            .name_prefix
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        info!("Scanning for Bookoo scale named {}*...", name_prefix);
 
         let filter = DeviceFilter {
-            name_prefix: Some("BOOKOO_SC".to_string()),
+            name_prefix: Some(name_prefix.clone()),
             service_uuid: None,
         };
 
@@ -270,13 +442,31 @@ impl BookooScale {
             .await?
         {
             if let Some(ref name) = device.name {
-                if name.starts_with("BOOKOO_SC") {
+                if name.starts_with(&name_prefix) {
                     info!("Found Bookoo scale immediately: {}", name);
                     return Ok(device);
                 }
             }
         }
 
+        // Renamed scales and other variants still advertise the Bookoo service
+        info!("No scale named {}* - scanning by service UUID", name_prefix);
+        let filter = DeviceFilter {
+            name_prefix: None,
+            service_uuid: Some(Uuid::from_u16(BOOKOO_SERVICE_UUID_16)),
+        };
+        if let Some(device) = self
+            .ble_client
+            .scan_for_first_device(Some(filter), 5000)
+            .await?
+        {
+            info!(
+                "Found Bookoo scale by service UUID: {}",
+                device.name.as_deref().unwrap_or("<unnamed>")
+            );
+            return Ok(device);
+        }
+
         Err(ScaleError::ScaleNotFound)
     }
 
@@ -294,6 +484,9 @@ impl BookooScale {
         self.discover_scale_services(&connection).await?;
         info!("🔍 Discovered Bookoo scale services and characteristics");
 
+        // Best effort - a scale without the service still works
+        self.read_device_information(&connection).await;
+
         // Step 3: Subscribe to weight notifications with retry logic
         if let Some(ref weight_char) = self.weight_characteristic {
             self.subscribe_to_notifications_resilient(&connection, weight_char)
@@ -310,7 +503,7 @@ impl BookooScale {
     /// Start monitoring connected device for scale data with command support
     /// This method is used after connect_to_device() to begin data processing
     pub async fn start_monitoring_with_commands(
-        &self,
+        &mut self,
         command_channel: Arc<ScaleCommandChannel>,
     ) -> Result<(), ScaleError> {
         if !self.is_connected() {
@@ -322,7 +515,7 @@ impl BookooScale {
     }
 
     /// Start monitoring connected device for scale data (no commands)
-    pub async fn start_monitoring(&self) -> Result<(), ScaleError> {
+    pub async fn start_monitoring(&mut self) -> Result<(), ScaleError> {
         if !self.is_connected() {
             return Err(ScaleError::NotConnected);
         }
@@ -384,6 +577,11 @@ impl BookooScale {
         let bookoo_service_uuid_16 = Uuid::from_u16(BOOKOO_SERVICE_UUID_16);
         let bookoo_service_uuid_128 = Uuid::from_u128_bytes(BOOKOO_SERVICE_UUID_128);
 
+        self.device_info_service = services
+            .iter()
+            .find(|service| service.uuid == Uuid::from_u16(DEVICE_INFO_SERVICE_UUID_16))
+            .cloned();
+
         let scale_service = services
             .iter()
             .find(|service| {
@@ -458,6 +656,82 @@ impl BookooScale {
         Ok(())
     }
 
+    /// Read manufacturer, model and firmware revision from the Device Information Service
+    async fn read_device_information(&mut self, connection: &Connection) {
+        let Some(service) = self.device_info_service.clone() else {
+            info!("📇 Scale has no Device Information Service - firmware version unknown");
+            return;
+        };
+
+        let characteristics = match self
+            .ble_client
+            .discover_characteristics(connection, &service)
+            .await
+        {
+            Ok(characteristics) => characteristics,
+            Err(e) => {
+                warn!("📇 Device Information discovery failed: {}", e);
+                return;
+            }
+        };
+
+        let manufacturer = self
+            .read_info_string(connection, &characteristics, MANUFACTURER_NAME_UUID_16)
+            .await;
+        let model = self
+            .read_info_string(connection, &characteristics, MODEL_NUMBER_UUID_16)
+            .await;
+        let firmware = self
+            .read_info_string(connection, &characteristics, FIRMWARE_REVISION_UUID_16)
+            .await;
+
+        info!(
+            "📇 Scale device info: manufacturer={}, model={}, firmware={}",
+            manufacturer.as_deref().unwrap_or("?"),
+            model.as_deref().unwrap_or("?"),
+            firmware.as_deref().unwrap_or("?")
+        );
+
+        if let Some(model) = model {
+            self.info.model = model;
+        }
+        self.info.version = firmware;
+
+        if let Some(signal) = &self.info_signal {
+            signal.signal(self.info.clone());
+        }
+    }
+
+    /// Read one Device Information string, None if absent, unreadable or empty
+    async fn read_info_string(
+        &self,
+        connection: &Connection,
+        characteristics: &[Characteristic],
+        uuid: u16,
+    ) -> Option<String> {
+        let characteristic = characteristics
+            .iter()
+            .find(|c| c.uuid == Uuid::from_u16(uuid))?;
+
+        match self
+            .ble_client
+            .read_characteristic(connection, characteristic)
+            .await
+        {
+            Ok(bytes) => {
+                let value = String::from_utf8_lossy(&bytes)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string();
+                (!value.is_empty()).then_some(value)
+            }
+            Err(e) => {
+                warn!("📇 Reading 0x{:04X} failed: {}", uuid, e);
+                None
+            }
+        }
+    }
+
     /// Subscribe to notifications with retry logic for resilience
     async fn subscribe_to_notifications_resilient(
         &self,
@@ -467,6 +741,9 @@ impl BookooScale {
         const MAX_SUBSCRIPTION_RETRIES: u32 = 3;
         const SUBSCRIPTION_RETRY_DELAY_MS: u64 = 500;
 
+        // Stale samples from a previous connection would otherwise be processed first
+        self.ble_client.clear_notifications();
+
         for attempt in 1..=MAX_SUBSCRIPTION_RETRIES {
             match self
                 .ble_client
@@ -494,72 +771,171 @@ impl BookooScale {
     }
 
     /// Monitor scale for incoming data
-    async fn monitor_scale_data(&self) -> Result<(), ScaleError> {
+    async fn monitor_scale_data(&mut self) -> Result<(), ScaleError> {
         info!("Monitoring scale for weight data...");
 
-        let mut no_data_count = 0;
-        const MAX_NO_DATA_COUNT: u32 = 300; // 5 minutes without data
+        let mut last_data = Instant::now();
+        let mut last_wait_log = Instant::now();
+        let mut last_keep_alive = Instant::now();
+        let mut last_link_report = Instant::now();
 
         loop {
-            Timer::after(Duration::from_millis(100)).await;
-
-            // Check for new notification data
-            if let Some(data) = self.ble_client.get_notification_data() {
-                no_data_count = 0;
-
-                debug!("Received scale data: {} bytes: {:02X?}", data.len(), data);
-
-                // Parse the scale data
-                if let Some(scale_data) = parse_scale_data(&data) {
-                    info!(
-                        "Parsed weight: {:.2}g, flow: {:.2}g/s, battery: {}%, timer: {}",
-                        scale_data.weight_g,
-                        scale_data.flow_rate_g_per_s,
-                        scale_data.battery_percent,
-                        scale_data.timer_running
-                    );
-
-                    // Send data to the main application
-                    if let Err(_) = self.data_channel.try_send(scale_data) {
-                        warn!("Failed to send scale data - channel full");
+            // Wake on notifications; the timer only bounds how long connection checks can lag
+            match select(
+                self.ble_client.next_notification(),
+                Timer::after(Duration::from_millis(CONNECTION_CHECK_INTERVAL_MS)),
+            )
+            .await
+            {
+                Either::First(notification) => {
+                    if self.process_notification(&notification) {
+                        last_data = Instant::now();
                     }
-                } else {
-                    warn!(
-                        "Failed to parse scale data: {} bytes: {:02X?}",
-                        data.len(),
-                        data
-                    );
-                }
-            } else {
-                no_data_count += 1;
-
-                // Log status every 30 seconds
-                if no_data_count % 300 == 0 {
-                    info!(
-                        "Waiting for scale data... ({} seconds without data)",
-                        no_data_count / 10
-                    );
-                }
-
-                // Timeout after 5 minutes without data
-                if no_data_count > MAX_NO_DATA_COUNT {
-                    warn!("No data received from scale for 5 minutes - reconnecting");
-                    return Err(ScaleError::BleError(BleError::ConnectionFailed(
-                        "No data timeout".to_string(),
-                    )));
                 }
+                Either::Second(_) => self.check_data_timeout(last_data, &mut last_wait_log)?,
             }
 
-            // Check if still connected
-            if self.connection.is_none() {
-                return Err(ScaleError::NotConnected);
-            }
+            self.check_connection()?;
+            self.keep_alive_if_due(&mut last_keep_alive).await;
+            self.report_link_quality_if_due(&mut last_link_report);
+        }
+    }
 
-            // Check BLE connection status from the client
-            if !self.ble_client.is_connected() {
-                warn!("BLE connection lost - returning to reconnect");
-                return Err(ScaleError::NotConnected);
-            }
+    /// Parse a weight notification and forward it to the application.
+    /// Returns false for notifications from any other characteristic.
+    fn process_notification(&mut self, notification: &Notification) -> bool {
+        let is_weight = self
+            .weight_characteristic
+            .as_ref()
+            .is_some_and(|c| c.handle == notification.attr_handle);
+        if !is_weight {
+            debug!("Ignoring notification from handle {}", notification.attr_handle);
+            return false;
+        }
+
+        let data = notification.data.as_slice();
+        debug!("Received scale data: {} bytes: {:02X?}", data.len(), data);
+
+        // Parse the scale data
+        if let Some(scale_data) = parse_scale_data(data) {
+            debug!(
+                "Parsed weight: {:.2}g, flow: {:.2}g/s, battery: {}%, timer: {}",
+                scale_data.weight_g,
+                scale_data.flow_rate_g_per_s,
+                scale_data.battery_percent,
+                scale_data.timer_running
+            );
+
+            // Send data to the main application - a full queue discards stale samples, never this one
+            self.data_channel.push(scale_data);
+            self.update_settings(data);
+            self.link_quality.record_frame(true);
+        } else {
+            self.link_quality.record_frame(false);
+            warn!(
+                "Failed to parse scale data: {} bytes: {:02X?}",
+                data.len(),
+                data
+            );
+        }
+        true
+    }
+
+    /// The Bookoo repeats its settings in every weight frame - publish them when they change
+    fn update_settings(&mut self, data: &[u8]) {
+        let settings = parse_scale_settings(data);
+        if settings.is_none() || settings == self.info.settings {
+            return;
+        }
+        info!("📇 Scale settings: {:?}", settings);
+        self.info.settings = settings;
+        if let Some(signal) = &self.info_signal {
+            signal.signal(self.info.clone());
+        }
+    }
+
+    /// Log while waiting for data and give up after the no-data timeout
+    fn check_data_timeout(&self, last_data: Instant, last_wait_log: &mut Instant) -> Result<(), ScaleError> {
+        let silent_secs = last_data.elapsed().as_secs();
+
+        // Log status every 30 seconds
+        if silent_secs >= WAITING_LOG_INTERVAL_SECS
+            && last_wait_log.elapsed().as_secs() >= WAITING_LOG_INTERVAL_SECS
+        {
+            info!("Waiting for scale data... ({} seconds without data)", silent_secs);
+            *last_wait_log = Instant::now();
+        }
+
+        // Timeout after 5 minutes without data
+        if silent_secs > NO_DATA_TIMEOUT_SECS {
+            warn!("No data received from scale for 5 minutes - reconnecting");
+            return Err(ScaleError::BleError(BleError::ConnectionFailed(
+                "No data timeout".to_string(),
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Read the weight characteristic once the keep-alive interval has passed - link activity
+    /// resets the idle auto-off timer. Scales without auto-off never get the extra traffic
+    async fn keep_alive_if_due(&self, last_keep_alive: &mut Instant) {
+        let interval_ms = self.keep_alive_ms.load(Ordering::Relaxed);
+        if !self.info.capabilities.supports_auto_off
+            || interval_ms == 0
+            || last_keep_alive.elapsed() < Duration::from_millis(interval_ms as u64)
+        {
+            return;
+        }
+        *last_keep_alive = Instant::now();
+
+        let (Some(connection), Some(characteristic)) =
+            (&self.connection, &self.weight_characteristic)
+        else {
+            return;
+        };
+        match self.ble_client.read_characteristic(connection, characteristic).await {
+            Ok(_) => debug!("Keep-alive read sent to scale"),
+            Err(e) => warn!("Scale keep-alive read failed: {}", e),
+        }
+    }
+
+    /// Sample the link's RSSI and publish the quality score every report interval
+    fn report_link_quality_if_due(&mut self, last_report: &mut Instant) {
+        if last_report.elapsed() < Duration::from_millis(LINK_QUALITY_REPORT_INTERVAL_MS) {
+            return;
+        }
+        *last_report = Instant::now();
+
+        if let Some(rssi) = BleClient::current_rssi() {
+            self.link_quality.record_rssi(rssi);
+        }
+        let report = self.link_quality.report(Instant::now());
+        debug!("📶 Link quality: {:?}", report);
+        if let Some(signal) = &self.link_quality_signal {
+            signal.signal(report);
+        }
+    }
+
+    fn check_connection(&self) -> Result<(), ScaleError> {
+        // Check if still connected
+        if self.connection.is_none() {
+            return Err(ScaleError::NotConnected);
+        }
+
+        // Check BLE connection status from the client
+        if !self.ble_client.is_connected() {
+            warn!("BLE connection lost - returning to reconnect");
+            return Err(ScaleError::NotConnected);
+        }
+
+        Ok(())
+    }
+
+    /// A cycle ending in an error while connected is a dropped link - counts against link quality
+    fn note_connection_lost(&mut self) {
+        if self.connection.is_some() {
+            self.link_quality.record_drop(Instant::now());
         }
     }
 
@@ -574,6 +950,9 @@ impl BookooScale {
         self.connection = None;
         self.weight_characteristic = None;
         self.command_characteristic = None;
+        // Read back afresh from the next connection's first frame
+        self.info.settings = None;
+        self.link_quality.clear_rssi();
 
         info!("Scale connection cleanup completed");
     }
@@ -583,6 +962,16 @@ impl BookooScale {
         self.connection.is_some()
     }
 
+    /// Switch the link between the brewing and idle connection intervals
+    pub fn set_connection_profile(&self, profile: ConnectionProfile) -> Result<(), ScaleError> {
+        let connection = self.connection.as_ref().ok_or(ScaleError::NotConnected)?;
+        let params = match profile {
+            ConnectionProfile::Fast => ConnParams::FAST,
+            ConnectionProfile::Slow => ConnParams::SLOW,
+        };
+        Ok(self.ble_client.update_conn_params(connection, &params)?)
+    }
+
     /// Send tare command to scale
     pub async fn send_tare_command(&self) -> Result<(), ScaleError> {
         let command = [0x03, 0x0A, 0x01, 0x00, 0x00, 0x08]; // COMMAND_TARE from Python
@@ -640,92 +1029,47 @@ impl BookooScale {
 
     /// Monitor scale for incoming data and process commands
     async fn monitor_scale_data_with_commands(
-        &self,
+        &mut self,
         command_channel: Arc<ScaleCommandChannel>,
     ) -> Result<(), ScaleError> {
         info!("Monitoring scale for weight data and commands...");
 
-        let mut no_data_count = 0;
-        const MAX_NO_DATA_COUNT: u32 = 300; // 5 minutes without data
+        let mut last_data = Instant::now();
+        let mut last_wait_log = Instant::now();
+        let mut last_keep_alive = Instant::now();
+        let mut last_link_report = Instant::now();
 
         loop {
-            // Check for commands with a timeout so we don't block data processing
-            match embassy_futures::select::select(
+            let event = select3(
                 command_channel.receive(),
-                Timer::after(Duration::from_millis(100)),
+                self.ble_client.next_notification(),
+                Timer::after(Duration::from_millis(CONNECTION_CHECK_INTERVAL_MS)),
             )
-            .await
-            {
-                embassy_futures::select::Either::First(command) => {
-                    self.handle_command(command).await;
-                }
-                embassy_futures::select::Either::Second(_) => {
-                    // Timer expired, continue to data processing
-                }
-            }
-
-            // Check for new notification data
-            if let Some(data) = self.ble_client.get_notification_data() {
-                no_data_count = 0;
-
-                debug!("Received scale data: {} bytes: {:02X?}", data.len(), data);
-
-                // Parse the scale data
-                if let Some(scale_data) = parse_scale_data(&data) {
-                    info!(
-                        "Parsed weight: {:.2}g, flow: {:.2}g/s, battery: {}%, timer: {}",
-                        scale_data.weight_g,
-                        scale_data.flow_rate_g_per_s,
-                        scale_data.battery_percent,
-                        scale_data.timer_running
-                    );
-
-                    // Send data to the main application
-                    if let Err(_) = self.data_channel.try_send(scale_data) {
-                        warn!("Failed to send scale data - channel full");
+            .await;
+            match event {
+                Either3::First(command) => {
+                    if self.handle_command(command).await {
+                        // User picked another device or we're shutting down - end this cycle
+                        return Ok(());
                     }
-                } else {
-                    warn!(
-                        "Failed to parse scale data: {} bytes: {:02X?}",
-                        data.len(),
-                        data
-                    );
                 }
-            } else {
-                no_data_count += 1;
-
-                // Log status every 30 seconds
-                if no_data_count % 300 == 0 {
-                    info!(
-                        "Waiting for scale data... ({} seconds without data)",
-                        no_data_count / 10
-                    );
-                }
-
-                // Timeout after 5 minutes without data
-                if no_data_count > MAX_NO_DATA_COUNT {
-                    warn!("No data received from scale for 5 minutes - reconnecting");
-                    return Err(ScaleError::BleError(BleError::ConnectionFailed(
-                        "No data timeout".to_string(),
-                    )));
+                Either3::Second(notification) => {
+                    if self.process_notification(&notification) {
+                        last_data = Instant::now();
+                    }
                 }
+                Either3::Third(_) => self.check_data_timeout(last_data, &mut last_wait_log)?,
             }
 
-            // Check if still connected
-            if self.connection.is_none() {
-                return Err(ScaleError::NotConnected);
-            }
-
-            // Check BLE connection status from the client
-            if !self.ble_client.is_connected() {
-                warn!("BLE connection lost - returning to reconnect");
-                return Err(ScaleError::NotConnected);
-            }
+            self.check_connection()?;
+            self.keep_alive_if_due(&mut last_keep_alive).await;
+            self.report_link_quality_if_due(&mut last_link_report);
         }
     }
 
-    /// Handle incoming scale commands
-    async fn handle_command(&self, command: ScaleCommand) {
+    /// Handle incoming scale commands. True when the connection should end - the user switched to
+    /// another device, or the controller is shutting down
+    async fn handle_command(&mut self, command: ScaleCommand) -> bool {
         match command {
             ScaleCommand::Tare => {
                 info!("Processing tare command from channel");
@@ -751,7 +1095,25 @@ impl BookooScale {
                     warn!("Failed to execute reset timer command: {:?}", e);
                 }
             }
+            ScaleCommand::SetConnectionProfile(profile) => {
+                info!("Processing {:?} connection profile from channel", profile);
+                if let Err(e) = self.set_connection_profile(profile) {
+                    warn!("Failed to update connection parameters: {:?}", e);
+                }
+            }
+            ScaleCommand::ScanDevices { duration_ms } => {
+                self.scan_nearby_devices(duration_ms).await;
+            }
+            ScaleCommand::ConnectToDevice { address } => {
+                return self.select_device(address);
+            }
+            ScaleCommand::Shutdown => {
+                info!("Processing shutdown command from channel");
+                self.stopping = true;
+                return true;
+            }
         }
+        false
     }
 }
 
@@ -804,6 +1166,15 @@ impl BleScale for BookooScale {
             ScaleCommand::StartTimer => [0x03, 0x00, 0x00, 0x00, 0x00, 0x03],
             ScaleCommand::StopTimer => [0x04, 0x00, 0x00, 0x00, 0x00, 0x04],
             ScaleCommand::ResetTimer => [0x05, 0x00, 0x00, 0x00, 0x00, 0x05],
+            ScaleCommand::SetConnectionProfile(_)
+            | ScaleCommand::ScanDevices { .. }
+            | ScaleCommand::ConnectToDevice { .. }
+            | ScaleCommand::Shutdown => {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Not a scale protocol command",
+                )));
+            }
         };
         Ok(cmd_bytes.to_vec())
     }
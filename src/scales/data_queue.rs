@@ -0,0 +1,197 @@
+//! Bounded scale sample queue between the BLE notification path and the event bridge
+//! When the consumer falls behind the newest reading is always kept - stale ones are discarded instead
+
+use crate::types::ScaleData;
+use core::cell::RefCell;
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
+use heapless::Deque;
+use log::{debug, warn};
+
+/// 5 seconds buffer at 10Hz
+pub const SCALE_DATA_QUEUE_DEPTH: usize = 50;
+
+/// What to do with queued samples when a new one arrives and the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScaleDataOverflowPolicy {
+    /// Discard the stalest sample to make room - keeps as much history as fits
+    #[default]
+    DropOldest,
+    /// Discard everything queued and keep only the newest sample - minimal latency
+    CoalesceLatest,
+}
+
+/// Fixed-capacity sample ring applying an overflow policy - the pure part of `ScaleDataQueue`
+pub struct ScaleDataRing<const N: usize> {
+    samples: Deque<ScaleData, N>,
+    policy: ScaleDataOverflowPolicy,
+    dropped: u32,
+}
+
+impl<const N: usize> ScaleDataRing<N> {
+    pub const fn new(policy: ScaleDataOverflowPolicy) -> Self {
+        Self {
+            samples: Deque::new(),
+            policy,
+            dropped: 0,
+        }
+    }
+
+    /// Queue a sample, returning how many older samples were discarded to fit it
+    pub fn push(&mut self, data: ScaleData) -> usize {
+        let mut discarded = 0;
+
+        if self.samples.is_full() {
+            match self.policy {
+                ScaleDataOverflowPolicy::DropOldest => {
+                    self.samples.pop_front();
+                    discarded = 1;
+                }
+                ScaleDataOverflowPolicy::CoalesceLatest => {
+                    discarded = self.samples.len();
+                    self.samples.clear();
+                }
+            }
+        }
+
+        // Cannot fail - room was made above
+        let _ = self.samples.push_back(data);
+        self.dropped = self.dropped.saturating_add(discarded as u32);
+        discarded
+    }
+
+    pub fn pop(&mut self) -> Option<ScaleData> {
+        self.samples.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn policy(&self) -> ScaleDataOverflowPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ScaleDataOverflowPolicy) {
+        self.policy = policy;
+    }
+
+    /// Total samples discarded due to overflow
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+/// Async single-consumer queue of scale samples; `push` never blocks and never drops the newest sample
+pub struct ScaleDataQueue {
+    ring: Mutex<CriticalSectionRawMutex, RefCell<ScaleDataRing<SCALE_DATA_QUEUE_DEPTH>>>,
+    ready: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl ScaleDataQueue {
+    pub const fn new(policy: ScaleDataOverflowPolicy) -> Self {
+        Self {
+            ring: Mutex::new(RefCell::new(ScaleDataRing::new(policy))),
+            ready: Signal::new(),
+        }
+    }
+
+    /// Queue a sample from the notification path
+    pub fn push(&self, data: ScaleData) {
+        let (discarded, policy) = self.ring.lock(|ring| {
+            let mut ring = ring.borrow_mut();
+            (ring.push(data), ring.policy())
+        });
+
+        if discarded > 0 {
+            warn!("Scale data queue full - discarded {} stale sample(s) ({:?})", discarded, policy);
+        }
+        self.ready.signal(());
+    }
+
+    /// Wait for the next sample
+    pub async fn receive(&self) -> ScaleData {
+        loop {
+            if let Some(data) = self.try_receive() {
+                return data;
+            }
+            self.ready.wait().await;
+        }
+    }
+
+    pub fn try_receive(&self) -> Option<ScaleData> {
+        self.ring.lock(|ring| ring.borrow_mut().pop())
+    }
+
+    pub fn set_policy(&self, policy: ScaleDataOverflowPolicy) {
+        debug!("Scale data overflow policy set to {:?}", policy);
+        self.ring.lock(|ring| ring.borrow_mut().set_policy(policy));
+    }
+
+    /// Total samples discarded due to overflow since boot
+    pub fn dropped(&self) -> u32 {
+        self.ring.lock(|ring| ring.borrow().dropped())
+    }
+}
+
+impl Default for ScaleDataQueue {
+    fn default() -> Self {
+        Self::new(ScaleDataOverflowPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_time::Instant;
+
+    fn sample(weight_g: f32) -> ScaleData {
+        ScaleData {
+            timestamp_ms: 0,
+            weight_g,
+            flow_rate_g_per_s: 0.0,
+            battery_percent: 100,
+            timer_running: false,
+            received_at: Instant::from_millis(0),
+        }
+    }
+
+    fn drain<const N: usize>(ring: &mut ScaleDataRing<N>) -> Vec<f32> {
+        std::iter::from_fn(|| ring.pop()).map(|d| d.weight_g).collect()
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest() {
+        let mut ring: ScaleDataRing<3> = ScaleDataRing::new(ScaleDataOverflowPolicy::DropOldest);
+        for w in 1..=5 {
+            ring.push(sample(w as f32));
+        }
+        assert_eq!(drain(&mut ring), vec![3.0, 4.0, 5.0]);
+        assert_eq!(ring.dropped(), 2);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_only_latest_on_overflow() {
+        let mut ring: ScaleDataRing<3> = ScaleDataRing::new(ScaleDataOverflowPolicy::CoalesceLatest);
+        for w in 1..=4 {
+            ring.push(sample(w as f32));
+        }
+        assert_eq!(drain(&mut ring), vec![4.0]);
+        assert_eq!(ring.dropped(), 3);
+    }
+
+    #[test]
+    fn test_no_drops_below_capacity() {
+        let mut ring: ScaleDataRing<3> = ScaleDataRing::new(ScaleDataOverflowPolicy::DropOldest);
+        assert_eq!(ring.push(sample(1.0)), 0);
+        assert_eq!(ring.push(sample(2.0)), 0);
+        assert_eq!(drain(&mut ring), vec![1.0, 2.0]);
+        assert_eq!(ring.dropped(), 0);
+    }
+}
@@ -2,7 +2,9 @@
 //! Analyzes raw scale data to detect user actions and state changes
 
 use crate::system::events::{ScaleButton, ScaleEvent};
-use crate::types::ScaleData;
+use crate::types::{
+    ScaleData, SHUTDOWN_FLOW_RATE_G_PER_S, SHUTDOWN_WEIGHT_JUMP_G, TIMER_STOP_FROZEN_SAMPLES,
+};
 use embassy_time::{Duration, Instant};
 use log::{debug, info};
 
@@ -47,6 +49,7 @@ pub struct ScaleEventDetector {
     last_timer_timestamp: Option<u32>,
     timer_running: bool,
     last_timer_update: Option<Instant>,
+    stopped_timer_samples: u8, // Samples in a row reading 0 or repeating the previous timestamp
     
     // Weight change tracking
     last_stable_weight: Option<f32>,
@@ -59,6 +62,10 @@ pub struct ScaleEventDetector {
     // Object detection state
     object_present: bool,
     last_object_change: Option<Instant>,
+
+    // Scale shutdown detection - from config, kept across resets
+    shutdown_flow_rate_g_per_s: f32,
+    shutdown_weight_jump_g: f32,
 }
 
 impl Default for ScaleEventDetector {
@@ -69,12 +76,15 @@ impl Default for ScaleEventDetector {
             last_timer_timestamp: None,
             timer_running: false,
             last_timer_update: None,
+            stopped_timer_samples: 0,
             last_stable_weight: None,
             weight_stable_since: None,
             last_weight_change: None,
             last_button_detection: None,
             object_present: false,
             last_object_change: None,
+            shutdown_flow_rate_g_per_s: SHUTDOWN_FLOW_RATE_G_PER_S,
+            shutdown_weight_jump_g: SHUTDOWN_WEIGHT_JUMP_G,
         }
     }
 }
@@ -83,23 +93,32 @@ impl ScaleEventDetector {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Flow (g/s) and per-sample weight jump beyond which a timer start is the scale shutting down
+    pub fn set_shutdown_thresholds(&mut self, flow_rate_g_per_s: f32, weight_jump_g: f32) {
+        self.shutdown_flow_rate_g_per_s = flow_rate_g_per_s;
+        self.shutdown_weight_jump_g = weight_jump_g;
+    }
     
     /// Process new scale data and detect events
     pub fn process_data(&mut self, data: &ScaleData) -> Vec<ScaleEvent> {
         let now = Instant::now();
         let mut events = Vec::new();
+        // A Bookoo powering off runs its timer while the readings go wild - not a brew
+        let previous_weight_g = self.history.last().map(|point| point.data.weight_g);
+        let shutting_down = self.looks_like_shutdown(previous_weight_g, data);
         
         // Add to history
         self.add_to_history(data.clone(), now);
         
         // Detect timer events
-        events.extend(self.detect_timer_events(data, now));
+        events.extend(self.detect_timer_events(data, shutting_down, now));
         
         // Detect weight-based events
         events.extend(self.detect_weight_events(data, now));
         
         // Detect button presses (inferred from sudden timer changes)
-        events.extend(self.detect_button_events(data, now));
+        events.extend(self.detect_button_events(data, shutting_down, now));
         
         // Detect object placement/removal
         events.extend(self.detect_object_events(data, now));
@@ -117,8 +136,21 @@ impl ScaleEventDetector {
         }
     }
     
+    /// Reading is physically impossible for a pour - flow or a jump from the previous sample
+    /// beyond the configured limits, as seen while the scale shuts down
+    fn looks_like_shutdown(&self, previous_weight_g: Option<f32>, data: &ScaleData) -> bool {
+        let weight_jump_g = previous_weight_g.map_or(0.0, |w| (data.weight_g - w).abs());
+        data.flow_rate_g_per_s > self.shutdown_flow_rate_g_per_s
+            || weight_jump_g > self.shutdown_weight_jump_g
+    }
+
     /// Detect timer start/stop events
-    fn detect_timer_events(&mut self, data: &ScaleData, now: Instant) -> Vec<ScaleEvent> {
+    fn detect_timer_events(
+        &mut self,
+        data: &ScaleData,
+        shutting_down: bool,
+        now: Instant,
+    ) -> Vec<ScaleEvent> {
         let mut events = Vec::new();
         
         // Check for timer state changes based on timestamp behavior
@@ -129,6 +161,13 @@ impl ScaleEventDetector {
                 // Handle timestamp rollover or reset
                 data.timestamp_ms
             };
+
+            // A stopped timer reads 0 or repeats itself - count it, so one odd frame isn't a stop
+            if data.timestamp_ms == 0 || data.timestamp_ms == last_timestamp {
+                self.stopped_timer_samples = self.stopped_timer_samples.saturating_add(1);
+            } else {
+                self.stopped_timer_samples = 0;
+            }
             
             // Timer just started - detect multiple scenarios:
             // 1. Timer was at 0 and now has a small positive value (fresh start)
@@ -142,7 +181,12 @@ impl ScaleEventDetector {
                     timestamp_delta < TIMER_RESTART_THRESHOLD_MS
                 };
                 
-                if timer_start_detected {
+                if timer_start_detected && shutting_down {
+                    info!(
+                        "Ignoring timer start - {:.1}g at {:.1}g/s indicates scale shutdown",
+                        data.weight_g, data.flow_rate_g_per_s
+                    );
+                } else if timer_start_detected {
                     info!("⏱️ Timer started detected: {}ms (delta: {}ms)", data.timestamp_ms, timestamp_delta);
                     self.timer_running = true;
                     self.last_timer_update = Some(now);
@@ -151,16 +195,23 @@ impl ScaleEventDetector {
                     });
                 }
             }
-            // Timer stopped (timestamp went to 0)
-            else if self.timer_running && data.timestamp_ms == 0 {
-                info!("⏹️ Timer stopped detected: {}ms", data.timestamp_ms);
-                self.timer_running = false;
-                events.push(ScaleEvent::TimerStopped { 
-                    timestamp_ms: data.timestamp_ms 
-                });
+            // Timer stopped (timestamp went to 0 or froze) - only once it stays that way for
+            // `TIMER_STOP_FROZEN_SAMPLES` samples, so one duplicated or zeroed frame keeps brewing
+            else if self.timer_running
+                && (data.timestamp_ms == 0 || data.timestamp_ms == last_timestamp)
+            {
+                if self.stopped_timer_samples >= TIMER_STOP_FROZEN_SAMPLES {
+                    info!(
+                        "⏹️ Timer stopped detected: {}ms for {} samples",
+                        data.timestamp_ms, self.stopped_timer_samples
+                    );
+                    self.timer_running = false;
+                    events.push(ScaleEvent::TimerStopped {
+                        timestamp_ms: data.timestamp_ms,
+                    });
+                }
             }
             // Timer stopped due to no timestamp updates for extended period (handled by timeout logic below)
-            // NOTE: We don't trigger stop on same timestamp anymore to avoid false positives
             // Timer reset (timestamp jumped to small value)
             else if data.timestamp_ms < 1000 && last_timestamp > 5000 {
                 info!("🔄 Timer reset detected: {}ms -> {}ms", last_timestamp, data.timestamp_ms);
@@ -172,6 +223,11 @@ impl ScaleEventDetector {
                     });
                 }
             }
+        } else if data.timestamp_ms > 0 && shutting_down {
+            info!(
+                "Ignoring initial timer start - {:.1}g at {:.1}g/s indicates scale shutdown",
+                data.weight_g, data.flow_rate_g_per_s
+            );
         } else if data.timestamp_ms > 0 {
             // First timer start
             info!("⏱️ Initial timer start detected: {}ms", data.timestamp_ms);
@@ -195,10 +251,11 @@ impl ScaleEventDetector {
             }
         }
         
-        self.last_timer_timestamp = Some(data.timestamp_ms);
-        if data.timestamp_ms > (self.last_timer_timestamp.unwrap_or(0)) {
+        // The timeout runs from the last time the timestamp moved, not from the timer start
+        if data.timestamp_ms > self.last_timer_timestamp.unwrap_or(0) {
             self.last_timer_update = Some(now);
         }
+        self.last_timer_timestamp = Some(data.timestamp_ms);
         
         events
     }
@@ -241,7 +298,12 @@ impl ScaleEventDetector {
     }
     
     /// Detect button presses (inferred from data patterns)
-    fn detect_button_events(&mut self, data: &ScaleData, now: Instant) -> Vec<ScaleEvent> {
+    fn detect_button_events(
+        &mut self,
+        data: &ScaleData,
+        shutting_down: bool,
+        now: Instant,
+    ) -> Vec<ScaleEvent> {
         let mut events = Vec::new();
         
         // Debounce button detection
@@ -260,8 +322,8 @@ impl ScaleEventDetector {
             }
         }
         
-        // Detect timer button (sudden timer state change without flow)
-        if let Some(last_timestamp) = self.last_timer_timestamp {
+        // Detect timer button (sudden timer state change without flow) - not while shutting down
+        if let Some(last_timestamp) = self.last_timer_timestamp.filter(|_| !shutting_down) {
             // Timer button pressed if timer state changed abruptly without significant flow
             if (data.timestamp_ms == 0 && last_timestamp > 1000) || 
                (data.timestamp_ms > 0 && last_timestamp == 0) {
@@ -316,7 +378,11 @@ impl ScaleEventDetector {
     
     /// Reset all state
     pub fn reset(&mut self) {
-        *self = Self::default();
+        *self = Self {
+            shutdown_flow_rate_g_per_s: self.shutdown_flow_rate_g_per_s,
+            shutdown_weight_jump_g: self.shutdown_weight_jump_g,
+            ..Self::default()
+        };
     }
     
     /// Get current timer state
@@ -444,6 +510,117 @@ mod tests {
         assert!(events.iter().any(|e| matches!(e, ScaleEvent::TimerStarted { .. })));
     }
     
+    // Replays scale timestamps and returns the sample index the timer was declared stopped at
+    fn stopped_at(timestamps: &[u32]) -> Option<usize> {
+        let mut detector = ScaleEventDetector::new();
+        timestamps.iter().position(|&timestamp_ms| {
+            let data = ScaleData {
+                timestamp_ms,
+                weight_g: 0.0,
+                flow_rate_g_per_s: 0.0,
+                battery_percent: 100,
+                timer_running: timestamp_ms > 0,
+                received_at: Instant::now(),
+            };
+            detector
+                .process_data(&data)
+                .iter()
+                .any(|event| matches!(event, ScaleEvent::TimerStopped { .. }))
+        })
+    }
+
+    #[test]
+    fn test_duplicate_timer_frame_keeps_brewing() {
+        assert_eq!(stopped_at(&[1000, 1100, 1200, 1200, 1300, 1400, 1500]), None);
+        // One zeroed frame mid-brew is not a reset either
+        assert_eq!(stopped_at(&[1000, 1100, 0, 1200, 1300]), None);
+    }
+
+    #[test]
+    fn test_frozen_timer_stops_after_consecutive_samples() {
+        assert_eq!(stopped_at(&[1000, 1100, 1200, 1200, 1200, 1200]), Some(4));
+        assert_eq!(stopped_at(&[1000, 1100, 1200, 0, 0]), Some(4));
+        // A repeat, a fresh tick, then a repeat again is not a freeze
+        assert_eq!(stopped_at(&[1000, 1000, 1100, 1100, 1200]), None);
+    }
+
+    #[test]
+    fn test_reset_timer_is_not_frozen() {
+        assert_eq!(stopped_at(&[0, 0, 0, 0]), None);
+    }
+
+    // Replays (weight, flow) samples and returns the indices that look like a shutdown
+    fn shutdown_samples(samples: &[(f32, f32)]) -> Vec<usize> {
+        let detector = ScaleEventDetector::new();
+        let mut previous_weight_g = None;
+        samples
+            .iter()
+            .enumerate()
+            .filter(|&(i, &(weight_g, flow_rate_g_per_s))| {
+                let data = ScaleData {
+                    timestamp_ms: i as u32 * 100,
+                    weight_g,
+                    flow_rate_g_per_s,
+                    battery_percent: 80,
+                    timer_running: true,
+                    received_at: Instant::from_millis(i as u64 * 100),
+                };
+                detector.looks_like_shutdown(previous_weight_g.replace(weight_g), &data)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_shutdown_sequence_detected_with_zeroed_flow() {
+        // Bookoo powering off mid-tare: weight swings wildly while flow already reads zero
+        let frames = [(0.0, 0.0), (0.1, 0.0), (187.4, 0.0), (-62.3, 0.0), (0.0, 0.0)];
+        assert_eq!(shutdown_samples(&frames), [2, 3, 4]);
+    }
+
+    #[test]
+    fn test_shutdown_sequence_detected_by_flow() {
+        let frames = [(0.0, 0.0), (3.2, 31.8), (6.4, 64.0)];
+        assert_eq!(shutdown_samples(&frames), [1, 2]);
+    }
+
+    #[test]
+    fn test_fast_pour_is_not_shutdown() {
+        // Pour-over kettle at ~15g/s, the fastest legitimate flow seen on the scale
+        let frames: Vec<(f32, f32)> = (0..30).map(|i| (i as f32 * 1.5, 15.0)).collect();
+        assert!(shutdown_samples(&frames).is_empty());
+
+        // A cup set down right as the timer starts stays under the jump limit
+        assert!(shutdown_samples(&[(0.0, 0.0), (8.5, 4.2), (9.0, 5.0)]).is_empty());
+    }
+
+    #[test]
+    fn test_timer_start_during_shutdown_ignored() {
+        let mut detector = ScaleEventDetector::new();
+        let frame = |timestamp_ms: u32, weight_g: f32| ScaleData {
+            timestamp_ms,
+            weight_g,
+            flow_rate_g_per_s: 0.0,
+            battery_percent: 100,
+            timer_running: timestamp_ms > 0,
+            received_at: Instant::now(),
+        };
+        assert!(detector.process_data(&frame(0, 0.1)).is_empty());
+        let events = detector.process_data(&frame(100, 187.4));
+        let started = |e: &ScaleEvent| {
+            matches!(e, ScaleEvent::TimerStarted { .. } | ScaleEvent::ButtonPressed(_))
+        };
+        assert!(!events.iter().any(started));
+        assert!(!detector.is_timer_running());
+
+        // Thresholds from config survive a reset
+        detector.set_shutdown_thresholds(25.0, 500.0);
+        detector.reset();
+        assert!(detector.process_data(&frame(0, 0.1)).is_empty());
+        let events = detector.process_data(&frame(100, 187.4));
+        assert!(events.iter().any(started));
+    }
+
     #[test]
     fn test_button_detection() {
         let mut detector = ScaleEventDetector::new();
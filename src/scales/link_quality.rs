@@ -0,0 +1,159 @@
+//! Scale link quality - signal strength, corrupt frames and reconnects folded into one 0-100 score
+//! so users can tell a flaky setup from a good one without reading the individual diagnostics
+
+use crate::types::{
+    LINK_QUALITY_FRAME_WINDOW, LINK_QUALITY_MAX_FAILED_FRACTION, LINK_QUALITY_MAX_RECONNECTS,
+    LINK_QUALITY_RECONNECT_WINDOW_MS, LINK_QUALITY_RSSI_BAD_DBM, LINK_QUALITY_RSSI_GOOD_DBM,
+};
+use embassy_time::{Duration, Instant};
+use heapless::Deque;
+use serde::Serialize;
+
+/// Weights of the three components in the score - signal strength leads, it predicts the others
+const RSSI_WEIGHT: f32 = 0.4;
+const FRAMES_WEIGHT: f32 = 0.3;
+const RECONNECTS_WEIGHT: f32 = 0.3;
+
+/// Link quality as served in status
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LinkQualityReport {
+    /// 0 (unusable) to 100 (solid)
+    pub score: u8,
+    /// Smoothed signal strength, null until the stack has reported one
+    pub rssi_dbm: Option<i8>,
+    /// Share of recent weight frames that failed to parse
+    pub failed_frame_percent: f32,
+    /// Connections lost in the last hour
+    pub reconnects_last_hour: u8,
+}
+
+/// Rolling link statistics - lives as long as the scale client so reconnects are counted across them
+#[derive(Debug, Default)]
+pub struct LinkQualityTracker {
+    // Recent weight frames, true when the frame parsed
+    frames: Deque<bool, LINK_QUALITY_FRAME_WINDOW>,
+    drops: Deque<Instant, LINK_QUALITY_MAX_RECONNECTS>,
+    rssi_dbm: Option<f32>,
+}
+
+impl LinkQualityTracker {
+    pub fn record_frame(&mut self, parsed: bool) {
+        if self.frames.is_full() {
+            self.frames.pop_front();
+        }
+        let _ = self.frames.push_back(parsed);
+    }
+
+    /// Fold in an RSSI reading - smoothed, single readings swing several dB
+    pub fn record_rssi(&mut self, rssi_dbm: i8) {
+        let rssi = rssi_dbm as f32;
+        self.rssi_dbm = Some(self.rssi_dbm.map_or(rssi, |smoothed| smoothed * 0.7 + rssi * 0.3));
+    }
+
+    /// A live connection was lost - the scale client will have to reconnect
+    pub fn record_drop(&mut self, at: Instant) {
+        // Past the cap the score is already zero, the oldest drop can go
+        if self.drops.is_full() {
+            self.drops.pop_front();
+        }
+        let _ = self.drops.push_back(at);
+    }
+
+    /// Forget the signal strength of a link that is gone - frames and drops keep their history
+    pub fn clear_rssi(&mut self) {
+        self.rssi_dbm = None;
+    }
+
+    pub fn report(&self, now: Instant) -> LinkQualityReport {
+        let window = Duration::from_millis(LINK_QUALITY_RECONNECT_WINDOW_MS);
+        let reconnects = self
+            .drops
+            .iter()
+            .filter(|&&at| now.saturating_duration_since(at) < window)
+            .count();
+        let failed = self.frames.iter().filter(|&&parsed| !parsed).count();
+        let failed_fraction = if self.frames.is_empty() {
+            0.0
+        } else {
+            failed as f32 / self.frames.len() as f32
+        };
+
+        let frames_score = 1.0 - failed_fraction / LINK_QUALITY_MAX_FAILED_FRACTION;
+        let reconnects_score = 1.0 - reconnects as f32 / LINK_QUALITY_MAX_RECONNECTS as f32;
+        let mut weighted = FRAMES_WEIGHT * frames_score.clamp(0.0, 1.0)
+            + RECONNECTS_WEIGHT * reconnects_score.clamp(0.0, 1.0);
+        let mut total_weight = FRAMES_WEIGHT + RECONNECTS_WEIGHT;
+        // Without a reading the score rests on the other two alone
+        if let Some(rssi) = self.rssi_dbm {
+            let span = (LINK_QUALITY_RSSI_GOOD_DBM - LINK_QUALITY_RSSI_BAD_DBM) as f32;
+            let rssi_score = (rssi - LINK_QUALITY_RSSI_BAD_DBM as f32) / span;
+            weighted += RSSI_WEIGHT * rssi_score.clamp(0.0, 1.0);
+            total_weight += RSSI_WEIGHT;
+        }
+
+        LinkQualityReport {
+            score: (weighted / total_weight * 100.0).round() as u8,
+            rssi_dbm: self.rssi_dbm.map(|rssi| rssi.round() as i8),
+            failed_frame_percent: failed_fraction * 100.0,
+            reconnects_last_hour: reconnects as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LINK_QUALITY_WARN_SCORE;
+
+    #[test]
+    fn test_clean_strong_link_scores_full() {
+        let mut tracker = LinkQualityTracker::default();
+        for _ in 0..100 {
+            tracker.record_frame(true);
+        }
+        tracker.record_rssi(-50);
+
+        let report = tracker.report(Instant::from_millis(0));
+        assert_eq!(report.score, 100);
+        assert_eq!(report.rssi_dbm, Some(-50));
+        assert_eq!(report.failed_frame_percent, 0.0);
+        assert_eq!(report.reconnects_last_hour, 0);
+    }
+
+    #[test]
+    fn test_weak_flaky_link_scores_below_warning() {
+        let mut tracker = LinkQualityTracker::default();
+        for i in 0..100 {
+            // One frame in ten corrupted
+            tracker.record_frame(i % 10 != 0);
+        }
+        tracker.record_rssi(-88);
+        let now = Instant::from_millis(3_600_000);
+        tracker.record_drop(now - Duration::from_secs(600));
+        tracker.record_drop(now - Duration::from_secs(60));
+
+        let report = tracker.report(now);
+        assert_eq!(report.reconnects_last_hour, 2);
+        assert!((report.failed_frame_percent - 10.0).abs() < 1e-4);
+        assert!(report.score < LINK_QUALITY_WARN_SCORE, "score {}", report.score);
+    }
+
+    #[test]
+    fn test_old_reconnects_and_frames_roll_off() {
+        let mut tracker = LinkQualityTracker::default();
+        tracker.record_drop(Instant::from_millis(0));
+        for _ in 0..LINK_QUALITY_FRAME_WINDOW {
+            tracker.record_frame(false);
+        }
+        // A full window of good frames pushes every failure out
+        for _ in 0..LINK_QUALITY_FRAME_WINDOW {
+            tracker.record_frame(true);
+        }
+
+        let later = Instant::from_millis(LINK_QUALITY_RECONNECT_WINDOW_MS + 1);
+        let report = tracker.report(later);
+        assert_eq!(report.reconnects_last_hour, 0);
+        assert_eq!(report.failed_frame_percent, 0.0);
+        assert_eq!(report.score, 100);
+    }
+}
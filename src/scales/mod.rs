@@ -1,10 +1,13 @@
 pub mod bookoo;
+pub mod data_queue;
 pub mod event_detection;
+pub mod link_quality;
 pub mod protocol;
 pub mod simple_scanner;
 pub mod traits;
 
 pub use bookoo::*;
+pub use data_queue::*;
 pub use event_detection::*;
 pub use simple_scanner::*;
 pub use traits::*;
@@ -1,3 +1,4 @@
+use crate::scales::traits::{ScaleSettings, ScaleUnit};
 use crate::types::ScaleData;
 use embassy_time::Instant;
 use log::{debug, warn};
@@ -15,6 +16,91 @@ pub const START_TIMER_COMMAND: [u8; 6] = [0x03, 0x0A, 0x04, 0x00, 0x00, 0x0A];
 pub const STOP_TIMER_COMMAND: [u8; 6] = [0x03, 0x0A, 0x05, 0x00, 0x00, 0x0D];
 pub const RESET_TIMER_COMMAND: [u8; 6] = [0x03, 0x0A, 0x06, 0x00, 0x00, 0x0C];
 
+/// Byte order of a multi-byte reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+/// How a reading carries its sign
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignEncoding {
+    /// Never negative
+    Unsigned,
+    /// Magnitude only, sign in its own byte at `offset` - `'+'` (0x2B) is positive, anything else
+    /// negative. The Bookoo's scheme
+    SignByte { offset: usize },
+    /// Two's complement across the reading's full width - a 24-bit reading sign-extends from bit 23
+    TwosComplement,
+}
+
+/// Where a weight or flow reading sits in a notification frame and how to turn it into grams.
+/// Scale drivers describe their layout with this instead of hand-rolling the bit twiddling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightEncoding {
+    /// First byte of the reading
+    pub offset: usize,
+    /// Bytes in the reading, 1-4
+    pub width: usize,
+    pub byte_order: ByteOrder,
+    pub sign: SignEncoding,
+    /// Grams (or g/s) per raw count - 0.01 for a reading in hundredths
+    pub scale_factor: f32,
+}
+
+impl WeightEncoding {
+    /// Decoded reading, None if the frame is too short for it or `width` is out of range
+    pub fn decode(&self, frame: &[u8]) -> Option<f32> {
+        if !(1..=4).contains(&self.width) {
+            return None;
+        }
+        let bytes = frame.get(self.offset..self.offset + self.width)?;
+        let raw = match self.byte_order {
+            ByteOrder::BigEndian => bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32),
+            ByteOrder::LittleEndian => {
+                bytes.iter().rev().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+            }
+        };
+
+        let value = match self.sign {
+            SignEncoding::Unsigned => raw as f32,
+            SignEncoding::SignByte { offset } => {
+                let magnitude = raw as f32;
+                if *frame.get(offset)? == b'+' {
+                    magnitude
+                } else {
+                    -magnitude
+                }
+            }
+            SignEncoding::TwosComplement => {
+                // Move the reading's top bit up to bit 31 and shift back, dragging the sign along
+                let unused_bits = 32 - 8 * self.width as u32;
+                (((raw << unused_bits) as i32) >> unused_bits) as f32
+            }
+        };
+        Some(value * self.scale_factor)
+    }
+}
+
+/// Bookoo weight: 24-bit big endian hundredths of a gram, sign in byte 6
+pub const BOOKOO_WEIGHT_ENCODING: WeightEncoding = WeightEncoding {
+    offset: 7,
+    width: 3,
+    byte_order: ByteOrder::BigEndian,
+    sign: SignEncoding::SignByte { offset: 6 },
+    scale_factor: 0.01,
+};
+
+/// Bookoo flow: 16-bit big endian hundredths of a g/s, sign in byte 10
+pub const BOOKOO_FLOW_ENCODING: WeightEncoding = WeightEncoding {
+    offset: 11,
+    width: 2,
+    byte_order: ByteOrder::BigEndian,
+    sign: SignEncoding::SignByte { offset: 10 },
+    scale_factor: 0.01,
+};
+
 fn calculate_xor_checksum(data: &[u8]) -> u8 {
     data.iter().fold(0, |acc, &byte| acc ^ byte)
 }
@@ -66,15 +152,9 @@ pub fn parse_scale_data(data: &[u8]) -> Option<ScaleData> {
     // Parse timestamp (3 bytes, big endian in Python implementation)
     let timestamp_ms = ((data[2] as u32) << 16) | ((data[3] as u32) << 8) | (data[4] as u32);
 
-    // Parse weight with sign (Python implementation)
-    let weight_sign = if data[6] == 0x2B { 1.0 } else { -1.0 }; // 0x2B = '+', 0x2D = '-'
-    let weight_raw = ((data[7] as u32) << 16) | ((data[8] as u32) << 8) | (data[9] as u32);
-    let weight_g = (weight_raw as f32 / 100.0) * weight_sign;
-
-    // Parse flow rate with sign (Python implementation)
-    let flow_sign = if data[10] == 0x2B { 1.0 } else { -1.0 }; // 0x2B = '+', 0x2D = '-'
-    let flow_raw = ((data[11] as u16) << 8) | (data[12] as u16);
-    let flow_rate_g_per_s = (flow_raw as f32 / 100.0) * flow_sign;
+    // Length was checked above, so both readings fit
+    let weight_g = BOOKOO_WEIGHT_ENCODING.decode(data)?;
+    let flow_rate_g_per_s = BOOKOO_FLOW_ENCODING.decode(data)?;
 
     let battery_percent = data[13];
 
@@ -93,6 +173,25 @@ pub fn parse_scale_data(data: &[u8]) -> Option<ScaleData> {
     })
 }
 
+/// Settings the Bookoo repeats in every weight frame: byte 5 is the unit (0 = grams) and
+/// bytes 14-15 the standby minutes before auto-off. Expects a frame `parse_scale_data` accepted
+pub fn parse_scale_settings(data: &[u8]) -> Option<ScaleSettings> {
+    if data.len() != 20 {
+        return None;
+    }
+
+    let unit = match data[5] {
+        0x00 => ScaleUnit::Grams,
+        code => ScaleUnit::Other(code),
+    };
+    let auto_off_minutes = ((data[14] as u16) << 8) | (data[15] as u16);
+
+    Some(ScaleSettings {
+        unit,
+        auto_off_minutes,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +203,88 @@ mod tests {
         assert_eq!(checksum, 0x08);
     }
 
+    // Weight frame for 36.50g at 1.20g/s with the given unit code and standby minutes
+    fn weight_frame(unit: u8, standby_minutes: u16) -> [u8; 20] {
+        let mut frame = [
+            0x03, 0x0B, 0x00, 0x4E, 0x20, unit, 0x2B, 0x00, 0x0E, 0x42, 0x2B, 0x00, 0x78, 0x50,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        frame[14..16].copy_from_slice(&standby_minutes.to_be_bytes());
+        frame[19] = calculate_xor_checksum(&frame[..19]);
+        frame
+    }
+
+    #[test]
+    fn test_settings_read_from_weight_frame() {
+        let frame = weight_frame(0x00, 5);
+        assert!(parse_scale_data(&frame).is_some());
+        assert_eq!(
+            parse_scale_settings(&frame),
+            Some(ScaleSettings {
+                unit: ScaleUnit::Grams,
+                auto_off_minutes: 5
+            })
+        );
+
+        let frame = weight_frame(0x01, 0);
+        let settings = parse_scale_settings(&frame).unwrap();
+        assert_eq!(settings.unit, ScaleUnit::Other(0x01));
+        assert_eq!(settings.auto_off_minutes, 0);
+
+        assert_eq!(parse_scale_settings(&frame[..19]), None);
+    }
+
+    #[test]
+    fn test_bookoo_negative_weight_and_flow() {
+        let mut frame = weight_frame(0x00, 0);
+        assert!((parse_scale_data(&frame).unwrap().weight_g - 36.5).abs() < 1e-4);
+
+        // Cup lifted after a tare: -12.34g, draining at -0.50g/s
+        frame[6] = b'-';
+        frame[7..10].copy_from_slice(&[0x00, 0x04, 0xD2]);
+        frame[10] = b'-';
+        frame[11..13].copy_from_slice(&[0x00, 0x32]);
+        frame[19] = calculate_xor_checksum(&frame[..19]);
+
+        let data = parse_scale_data(&frame).unwrap();
+        assert!((data.weight_g + 12.34).abs() < 1e-4);
+        assert!((data.flow_rate_g_per_s + 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_twos_complement_sign_bit_per_width() {
+        let encoding = |width, byte_order| WeightEncoding {
+            offset: 1,
+            width,
+            byte_order,
+            sign: SignEncoding::TwosComplement,
+            scale_factor: 1.0,
+        };
+        let decode = |encoding: WeightEncoding, frame: &[u8]| encoding.decode(frame).unwrap();
+
+        // 24-bit: bit 23 is the sign, so 0x800000 is the most negative reading
+        let be24 = encoding(3, ByteOrder::BigEndian);
+        assert_eq!(decode(be24, &[0xAA, 0xFF, 0xFF, 0x85]), -123.0);
+        assert_eq!(decode(be24, &[0xAA, 0x7F, 0xFF, 0xFF]), 8_388_607.0);
+        assert_eq!(decode(be24, &[0xAA, 0x80, 0x00, 0x00]), -8_388_608.0);
+        assert_eq!(decode(be24, &[0xAA, 0x00, 0x01, 0x71]), 369.0);
+
+        // The same 24 bits inside a 32-bit reading are positive - the sign bit moved to bit 31
+        let be32 = encoding(4, ByteOrder::BigEndian);
+        assert_eq!(decode(be32, &[0xAA, 0x00, 0xFF, 0xFF, 0x85]), 16_777_093.0);
+        assert_eq!(decode(be32, &[0xAA, 0xFF, 0xFF, 0xFF, 0x85]), -123.0);
+
+        let le24 = encoding(3, ByteOrder::LittleEndian);
+        assert_eq!(decode(le24, &[0xAA, 0x85, 0xFF, 0xFF]), -123.0);
+
+        let hundredths = WeightEncoding { scale_factor: 0.01, ..be24 };
+        assert!((decode(hundredths, &[0xAA, 0xFF, 0xFF, 0x85]) + 1.23).abs() < 1e-4);
+
+        // Too short for the reading, or a width that doesn't fit in 32 bits
+        assert_eq!(be32.decode(&[0xAA, 0xFF, 0xFF, 0x85]), None);
+        assert_eq!(encoding(5, ByteOrder::BigEndian).decode(&[0; 8]), None);
+    }
+
     #[test]
     fn test_checksum_verification() {
         let valid_data = [0x03, 0x0A, 0x01, 0x00, 0x00, 0x08];
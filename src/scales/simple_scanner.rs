@@ -385,7 +385,7 @@ mod tests {
 
     #[test]
     fn test_scale_manager_creation() {
-        let data_channel = Arc::new(embassy_sync::channel::Channel::new());
+        let data_channel = Arc::new(ScaleDataChannel::default());
         let status_channel = Arc::new(embassy_sync::channel::Channel::new());
         let config = ScanConfig::default();
 
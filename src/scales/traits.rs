@@ -3,8 +3,12 @@
 //! This allows the system to work with Bookoo, Acaia, Hario, or other smart scales
 //! by implementing a common interface.
 
+use crate::ble::Device;
+use crate::scales::data_queue::ScaleDataQueue;
+use crate::scales::link_quality::LinkQualityReport;
 use crate::types::ScaleData;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, signal::Signal};
+use serde::Serialize;
 
 // Command types that all scales should support
 #[derive(Debug, Clone)]
@@ -13,6 +17,33 @@ pub enum ScaleCommand {
     StartTimer,
     StopTimer,
     ResetTimer,
+    /// Link timing rather than a scale protocol command
+    SetConnectionProfile(ConnectionProfile),
+    /// List nearby devices for the user to pick from
+    ScanDevices { duration_ms: u32 },
+    /// Drop the current scale and connect to this address from the last scan
+    ConnectToDevice { address: [u8; 6] },
+    /// Disconnect and end the scale task - the controller is shutting down
+    Shutdown,
+}
+
+// How quickly the scale link should deliver samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionProfile {
+    /// Lowest latency while brewing
+    Fast,
+    /// Power saving while idle
+    Slow,
+}
+
+/// Steps of a scale connection attempt, in order - where a stalled connection got stuck
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStage {
+    Scanning,
+    Connecting,
+    Connected { device_name: String },
+    Discovering,
+    Subscribed,
 }
 
 // Scale capability flags
@@ -22,9 +53,27 @@ pub struct ScaleCapabilities {
     pub has_flow_rate: bool,
     pub has_battery_level: bool,
     pub supports_tare: bool,
+    /// Powers itself off when idle - such scales get periodic keep-alive reads
     pub supports_auto_off: bool,
 }
 
+/// Weight unit the scale itself is set to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleUnit {
+    Grams,
+    /// Any other unit code the scale reports - its weights can't be trusted as grams
+    Other(u8),
+}
+
+/// Settings configured on the scale itself, as read back from it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ScaleSettings {
+    pub unit: ScaleUnit,
+    /// Idle minutes before the scale powers itself off, 0 when it stays on
+    pub auto_off_minutes: u16,
+}
+
 // Scale information
 #[derive(Debug, Clone)]
 pub struct ScaleInfo {
@@ -32,12 +81,18 @@ pub struct ScaleInfo {
     pub model: String,
     pub version: Option<String>,
     pub capabilities: ScaleCapabilities,
+    /// None until read, or when the scale doesn't report its settings
+    pub settings: Option<ScaleSettings>,
 }
 
 // Status channel for connection state
 pub type StatusChannel = Channel<CriticalSectionRawMutex, bool, 2>;
-pub type ScaleDataChannel = Channel<CriticalSectionRawMutex, ScaleData, 50>; // 5 seconds buffer at 10Hz
+pub type ScaleDataChannel = ScaleDataQueue; // Never discards the newest sample when full
 pub type ScaleCommandChannel = Channel<CriticalSectionRawMutex, ScaleCommand, 20>; // More command buffer
+pub type ScaleInfoSignal = Signal<CriticalSectionRawMutex, ScaleInfo>; // Latest info read from the device
+pub type NearbyDevicesSignal = Signal<CriticalSectionRawMutex, Vec<Device>>; // Latest scan results
+pub type LinkQualitySignal = Signal<CriticalSectionRawMutex, LinkQualityReport>; // Latest score
+pub type ConnectionStageChannel = Channel<CriticalSectionRawMutex, ConnectionStage, 8>; // Every step, in order
 
 /// Main trait that all smart scales must implement
 /// This trait is object-safe to support dynamic dispatch
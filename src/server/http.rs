@@ -1,14 +1,40 @@
-use crate::types::SystemState;
+use crate::ble::{BleClient, Device};
+use crate::brewing::profile::FlowProfile;
+use crate::brewing::recorder::SharedBrewRecording;
+use crate::brewing::trace::SharedBrewTrace;
+use crate::brewing::states::{
+    BrewSnapshot, DoseStatus, OvershootStatus, SystemState as MachineState,
+};
+use crate::hardware::relay::{RelayBootCheck, RelayStats};
+use crate::scales::link_quality::LinkQualityReport;
+use crate::scales::traits::{ScaleInfo, ScaleSettings};
+use crate::state::recent_log_entries;
+use crate::system::event_stats::{EventCount, EventStats};
+use crate::system::events::{EventBus, ScaleEvent, SystemEvent, UserEvent};
+use crate::system::self_test::SelfTestReport;
+use crate::system::safety::SafetyStatus;
+use crate::system::shutdown::ShutdownToken;
+use crate::types::{
+    AutoTareProfile, AutoTareState, BrewConfig, BrewProgress, ConfirmableCommand, LogLevel,
+    OvershootLearningSchedule, RadioPriority, ScaleDetectorStatus, StopMode, SystemState,
+    WeightUnit, LOG_BUFFER_SIZE, RELAY_TEST_MS,
+};
 use anyhow;
+use embassy_futures::select::{select, Either};
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, mutex::Mutex};
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
+use esp_idf_svc::hal::reset::ResetReason;
+use esp_idf_svc::http::server::ws::{EspHttpWsConnection, EspHttpWsDetachedSender};
 use esp_idf_svc::http::server::{Configuration, EspHttpServer};
 use esp_idf_svc::http::Method;
 use esp_idf_svc::io::Write;
+use esp_idf_svc::sys::EspError;
+use esp_idf_svc::ws::FrameType;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
 
 pub type WebSocketCommandChannel = Channel<CriticalSectionRawMutex, WebSocketCommand, 10>;
 
@@ -17,12 +43,117 @@ pub type WebSocketCommandChannel = Channel<CriticalSectionRawMutex, WebSocketCom
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type")]
 pub enum WebSocketCommand {
+    /// `unit` defaults to grams so integrations that predate it keep working
     #[serde(rename = "set_target_weight")]
-    SetTargetWeight { weight: f32 },
+    SetTargetWeight {
+        weight: f32,
+        #[serde(default)]
+        unit: WeightUnit,
+    },
+    /// Unit the web UI shows weights in - internal values stay in grams
+    #[serde(rename = "set_weight_unit")]
+    SetWeightUnit { unit: WeightUnit },
+    /// Batch brewing - the brew target becomes the per-cup target times this
+    #[serde(rename = "set_cups")]
+    SetCups { cups: u8 },
     #[serde(rename = "set_auto_tare")]
     SetAutoTare { enabled: bool },
+    #[serde(rename = "set_tare_before_brew")]
+    SetTareBeforeBrew { enabled: bool },
+    /// Make `start_timer` or `stop_timer` need a second press within a few seconds
+    #[serde(rename = "set_command_confirmation")]
+    SetCommandConfirmation { command: ConfirmableCommand, enabled: bool },
     #[serde(rename = "set_predictive_stop")]
     SetPredictiveStop { enabled: bool },
+    /// Let overshoot learning readiness switch predictive stop - `set_predictive_stop` leaves auto mode
+    #[serde(rename = "set_predictive_stop_auto")]
+    SetPredictiveStopAuto { enabled: bool },
+    #[serde(rename = "set_auto_tare_empty_threshold")]
+    SetAutoTareEmptyThreshold { threshold_g: f32 },
+    #[serde(rename = "set_auto_tare_stable_readings")]
+    SetAutoTareStableReadings { readings: usize },
+    #[serde(rename = "set_auto_tare_profile")]
+    SetAutoTareProfile { profile: AutoTareProfile },
+    #[serde(rename = "set_auto_tare_cooldown")]
+    SetAutoTareCooldown { secs: u32 },
+    /// How long a placed object must hold still before auto-tare fires
+    #[serde(rename = "set_auto_tare_hold")]
+    SetAutoTareHold { hold_ms: u32 },
+    /// Predictive stop window: opens at learned delay + margin, closes at that times the multiplier
+    #[serde(rename = "set_prediction_window")]
+    SetPredictionWindow { safety_margin_ms: u32, multiplier: f32 },
+    /// Brews after an overshoot reset that deliberately stop early
+    #[serde(rename = "set_overshoot_warmup")]
+    SetOvershootWarmup { brews: u32 },
+    /// Where the settled weight should land relative to the target, negative to stop short
+    #[serde(rename = "set_overshoot_target")]
+    SetOvershootTarget { grams: f32 },
+    /// Overshoot learning-rate schedule - omitted fields take their defaults
+    #[serde(rename = "set_overshoot_learning")]
+    SetOvershootLearning(OvershootLearningSchedule),
+    /// Beverage-to-dose ratio, null for a fixed target
+    #[serde(rename = "set_brew_ratio")]
+    SetBrewRatio { ratio: Option<f32> },
+    /// Query - answered directly with a `ConfigMsg` holding the whole config
+    #[serde(rename = "get_config")]
+    GetConfig,
+    /// Replace the whole config in one step - validated as a unit, omitted fields take defaults
+    #[serde(rename = "set_config")]
+    SetConfig { config: Box<BrewConfig> },
+    /// Enter or leave dose weighing - auto-tare pauses and stable readings become the pending dose
+    #[serde(rename = "set_dose_weighing")]
+    SetDoseWeighing { active: bool },
+    /// Lock in the pending dose
+    #[serde(rename = "confirm_dose")]
+    ConfirmDose,
+    /// What ends a shot: target weight, flow ceasing, or elapsed time
+    #[serde(rename = "set_stop_mode")]
+    SetStopMode { mode: StopMode },
+    /// How long flow must stay stopped to count as stopped
+    #[serde(rename = "set_flow_stop_hold")]
+    SetFlowStopHold { hold_ms: u32 },
+    /// How long a mid-brew scale drop may last and still resume the shot
+    #[serde(rename = "set_scale_reconnect_grace")]
+    SetScaleReconnectGrace { grace_ms: u32 },
+    /// How still the weight must be before settling finishes
+    #[serde(rename = "set_settle_stability")]
+    SetSettleStability { stability_g: f32 },
+    /// Shot length in time stop mode
+    #[serde(rename = "set_brew_time")]
+    SetBrewTime { brew_time_ms: u32 },
+    /// Flow curve the pump follows from the next shot (PWM relays only) - null turns it off
+    #[serde(rename = "set_flow_profile")]
+    SetFlowProfile { profile: Option<FlowProfile> },
+    /// Flow and timing gates for predictive stop and overshoot recording
+    #[serde(rename = "set_prediction_thresholds")]
+    SetPredictionThresholds {
+        min_flow_for_prediction: f32,
+        flow_stopped_threshold: f32,
+        prediction_start_delay_ms: u32,
+    },
+    #[serde(rename = "set_log_level")]
+    SetLogLevel { level: LogLevel },
+    /// Advertised name the scale scan looks for - takes effect on the next scan
+    #[serde(rename = "set_scale_name_prefix")]
+    SetScaleNamePrefix { prefix: String },
+    /// Weight rate (g/s) beyond which a lone frame is dropped as corrupt
+    #[serde(rename = "set_weight_rate_limit")]
+    SetWeightRateLimit { limit_g_per_s: f32 },
+    /// Keep-alive interval for scales that power off when idle - 0 disables
+    #[serde(rename = "set_scale_keep_alive")]
+    SetScaleKeepAlive { interval_ms: u32 },
+    /// How often the brew trace keeps a weight/flow sample
+    #[serde(rename = "set_brew_trace_interval")]
+    SetBrewTraceInterval { interval_ms: u32 },
+    /// UI broadcast interval while brewing, 0 to keep full rate
+    #[serde(rename = "set_ble_priority")]
+    SetBlePriority { interval_ms: u32 },
+    /// Shortest gap between OLED redraws
+    #[serde(rename = "set_display_refresh")]
+    SetDisplayRefresh { interval_ms: u32 },
+    /// Shortest gap between UI updates that only carry new weight, 0 for every sample
+    #[serde(rename = "set_weight_stream")]
+    SetWeightStream { interval_ms: u32 },
     #[serde(rename = "tare_scale")]
     TareScale,
     #[serde(rename = "start_timer")]
@@ -33,57 +164,547 @@ pub enum WebSocketCommand {
     ResetTimer,
     #[serde(rename = "reset_overshoot")]
     ResetOvershoot,
+    /// Overshoot model brews learn into - a new name starts a fresh profile
+    #[serde(rename = "select_profile")]
+    SelectProfile { name: String },
+    /// Support snapshot - BLE, NVS, relay, WiFi and scale checks, reported in `/api/status`
+    #[serde(rename = "run_self_test")]
+    RunSelfTest,
+    /// Log how often each kind of event fired since the last ask - totals are in `/metrics`
+    #[serde(rename = "log_event_stats")]
+    LogEventStats,
+    /// Wiring check - `duration_ms` defaults to a short click and is clamped to a safe maximum
     #[serde(rename = "test_relay")]
-    TestRelay,
+    TestRelay {
+        #[serde(default)]
+        duration_ms: Option<u32>,
+    },
+    /// Scale picker - results appear in `nearby_devices` of the next state snapshot
+    #[serde(rename = "scan_devices")]
+    ScanDevices { duration_ms: u32 },
+    /// Scale picker - `AA:BB:CC:DD:EE:FF` address from the last scan
+    #[serde(rename = "connect_to_device")]
+    ConnectToDevice { address: String },
+    /// Killswitch - when disabled the state machine ignores scale input and never drives the relay
+    #[serde(rename = "set_system_enabled")]
+    SetSystemEnabled { enabled: bool },
+    /// Query - answered directly by the HTTP handler with recent log entries
+    #[serde(rename = "get_logs")]
+    GetLogs { limit: Option<usize> },
+    /// Query - answered directly by the HTTP handler with a `StateMsg`
+    #[serde(rename = "get_state")]
+    GetState,
+    /// Start capturing brew inputs for offline replay (discards the previous recording)
+    #[serde(rename = "start_recording")]
+    StartRecording,
+    #[serde(rename = "stop_recording")]
+    StopRecording,
+    /// Query - answered directly by the HTTP handler with the captured recording
+    #[serde(rename = "get_recording")]
+    GetRecording,
+    /// Query - answered directly by the HTTP handler with the last brew's samples as CSV
+    #[serde(rename = "export_last_brew_csv")]
+    ExportLastBrewCsv,
+    /// Heartbeat - answered directly with a `PongMsg` echoing the nonce
+    #[serde(rename = "ping")]
+    Ping { nonce: u32 },
 }
 
+impl WebSocketCommand {
+    /// The user event a web command stands for - the single mapping used by every command path.
+    /// None for queries and recorder control, which the HTTP handler answers itself.
+    pub fn into_user_event(self) -> Option<UserEvent> {
+        match self {
+            WebSocketCommand::SetTargetWeight { weight, unit } => {
+                Some(UserEvent::SetTargetWeight(unit.to_grams(weight)))
+            }
+            WebSocketCommand::SetWeightUnit { unit } => Some(UserEvent::SetWeightUnit(unit)),
+            WebSocketCommand::SetCups { cups } => Some(UserEvent::SetCups(cups)),
+            WebSocketCommand::SetAutoTare { enabled } => Some(UserEvent::SetAutoTare(enabled)),
+            WebSocketCommand::SetTareBeforeBrew { enabled } => {
+                Some(UserEvent::SetTareBeforeBrew(enabled))
+            }
+            WebSocketCommand::SetCommandConfirmation { command, enabled } => {
+                Some(UserEvent::SetCommandConfirmation { command, enabled })
+            }
+            WebSocketCommand::SetPredictiveStop { enabled } => {
+                Some(UserEvent::SetPredictiveStop(enabled))
+            }
+            WebSocketCommand::SetPredictiveStopAuto { enabled } => {
+                Some(UserEvent::SetPredictiveStopAuto(enabled))
+            }
+            WebSocketCommand::SetAutoTareEmptyThreshold { threshold_g } => {
+                Some(UserEvent::SetAutoTareEmptyThreshold(threshold_g))
+            }
+            WebSocketCommand::SetAutoTareStableReadings { readings } => {
+                Some(UserEvent::SetAutoTareStableReadings(readings))
+            }
+            WebSocketCommand::SetAutoTareProfile { profile } => {
+                Some(UserEvent::SetAutoTareProfile(profile))
+            }
+            WebSocketCommand::SetAutoTareCooldown { secs } => Some(UserEvent::SetAutoTareCooldown(secs)),
+            WebSocketCommand::SetAutoTareHold { hold_ms } => Some(UserEvent::SetAutoTareHold(hold_ms)),
+            WebSocketCommand::SetPredictionWindow {
+                safety_margin_ms,
+                multiplier,
+            } => Some(UserEvent::SetPredictionWindow {
+                safety_margin_ms,
+                multiplier,
+            }),
+            WebSocketCommand::SetOvershootWarmup { brews } => Some(UserEvent::SetOvershootWarmup(brews)),
+            WebSocketCommand::SetOvershootTarget { grams } => {
+                Some(UserEvent::SetOvershootTarget(grams))
+            }
+            WebSocketCommand::SetOvershootLearning(schedule) => {
+                Some(UserEvent::SetOvershootLearning(schedule))
+            }
+            WebSocketCommand::SetBrewRatio { ratio } => Some(UserEvent::SetBrewRatio(ratio)),
+            WebSocketCommand::SetConfig { config } => Some(UserEvent::SetConfig(config)),
+            WebSocketCommand::SetDoseWeighing { active } => Some(UserEvent::SetDoseWeighing(active)),
+            WebSocketCommand::ConfirmDose => Some(UserEvent::ConfirmDose),
+            WebSocketCommand::SetStopMode { mode } => Some(UserEvent::SetStopMode(mode)),
+            WebSocketCommand::SetFlowStopHold { hold_ms } => Some(UserEvent::SetFlowStopHold(hold_ms)),
+            WebSocketCommand::SetScaleReconnectGrace { grace_ms } => {
+                Some(UserEvent::SetScaleReconnectGrace(grace_ms))
+            }
+            WebSocketCommand::SetSettleStability { stability_g } => {
+                Some(UserEvent::SetSettleStability(stability_g))
+            }
+            WebSocketCommand::SetBrewTime { brew_time_ms } => Some(UserEvent::SetBrewTime(brew_time_ms)),
+            WebSocketCommand::SetFlowProfile { profile } => {
+                Some(UserEvent::SetFlowProfile(profile))
+            }
+            WebSocketCommand::SetPredictionThresholds {
+                min_flow_for_prediction,
+                flow_stopped_threshold,
+                prediction_start_delay_ms,
+            } => Some(UserEvent::SetPredictionThresholds {
+                min_flow_for_prediction,
+                flow_stopped_threshold,
+                prediction_start_delay_ms,
+            }),
+            WebSocketCommand::SetLogLevel { level } => Some(UserEvent::SetLogLevel(level)),
+            WebSocketCommand::SetScaleNamePrefix { prefix } => {
+                Some(UserEvent::SetScaleNamePrefix(prefix))
+            }
+            WebSocketCommand::SetWeightRateLimit { limit_g_per_s } => {
+                Some(UserEvent::SetWeightRateLimit(limit_g_per_s))
+            }
+            WebSocketCommand::SetScaleKeepAlive { interval_ms } => {
+                Some(UserEvent::SetScaleKeepAlive(interval_ms))
+            }
+            WebSocketCommand::SetBrewTraceInterval { interval_ms } => {
+                Some(UserEvent::SetBrewTraceInterval(interval_ms))
+            }
+            WebSocketCommand::SetBlePriority { interval_ms } => {
+                Some(UserEvent::SetBlePriorityBroadcast(interval_ms))
+            }
+            WebSocketCommand::SetDisplayRefresh { interval_ms } => {
+                Some(UserEvent::SetDisplayRefresh(interval_ms))
+            }
+            WebSocketCommand::SetWeightStream { interval_ms } => {
+                Some(UserEvent::SetWeightStream(interval_ms))
+            }
+            WebSocketCommand::TareScale => Some(UserEvent::TareScale),
+            WebSocketCommand::StartTimer => Some(UserEvent::StartBrewing),
+            WebSocketCommand::StopTimer => Some(UserEvent::StopBrewing),
+            WebSocketCommand::ResetTimer => Some(UserEvent::ResetTimer),
+            WebSocketCommand::TestRelay { duration_ms } => Some(UserEvent::TestRelay {
+                duration_ms: duration_ms.unwrap_or(RELAY_TEST_MS),
+            }),
+            WebSocketCommand::RunSelfTest => Some(UserEvent::RunSelfTest),
+            WebSocketCommand::LogEventStats => Some(UserEvent::LogEventStats),
+            WebSocketCommand::ScanDevices { duration_ms } => {
+                Some(UserEvent::ScanDevices { duration_ms })
+            }
+            WebSocketCommand::ConnectToDevice { address } => {
+                Some(UserEvent::ConnectToDevice { address })
+            }
+            WebSocketCommand::ResetOvershoot => Some(UserEvent::ResetOvershoot),
+            WebSocketCommand::SelectProfile { name } => Some(UserEvent::SelectProfile(name)),
+            WebSocketCommand::SetSystemEnabled { enabled } => {
+                Some(UserEvent::SetSystemEnabled(enabled))
+            }
+            WebSocketCommand::GetLogs { .. }
+            | WebSocketCommand::GetState
+            | WebSocketCommand::GetConfig
+            | WebSocketCommand::StartRecording
+            | WebSocketCommand::StopRecording
+            | WebSocketCommand::GetRecording
+            | WebSocketCommand::ExportLastBrewCsv
+            | WebSocketCommand::Ping { .. } => None,
+        }
+    }
+}
+
+/// Larger commands are rejected - room for a `set_config` carrying a full flow profile
+const MAX_COMMAND_LEN: usize = 4096;
+
+/// Coalesce bursts of bus events into a single broadcast
+const WS_BROADCAST_COALESCE_MS: u64 = 20;
+
+/// Idle HTTP sessions are dropped after this - a client heartbeating every few seconds never hits it
+const STALE_SESSION_TIMEOUT_SECS: u64 = 30;
+
+/// Heartbeat reply - the client measures round-trip latency from its own send time
+#[derive(Debug, Serialize)]
+pub struct PongMsg {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub nonce: u32,
+    pub device_uptime_ms: u64,
+}
+
+impl PongMsg {
+    pub fn new(nonce: u32) -> Self {
+        Self {
+            kind: "pong",
+            nonce,
+            device_uptime_ms: Instant::now().as_millis(),
+        }
+    }
+}
+
+/// Reply to `get_config` - edit it and send it back as `set_config`
+#[derive(Debug, Serialize)]
+pub struct ConfigMsg {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub config: BrewConfig,
+}
+
+impl ConfigMsg {
+    pub fn new(config: BrewConfig) -> Self {
+        Self {
+            kind: "config",
+            config,
+        }
+    }
+}
+
+/// Version of the `/state` and `/api/status` JSON shapes.
+/// Bump whenever a field is added, removed, renamed or changes meaning so integrations can detect it.
+pub const STATUS_SCHEMA_VERSION: u32 = 22;
+
+/// Snapshot served at `/state` for the polling web UI
 #[derive(Debug, Serialize)]
 pub struct WebSocketResponse {
+    /// Always `STATUS_SCHEMA_VERSION`
+    pub schema_version: u32,
+    /// Latest scale sample, null until the scale has reported
     pub scale_data: Option<ScaleDataMsg>,
     pub system_state: SystemStateMsg,
+    /// Devices found by the last `scan_devices` command, empty until one runs
+    pub nearby_devices: Vec<NearbyDeviceMsg>,
+    /// Wall clock seconds since the Unix epoch (boot-relative until SNTP syncs)
     pub timestamp: u64,
 }
 
+impl WebSocketResponse {
+    /// Snapshot served at `/state` and pushed to `/ws` clients
+    pub fn from_state(state: &SystemState) -> Self {
+        let unit = state.config.weight_unit;
+        Self {
+            schema_version: STATUS_SCHEMA_VERSION,
+            scale_data: state.scale_data.as_ref().map(|data| ScaleDataMsg {
+                weight_g: data.weight_g,
+                flow_rate_g_per_s: data.flow_rate_g_per_s,
+                weight: unit.display(data.weight_g),
+                flow_rate: unit.display(data.flow_rate_g_per_s),
+                battery_percent: data.battery_percent,
+                timer_running: data.timer_running,
+                timestamp_ms: data.timestamp_ms,
+                received_at_ms: data.received_at_ms(),
+            }),
+            system_state: SystemStateMsg {
+                brew_state: format!("{:?}", state.brew_state),
+                timer_state: format!("{:?}", state.timer_state),
+                target_weight_g: state.config.target_weight_g,
+                cups: state.config.cups,
+                total_target_weight_g: state.config.total_target_weight_g(),
+                weight_unit: unit,
+                target_weight: unit.display(state.config.target_weight_g),
+                total_target_weight: unit.display(state.config.total_target_weight_g()),
+                auto_tare_enabled: state.config.auto_tare,
+                tare_before_brew: state.config.tare_before_brew,
+                auto_tare_profile: state.config.auto_tare_profile,
+                last_auto_tare_reason: state
+                    .last_auto_tare_reason
+                    .map(|reason| reason.to_string()),
+                predictive_stop_enabled: state.config.predictive_stop,
+                predictive_stop_auto: state.config.predictive_stop_auto,
+                relay_enabled: state.relay_enabled,
+                ble_connected: state.ble_connected,
+                ble_stage: state.ble_stage,
+                radio_priority: state.radio_priority,
+                system_enabled: state.system_enabled,
+                storage_available: state.storage_available,
+                error: state.last_error.clone(),
+                overshoot_info: "Learning data not available".to_string(),
+                progress: state.brew_progress(),
+                dose: state.dose,
+                dose_g: state.config.dose_g,
+                brew_ratio: state.config.brew_ratio,
+            },
+            nearby_devices: state.nearby_devices.iter().map(NearbyDeviceMsg::from).collect(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ScaleDataMsg {
     pub weight_g: f32,
     pub flow_rate_g_per_s: f32,
+    /// `weight_g` in the configured `weight_unit`, rounded for display
+    pub weight: f32,
+    /// Flow per second in the configured `weight_unit`, rounded for display
+    pub flow_rate: f32,
     pub battery_percent: u8,
     pub timer_running: bool,
+    /// Scale timer reading
     pub timestamp_ms: u32,
+    /// Controller capture time in ms since boot - keeps increasing when the scale timer resets
+    pub received_at_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NearbyDeviceMsg {
+    pub name: Option<String>,
+    /// `AA:BB:CC:DD:EE:FF` - pass back unchanged to `connect_to_device`
+    pub address: String,
+    pub rssi: i8,
+}
+
+impl From<&Device> for NearbyDeviceMsg {
+    fn from(device: &Device) -> Self {
+        Self {
+            name: device.name.clone(),
+            address: device.address.to_string(),
+            rssi: device.rssi,
+        }
+    }
+}
+
+/// Diagnostics served at `/api/status`
+#[derive(Debug, Serialize)]
+pub struct StatusMsg {
+    /// Always `STATUS_SCHEMA_VERSION`
+    pub schema_version: u32,
+    pub device_uptime_ms: u64,
+    /// Why the chip last reset - PowerOn, Software, Panic, TaskWatchdog, Brownout, ...
+    pub reset_reason: String,
+    /// Idle, Brewing or BrewSettling
+    pub brew_state: String,
+    /// Progress towards the total target, null unless brewing
+    pub progress: Option<BrewProgress>,
+    pub relay_enabled: bool,
+    pub ble_connected: bool,
+    /// Step the scale connection reached - shows where a connection stalls
+    pub ble_stage: Option<&'static str>,
+    /// Scale link interval the peer agreed to, null while disconnected
+    pub ble_conn_interval_ms: Option<f32>,
+    /// `ble` while a shot runs - UI updates are slowed to keep scale data on time
+    pub radio_priority: RadioPriority,
+    /// One 0-100 score from RSSI, corrupt frames and reconnects, null while disconnected
+    pub link_quality: Option<LinkQualityReport>,
+    /// False while the killswitch is engaged
+    pub system_enabled: bool,
+    /// Last error shown to the user, if any
+    pub error: Option<String>,
+    /// What the safety checks see and why they last tripped
+    pub safety: SafetyStatus,
+    /// Relay activation count and on-time, persisted across reboots
+    pub relay: RelayStats,
+    /// Whether the relay pins read back low at boot - `stuck_high` is a hardware fault
+    pub relay_boot_check: RelayBootCheck,
+    /// Overshoot learner progress
+    pub overshoot: OvershootStatus,
+    /// State machine's view of the current brew - weight, target, pending stop
+    pub brew: BrewSnapshot,
+    /// Flash writes since boot - should only move when settings change or a shot finishes
+    pub nvs_writes: u32,
+    /// False when NVS failed to initialize - settings changes won't survive a reboot
+    pub storage_available: bool,
+    /// Overshoot profile `overshoot` describes
+    pub overshoot_profile: String,
+    pub overshoot_profiles: Vec<String>,
+    /// Connected scale, null while disconnected
+    pub scale: Option<ScaleInfoMsg>,
+    /// Last `run_self_test` report, null until one has run
+    pub self_test: Option<SelfTestReport>,
+}
+
+/// Reply to `get_state` - the state machine's own view instead of the legacy brew state
+#[derive(Debug, Serialize)]
+pub struct StateMsg {
+    /// Always `STATUS_SCHEMA_VERSION`
+    pub schema_version: u32,
+    /// Hierarchical state - BleScanning, ScaleDisconnected, Idle, Brewing, SystemDisabled, ...
+    pub system_state: MachineState,
+    /// False while the killswitch is engaged
+    pub system_enabled: bool,
+    pub auto_tare_state: AutoTareState,
+    pub scale_detector: ScaleDetectorStatus,
+    pub safety: SafetyStatus,
+}
+
+impl StateMsg {
+    pub fn from_state(state: &SystemState) -> Self {
+        Self {
+            schema_version: STATUS_SCHEMA_VERSION,
+            system_state: state.machine_state,
+            system_enabled: state.system_enabled,
+            auto_tare_state: state.auto_tare_state,
+            scale_detector: state.scale_detector,
+            safety: state.safety.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScaleInfoMsg {
+    pub brand: String,
+    pub model: String,
+    /// Firmware revision from the Device Information Service, null if the scale doesn't report one
+    pub firmware: Option<String>,
+    /// Unit and auto-off read back from the scale, null until read or if it doesn't report them
+    pub settings: Option<ScaleSettings>,
+}
+
+impl From<&ScaleInfo> for ScaleInfoMsg {
+    fn from(info: &ScaleInfo) -> Self {
+        Self {
+            brand: info.brand.clone(),
+            model: info.model.clone(),
+            firmware: info.version.clone(),
+            settings: info.settings,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 pub struct SystemStateMsg {
     pub brew_state: String,
     pub timer_state: String,
+    /// Per-cup target
     pub target_weight_g: f32,
+    pub cups: u8,
+    /// Where the brew actually stops: per-cup target times cups
+    pub total_target_weight_g: f32,
+    /// Unit of the unsuffixed weight fields below and in `scale_data`
+    pub weight_unit: WeightUnit,
+    pub target_weight: f32,
+    pub total_target_weight: f32,
     pub auto_tare_enabled: bool,
+    /// Starting a brew tares first and waits for the scale to read zero
+    pub tare_before_brew: bool,
+    /// Active auto-tare preset, null when parameters were tuned individually
+    pub auto_tare_profile: Option<AutoTareProfile>,
+    /// Why the last auto-tare fired, if one has
+    pub last_auto_tare_reason: Option<String>,
     pub predictive_stop_enabled: bool,
+    /// Predictive stop is following overshoot learning readiness rather than the manual toggle
+    pub predictive_stop_auto: bool,
     pub relay_enabled: bool,
     pub ble_connected: bool,
+    /// Scale connection progress - scanning, connecting, connected, discovering, subscribed
+    pub ble_stage: Option<&'static str>,
+    /// `ble` while a shot runs and these updates are throttled, `balanced` otherwise
+    pub radio_priority: RadioPriority,
+    /// False while the killswitch is engaged
+    pub system_enabled: bool,
+    /// False when settings can't be saved - the UI warns that changes are lost on reboot
+    pub storage_available: bool,
     pub error: Option<String>,
     pub overshoot_info: String,
+    /// Progress towards `total_target_weight_g`, null unless brewing
+    pub progress: Option<BrewProgress>,
+    pub dose: DoseStatus,
+    /// Last confirmed dose
+    pub dose_g: Option<f32>,
+    /// Null when the target is fixed rather than derived from the dose
+    pub brew_ratio: Option<f32>,
+}
+
+/// Browsers connected to `/ws`, keyed by HTTP session - every one of them receives each broadcast
+#[derive(Default)]
+pub struct WsClients {
+    senders: StdMutex<HashMap<i32, EspHttpWsDetachedSender>>,
+}
+
+impl WsClients {
+    fn add(&self, session: i32, sender: EspHttpWsDetachedSender) {
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.insert(session, sender);
+        info!("🌐 WebSocket client {} connected ({} total)", session, senders.len());
+    }
+
+    fn remove(&self, session: i32) {
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        if senders.remove(&session).is_some() {
+            info!("🌐 WebSocket client {} disconnected ({} left)", session, senders.len());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.senders.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Send a text frame to every client, dropping any whose socket has gone away
+    pub fn broadcast(&self, json: &str) {
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.retain(|session, sender| match sender.send(FrameType::Text(false), json.as_bytes()) {
+            Ok(()) => true,
+            Err(e) => {
+                debug!("Dropping WebSocket client {}: {:?}", session, e);
+                false
+            }
+        });
+    }
 }
 
 #[derive(Clone)]
 pub struct WebSocketServer {
     state: Arc<Mutex<CriticalSectionRawMutex, SystemState>>,
     command_sender: Arc<WebSocketCommandChannel>,
+    recording: Arc<SharedBrewRecording>,
+    trace: Arc<SharedBrewTrace>,
+    event_stats: Arc<EventStats>,
+    clients: Arc<WsClients>,
 }
 
 impl WebSocketServer {
     pub fn new(
         state: Arc<Mutex<CriticalSectionRawMutex, SystemState>>,
         command_sender: Arc<WebSocketCommandChannel>,
+        recording: Arc<SharedBrewRecording>,
+        trace: Arc<SharedBrewTrace>,
+        event_stats: Arc<EventStats>,
         _port: u16,
     ) -> Self {
         Self {
             state,
             command_sender,
+            recording,
+            trace,
+            event_stats,
+            clients: Arc::new(WsClients::default()),
         }
     }
 
+    /// Connected `/ws` clients, for the broadcast task
+    pub fn clients(&self) -> Arc<WsClients> {
+        Arc::clone(&self.clients)
+    }
+
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
         info!("Starting HTTP server with WebSocket support");
 
@@ -93,8 +714,10 @@ impl WebSocketServer {
         // Configure HTTP server with much higher session limits for WebSocket
         let config = Configuration {
             stack_size: 10240, // Larger stack for WebSocket threads
-            session_timeout: std::time::Duration::from_secs(300), // 5 minute timeout for WebSocket
+            // Browsers that stopped heartbeating (sleeping tab, dropped WiFi) give their socket back
+            session_timeout: std::time::Duration::from_secs(STALE_SESSION_TIMEOUT_SECS),
             max_sessions: 16,  // Match ESP-IDF config - plenty for WebSocket + HTTP requests
+            lru_purge_enable: true, // Evict the least recently used session rather than refuse new clients
             ..Default::default()
         };
         let mut server = EspHttpServer::new(&config)?;
@@ -150,6 +773,9 @@ impl WebSocketServer {
 
         // Command endpoint for WebSocket commands sent via HTTP POST
         let command_channel_http = Arc::clone(&self.command_sender);
+        let command_state = Arc::clone(&self.state);
+        let recording = Arc::clone(&self.recording);
+        let trace = Arc::clone(&self.trace);
         server.fn_handler(
             "/command",
             Method::Post,
@@ -162,8 +788,7 @@ impl WebSocketServer {
                 let mut total_read = 0;
 
                 loop {
-                    if total_read >= 2048 {
-                        // Limit total read to 2KB
+                    if total_read >= MAX_COMMAND_LEN {
                         warn!("Request body too large, truncating");
                         break;
                     }
@@ -194,6 +819,159 @@ impl WebSocketServer {
                 info!("Command body: {}", body_str.trim());
 
                 match serde_json::from_str::<WebSocketCommand>(&body_str) {
+                    Ok(WebSocketCommand::Ping { nonce }) => {
+                        let json = serde_json::to_string(&PongMsg::new(nonce))?;
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "application/json"),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(json.as_bytes())?;
+                        Ok(())
+                    }
+                    Ok(WebSocketCommand::GetLogs { limit }) => {
+                        let limit = limit.unwrap_or(LOG_BUFFER_SIZE).min(LOG_BUFFER_SIZE);
+                        let entries = match command_state.try_lock() {
+                            Ok(state) => recent_log_entries(&state, limit),
+                            Err(_) => {
+                                warn!("State locked, cannot serve logs");
+                                let mut response =
+                                    request.into_response(503, Some("Service Unavailable"), &[])?;
+                                response.write_all(b"State busy, retry")?;
+                                return Ok(());
+                            }
+                        };
+
+                        let json = serde_json::to_string(&entries)?;
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "application/json"),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(json.as_bytes())?;
+                        Ok(())
+                    }
+                    Ok(WebSocketCommand::GetConfig) => {
+                        let config_msg = match command_state.try_lock() {
+                            Ok(state) => ConfigMsg::new(state.config.clone()),
+                            Err(_) => {
+                                warn!("State locked, cannot serve config query");
+                                let mut response =
+                                    request.into_response(503, Some("Service Unavailable"), &[])?;
+                                response.write_all(b"State busy, retry")?;
+                                return Ok(());
+                            }
+                        };
+
+                        let json = serde_json::to_string(&config_msg)?;
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "application/json"),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(json.as_bytes())?;
+                        Ok(())
+                    }
+                    Ok(WebSocketCommand::GetState) => {
+                        let state_msg = match command_state.try_lock() {
+                            Ok(state) => StateMsg::from_state(&state),
+                            Err(_) => {
+                                warn!("State locked, cannot serve state query");
+                                let mut response =
+                                    request.into_response(503, Some("Service Unavailable"), &[])?;
+                                response.write_all(b"State busy, retry")?;
+                                return Ok(());
+                            }
+                        };
+
+                        let json = serde_json::to_string(&state_msg)?;
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "application/json"),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(json.as_bytes())?;
+                        Ok(())
+                    }
+                    Ok(
+                        command @ (WebSocketCommand::StartRecording
+                        | WebSocketCommand::StopRecording
+                        | WebSocketCommand::GetRecording),
+                    ) => {
+                        let Ok(mut recording) = recording.try_lock() else {
+                            warn!("Recording locked, cannot serve {:?}", command);
+                            let mut response =
+                                request.into_response(503, Some("Service Unavailable"), &[])?;
+                            response.write_all(b"Recorder busy, retry")?;
+                            return Ok(());
+                        };
+
+                        let json = match command {
+                            WebSocketCommand::StartRecording => {
+                                recording.start();
+                                serde_json::to_string(&*recording)?
+                            }
+                            WebSocketCommand::StopRecording => {
+                                recording.stop();
+                                serde_json::to_string(&*recording)?
+                            }
+                            _ => serde_json::to_string(&*recording)?,
+                        };
+                        drop(recording);
+
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "application/json"),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(json.as_bytes())?;
+                        Ok(())
+                    }
+                    Ok(WebSocketCommand::ExportLastBrewCsv) => {
+                        let csv = match trace.try_lock() {
+                            Ok(trace) => trace.to_csv(),
+                            Err(_) => {
+                                warn!("Brew trace locked, cannot export it");
+                                let mut response =
+                                    request.into_response(503, Some("Service Unavailable"), &[])?;
+                                response.write_all(b"Brew trace busy, retry")?;
+                                return Ok(());
+                            }
+                        };
+
+                        let mut response = request.into_response(
+                            200,
+                            Some("OK"),
+                            &[
+                                ("Content-Type", "text/csv"),
+                                ("Content-Disposition", "attachment; filename=\"brew.csv\""),
+                                ("Cache-Control", "no-cache"),
+                                ("Access-Control-Allow-Origin", "*"),
+                            ],
+                        )?;
+                        response.write_all(csv.as_bytes())?;
+                        Ok(())
+                    }
                     Ok(command) => {
                         info!("Parsed command: {:?}", command);
                         // Send command to processing channel (async, non-blocking)
@@ -239,30 +1017,7 @@ impl WebSocketServer {
                 debug!("Serving /state endpoint for polling client");
 
                 if let Ok(state) = state_handle.try_lock() {
-                    let response = WebSocketResponse {
-                        scale_data: state.scale_data.as_ref().map(|data| ScaleDataMsg {
-                            weight_g: data.weight_g,
-                            flow_rate_g_per_s: data.flow_rate_g_per_s,
-                            battery_percent: data.battery_percent,
-                            timer_running: data.timer_running,
-                            timestamp_ms: data.timestamp_ms,
-                        }),
-                        system_state: SystemStateMsg {
-                            brew_state: format!("{:?}", state.brew_state),
-                            timer_state: format!("{:?}", state.timer_state),
-                            target_weight_g: state.config.target_weight_g,
-                            auto_tare_enabled: state.config.auto_tare,
-                            predictive_stop_enabled: state.config.predictive_stop,
-                            relay_enabled: state.relay_enabled,
-                            ble_connected: state.ble_connected,
-                            error: state.last_error.clone(),
-                            overshoot_info: "Learning data not available".to_string(),
-                        },
-                        timestamp: std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs(),
-                    };
+                    let response = WebSocketResponse::from_state(&state);
 
                     if let Ok(json) = serde_json::to_string(&response) {
                         let mut http_response = request.into_response(
@@ -293,6 +1048,141 @@ impl WebSocketServer {
             },
         )?;
 
+        // Diagnostics endpoint - safety check state alongside the basics
+        let status_handle = Arc::clone(&self.state);
+        server.fn_handler(
+            "/api/status",
+            Method::Get,
+            move |request| -> Result<(), anyhow::Error> {
+                let status = match status_handle.try_lock() {
+                    Ok(state) => StatusMsg {
+                        schema_version: STATUS_SCHEMA_VERSION,
+                        device_uptime_ms: Instant::now().as_millis(),
+                        reset_reason: format!("{:?}", ResetReason::get()),
+                        brew_state: format!("{:?}", state.brew_state),
+                        progress: state.brew_progress(),
+                        relay_enabled: state.relay_enabled,
+                        ble_connected: state.ble_connected,
+                        ble_stage: state.ble_stage,
+                        ble_conn_interval_ms: BleClient::current_conn_interval_ms(),
+                        radio_priority: state.radio_priority,
+                        link_quality: state.link_quality,
+                        system_enabled: state.system_enabled,
+                        error: state.last_error.clone(),
+                        safety: state.safety.clone(),
+                        relay: state.relay_stats,
+                        relay_boot_check: state.relay_boot_check,
+                        overshoot: state.overshoot,
+                        brew: state.brew,
+                        nvs_writes: state.nvs_writes,
+                        storage_available: state.storage_available,
+                        overshoot_profile: state.overshoot_profile.clone(),
+                        overshoot_profiles: state.overshoot_profiles.clone(),
+                        scale: state.scale_info.as_ref().map(ScaleInfoMsg::from),
+                        self_test: state.self_test.clone(),
+                    },
+                    Err(_) => {
+                        warn!("State locked, cannot serve status");
+                        let mut response =
+                            request.into_response(503, Some("Service Unavailable"), &[])?;
+                        response.write_all(b"State temporarily unavailable")?;
+                        return Ok(());
+                    }
+                };
+
+                let json = serde_json::to_string(&status)?;
+                let mut response = request.into_response(
+                    200,
+                    Some("OK"),
+                    &[
+                        ("Content-Type", "application/json"),
+                        ("Cache-Control", "no-cache"),
+                        ("Access-Control-Allow-Origin", "*"),
+                    ],
+                )?;
+                response.write_all(json.as_bytes())?;
+                Ok(())
+            },
+        )?;
+
+        // Push channel - every connected browser gets the same state updates
+        let ws_clients = Arc::clone(&self.clients);
+        let ws_commands = Arc::clone(&self.command_sender);
+        let ws_state = Arc::clone(&self.state);
+        server.ws_handler("/ws", move |ws: &mut EspHttpWsConnection| -> Result<(), EspError> {
+            if ws.is_new() {
+                ws_clients.add(ws.session(), ws.create_detached_sender()?);
+                return Ok(());
+            }
+            if ws.is_closed() {
+                ws_clients.remove(ws.session());
+                return Ok(());
+            }
+
+            // Clients may also send commands over the socket
+            let (_, len) = ws.recv(&mut [])?;
+            if len > MAX_COMMAND_LEN {
+                warn!("WebSocket frame too large ({} bytes), ignoring", len);
+                return Ok(());
+            }
+            // On the heap - a full config doesn't belong on the httpd task's stack
+            let mut buf = vec![0u8; len];
+            ws.recv(&mut buf)?;
+            match serde_json::from_slice::<WebSocketCommand>(&buf) {
+                // Answered on the same socket so the client gets it in one round trip
+                Ok(WebSocketCommand::GetConfig) => {
+                    let Ok(state) = ws_state.try_lock() else {
+                        warn!("State locked, cannot serve WebSocket config query");
+                        return Ok(());
+                    };
+                    match serde_json::to_string(&ConfigMsg::new(state.config.clone())) {
+                        Ok(json) => ws.send(FrameType::Text(false), json.as_bytes())?,
+                        Err(e) => warn!("Failed to serialize config: {}", e),
+                    }
+                }
+                Ok(command) => {
+                    if ws_commands.try_send(command).is_err() {
+                        warn!("Command channel full, dropping WebSocket command");
+                    }
+                }
+                Err(e) => warn!("Invalid WebSocket command: {}", e),
+            }
+            Ok(())
+        })?;
+
+        // Prometheus-style metrics for long-term wear tracking and event bus load
+        let metrics_handle = Arc::clone(&self.state);
+        let metrics_events = Arc::clone(&self.event_stats);
+        server.fn_handler(
+            "/metrics",
+            Method::Get,
+            move |request| -> Result<(), anyhow::Error> {
+                let (relay, relay_on, nvs_writes) = match metrics_handle.try_lock() {
+                    Ok(state) => (state.relay_stats, state.relay_enabled, state.nvs_writes),
+                    Err(_) => {
+                        warn!("State locked, cannot serve metrics");
+                        let mut response =
+                            request.into_response(503, Some("Service Unavailable"), &[])?;
+                        response.write_all(b"State temporarily unavailable")?;
+                        return Ok(());
+                    }
+                };
+
+                let body =
+                    format_metrics(&relay, relay_on, nvs_writes, &metrics_events.snapshot());
+                let mut response = request.into_response(
+                    200,
+                    Some("OK"),
+                    &[
+                        ("Content-Type", "text/plain; version=0.0.4"),
+                        ("Cache-Control", "no-cache"),
+                    ],
+                )?;
+                response.write_all(body.as_bytes())?;
+                Ok(())
+            },
+        )?;
+
         info!("HTTP server started successfully (polling mode)");
         info!("Server configuration:");
         info!("  Max sessions: {}", config.max_sessions);
@@ -304,6 +1194,7 @@ impl WebSocketServer {
         info!("  GET  /script.js - JavaScript");
         info!("  GET  /state - Real-time state (for 5Hz polling)");
         info!("  POST /command - Command endpoint");
+        info!("  WS   /ws - State broadcast to all connected clients");
 
         // Keep server alive
         loop {
@@ -318,6 +1209,131 @@ impl WebSocketServer {
     }
 }
 
+/// Render relay, flash write and event bus counters in the Prometheus text exposition format
+fn format_metrics(
+    relay: &RelayStats,
+    relay_on: bool,
+    nvs_writes: u32,
+    events: &[EventCount],
+) -> String {
+    let mut body = format!(
+        "# HELP gravel_relay_activations_total Relay off-to-on transitions\n\
+         # TYPE gravel_relay_activations_total counter\n\
+         gravel_relay_activations_total {}\n\
+         # HELP gravel_relay_on_seconds_total Cumulative relay on-time\n\
+         # TYPE gravel_relay_on_seconds_total counter\n\
+         gravel_relay_on_seconds_total {:.3}\n\
+         # HELP gravel_relay_on Whether any relay channel is currently on\n\
+         # TYPE gravel_relay_on gauge\n\
+         gravel_relay_on {}\n\
+         # HELP gravel_nvs_writes_total Flash writes since boot\n\
+         # TYPE gravel_nvs_writes_total counter\n\
+         gravel_nvs_writes_total {}\n",
+        relay.activations,
+        relay.total_on_ms as f64 / 1000.0,
+        relay_on as u8,
+        nvs_writes
+    );
+    body.push_str(
+        "# HELP gravel_events_published_total Events published on the bus, by kind\n\
+         # TYPE gravel_events_published_total counter\n",
+    );
+    for count in events {
+        body.push_str(&format!(
+            "gravel_events_published_total{{event=\"{}\"}} {}\n",
+            count.name, count.published
+        ));
+    }
+    body.push_str(
+        "# HELP gravel_events_delivered_total Events taken off the bus by subscribers, by kind\n\
+         # TYPE gravel_events_delivered_total counter\n",
+    );
+    for count in events {
+        body.push_str(&format!(
+            "gravel_events_delivered_total{{event=\"{}\"}} {}\n",
+            count.name, count.delivered
+        ));
+    }
+    body
+}
+
+/// Bus events that change what the web UI shows
+fn affects_ui(event: &SystemEvent) -> bool {
+    matches!(
+        event,
+        SystemEvent::Scale(_)
+            | SystemEvent::Brew(_)
+            | SystemEvent::User(_)
+            | SystemEvent::Safety(_)
+            | SystemEvent::Network(_)
+    )
+}
+
+/// New weight and nothing else - the part of the stream that gets downsampled
+fn is_weight_sample(event: &SystemEvent) -> bool {
+    matches!(event, SystemEvent::Scale(ScaleEvent::WeightChanged { .. }))
+}
+
+/// Push a fresh state snapshot to every `/ws` client whenever the bus reports a UI-visible change
+#[embassy_executor::task]
+pub async fn ws_broadcast_task(
+    event_bus: Arc<EventBus>,
+    state: Arc<Mutex<CriticalSectionRawMutex, SystemState>>,
+    clients: Arc<WsClients>,
+    shutdown: Arc<ShutdownToken>,
+) {
+    info!("🌐 WebSocket broadcast task started");
+    let mut subscriber = event_bus.subscriber();
+    let mut last_broadcast: Option<Instant> = None;
+
+    let broadcasts = async {
+        loop {
+            let event = subscriber.next_event().await;
+            if !affects_ui(&event) || clients.is_empty() {
+                continue;
+            }
+
+            // With BLE prioritised mid-shot, hold back so WiFi doesn't crowd out the scale
+            let (interval_ms, weight_interval_ms) = {
+                let state = state.lock().await;
+                (state.broadcast_interval_ms(), state.weight_broadcast_interval_ms())
+            };
+            if let Some(last) = last_broadcast {
+                let next_allowed = last + Duration::from_millis(interval_ms as u64);
+                if next_allowed > Instant::now() {
+                    Timer::at(next_allowed).await;
+                }
+
+                // Weight alone is downsampled - a brew event arriving meanwhile cuts the wait
+                // short, so starts and stops still reach the UI at once
+                let next_sample = last + Duration::from_millis(weight_interval_ms as u64);
+                let mut waiting = is_weight_sample(&event);
+                while waiting && next_sample > Instant::now() {
+                    match select(Timer::at(next_sample), subscriber.next_event()).await {
+                        Either::First(()) => waiting = false,
+                        Either::Second(event) => {
+                            waiting = !affects_ui(&event) || is_weight_sample(&event)
+                        }
+                    }
+                }
+            }
+
+            // Let the controller apply the change first, then fold anything else that arrived
+            Timer::after(Duration::from_millis(WS_BROADCAST_COALESCE_MS)).await;
+            while subscriber.try_next_event().is_some() {}
+
+            last_broadcast = Some(Instant::now());
+            let snapshot = WebSocketResponse::from_state(&*state.lock().await);
+            match serde_json::to_string(&snapshot) {
+                Ok(json) => clients.broadcast(&json),
+                Err(e) => warn!("Failed to serialize broadcast state: {}", e),
+            }
+        }
+    };
+    select(broadcasts, shutdown.cancelled()).await;
+    info!("🌐 WebSocket broadcast task stopped");
+}
+
 // Helper function for processing WebSocket commands (simplified for build)
 pub async fn process_websocket_command(
     command: WebSocketCommand,
@@ -328,15 +1344,127 @@ pub async fn process_websocket_command(
     // In a full implementation, this would update the system state
     // based on the received command
     match command {
-        WebSocketCommand::SetTargetWeight { weight } => {
-            info!("Would set target weight to: {:.1}g", weight);
+        WebSocketCommand::SetTargetWeight { weight, unit } => {
+            info!("Would set target weight to: {:.2}{}", weight, unit.symbol());
+        }
+        WebSocketCommand::SetWeightUnit { unit } => {
+            info!("Would set weight unit to: {:?}", unit);
+        }
+        WebSocketCommand::SetCups { cups } => {
+            info!("Would set cups to: {}", cups);
         }
         WebSocketCommand::SetAutoTare { enabled } => {
             info!("Would set auto-tare to: {}", enabled);
         }
+        WebSocketCommand::SetTareBeforeBrew { enabled } => {
+            info!("Would set tare before brew to: {}", enabled);
+        }
+        WebSocketCommand::SetCommandConfirmation { command, enabled } => {
+            info!("Would set {} confirmation to: {}", command.as_str(), enabled);
+        }
         WebSocketCommand::SetPredictiveStop { enabled } => {
             info!("Would set predictive stop to: {}", enabled);
         }
+        WebSocketCommand::SetPredictiveStopAuto { enabled } => {
+            info!("Would set predictive stop auto mode to: {}", enabled);
+        }
+        WebSocketCommand::SetAutoTareEmptyThreshold { threshold_g } => {
+            info!("Would set auto-tare empty threshold to: {:.1}g", threshold_g);
+        }
+        WebSocketCommand::SetAutoTareStableReadings { readings } => {
+            info!("Would set auto-tare stable readings to: {}", readings);
+        }
+        WebSocketCommand::SetAutoTareProfile { profile } => {
+            info!("Would set auto-tare profile to: {:?}", profile);
+        }
+        WebSocketCommand::SetAutoTareCooldown { secs } => {
+            info!("Would set auto-tare cooldown to: {}s", secs);
+        }
+        WebSocketCommand::SetAutoTareHold { hold_ms } => {
+            info!("Would set auto-tare hold to: {}ms", hold_ms);
+        }
+        WebSocketCommand::SetPredictionWindow {
+            safety_margin_ms,
+            multiplier,
+        } => {
+            info!(
+                "Would set prediction window to: +{}ms, x{:.1}",
+                safety_margin_ms, multiplier
+            );
+        }
+        WebSocketCommand::SetOvershootWarmup { brews } => {
+            info!("Would set overshoot warmup to: {} brews", brews);
+        }
+        WebSocketCommand::SetOvershootTarget { grams } => {
+            info!("Would set overshoot setpoint to: {:+.1}g", grams);
+        }
+        WebSocketCommand::SetOvershootLearning(schedule) => {
+            info!("Would set overshoot learning schedule to: {:?}", schedule);
+        }
+        WebSocketCommand::SetBrewRatio { ratio } => {
+            info!("Would set brew ratio to: {:?}", ratio);
+        }
+        WebSocketCommand::SetConfig { config } => {
+            info!("Would replace the config (target {:.1}g)", config.target_weight_g);
+        }
+        WebSocketCommand::SetDoseWeighing { active } => {
+            info!("Would set dose weighing: {}", active);
+        }
+        WebSocketCommand::ConfirmDose => {
+            info!("Would confirm dose");
+        }
+        WebSocketCommand::SetStopMode { mode } => {
+            info!("Would set stop mode to: {:?}", mode);
+        }
+        WebSocketCommand::SetFlowStopHold { hold_ms } => {
+            info!("Would set flow stop hold to: {}ms", hold_ms);
+        }
+        WebSocketCommand::SetScaleReconnectGrace { grace_ms } => {
+            info!("Would set scale reconnect grace to: {}ms", grace_ms);
+        }
+        WebSocketCommand::SetSettleStability { stability_g } => {
+            info!("Would set settle stability to: {}g", stability_g);
+        }
+        WebSocketCommand::SetBrewTime { brew_time_ms } => {
+            info!("Would set brew time to: {}ms", brew_time_ms);
+        }
+        WebSocketCommand::SetFlowProfile { profile } => {
+            info!("Would set flow profile to: {:?}", profile);
+        }
+        WebSocketCommand::SetPredictionThresholds {
+            min_flow_for_prediction,
+            flow_stopped_threshold,
+            prediction_start_delay_ms,
+        } => {
+            info!(
+                "Would set prediction thresholds to: flow >{:.1}g/s, stopped <{:.1}g/s, after {}ms",
+                min_flow_for_prediction, flow_stopped_threshold, prediction_start_delay_ms
+            );
+        }
+        WebSocketCommand::SetLogLevel { level } => {
+            info!("Would set log level to: {:?}", level);
+        }
+        WebSocketCommand::SetScaleNamePrefix { prefix } => {
+            info!("Would set scale name prefix to: {}", prefix);
+        }
+        WebSocketCommand::SetWeightRateLimit { limit_g_per_s } => {
+            info!("Would set weight rate limit to: {}g/s", limit_g_per_s);
+        }
+        WebSocketCommand::SetScaleKeepAlive { interval_ms } => {
+            info!("Would set scale keep-alive to: {}ms", interval_ms);
+        }
+        WebSocketCommand::SetBrewTraceInterval { interval_ms } => {
+            info!("Would set brew trace interval to: {}ms", interval_ms);
+        }
+        WebSocketCommand::SetBlePriority { interval_ms } => {
+            info!("Would set BLE priority broadcast interval to: {}ms", interval_ms);
+        }
+        WebSocketCommand::SetDisplayRefresh { interval_ms } => {
+            info!("Would set display refresh interval to: {}ms", interval_ms);
+        }
+        WebSocketCommand::SetWeightStream { interval_ms } => {
+            info!("Would set weight stream interval to: {}ms", interval_ms);
+        }
         WebSocketCommand::TareScale => {
             info!("Would send tare command");
         }
@@ -352,10 +1480,515 @@ pub async fn process_websocket_command(
         WebSocketCommand::ResetOvershoot => {
             info!("Would reset overshoot learning");
         }
-        WebSocketCommand::TestRelay => {
-            info!("Would test relay");
+        WebSocketCommand::SelectProfile { name } => {
+            info!("Would select overshoot profile: {}", name);
+        }
+        WebSocketCommand::TestRelay { duration_ms } => {
+            info!("Would test relay for {:?}ms", duration_ms);
+        }
+        WebSocketCommand::RunSelfTest => {
+            info!("Would run the self-test");
+        }
+        WebSocketCommand::LogEventStats => {
+            info!("Would log event stats");
+        }
+        WebSocketCommand::ScanDevices { duration_ms } => {
+            info!("Would scan for nearby devices for {}ms", duration_ms);
+        }
+        WebSocketCommand::ConnectToDevice { address } => {
+            info!("Would connect to device {}", address);
+        }
+        WebSocketCommand::SetSystemEnabled { enabled } => {
+            info!("Would set system enabled to: {}", enabled);
+        }
+        WebSocketCommand::GetLogs { limit } => {
+            info!("Would return up to {:?} log entries", limit);
+        }
+        WebSocketCommand::GetState => {
+            info!("Would return the state machine state");
+        }
+        WebSocketCommand::GetConfig => {
+            info!("Would return the whole config");
+        }
+        WebSocketCommand::StartRecording => {
+            info!("Would start brew recording");
+        }
+        WebSocketCommand::StopRecording => {
+            info!("Would stop brew recording");
+        }
+        WebSocketCommand::GetRecording => {
+            info!("Would return the brew recording");
+        }
+        WebSocketCommand::ExportLastBrewCsv => {
+            info!("Would return the last brew as CSV");
+        }
+        WebSocketCommand::Ping { nonce } => {
+            debug!("Would answer ping {}", nonce);
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::brewing_state;
+    use crate::types::{
+        AutoTareProfile, BrewConfig, BrewState, LogLevel, ScaleData, WeightUnit,
+        BLE_PRIORITY_BROADCAST_MS, MAX_BREW_RATIO, MAX_DOSE_G, MAX_TARGET_WEIGHT_G,
+        MIN_BREW_RATIO, MIN_DOSE_G, MIN_TARGET_WEIGHT_G, WEIGHT_STREAM_MS,
+    };
+
+    fn user_event(json: &str) -> Option<UserEvent> {
+        serde_json::from_str::<WebSocketCommand>(json)
+            .expect("command should parse")
+            .into_user_event()
+    }
+
+    #[test]
+    fn test_every_command_maps_to_expected_user_event() {
+        assert!(matches!(
+            user_event(r#"{"type":"set_target_weight","weight":40.0}"#),
+            Some(UserEvent::SetTargetWeight(w)) if w == 40.0
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_weight_unit","unit":"ounces"}"#),
+            Some(UserEvent::SetWeightUnit(WeightUnit::Ounces))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_cups","cups":3}"#),
+            Some(UserEvent::SetCups(3))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare","enabled":false}"#),
+            Some(UserEvent::SetAutoTare(false))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_tare_before_brew","enabled":true}"#),
+            Some(UserEvent::SetTareBeforeBrew(true))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_command_confirmation","command":"stop","enabled":true}"#),
+            Some(UserEvent::SetCommandConfirmation {
+                command: ConfirmableCommand::Stop,
+                enabled: true
+            })
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_predictive_stop","enabled":true}"#),
+            Some(UserEvent::SetPredictiveStop(true))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_predictive_stop_auto","enabled":true}"#),
+            Some(UserEvent::SetPredictiveStopAuto(true))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare_empty_threshold","threshold_g":3.0}"#),
+            Some(UserEvent::SetAutoTareEmptyThreshold(t)) if t == 3.0
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_scale_name_prefix","prefix":"MY_SCALE"}"#),
+            Some(UserEvent::SetScaleNamePrefix(p)) if p == "MY_SCALE"
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_weight_rate_limit","limit_g_per_s":300.0}"#),
+            Some(UserEvent::SetWeightRateLimit(limit)) if limit == 300.0
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_scale_keep_alive","interval_ms":30000}"#),
+            Some(UserEvent::SetScaleKeepAlive(30000))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_brew_trace_interval","interval_ms":500}"#),
+            Some(UserEvent::SetBrewTraceInterval(500))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_ble_priority","interval_ms":0}"#),
+            Some(UserEvent::SetBlePriorityBroadcast(0))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_display_refresh","interval_ms":500}"#),
+            Some(UserEvent::SetDisplayRefresh(500))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_weight_stream","interval_ms":0}"#),
+            Some(UserEvent::SetWeightStream(0))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare_stable_readings","readings":6}"#),
+            Some(UserEvent::SetAutoTareStableReadings(6))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare_profile","profile":"relaxed"}"#),
+            Some(UserEvent::SetAutoTareProfile(AutoTareProfile::Relaxed))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare_cooldown","secs":30}"#),
+            Some(UserEvent::SetAutoTareCooldown(30))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_auto_tare_hold","hold_ms":800}"#),
+            Some(UserEvent::SetAutoTareHold(800))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_prediction_window","safety_margin_ms":300,"multiplier":2.5}"#),
+            Some(UserEvent::SetPredictionWindow { safety_margin_ms: 300, multiplier }) if multiplier == 2.5
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_overshoot_warmup","brews":5}"#),
+            Some(UserEvent::SetOvershootWarmup(5))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_overshoot_target","grams":-1.5}"#),
+            Some(UserEvent::SetOvershootTarget(grams)) if grams == -1.5
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_overshoot_learning","uncertain_rate":0.5,"full_experience_brews":10}"#),
+            Some(UserEvent::SetOvershootLearning(schedule)) if schedule == OvershootLearningSchedule {
+                uncertain_rate: 0.5,
+                full_experience_brews: 10,
+                ..OvershootLearningSchedule::default()
+            }
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_brew_ratio","ratio":2.5}"#),
+            Some(UserEvent::SetBrewRatio(Some(r))) if r == 2.5
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_brew_ratio","ratio":null}"#),
+            Some(UserEvent::SetBrewRatio(None))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_config","config":{"target_weight_g":40.0,"cups":2}}"#),
+            Some(UserEvent::SetConfig(c)) if c.target_weight_g == 40.0 && c.cups == 2
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_dose_weighing","active":true}"#),
+            Some(UserEvent::SetDoseWeighing(true))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"confirm_dose"}"#),
+            Some(UserEvent::ConfirmDose)
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_stop_mode","mode":"flow_stopped"}"#),
+            Some(UserEvent::SetStopMode(StopMode::FlowStopped))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_flow_stop_hold","hold_ms":2000}"#),
+            Some(UserEvent::SetFlowStopHold(2000))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_scale_reconnect_grace","grace_ms":5000}"#),
+            Some(UserEvent::SetScaleReconnectGrace(5000))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_settle_stability","stability_g":0.5}"#),
+            Some(UserEvent::SetSettleStability(s)) if s == 0.5
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_brew_time","brew_time_ms":240000}"#),
+            Some(UserEvent::SetBrewTime(240000))
+        ));
+        assert!(matches!(
+            user_event(concat!(
+                r#"{"type":"set_flow_profile","profile":{"points":["#,
+                r#"{"time_ms":0,"flow_g_per_s":1.0},{"time_ms":8000,"flow_g_per_s":2.5}]}}"#
+            )),
+            Some(UserEvent::SetFlowProfile(Some(p))) if p.points.len() == 2 && p.gains.kp > 0.0
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_flow_profile","profile":null}"#),
+            Some(UserEvent::SetFlowProfile(None))
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_prediction_thresholds","min_flow_for_prediction":0.5,"flow_stopped_threshold":0.8,"prediction_start_delay_ms":3000}"#),
+            Some(UserEvent::SetPredictionThresholds {
+                min_flow_for_prediction,
+                flow_stopped_threshold,
+                prediction_start_delay_ms: 3000,
+            }) if min_flow_for_prediction == 0.5 && flow_stopped_threshold == 0.8
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_log_level","level":"debug"}"#),
+            Some(UserEvent::SetLogLevel(LogLevel::Debug))
+        ));
+        assert!(matches!(user_event(r#"{"type":"tare_scale"}"#), Some(UserEvent::TareScale)));
+        assert!(matches!(user_event(r#"{"type":"start_timer"}"#), Some(UserEvent::StartBrewing)));
+        assert!(matches!(user_event(r#"{"type":"stop_timer"}"#), Some(UserEvent::StopBrewing)));
+        assert!(matches!(user_event(r#"{"type":"reset_timer"}"#), Some(UserEvent::ResetTimer)));
+        assert!(matches!(user_event(r#"{"type":"reset_overshoot"}"#), Some(UserEvent::ResetOvershoot)));
+        assert!(matches!(
+            user_event(r#"{"type":"select_profile","name":"filter"}"#),
+            Some(UserEvent::SelectProfile(name)) if name == "filter"
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"test_relay"}"#),
+            Some(UserEvent::TestRelay { duration_ms: RELAY_TEST_MS })
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"test_relay","duration_ms":2000}"#),
+            Some(UserEvent::TestRelay { duration_ms: 2000 })
+        ));
+        assert!(matches!(user_event(r#"{"type":"run_self_test"}"#), Some(UserEvent::RunSelfTest)));
+        assert!(matches!(
+            user_event(r#"{"type":"log_event_stats"}"#),
+            Some(UserEvent::LogEventStats)
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"scan_devices","duration_ms":5000}"#),
+            Some(UserEvent::ScanDevices { duration_ms: 5000 })
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"connect_to_device","address":"AA:BB:CC:DD:EE:FF"}"#),
+            Some(UserEvent::ConnectToDevice { address }) if address == "AA:BB:CC:DD:EE:FF"
+        ));
+        assert!(matches!(
+            user_event(r#"{"type":"set_system_enabled","enabled":false}"#),
+            Some(UserEvent::SetSystemEnabled(false))
+        ));
+    }
+
+    #[test]
+    fn test_status_json_carries_schema_version() {
+        let status = StatusMsg {
+            schema_version: STATUS_SCHEMA_VERSION,
+            device_uptime_ms: 1234,
+            reset_reason: "PowerOn".to_string(),
+            brew_state: "Idle".to_string(),
+            progress: None,
+            relay_enabled: false,
+            ble_connected: false,
+            ble_stage: Some("discovering"),
+            ble_conn_interval_ms: None,
+            radio_priority: RadioPriority::Ble,
+            link_quality: None,
+            system_enabled: true,
+            error: None,
+            safety: SafetyStatus::default(),
+            relay: RelayStats::default(),
+            relay_boot_check: RelayBootCheck::Low,
+            overshoot: OvershootStatus::default(),
+            brew: BrewSnapshot::default(),
+            nvs_writes: 0,
+            storage_available: true,
+            overshoot_profile: "default".to_string(),
+            overshoot_profiles: vec!["default".to_string()],
+            scale: None,
+            self_test: None,
+        };
+        let json: serde_json::Value = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["schema_version"], STATUS_SCHEMA_VERSION);
+        assert_eq!(json["ble_stage"], "discovering");
+        assert_eq!(json["radio_priority"], "ble");
+        assert_eq!(json["relay_boot_check"]["result"], "low");
+    }
+
+    #[test]
+    fn test_progress_only_while_brewing() {
+        let mut state = brewing_state(18.0, 2.0);
+        let progress = WebSocketResponse::from_state(&state).system_state.progress.unwrap();
+        assert_eq!(progress.fraction, 0.5);
+        assert_eq!(progress.percent(), 50.0);
+        assert_eq!(progress.eta_ms, Some(9000));
+
+        state.brew_state = BrewState::BrewSettling;
+        assert!(WebSocketResponse::from_state(&state).system_state.progress.is_none());
+    }
+
+    #[test]
+    fn test_missing_storage_reaches_the_ui() {
+        let mut state = SystemState::default();
+        assert!(WebSocketResponse::from_state(&state).system_state.storage_available);
+
+        state.storage_available = false;
+        let json = serde_json::to_value(WebSocketResponse::from_state(&state)).unwrap();
+        assert_eq!(json["system_state"]["storage_available"], false);
+    }
+
+    #[test]
+    fn test_progress_clamps_and_needs_flow_for_eta() {
+        let overshoot = brewing_state(38.5, 0.3).brew_progress().unwrap();
+        assert_eq!(overshoot.fraction, 1.0);
+        assert_eq!(overshoot.eta_ms, Some(0));
+
+        let tared_negative = brewing_state(-0.4, 0.0).brew_progress().unwrap();
+        assert_eq!(tared_negative.fraction, 0.0);
+        assert_eq!(tared_negative.eta_ms, None);
+
+        // Batch brews aim for the total across cups
+        let mut batch = brewing_state(36.0, 2.0);
+        batch.config.cups = 2;
+        assert_eq!(batch.brew_progress().unwrap().fraction, 0.5);
+    }
+
+    #[test]
+    fn test_target_weight_in_ounces_converts_to_grams() {
+        let Some(UserEvent::SetTargetWeight(grams)) =
+            user_event(r#"{"type":"set_target_weight","weight":1.5,"unit":"ounces"}"#)
+        else {
+            panic!("expected SetTargetWeight");
+        };
+        assert!((grams - 42.52).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_confirmed_dose_sets_target_from_ratio() {
+        let mut config = BrewConfig::default();
+        let fixed_target = config.target_weight_g;
+        config.apply_dose(18.0).unwrap();
+        assert_eq!(config.dose_g, Some(18.0));
+        assert_eq!(config.target_weight_g, fixed_target);
+
+        assert!(config.set_brew_ratio(Some(0.5)).is_err());
+        config.set_brew_ratio(Some(2.5)).unwrap();
+        config.apply_dose(18.0).unwrap();
+        assert_eq!(config.target_weight_g, 45.0);
+
+        // Three long cups would blow through the safety cutoff
+        config.set_brew_ratio(Some(MAX_BREW_RATIO)).unwrap();
+        config.cups = 3;
+        assert!(config.apply_dose(40.0).is_err());
+        assert_eq!(config.target_weight_g, 45.0);
+    }
+
+    #[test]
+    fn test_out_of_range_settings_are_rejected() {
+        let mut config = BrewConfig::default();
+        config.set_target_weight(MIN_TARGET_WEIGHT_G).unwrap();
+        config.set_target_weight(MAX_TARGET_WEIGHT_G).unwrap();
+        for weight in [MIN_TARGET_WEIGHT_G - 0.1, -36.0, MAX_TARGET_WEIGHT_G + 0.1, f32::NAN] {
+            assert!(config.set_target_weight(weight).is_err(), "{} accepted", weight);
+        }
+        assert_eq!(config.target_weight_g, MAX_TARGET_WEIGHT_G);
+        // In range per cup, but three cups pass the safety cutoff
+        config.cups = 3;
+        assert!(config.set_target_weight(MAX_TARGET_WEIGHT_G).is_err());
+
+        let mut config = BrewConfig::default();
+        config.apply_dose(MIN_DOSE_G).unwrap();
+        config.apply_dose(MAX_DOSE_G).unwrap();
+        assert!(config.apply_dose(MIN_DOSE_G - 0.1).is_err());
+        assert!(config.apply_dose(MAX_DOSE_G + 0.1).is_err());
+        assert_eq!(config.dose_g, Some(MAX_DOSE_G));
+        // The longest ratio on a full dose derives a target past the per-cup range
+        config.set_brew_ratio(Some(MIN_BREW_RATIO)).unwrap();
+        config.set_brew_ratio(Some(MAX_BREW_RATIO)).unwrap();
+        assert!(config.apply_dose(MAX_DOSE_G).is_err());
+        for ratio in [MIN_BREW_RATIO - 0.1, MAX_BREW_RATIO + 0.1, 16.0, f32::INFINITY] {
+            assert!(config.set_brew_ratio(Some(ratio)).is_err(), "1:{} accepted", ratio);
+        }
+
+        assert!(BrewConfig::default().validate().is_ok());
+        let mut config = BrewConfig::default();
+        config.target_weight_g = 0.0;
+        assert!(config.validate().is_err());
+        let mut config = BrewConfig::default();
+        config.brew_time_ms = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_weight_unit_display_rounding() {
+        assert!((WeightUnit::Grams.display(36.04) - 36.0).abs() < 1e-4);
+        assert!((WeightUnit::Ounces.display(36.0) - 1.27).abs() < 1e-4);
+        assert!((WeightUnit::Ounces.to_grams(WeightUnit::Ounces.from_grams(36.0)) - 36.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pong_echoes_nonce() {
+        let json = serde_json::to_value(PongMsg::new(42)).unwrap();
+        assert_eq!(json["type"], "pong");
+        assert_eq!(json["nonce"], 42);
+        assert!(json["device_uptime_ms"].is_u64());
+    }
+
+    #[test]
+    fn test_config_msg_round_trips_into_set_config() {
+        let mut config = BrewConfig::default();
+        config.target_weight_g = 42.0;
+        let mut json = serde_json::to_value(ConfigMsg::new(config)).unwrap();
+        assert_eq!(json["type"], "config");
+
+        // A client edits the reply and sends it straight back
+        json["type"] = "set_config".into();
+        assert!(matches!(
+            user_event(&json.to_string()),
+            Some(UserEvent::SetConfig(c)) if c.target_weight_g == 42.0
+        ));
+    }
+
+    #[test]
+    fn test_only_weight_samples_are_downsampled() {
+        let sample = SystemEvent::Scale(ScaleEvent::WeightChanged {
+            data: ScaleData {
+                timestamp_ms: 1000,
+                weight_g: 12.0,
+                flow_rate_g_per_s: 2.0,
+                battery_percent: 80,
+                timer_running: true,
+                received_at: Instant::now(),
+            },
+        });
+        assert!(is_weight_sample(&sample));
+        assert!(!is_weight_sample(&SystemEvent::User(UserEvent::StopBrewing)));
+
+        // Under BLE priority the slower of the two limits wins
+        let mut state = SystemState::default();
+        assert_eq!(state.weight_broadcast_interval_ms(), WEIGHT_STREAM_MS);
+        state.radio_priority = RadioPriority::Ble;
+        assert_eq!(state.weight_broadcast_interval_ms(), BLE_PRIORITY_BROADCAST_MS);
+        state.config.weight_stream_ms = 1000;
+        assert_eq!(state.weight_broadcast_interval_ms(), 1000);
+        assert!(state.config.set_weight_stream_ms(10).is_err());
+        assert!(state.config.set_weight_stream_ms(0).is_ok());
+    }
+
+    #[test]
+    fn test_metrics_break_events_down_by_kind() {
+        let stats = EventStats::new();
+        stats.record_published(&SystemEvent::User(UserEvent::TareScale));
+        stats.record_delivered(&SystemEvent::User(UserEvent::TareScale));
+        let body = format_metrics(&RelayStats::default(), false, 3, &stats.snapshot());
+        assert!(body.contains("gravel_nvs_writes_total 3\n"));
+        assert!(body.contains("gravel_events_published_total{event=\"User::TareScale\"} 1\n"));
+        assert!(body.contains("gravel_events_delivered_total{event=\"User::TareScale\"} 1\n"));
+    }
+
+    #[test]
+    fn test_http_served_commands_have_no_user_event() {
+        for json in [
+            r#"{"type":"get_logs","limit":10}"#,
+            r#"{"type":"get_state"}"#,
+            r#"{"type":"get_config"}"#,
+            r#"{"type":"start_recording"}"#,
+            r#"{"type":"stop_recording"}"#,
+            r#"{"type":"get_recording"}"#,
+            r#"{"type":"export_last_brew_csv"}"#,
+            r#"{"type":"ping","nonce":7}"#,
+        ] {
+            assert!(user_event(json).is_none(), "{} should not reach the controller", json);
+        }
+    }
+
+    #[test]
+    fn test_state_msg_reports_hierarchical_state() {
+        let state = SystemState {
+            machine_state: MachineState::BleScanning,
+            scale_detector: ScaleDetectorStatus {
+                timer_running: true,
+                stable_weight_g: Some(18.5),
+            },
+            ..SystemState::default()
+        };
+
+        let json = serde_json::to_value(StateMsg::from_state(&state)).unwrap();
+        assert_eq!(json["schema_version"], STATUS_SCHEMA_VERSION);
+        assert_eq!(json["system_state"], "BleScanning");
+        assert_eq!(json["system_enabled"], false);
+        assert_eq!(json["auto_tare_state"], "Empty");
+        assert_eq!(json["scale_detector"]["timer_running"], true);
+        assert_eq!(json["scale_detector"]["stable_weight_g"], 18.5);
+    }
+}
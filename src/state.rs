@@ -1,4 +1,17 @@
-use crate::types::{AutoTareState, BrewConfig, BrewState, ScaleData, SystemState, TimerState};
+use crate::types::{
+    AutoTareReason, AutoTareState, BrewConfig, BrewProgress, BrewState, LogEntry, LogLevel,
+    RadioPriority, ScaleData, ScaleDetectorStatus, SystemState, TimerState,
+};
+use crate::system::logging::log_level_enabled;
+use crate::brewing::states::{
+    BrewSnapshot, DoseStatus, OvershootStatus, SystemState as MachineState,
+};
+use crate::ble::Device;
+use crate::hardware::relay::{RelayBootCheck, RelayStats};
+use crate::scales::link_quality::LinkQualityReport;
+use crate::scales::traits::ScaleInfo;
+use crate::system::safety::SafetyStatus;
+use crate::system::self_test::SelfTestReport;
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
 use embassy_time::Instant;
 use log::{debug, info};
@@ -54,6 +67,75 @@ impl StateManager {
         }
     }
 
+    /// Record why the last auto-tare fired so the UI can explain it
+    pub async fn set_last_auto_tare_reason(&self, reason: AutoTareReason) {
+        let mut state = self.state.lock().await;
+        state.last_auto_tare_reason = Some(reason);
+        self.add_log_message(&mut state, format!("Auto-tare: {}", reason));
+    }
+
+    pub async fn set_relay_stats(&self, stats: RelayStats) {
+        self.state.lock().await.relay_stats = stats;
+    }
+
+    pub async fn set_relay_boot_check(&self, check: RelayBootCheck) {
+        self.state.lock().await.relay_boot_check = check;
+    }
+
+    pub async fn set_safety_status(&self, status: SafetyStatus) {
+        self.state.lock().await.safety = status;
+    }
+
+    pub async fn set_overshoot_status(&self, status: OvershootStatus) {
+        self.state.lock().await.overshoot = status;
+    }
+
+    pub async fn set_overshoot_profiles(&self, active: String, names: Vec<String>) {
+        let mut state = self.state.lock().await;
+        state.overshoot_profile = active;
+        state.overshoot_profiles = names;
+    }
+
+    pub async fn set_brew_snapshot(&self, snapshot: BrewSnapshot) {
+        self.state.lock().await.brew = snapshot;
+    }
+
+    pub async fn set_dose_status(&self, status: DoseStatus) {
+        self.state.lock().await.dose = status;
+    }
+
+    pub async fn set_machine_state(&self, machine_state: MachineState) {
+        self.state.lock().await.machine_state = machine_state;
+    }
+
+    pub async fn set_scale_detector_status(&self, status: ScaleDetectorStatus) {
+        self.state.lock().await.scale_detector = status;
+    }
+
+    pub async fn set_nvs_writes(&self, writes: u32) {
+        self.state.lock().await.nvs_writes = writes;
+    }
+
+    pub async fn set_storage_available(&self, available: bool) {
+        self.state.lock().await.storage_available = available;
+    }
+
+    pub async fn set_scale_info(&self, info: Option<ScaleInfo>) {
+        self.state.lock().await.scale_info = info;
+    }
+
+    pub async fn set_link_quality(&self, report: Option<LinkQualityReport>) {
+        self.state.lock().await.link_quality = report;
+    }
+
+    pub async fn set_self_test(&self, report: SelfTestReport) {
+        self.state.lock().await.self_test = Some(report);
+    }
+
+    pub async fn set_nearby_devices(&self, devices: Vec<Device>) {
+        self.state.lock().await.nearby_devices = devices;
+    }
+
     pub async fn update_auto_tare_state(&self, auto_tare_state: AutoTareState) {
         let mut state = self.state.lock().await;
         if state.auto_tare_state != auto_tare_state {
@@ -86,6 +168,17 @@ impl StateManager {
         }
     }
 
+    pub async fn set_system_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().await;
+        if state.system_enabled != enabled {
+            state.system_enabled = enabled;
+            self.add_log_message(
+                &mut state,
+                format!("System: {}", if enabled { "Enabled" } else { "Disabled (killswitch)" }),
+            );
+        }
+    }
+
     pub async fn set_ble_connected(&self, connected: bool) {
         let mut state = self.state.lock().await;
         if state.ble_connected != connected {
@@ -98,6 +191,9 @@ impl StateManager {
                 }
             );
             state.ble_connected = connected;
+            if !connected {
+                state.ble_stage = None;
+            }
             self.add_log_message(
                 &mut state,
                 format!(
@@ -112,6 +208,19 @@ impl StateManager {
         }
     }
 
+    pub async fn set_ble_stage(&self, stage: Option<&'static str>) {
+        self.state.lock().await.ble_stage = stage;
+    }
+
+    pub async fn set_radio_priority(&self, priority: RadioPriority) {
+        let mut state = self.state.lock().await;
+        if state.radio_priority != priority {
+            info!("Radio priority: {:?}", priority);
+            state.radio_priority = priority;
+            self.add_log_message(&mut state, format!("Radio priority: {:?}", priority));
+        }
+    }
+
     pub async fn set_wifi_connected(&self, connected: bool) {
         let mut state = self.state.lock().await;
         if state.wifi_connected != connected {
@@ -142,29 +251,45 @@ impl StateManager {
         let mut state = self.state.lock().await;
         state.last_error = error.clone();
         if let Some(err) = error {
-            self.add_log_message(&mut state, format!("ERROR: {}", err));
+            Self::push_log_entry(&mut state, LogLevel::Error, err);
         }
     }
 
     pub async fn add_log(&self, message: String) {
+        self.add_log_with_level(LogLevel::Info, message).await;
+    }
+
+    pub async fn add_log_with_level(&self, level: LogLevel, message: String) {
         let mut state = self.state.lock().await;
-        self.add_log_message(&mut state, message);
+        Self::push_log_entry(&mut state, level, message);
+    }
+
+    /// Most recent log entries, oldest first
+    pub async fn get_logs(&self, limit: usize) -> Vec<LogEntry> {
+        let state = self.state.lock().await;
+        recent_log_entries(&state, limit)
     }
 
     fn add_log_message(&self, state: &mut SystemState, message: String) {
-        // Use a simple counter instead of timestamp for embedded compatibility
-        static mut COUNTER: u32 = 0;
-        let count = unsafe {
-            COUNTER += 1;
-            COUNTER
-        };
-        let log_entry = format!("[{}] {}", count, message);
-
-        if state.log_messages.len() >= 100 {
-            state.log_messages.remove(0);
+        Self::push_log_entry(state, LogLevel::Info, message);
+    }
+
+    fn push_log_entry(state: &mut SystemState, level: LogLevel, message: String) {
+        // Same filter as the console so a quiet device doesn't churn the ring either
+        if !log_level_enabled(level) {
+            return;
         }
 
-        let _ = state.log_messages.push(log_entry);
+        // Ring behaviour: drop the oldest entry once full
+        if state.log_entries.is_full() {
+            state.log_entries.remove(0);
+        }
+
+        let _ = state.log_entries.push(LogEntry {
+            timestamp_ms: Instant::now().as_millis(),
+            level,
+            message,
+        });
     }
 
     pub async fn get_current_weight(&self) -> Option<f32> {
@@ -172,9 +297,10 @@ impl StateManager {
         state.scale_data.as_ref().map(|d| d.weight_g)
     }
 
+    /// Effective brew target (per-cup target times cups)
     pub async fn get_target_weight(&self) -> f32 {
         let state = self.state.lock().await;
-        state.config.target_weight_g
+        state.config.total_target_weight_g()
     }
 
     pub async fn get_current_flow_rate(&self) -> Option<f32> {
@@ -182,6 +308,12 @@ impl StateManager {
         state.scale_data.as_ref().map(|d| d.flow_rate_g_per_s)
     }
 
+    /// Progress of the shot in flight, None unless brewing
+    pub async fn get_brew_progress(&self) -> Option<BrewProgress> {
+        let state = self.state.lock().await;
+        state.brew_progress()
+    }
+
     pub async fn is_auto_tare_enabled(&self) -> bool {
         let state = self.state.lock().await;
         state.config.auto_tare
@@ -241,3 +373,9 @@ impl StateManager {
         self.add_log_message(&mut state, "System reset to idle state".to_string());
     }
 }
+
+/// Last `limit` log entries, oldest first - shared by the API and the state manager
+pub fn recent_log_entries(state: &SystemState, limit: usize) -> Vec<LogEntry> {
+    let skip = state.log_entries.len().saturating_sub(limit);
+    state.log_entries[skip..].to_vec()
+}
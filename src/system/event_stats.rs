@@ -0,0 +1,213 @@
+//! Per-event counters for the bus - how often each kind of event is published and handed to
+//! subscribers, so a flood from a misbehaving scale or a too-tight loop shows up by name
+
+use crate::system::events::{
+    BrewEvent, HardwareEvent, NetworkEvent, SafetyEvent, ScaleEvent, SystemEvent, TimeEvent,
+    UserEvent,
+};
+use core::cell::RefCell;
+use core::mem::{discriminant, Discriminant};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::{Duration, Instant};
+use std::collections::HashMap;
+
+/// One kind of event - the variant, whatever it carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EventKind {
+    Scale(Discriminant<ScaleEvent>),
+    Brew(Discriminant<BrewEvent>),
+    User(Discriminant<UserEvent>),
+    Time(Discriminant<TimeEvent>),
+    Safety(Discriminant<SafetyEvent>),
+    Hardware(Discriminant<HardwareEvent>),
+    Network(Discriminant<NetworkEvent>),
+}
+
+impl EventKind {
+    fn of(event: &SystemEvent) -> Self {
+        match event {
+            SystemEvent::Scale(inner) => EventKind::Scale(discriminant(inner)),
+            SystemEvent::Brew(inner) => EventKind::Brew(discriminant(inner)),
+            SystemEvent::User(inner) => EventKind::User(discriminant(inner)),
+            SystemEvent::Time(inner) => EventKind::Time(discriminant(inner)),
+            SystemEvent::Safety(inner) => EventKind::Safety(discriminant(inner)),
+            SystemEvent::Hardware(inner) => EventKind::Hardware(discriminant(inner)),
+            SystemEvent::Network(inner) => EventKind::Network(discriminant(inner)),
+        }
+    }
+}
+
+/// `Scale::WeightChanged` and the like - worked out from `Debug` the first time a kind is seen
+fn event_name(event: &SystemEvent) -> String {
+    let debug = format!("{:?}", event);
+    let (category, inner) = debug.split_once('(').unwrap_or((debug.as_str(), ""));
+    let variant = inner.split([' ', '(', '{', ')']).next().unwrap_or_default();
+    format!("{}::{}", category, variant)
+}
+
+/// Counts for one kind of event
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventCount {
+    pub name: String,
+    /// Publishes since boot
+    pub published: u64,
+    /// Times a subscriber took it off the bus since boot - filtered subscribers count too
+    pub delivered: u64,
+    /// Publishes since the last `take_window`
+    window_published: u64,
+}
+
+/// Publishes per kind over the span since the previous window
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventWindow {
+    pub span: Duration,
+    /// Busiest first, kinds not published in the window left out
+    pub counts: Vec<(String, u64)>,
+}
+
+impl EventWindow {
+    /// One line for the log - the busiest kinds with their rate
+    pub fn summary(&self, limit: usize) -> String {
+        let secs = (self.span.as_millis() as f32 / 1000.0).max(0.001);
+        if self.counts.is_empty() {
+            return format!("No events in the last {:.1}s", secs);
+        }
+        let busiest: Vec<String> = self
+            .counts
+            .iter()
+            .take(limit)
+            .map(|(name, count)| format!("{} {:.1}/s", name, *count as f32 / secs))
+            .collect();
+        format!("Events over the last {:.1}s: {}", secs, busiest.join(", "))
+    }
+}
+
+struct Counters {
+    by_kind: HashMap<EventKind, EventCount>,
+    window_start: Instant,
+}
+
+/// Bus-wide counters - shared by every publisher and subscriber of one `EventBus`
+pub struct EventStats {
+    counters: Mutex<CriticalSectionRawMutex, RefCell<Counters>>,
+}
+
+impl EventStats {
+    pub fn new() -> Self {
+        Self {
+            counters: Mutex::new(RefCell::new(Counters {
+                by_kind: HashMap::new(),
+                window_start: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn record_published(&self, event: &SystemEvent) {
+        self.counters.lock(|counters| {
+            let mut counters = counters.borrow_mut();
+            let count = counters
+                .by_kind
+                .entry(EventKind::of(event))
+                .or_insert_with(|| EventCount {
+                    name: event_name(event),
+                    published: 0,
+                    delivered: 0,
+                    window_published: 0,
+                });
+            count.published += 1;
+            count.window_published += 1;
+        });
+    }
+
+    pub fn record_delivered(&self, event: &SystemEvent) {
+        self.counters.lock(|counters| {
+            // Every event was counted on its way in, so the kind is already there
+            if let Some(count) = counters.borrow_mut().by_kind.get_mut(&EventKind::of(event)) {
+                count.delivered += 1;
+            }
+        });
+    }
+
+    /// Totals since boot, busiest first
+    pub fn snapshot(&self) -> Vec<EventCount> {
+        let mut counts: Vec<EventCount> =
+            self.counters.lock(|counters| counters.borrow().by_kind.values().cloned().collect());
+        counts.sort_by(|a, b| b.published.cmp(&a.published).then_with(|| a.name.cmp(&b.name)));
+        counts
+    }
+
+    /// Publishes since the previous call, which starts a new window
+    pub fn take_window(&self, now: Instant) -> EventWindow {
+        self.counters.lock(|counters| {
+            let mut counters = counters.borrow_mut();
+            let span = now.saturating_duration_since(counters.window_start);
+            counters.window_start = now;
+            let mut counts: Vec<(String, u64)> = counters
+                .by_kind
+                .values_mut()
+                .filter(|count| count.window_published > 0)
+                .map(|count| (count.name.clone(), std::mem::take(&mut count.window_published)))
+                .collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            EventWindow { span, counts }
+        })
+    }
+}
+
+impl Default for EventStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ScaleData;
+
+    fn weight() -> SystemEvent {
+        SystemEvent::Scale(ScaleEvent::WeightChanged {
+            data: ScaleData {
+                timestamp_ms: 0,
+                weight_g: 18.0,
+                flow_rate_g_per_s: 0.0,
+                battery_percent: 100,
+                timer_running: false,
+                received_at: Instant::from_millis(0),
+            },
+        })
+    }
+
+    #[test]
+    fn test_counts_by_variant_not_payload() {
+        let stats = EventStats::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            stats.record_published(&weight());
+            stats.record_delivered(&weight());
+            stats.record_delivered(&weight());
+        }
+        stats.record_published(&SystemEvent::Time(TimeEvent::Tick));
+        stats.record_published(&SystemEvent::User(UserEvent::SetTargetWeight(36.0)));
+        stats.record_published(&SystemEvent::User(UserEvent::SetTargetWeight(40.0)));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot[0].name, "Scale::WeightChanged");
+        assert_eq!((snapshot[0].published, snapshot[0].delivered), (3, 6));
+        assert_eq!(snapshot[1].name, "User::SetTargetWeight");
+        assert_eq!(snapshot[1].published, 2);
+        assert_eq!(snapshot[2].name, "Time::Tick");
+
+        let window = stats.take_window(start + Duration::from_secs(2));
+        assert_eq!(window.counts[0], ("Scale::WeightChanged".to_string(), 3));
+        let summary = window.summary(1);
+        assert!(summary.ends_with(": Scale::WeightChanged 1.5/s"), "{}", summary);
+
+        // The window starts over, the totals don't
+        stats.record_published(&SystemEvent::Time(TimeEvent::Tick));
+        let window = stats.take_window(start + Duration::from_secs(3));
+        assert_eq!(window.counts, vec![("Time::Tick".to_string(), 1)]);
+        assert_eq!(stats.snapshot()[0].published, 3);
+    }
+}
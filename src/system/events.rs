@@ -1,13 +1,22 @@
 //! World-class event bus for the espresso controller
 //! Clean, type-safe interface hiding embassy-sync complexity
 
-use crate::types::{BrewState, ScaleData};
+use crate::ble::Device;
+use crate::brewing::profile::FlowProfile;
+use crate::types::{
+    AutoTareProfile, AutoTareReason, BrewConfig, BrewState, ConfirmableCommand, LogLevel,
+    OvershootLearningSchedule, ScaleData, StopMode, WeightUnit,
+};
+use crate::scales::link_quality::LinkQualityReport;
 use crate::scales::traits::{ScaleInfo, ScaleCommand as TraitScaleCommand};
+use crate::system::event_stats::EventStats;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
-    pubsub::{PubSubChannel, Publisher, Subscriber},
+    pubsub::{ImmediatePublisher, PubSubChannel, Publisher, Subscriber},
 };
 use embassy_time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use core::marker::PhantomData;
 use std::sync::Arc;
 
 // === COMPREHENSIVE EVENT HIERARCHY ===
@@ -31,6 +40,12 @@ pub enum ScaleEvent {
     WeightChanged { data: ScaleData },
     Connected { info: ScaleInfo },
     Disconnected { reason: String },
+    /// Model and firmware read from the device after connecting
+    InfoUpdated { info: ScaleInfo },
+    /// Results of a user-requested device scan
+    DevicesDiscovered { devices: Vec<Device> },
+    /// Link quality score, reported periodically while connected
+    LinkQualityUpdated { report: LinkQualityReport },
     
     // Inferred user actions (from ScaleEventDetector strategies)
     ButtonPressed(ScaleButton),
@@ -60,35 +75,98 @@ pub enum BrewEvent {
     Started { target_weight: f32 },
     TargetWeightReached { actual: f32, target: f32 },
     PredictiveStopTriggered { predicted_overshoot: f32 },
+    /// Predictive stop disabled - where it would have stopped
+    PredictiveStopPreview { predicted: f32 },
+    /// Auto mode switched predictive stop as overshoot learning became ready or lost confidence
+    PredictiveStopAutoChanged { enabled: bool, confidence: f32 },
     Finished { final_weight: f32, duration_ms: u32 },
+    /// Throttled to `BREW_PROGRESS_INTERVAL_MS` - percent of the total target, ETA at current flow
+    Progress { percent: f32, eta_ms: Option<u32> },
+    /// Dry dose locked in from the dose-weighing mode
+    DoseCaptured { grams: f32 },
     
     // Auto-tare events
-    AutoTareTriggered { reason: String },
+    AutoTareTriggered { reason: AutoTareReason },
     ObjectDetected { weight: f32 },
     ObjectRemoved,
 }
 
 /// User-initiated events (web interface, future physical buttons)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum UserEvent {
     // Configuration changes
     SetTargetWeight(f32),
+    SetCups(u8),
     SetAutoTare(bool),
+    SetTareBeforeBrew(bool),
+    /// Whether a web start or stop has to be sent twice to go ahead
+    SetCommandConfirmation { command: ConfirmableCommand, enabled: bool },
     SetPredictiveStop(bool),
+    SetPredictiveStopAuto(bool),
+    SetAutoTareEmptyThreshold(f32),
+    SetAutoTareStableReadings(usize),
+    SetAutoTareProfile(AutoTareProfile),
+    SetAutoTareCooldown(u32),
+    SetAutoTareHold(u32),
+    SetPredictionWindow { safety_margin_ms: u32, multiplier: f32 },
+    SetOvershootWarmup(u32),
+    /// Grams off target the settled weight should land on, negative to undershoot
+    SetOvershootTarget(f32),
+    SetOvershootLearning(OvershootLearningSchedule),
+    SetStopMode(StopMode),
+    SetFlowStopHold(u32),
+    /// How long a mid-brew scale drop may last and still resume the shot, 0 ends it
+    SetScaleReconnectGrace(u32),
+    SetSettleStability(f32),
+    SetBrewTime(u32),
+    /// None switches flow profiling off
+    SetFlowProfile(Option<FlowProfile>),
+    SetPredictionThresholds {
+        min_flow_for_prediction: f32,
+        flow_stopped_threshold: f32,
+        prediction_start_delay_ms: u32,
+    },
+    SetLogLevel(LogLevel),
+    SetScaleNamePrefix(String),
+    SetWeightRateLimit(f32),
+    SetScaleKeepAlive(u32),
+    SetBrewTraceInterval(u32),
+    SetBlePriorityBroadcast(u32),
+    SetDisplayRefresh(u32),
+    SetWeightStream(u32),
+    SetWeightUnit(WeightUnit),
+    SetBrewRatio(Option<f32>),
+    /// Replace the whole config at once - validated as a unit, nothing applies if any field fails
+    SetConfig(Box<BrewConfig>),
+    
+    // Dose weighing - a stable reading becomes the pending dose, ConfirmDose locks it in
+    SetDoseWeighing(bool),
+    ConfirmDose,
     
     // Manual actions
     TareScale,
     StartBrewing,
     StopBrewing,
     ResetTimer,
-    TestRelay,
+    /// Switch the relay on for this long - clamped to `MIN_RELAY_TEST_MS..=MAX_RELAY_TEST_MS`
+    TestRelay { duration_ms: u32 },
     ResetOvershoot,
+    SelectProfile(String),
+    /// Run the diagnostics checks - the report lands in `/api/status` as `self_test`
+    RunSelfTest,
+    /// Log the busiest event kinds since the last time this was asked
+    LogEventStats,
+    
+    // Scale picker
+    ScanDevices { duration_ms: u32 },
+    ConnectToDevice { address: String },
     
     // WiFi provisioning
     StartWifiProvisioning,
     ResetWifiCredentials,
     
     // System control
+    SetSystemEnabled(bool),
     EmergencyStop,
     RebootSystem,
 }
@@ -136,9 +214,11 @@ impl AlertLevel {
 /// Hardware control events (pure side effects)
 #[derive(Debug, Clone)]
 pub enum HardwareEvent {
-    // Relay control
-    RelayOn,
-    RelayOff,
+    // Relay control (channel 0 is the pump - see PRIMARY_RELAY_CHANNEL)
+    RelayOn { channel: usize },
+    RelayOff { channel: usize },
+    RelayPower { channel: usize, fraction: f32 }, // Proportional power 0.0..=1.0 (PWM relays only)
+    AllRelaysOff,                                 // Safety: every channel off
     
     // Scale commands
     SendScaleCommand(ScaleCommand),
@@ -157,18 +237,40 @@ pub struct DisplayState {
     pub target_weight_g: f32,
     pub flow_rate_g_per_s: f32,
     pub timer_running: bool,
+    pub timer_ms: u32,
     pub brew_state: String,
     pub ble_connected: bool,
     pub battery_percent: u8,
     pub error: Option<String>,
 }
 
+impl Default for DisplayState {
+    fn default() -> Self {
+        Self {
+            weight_g: 0.0,
+            target_weight_g: 36.0,
+            flow_rate_g_per_s: 0.0,
+            timer_running: false,
+            timer_ms: 0,
+            brew_state: "Idle".to_string(),
+            ble_connected: false,
+            battery_percent: 0,
+            error: None,
+        }
+    }
+}
+
 /// Network and connectivity events
 #[derive(Debug, Clone)]
 pub enum NetworkEvent {
     WifiConnected { ssid: String },
     WifiDisconnected,
+    /// Scale connection progress - scanning, connecting, connected, discovering, subscribed
+    BleScanning,
+    BleConnecting,
     BleConnected { device_name: String },
+    BleDiscovering,
+    BleSubscribed,
     BleDisconnected,
     WebSocketClientConnected,
     WebSocketClientDisconnected,
@@ -178,24 +280,90 @@ pub enum NetworkEvent {
 
 // === CLEAN EVENT BUS INTERFACE ===
 
+/// Events buffered on the bus; each stays queued until every subscriber has read it
+pub const EVENT_BUS_CAPACITY: usize = 64;
+/// An event that waits longer than this for the control loop is logged - samples arrive at 10Hz
+pub const EVENT_BUS_SLOW_DELIVERY_MS: u64 = 200;
+pub const EVENT_BUS_MAX_SUBSCRIBERS: usize = 8;
+pub const EVENT_BUS_MAX_PUBLISHERS: usize = 8;
+
+/// A bus event and the moment it was published - the envelope the channel carries, so events
+/// from different publishers can be put in order without touching the event types themselves
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    /// Monotonic time of the `publish` call
+    pub published_at: Instant,
+    pub event: SystemEvent,
+}
+
+impl TimedEvent {
+    pub fn now(event: SystemEvent) -> Self {
+        Self {
+            published_at: Instant::now(),
+            event,
+        }
+    }
+
+    /// How long the event has been on the bus
+    pub fn age(&self) -> Duration {
+        Instant::now().saturating_duration_since(self.published_at)
+    }
+}
+
+type EventChannel = PubSubChannel<
+    CriticalSectionRawMutex,
+    TimedEvent,
+    EVENT_BUS_CAPACITY,
+    EVENT_BUS_MAX_SUBSCRIBERS,
+    EVENT_BUS_MAX_PUBLISHERS,
+>;
+type EventChannelSubscriber<'a> = Subscriber<
+    'a,
+    CriticalSectionRawMutex,
+    TimedEvent,
+    EVENT_BUS_CAPACITY,
+    EVENT_BUS_MAX_SUBSCRIBERS,
+    EVENT_BUS_MAX_PUBLISHERS,
+>;
+
 /// World-class event bus with clean, type-safe interface
 /// Hides embassy-sync complexity behind simple publish/subscribe API
+///
+/// Delivery semantics:
+/// - The bus is bounded at `EVENT_BUS_CAPACITY` events - nothing is queued unbounded
+/// - `EventPublisher::publish` applies backpressure: when the buffer is full it waits until
+///   the slowest subscriber catches up, so no event is ever dropped on that path
+/// - Only immediate (non-waiting) publishes can overwrite unread events; a subscriber that
+///   misses events counts them (`lagged_events`) and raises `SafetyEvent::SystemAlert`
+/// - A task must never await `publish` on the bus it is the slowest subscriber of while the
+///   buffer is full - keep subscribers draining promptly
+/// - Every event is stamped when it is published; `EventSubscriber::next_timed_event` hands
+///   out the stamp, the other receive methods strip it
+/// - Every publish and every hand-off to a subscriber is counted per event kind in `stats`
 pub struct EventBus {
     // Single channel for all system events
-    channel: PubSubChannel<CriticalSectionRawMutex, SystemEvent, 64, 8, 8>,
+    channel: EventChannel,
+    stats: Arc<EventStats>,
 }
 
 impl EventBus {
     pub fn new() -> Self {
         Self {
             channel: PubSubChannel::new(),
+            stats: Arc::new(EventStats::new()),
         }
     }
 
+    /// Per-kind event counters, for `/metrics` and the `log_event_stats` command
+    pub fn stats(&self) -> Arc<EventStats> {
+        Arc::clone(&self.stats)
+    }
+
     /// Get a publisher handle - clean interface
     pub fn publisher(&self) -> EventPublisher {
         EventPublisher {
             inner: self.channel.publisher().unwrap(),
+            stats: &self.stats,
         }
     }
 
@@ -203,6 +371,8 @@ impl EventBus {
     pub fn subscriber(&self) -> EventSubscriber {
         EventSubscriber {
             inner: self.channel.subscriber().unwrap(),
+            lag: self.lag_monitor("events"),
+            stats: &self.stats,
         }
     }
 
@@ -213,10 +383,31 @@ impl EventBus {
     {
         FilteredEventSubscriber {
             inner: self.channel.subscriber().unwrap(),
+            lag: self.lag_monitor("filtered"),
+            stats: &self.stats,
             filter,
         }
     }
 
+    /// Subscribe to a single event category - receives the inner event, no re-matching on `SystemEvent`
+    pub fn subscribe<T: EventCategory>(&self) -> TypedEventSubscriber<T> {
+        TypedEventSubscriber {
+            inner: self.channel.subscriber().unwrap(),
+            lag: self.lag_monitor(core::any::type_name::<T>()),
+            stats: &self.stats,
+            _category: PhantomData,
+        }
+    }
+
+    fn lag_monitor(&self, subscriber: &'static str) -> LagMonitor {
+        LagMonitor {
+            alerts: self.channel.immediate_publisher(),
+            stats: &self.stats,
+            subscriber,
+            lagged: 0,
+        }
+    }
+
     /// Convenience method: subscribe only to scale events
     pub fn scale_events_subscriber(&self) -> FilteredEventSubscriber<impl Fn(&SystemEvent) -> bool> {
         self.filtered_subscriber(|event| matches!(event, SystemEvent::Scale(_)))
@@ -240,13 +431,22 @@ impl EventBus {
 
 /// Clean publisher interface - no exposed embassy types
 pub struct EventPublisher<'a> {
-    inner: Publisher<'a, CriticalSectionRawMutex, SystemEvent, 64, 8, 8>,
+    inner: Publisher<
+        'a,
+        CriticalSectionRawMutex,
+        TimedEvent,
+        EVENT_BUS_CAPACITY,
+        EVENT_BUS_MAX_SUBSCRIBERS,
+        EVENT_BUS_MAX_PUBLISHERS,
+    >,
+    stats: &'a EventStats,
 }
 
 impl<'a> EventPublisher<'a> {
     /// Publish any system event - single clean interface
     pub async fn publish(&self, event: SystemEvent) {
-        self.inner.publish(event).await;
+        self.stats.record_published(&event);
+        self.inner.publish(TimedEvent::now(event)).await;
     }
 
     /// Convenience methods for common events
@@ -267,32 +467,120 @@ impl<'a> EventPublisher<'a> {
     }
 
     pub async fn relay_on(&self) {
-        self.publish(SystemEvent::Hardware(HardwareEvent::RelayOn)).await;
+        self.relay_on_channel(crate::hardware::relay::PRIMARY_RELAY_CHANNEL).await;
     }
 
     pub async fn relay_off(&self) {
-        self.publish(SystemEvent::Hardware(HardwareEvent::RelayOff)).await;
+        self.relay_off_channel(crate::hardware::relay::PRIMARY_RELAY_CHANNEL).await;
+    }
+
+    pub async fn relay_on_channel(&self, channel: usize) {
+        self.publish(SystemEvent::Hardware(HardwareEvent::RelayOn { channel })).await;
+    }
+
+    pub async fn relay_off_channel(&self, channel: usize) {
+        self.publish(SystemEvent::Hardware(HardwareEvent::RelayOff { channel })).await;
+    }
+
+    pub async fn all_relays_off(&self) {
+        self.publish(SystemEvent::Hardware(HardwareEvent::AllRelaysOff)).await;
     }
 }
 
 /// Clean subscriber interface
 pub struct EventSubscriber<'a> {
-    inner: Subscriber<'a, CriticalSectionRawMutex, SystemEvent, 64, 8, 8>,
+    inner: EventChannelSubscriber<'a>,
+    lag: LagMonitor<'a>,
+    stats: &'a EventStats,
 }
 
 impl<'a> EventSubscriber<'a> {
     /// Wait for any system event
     pub async fn next_event(&mut self) -> SystemEvent {
+        self.next_timed_event().await.event
+    }
+
+    /// Wait for any system event, with the time it was published
+    pub async fn next_timed_event(&mut self) -> TimedEvent {
         loop {
             match self.inner.next_message().await {
-                embassy_sync::pubsub::WaitResult::Lagged(_count) => {
-                    // If lagged, continue to next iteration
+                embassy_sync::pubsub::WaitResult::Lagged(count) => {
+                    self.lag.record(count);
+                    continue;
+                }
+                embassy_sync::pubsub::WaitResult::Message(timed) => {
+                    self.stats.record_delivered(&timed.event);
+                    return timed;
+                }
+            }
+        }
+    }
+
+    /// Next event if one is already queued, without waiting
+    pub fn try_next_event(&mut self) -> Option<SystemEvent> {
+        self.try_next_timed_event().map(|timed| timed.event)
+    }
+
+    /// Next queued event with the time it was published, without waiting
+    pub fn try_next_timed_event(&mut self) -> Option<TimedEvent> {
+        loop {
+            match self.inner.try_next_message()? {
+                embassy_sync::pubsub::WaitResult::Lagged(count) => {
+                    self.lag.record(count);
                     continue;
                 }
-                embassy_sync::pubsub::WaitResult::Message(event) => return event,
+                embassy_sync::pubsub::WaitResult::Message(timed) => {
+                    self.stats.record_delivered(&timed.event);
+                    return Some(timed);
+                }
             }
         }
     }
+
+    /// Events this subscriber missed because it fell behind
+    pub fn lagged_events(&self) -> u64 {
+        self.lag.lagged
+    }
+}
+
+/// Per-subscriber count of missed events, reported on the bus as a `SystemAlert`
+struct LagMonitor<'a> {
+    alerts: ImmediatePublisher<
+        'a,
+        CriticalSectionRawMutex,
+        TimedEvent,
+        EVENT_BUS_CAPACITY,
+        EVENT_BUS_MAX_SUBSCRIBERS,
+        EVENT_BUS_MAX_PUBLISHERS,
+    >,
+    stats: &'a EventStats,
+    subscriber: &'static str,
+    lagged: u64,
+}
+
+impl<'a> LagMonitor<'a> {
+    fn record(&mut self, count: u64) {
+        self.lagged = self.lagged.saturating_add(count);
+        log::error!(
+            "📡 Event subscriber '{}' lagged: {} event(s) dropped ({} total)",
+            self.subscriber,
+            count,
+            self.lagged
+        );
+
+        // try_publish never overwrites, so the alert itself cannot make anyone else lag
+        let alert = SystemEvent::Safety(SafetyEvent::SystemAlert {
+            level: AlertLevel::Error,
+            message: format!(
+                "Event subscriber '{}' dropped {} event(s)",
+                self.subscriber, count
+            ),
+        });
+        self.stats.record_published(&alert);
+        if self.alerts.try_publish(TimedEvent::now(alert)).is_err() {
+            log::error!("📡 Event bus full - lag alert for '{}' not delivered", self.subscriber);
+        }
+    }
 }
 
 /// Filtered subscriber - only receives events matching the filter
@@ -300,7 +588,9 @@ pub struct FilteredEventSubscriber<'a, F>
 where
     F: Fn(&SystemEvent) -> bool + Send + Sync,
 {
-    inner: Subscriber<'a, CriticalSectionRawMutex, SystemEvent, 64, 8, 8>,
+    inner: EventChannelSubscriber<'a>,
+    lag: LagMonitor<'a>,
+    stats: &'a EventStats,
     filter: F,
 }
 
@@ -312,11 +602,14 @@ where
     pub async fn next_event(&mut self) -> SystemEvent {
         loop {
             let event = match self.inner.next_message().await {
-                embassy_sync::pubsub::WaitResult::Lagged(_count) => {
-                    // If lagged, continue to next iteration
+                embassy_sync::pubsub::WaitResult::Lagged(count) => {
+                    self.lag.record(count);
                     continue;
                 }
-                embassy_sync::pubsub::WaitResult::Message(event) => event,
+                embassy_sync::pubsub::WaitResult::Message(timed) => {
+                    self.stats.record_delivered(&timed.event);
+                    timed.event
+                }
             };
             if (self.filter)(&event) {
                 return event;
@@ -330,11 +623,14 @@ where
             match self.inner.try_next_message() {
                 Some(wait_result) => {
                     let event = match wait_result {
-                        embassy_sync::pubsub::WaitResult::Lagged(_count) => {
-                            // If lagged, continue to next iteration
+                        embassy_sync::pubsub::WaitResult::Lagged(count) => {
+                            self.lag.record(count);
                             continue;
                         }
-                        embassy_sync::pubsub::WaitResult::Message(event) => event,
+                        embassy_sync::pubsub::WaitResult::Message(timed) => {
+                            self.stats.record_delivered(&timed.event);
+                            timed.event
+                        }
                     };
                     if (self.filter)(&event) {
                         return Some(event);
@@ -345,6 +641,90 @@ where
             }
         }
     }
+
+    /// Events this subscriber missed because it fell behind
+    pub fn lagged_events(&self) -> u64 {
+        self.lag.lagged
+    }
+}
+
+/// An event category that can be unwrapped from a `SystemEvent`
+pub trait EventCategory: Sized {
+    fn extract(event: SystemEvent) -> Option<Self>;
+}
+
+macro_rules! impl_event_category {
+    ($($variant:ident => $ty:ty),* $(,)?) => {
+        $(
+            impl EventCategory for $ty {
+                fn extract(event: SystemEvent) -> Option<Self> {
+                    match event {
+                        SystemEvent::$variant(inner) => Some(inner),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_event_category! {
+    Scale => ScaleEvent,
+    Brew => BrewEvent,
+    User => UserEvent,
+    Time => TimeEvent,
+    Safety => SafetyEvent,
+    Hardware => HardwareEvent,
+    Network => NetworkEvent,
+}
+
+/// Typed subscriber - only yields events of category `T`, already unwrapped
+pub struct TypedEventSubscriber<'a, T: EventCategory> {
+    inner: EventChannelSubscriber<'a>,
+    lag: LagMonitor<'a>,
+    stats: &'a EventStats,
+    _category: PhantomData<T>,
+}
+
+impl<'a, T: EventCategory> TypedEventSubscriber<'a, T> {
+    /// Wait for the next event of this category
+    pub async fn next_event(&mut self) -> T {
+        loop {
+            match self.inner.next_message().await {
+                embassy_sync::pubsub::WaitResult::Lagged(count) => {
+                    self.lag.record(count);
+                    continue;
+                }
+                embassy_sync::pubsub::WaitResult::Message(timed) => {
+                    self.stats.record_delivered(&timed.event);
+                    if let Some(event) = T::extract(timed.event) {
+                        return event;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Try to get the next event of this category without blocking
+    pub fn try_next_event(&mut self) -> Option<T> {
+        while let Some(wait_result) = self.inner.try_next_message() {
+            match wait_result {
+                embassy_sync::pubsub::WaitResult::Lagged(count) => self.lag.record(count),
+                embassy_sync::pubsub::WaitResult::Message(timed) => {
+                    self.stats.record_delivered(&timed.event);
+                    if let Some(event) = T::extract(timed.event) {
+                        return Some(event);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Events this subscriber missed because it fell behind
+    pub fn lagged_events(&self) -> u64 {
+        self.lag.lagged
+    }
 }
 
 // === CONVENIENCE TRAITS FOR CLEAN INTEGRATION ===
@@ -365,3 +745,50 @@ pub trait EventSubscribing {
     type Subscriber;
     fn get_event_subscriber(&mut self) -> &mut Self::Subscriber;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_stamped_when_published() {
+        embassy_futures::block_on(async {
+            let bus = EventBus::new();
+            let mut subscriber = bus.subscriber();
+            let publisher = bus.publisher();
+            let before = Instant::now();
+            publisher.publish(SystemEvent::Time(TimeEvent::Tick)).await;
+            publisher.user_command(UserEvent::StartBrewing).await;
+
+            let first = subscriber.try_next_timed_event().unwrap();
+            assert!(matches!(first.event, SystemEvent::Time(TimeEvent::Tick)));
+            assert!(first.published_at >= before);
+            // Untimed receives see the same events, the stamp stripped
+            let second = subscriber.try_next_event().unwrap();
+            assert!(matches!(second, SystemEvent::User(UserEvent::StartBrewing)));
+            assert!(subscriber.try_next_event().is_none());
+        });
+    }
+
+    #[test]
+    fn test_bus_counts_every_publish_and_hand_off() {
+        embassy_futures::block_on(async {
+            let bus = EventBus::new();
+            let mut all = bus.subscriber();
+            let mut user = bus.subscribe::<UserEvent>();
+            let publisher = bus.publisher();
+            publisher.publish(SystemEvent::Time(TimeEvent::Tick)).await;
+            publisher.publish(SystemEvent::Time(TimeEvent::Tick)).await;
+            publisher.user_command(UserEvent::TareScale).await;
+            while all.try_next_event().is_some() {}
+            assert!(matches!(user.try_next_event(), Some(UserEvent::TareScale)));
+
+            // The typed subscriber took both ticks off the bus on the way to the tare
+            let counts = bus.stats().snapshot();
+            assert_eq!(counts[0].name, "Time::Tick");
+            assert_eq!((counts[0].published, counts[0].delivered), (2, 4));
+            assert_eq!(counts[1].name, "User::TareScale");
+            assert_eq!((counts[1].published, counts[1].delivered), (1, 2));
+        });
+    }
+}
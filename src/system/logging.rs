@@ -0,0 +1,42 @@
+//! Runtime log level control on top of the ESP-IDF logger
+//! Quiet in production, verbose while diagnosing - without reflashing
+
+use crate::types::LogLevel;
+use esp_idf_svc::sys::{
+    esp_log_level_set, esp_log_level_t_ESP_LOG_DEBUG, esp_log_level_t_ESP_LOG_ERROR,
+    esp_log_level_t_ESP_LOG_INFO, esp_log_level_t_ESP_LOG_WARN,
+};
+use log::{info, LevelFilter};
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+        }
+    }
+}
+
+/// Whether a message at `level` passes the current runtime filter
+pub fn log_level_enabled(level: LogLevel) -> bool {
+    LevelFilter::from(level) <= log::max_level()
+}
+
+/// Apply a log level to Rust `log` macros and native ESP-IDF components alike
+pub fn set_log_level(level: LogLevel) {
+    log::set_max_level(level.into());
+
+    let esp_level = match level {
+        LogLevel::Error => esp_log_level_t_ESP_LOG_ERROR,
+        LogLevel::Warn => esp_log_level_t_ESP_LOG_WARN,
+        LogLevel::Info => esp_log_level_t_ESP_LOG_INFO,
+        LogLevel::Debug => esp_log_level_t_ESP_LOG_DEBUG,
+    };
+    unsafe {
+        esp_log_level_set(c"*".as_ptr(), esp_level);
+    }
+
+    info!("📝 Log level set to {:?}", level);
+}
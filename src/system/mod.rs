@@ -1,9 +1,17 @@
 pub mod config;
+pub mod event_stats;
 pub mod events;
+pub mod logging;
+pub mod nvs_store;
 pub mod safety;
+pub mod self_test;
+pub mod shutdown;
 pub mod storage;
+pub mod watchdog;
 
 pub use config::*;
 pub use events::*;
+pub use logging::*;
 pub use safety::*;
 pub use storage::*;
+pub use watchdog::*;
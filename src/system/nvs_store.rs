@@ -0,0 +1,210 @@
+//! Key-value backends under `NvsStorage` - flash on the device, memory when NVS is unavailable.
+//! The storage layer above caches, debounces and migrates the same way over either one.
+
+use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsCustom};
+use esp_idf_svc::sys::EspError;
+use log::info;
+use std::collections::HashMap;
+
+// NVS namespace for our application
+const NVS_NAMESPACE: &str = "gravel_brew";
+
+/// Room for every blob the storage layer writes several times over
+pub const MEMORY_STORE_CAPACITY_BYTES: usize = 16 * 1024;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Esp(EspError),
+    /// The in-memory store is bounded - this value would take it past its capacity
+    Full { needed: usize, capacity: usize },
+    /// The stored blob is longer than the buffer it was read into
+    BufferTooSmall { len: usize },
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::Esp(e) => write!(f, "NVS error: {:?}", e),
+            StoreError::Full { needed, capacity } => {
+                write!(f, "Memory store full: {} bytes needed, {} available", needed, capacity)
+            }
+            StoreError::BufferTooSmall { len } => {
+                write!(f, "Stored value is {} bytes, larger than the read buffer", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<EspError> for StoreError {
+    fn from(e: EspError) -> Self {
+        StoreError::Esp(e)
+    }
+}
+
+/// The `EspNvs` calls the storage layer makes - blobs plus the one-byte layout and self-test keys
+pub trait NvsStore {
+    fn get_blob<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StoreError>;
+    /// Stored size of a blob, so it can be read into a buffer that fits
+    fn blob_len(&self, key: &str) -> Result<Option<usize>, StoreError>;
+    fn set_blob(&mut self, key: &str, data: &[u8]) -> Result<(), StoreError>;
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, StoreError>;
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), StoreError>;
+    /// True when the key existed
+    fn remove(&mut self, key: &str) -> Result<bool, StoreError>;
+    /// Whether values survive a reboot
+    fn is_persistent(&self) -> bool;
+}
+
+/// The app namespace in flash
+pub struct EspNvsStore {
+    nvs: EspNvs<NvsCustom>,
+}
+
+impl EspNvsStore {
+    pub fn new() -> Result<Self, EspError> {
+        // Try to use a custom NVS partition (separate from WiFi)
+        // If custom partition doesn't exist, fall back to default
+        let partition = EspNvsPartition::<NvsCustom>::take("nvs_custom").or_else(|_| {
+            info!("Custom NVS partition not found, using default NVS");
+            EspNvsPartition::<NvsCustom>::take("nvs")
+        })?;
+        let nvs = EspNvs::new(partition, NVS_NAMESPACE, true)?;
+        Ok(Self { nvs })
+    }
+}
+
+impl NvsStore for EspNvsStore {
+    fn get_blob<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StoreError> {
+        Ok(self.nvs.get_blob(key, buf)?)
+    }
+
+    fn blob_len(&self, key: &str) -> Result<Option<usize>, StoreError> {
+        Ok(self.nvs.blob_len(key)?)
+    }
+
+    fn set_blob(&mut self, key: &str, data: &[u8]) -> Result<(), StoreError> {
+        Ok(self.nvs.set_blob(key, data)?)
+    }
+
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, StoreError> {
+        Ok(self.nvs.get_u8(key)?)
+    }
+
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), StoreError> {
+        Ok(self.nvs.set_u8(key, value)?)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, StoreError> {
+        Ok(self.nvs.remove(key)?)
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+}
+
+/// Values kept in RAM and lost on reboot - bounded so a fallback can't eat the heap
+#[derive(Debug)]
+pub struct MemoryStore {
+    values: HashMap<String, Vec<u8>>,
+    capacity: usize,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: HashMap::new(),
+            capacity,
+        }
+    }
+
+    fn insert(&mut self, key: &str, data: &[u8]) -> Result<(), StoreError> {
+        let others: usize = self
+            .values
+            .iter()
+            .filter(|(stored_key, _)| stored_key.as_str() != key)
+            .map(|(_, value)| value.len())
+            .sum();
+        let needed = others + data.len();
+        if needed > self.capacity {
+            return Err(StoreError::Full {
+                needed,
+                capacity: self.capacity,
+            });
+        }
+        self.values.insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+}
+
+impl NvsStore for MemoryStore {
+    fn get_blob<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StoreError> {
+        let Some(value) = self.values.get(key) else {
+            return Ok(None);
+        };
+        let target = buf
+            .get_mut(..value.len())
+            .ok_or(StoreError::BufferTooSmall { len: value.len() })?;
+        target.copy_from_slice(value);
+        Ok(Some(target))
+    }
+
+    fn blob_len(&self, key: &str) -> Result<Option<usize>, StoreError> {
+        Ok(self.values.get(key).map(Vec::len))
+    }
+
+    fn set_blob(&mut self, key: &str, data: &[u8]) -> Result<(), StoreError> {
+        self.insert(key, data)
+    }
+
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, StoreError> {
+        Ok(self.values.get(key).and_then(|value| value.first().copied()))
+    }
+
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), StoreError> {
+        self.insert(key, &[value])
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, StoreError> {
+        Ok(self.values.remove(key).is_some())
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store_round_trips_and_stays_bounded() {
+        let mut store = MemoryStore::new(8);
+        store.set_blob("a", b"12345").unwrap();
+        store.set_u8("b", 7).unwrap();
+
+        let mut buf = [0u8; 16];
+        assert_eq!(store.get_blob("a", &mut buf).unwrap(), Some(&b"12345"[..]));
+        assert_eq!(store.get_u8("b").unwrap(), Some(7));
+        assert!(store.get_blob("missing", &mut buf).unwrap().is_none());
+        assert_eq!(store.blob_len("a").unwrap(), Some(5));
+        assert_eq!(store.blob_len("missing").unwrap(), None);
+        assert!(matches!(
+            store.get_blob("a", &mut [0u8; 2]),
+            Err(StoreError::BufferTooSmall { len: 5 })
+        ));
+
+        // 5 + 1 stored - a 3 byte blob doesn't fit, replacing one's own value does
+        assert!(matches!(
+            store.set_blob("c", b"xyz"),
+            Err(StoreError::Full { needed: 9, capacity: 8 })
+        ));
+        store.set_blob("a", b"1234567").unwrap();
+        assert!(store.remove("a").unwrap());
+        assert!(!store.remove("a").unwrap());
+        store.set_blob("c", b"xyz").unwrap();
+    }
+}
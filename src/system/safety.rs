@@ -1,10 +1,31 @@
-use crate::types::{SystemState, TimerState};
+use crate::brewing::BrewState as MachineState;
+use crate::types::{BrewState, SystemState, TimerState};
 use embassy_time::{Duration, Instant};
 use log::{error, info, warn};
+use serde::Serialize;
+
+/// Emergency stops requested within this window of the last one are treated as the same stop
+pub const EMERGENCY_STOP_DEBOUNCE_MS: u64 = 1000;
+
+/// What the safety checks currently see - how close the system is to a trip, and why the last one fired
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SafetyStatus {
+    /// Age of the newest scale sample (None before the first one)
+    pub last_data_age_ms: Option<u64>,
+    /// How long the relay has been on (None while off)
+    pub relay_on_duration_ms: Option<u64>,
+    /// Brewing checks are active (pump on, brewing or timer running)
+    pub armed: bool,
+    pub last_trip_reason: Option<String>,
+}
 
 pub struct SafetyController {
     last_data_received: Option<Instant>,
     last_relay_state: bool,
+    relay_on_since: Option<Instant>,
+    armed: bool,
+    last_trip_reason: Option<String>,
+    last_emergency_stop: Option<Instant>,
     watchdog_timeout: Duration,
 }
 
@@ -13,6 +34,10 @@ impl SafetyController {
         Self {
             last_data_received: None,
             last_relay_state: false,
+            relay_on_since: None,
+            armed: false,
+            last_trip_reason: None,
+            last_emergency_stop: None,
             watchdog_timeout: Duration::from_secs(10),
         }
     }
@@ -21,62 +46,134 @@ impl SafetyController {
         self.last_data_received = Some(Instant::now());
     }
 
-    pub fn should_emergency_stop(&mut self, state: &SystemState) -> bool {
-        let now = Instant::now();
+    /// Whether the last sample is older than `timeout` (or none has arrived yet)
+    fn data_is_stale(&self, now: Instant, timeout: Duration) -> bool {
+        match self.last_data_received {
+            Some(last_received) => now.saturating_duration_since(last_received) > timeout,
+            None => true,
+        }
+    }
+
+    /// `machine_state` is the brew state machine's own view, which the UI state can lag behind
+    pub fn should_emergency_stop(
+        &mut self,
+        state: &SystemState,
+        machine_state: MachineState,
+    ) -> bool {
+        self.armed = state.relay_enabled
+            || state.brew_state == BrewState::Brewing
+            || state.timer_state == TimerState::Running;
+
+        match self.trip_reason(state, machine_state, Instant::now()) {
+            Some(reason) => {
+                error!("SAFETY: {} - emergency stop", reason);
+                self.last_trip_reason = Some(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reason the last check tripped, for the emergency stop it raises
+    pub fn last_trip_reason(&self) -> Option<&str> {
+        self.last_trip_reason.as_deref()
+    }
+
+    /// Why the current state warrants an emergency stop, if it does
+    fn trip_reason(
+        &self,
+        state: &SystemState,
+        machine_state: MachineState,
+        now: Instant,
+    ) -> Option<String> {
+        // Only a brew turns the pump on - a relay on outside one means the side-effect handler
+        // and the state machine have lost track of each other
+        if state.relay_enabled && machine_state != MachineState::Brewing {
+            return Some(format!("Relay on with no brew active ({:?})", machine_state));
+        }
+
+        // Runaway reading or forgotten cup - applies whenever the pump may be running
+        if state.relay_enabled || state.brew_state == BrewState::Brewing {
+            if let Some(ref scale_data) = state.scale_data {
+                if scale_data.weight_g > state.config.max_weight_g {
+                    return Some(format!(
+                        "Weight {:.1}g exceeds cutoff {:.1}g",
+                        scale_data.weight_g, state.config.max_weight_g
+                    ));
+                }
+            }
+
+            // Mid-shot BLE stall - far shorter than the reconnect timeout
+            let stale_timeout = Duration::from_millis(state.config.stale_data_timeout_ms);
+            if self.data_is_stale(now, stale_timeout) {
+                return Some(format!(
+                    "No scale data for over {}ms while brewing",
+                    state.config.stale_data_timeout_ms
+                ));
+            }
+        }
 
         if state.timer_state == TimerState::Running {
             if !state.ble_connected {
-                error!("SAFETY: BLE disconnected during brewing - emergency stop");
-                return true;
+                return Some("BLE disconnected during brewing".to_string());
             }
 
             if let Some(last_received) = self.last_data_received {
                 if now.duration_since(last_received) > self.watchdog_timeout {
-                    error!("SAFETY: Data watchdog timeout during brewing - emergency stop");
-                    return true;
+                    return Some("Data watchdog timeout during brewing".to_string());
                 }
             } else {
-                error!("SAFETY: No data received during brewing - emergency stop");
-                return true;
+                return Some("No data received during brewing".to_string());
             }
 
             // TEMPORARY: Disable Wi-Fi safety check for BLE testing
             // if !state.wifi_connected {
-            //     warn!("SAFETY: Wi-Fi disconnected during brewing - emergency stop for safety");
-            //     return true;
+            //     return Some("Wi-Fi disconnected during brewing".to_string());
             // }
 
             if state.last_error.is_some() {
-                error!("SAFETY: System error during brewing - emergency stop");
-                return true;
+                return Some("System error during brewing".to_string());
             }
         }
 
-        false
+        None
     }
 
-    pub fn handle_emergency_stop(
-        &mut self,
-        relay_controller: &mut crate::hardware::relay::RelayController,
-    ) {
-        if self.last_relay_state {
-            error!("EMERGENCY STOP: Turning off relay immediately");
-            if let Err(e) = relay_controller.turn_off_immediately() {
-                error!(
-                    "CRITICAL: Failed to turn off relay during emergency stop: {:?}",
-                    e
-                );
-            }
-            self.last_relay_state = false;
+    /// Snapshot of the safety checks for the status API
+    pub fn status(&self) -> SafetyStatus {
+        let now = Instant::now();
+        SafetyStatus {
+            last_data_age_ms: self
+                .last_data_received
+                .map(|t| now.saturating_duration_since(t).as_millis()),
+            relay_on_duration_ms: self
+                .relay_on_since
+                .map(|t| now.saturating_duration_since(t).as_millis()),
+            armed: self.armed,
+            last_trip_reason: self.last_trip_reason.clone(),
+        }
+    }
+
+    /// Latch an emergency stop for alerting; false when one was raised moments ago (a repeat).
+    /// Repeats still turn the relays off - only the buzzer, alert and log are skipped
+    pub fn begin_emergency_stop(&mut self, now: Instant) -> bool {
+        let repeat = self.last_emergency_stop.is_some_and(|last| {
+            now.saturating_duration_since(last) < Duration::from_millis(EMERGENCY_STOP_DEBOUNCE_MS)
+        });
+        if !repeat {
+            self.last_emergency_stop = Some(now);
         }
+        !repeat
     }
 
     pub fn update_relay_state(&mut self, enabled: bool) {
         if enabled != self.last_relay_state {
             if enabled {
                 info!("SAFETY: Relay turned ON");
+                self.relay_on_since = Some(Instant::now());
             } else {
                 info!("SAFETY: Relay turned OFF");
+                self.relay_on_since = None;
             }
         }
         self.last_relay_state = enabled;
@@ -115,3 +212,80 @@ impl SafetyController {
         warnings
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brewing::BrewOutput;
+    use crate::test_helpers::{brewing_controller, brewing_state};
+    use crate::types::StopMode;
+
+    #[test]
+    fn test_stale_data_detection() {
+        let mut safety = SafetyController::new();
+        let timeout = Duration::from_millis(2000);
+        assert!(safety.data_is_stale(Instant::from_millis(0), timeout));
+
+        safety.last_data_received = Some(Instant::from_millis(1000));
+        assert!(!safety.data_is_stale(Instant::from_millis(2900), timeout));
+        assert!(safety.data_is_stale(Instant::from_millis(3100), timeout));
+    }
+
+    #[test]
+    fn test_stale_data_ignored_when_not_brewing() {
+        let mut safety = SafetyController::new();
+        assert!(!safety.should_emergency_stop(&SystemState::default(), MachineState::Idle));
+    }
+
+    #[test]
+    fn test_relay_on_outside_a_brew_trips() {
+        let mut safety = SafetyController::new();
+        safety.update_data_received();
+        let relay_on = SystemState {
+            relay_enabled: true,
+            ble_connected: true,
+            ..SystemState::default()
+        };
+        assert!(safety.should_emergency_stop(&relay_on, MachineState::Idle));
+        let reason = safety.last_trip_reason().unwrap();
+        assert!(reason.starts_with("Relay on with no brew active"), "{}", reason);
+
+        // The same relay is expected once the state machine is brewing
+        assert!(!safety.should_emergency_stop(&relay_on, MachineState::Brewing));
+    }
+
+    #[test]
+    fn test_repeated_emergency_stop_alerts_once() {
+        let mut safety = SafetyController::new();
+        assert!(safety.begin_emergency_stop(Instant::from_millis(5_000)));
+        assert!(!safety.begin_emergency_stop(Instant::from_millis(5_050)));
+
+        // A genuinely new stop later on alerts again
+        assert!(safety.begin_emergency_stop(Instant::from_millis(7_000)));
+    }
+
+    #[test]
+    fn test_weight_below_cutoff_keeps_brewing() {
+        let mut safety = SafetyController::new();
+        safety.update_data_received();
+        assert!(!safety.should_emergency_stop(&brewing_state(36.0, 2.0), MachineState::Brewing));
+    }
+
+    #[test]
+    fn test_weight_past_cutoff_stops_pump() {
+        let mut safety = SafetyController::new();
+        safety.update_data_received();
+        let mut controller = brewing_controller(StopMode::Weight);
+        assert_eq!(controller.get_state(), MachineState::Brewing);
+
+        let state = brewing_state(SystemState::default().config.max_weight_g + 1.0, 2.0);
+        assert!(safety.should_emergency_stop(&state, controller.get_state()));
+        let status = safety.status();
+        assert!(status.armed);
+        assert!(status.last_trip_reason.unwrap().contains("exceeds cutoff"));
+
+        let outputs = controller.emergency_stop();
+        assert!(outputs.iter().any(|o| matches!(o, BrewOutput::RelayOff)));
+        assert_eq!(controller.get_state(), MachineState::Idle);
+    }
+}
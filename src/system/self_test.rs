@@ -0,0 +1,97 @@
+//! One-shot diagnostics self-test - a consistent pass/fail snapshot to attach to a bug report
+//! The controller runs the checks, the report is served in `/api/status` until the next run
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckOutcome {
+    Pass,
+    Fail,
+    /// Not run - it would interfere with what the system is doing, e.g. a shot in progress
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelfTestCheck {
+    /// ble_stack, nvs, relay, relay_boot, wifi or scale
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+    /// What was seen, or why it failed
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelfTestReport {
+    /// Uptime when the test ran
+    pub ran_at_ms: u64,
+    /// No check failed - skipped checks don't count against it
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn new(ran_at_ms: u64) -> Self {
+        Self {
+            ran_at_ms,
+            passed: true,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, name: &'static str, outcome: CheckOutcome, detail: impl Into<String>) {
+        self.passed &= outcome != CheckOutcome::Fail;
+        self.checks.push(SelfTestCheck {
+            name,
+            outcome,
+            detail: detail.into(),
+        });
+    }
+
+    /// Pass or fail on a check's own result
+    pub fn check<E: std::fmt::Display>(&mut self, name: &'static str, result: Result<String, E>) {
+        match result {
+            Ok(detail) => self.record(name, CheckOutcome::Pass, detail),
+            Err(e) => self.record(name, CheckOutcome::Fail, e.to_string()),
+        }
+    }
+
+    /// One line for the log - the full report is in `/api/status`
+    pub fn summary(&self) -> String {
+        let failed: Vec<&str> = self
+            .checks
+            .iter()
+            .filter(|check| check.outcome == CheckOutcome::Fail)
+            .map(|check| check.name)
+            .collect();
+        if failed.is_empty() {
+            format!("Self-test passed ({} checks)", self.checks.len())
+        } else {
+            format!("Self-test failed: {}", failed.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_failure_fails_the_report() {
+        let mut report = SelfTestReport::new(1000);
+        report.check::<String>("nvs", Ok("read back what was written".to_string()));
+        report.record("relay", CheckOutcome::Skipped, "brew in progress");
+        assert!(report.passed);
+        assert_eq!(report.summary(), "Self-test passed (2 checks)");
+
+        report.check("scale", Err::<String, _>("not connected"));
+        report.record("wifi", CheckOutcome::Fail, "not connected");
+        assert!(!report.passed);
+        assert_eq!(report.summary(), "Self-test failed: scale, wifi");
+        assert_eq!(report.checks[2].detail, "not connected");
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][1]["outcome"], "skipped");
+        assert_eq!(json["passed"], false);
+    }
+}
@@ -0,0 +1,92 @@
+//! Cooperative shutdown - the controller cancels one token and every task it spawned returns at
+//! its next await point, so the controller can be stopped before a reboot or torn down in tests
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::Watch;
+use embassy_time::{Duration, Timer};
+
+/// Tasks that can wait on the token at once - the spawned tasks plus a few buttons
+const SHUTDOWN_MAX_WAITERS: usize = 12;
+
+/// How often a waiter past `SHUTDOWN_MAX_WAITERS` looks at the token instead
+const SHUTDOWN_POLL_MS: u64 = 50;
+
+pub struct ShutdownToken {
+    // Holds `()` once cancelled - every waiter is woken by the one send
+    cancelled: Watch<CriticalSectionRawMutex, (), SHUTDOWN_MAX_WAITERS>,
+}
+
+impl ShutdownToken {
+    pub const fn new() -> Self {
+        Self {
+            cancelled: Watch::new(),
+        }
+    }
+
+    /// Tell every task holding the token to stop - there is no way back
+    pub fn cancel(&self) {
+        self.cancelled.sender().send(());
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.try_get().is_some()
+    }
+
+    /// Resolves once the token is cancelled - `select` it against a task's work
+    pub async fn cancelled(&self) {
+        // A receiver starts out having seen nothing, so a token cancelled earlier resolves at once
+        match self.cancelled.receiver() {
+            Some(mut receiver) => receiver.changed().await,
+            None => {
+                while !self.is_cancelled() {
+                    Timer::after(Duration::from_millis(SHUTDOWN_POLL_MS)).await;
+                }
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for ShutdownToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ShutdownToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl Default for ShutdownToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::join::join;
+    use embassy_futures::select::{select, Either};
+
+    #[test]
+    fn test_cancelled_token_wins_the_select() {
+        let token = ShutdownToken::new();
+        assert!(!token.is_cancelled());
+
+        token.cancel();
+        assert!(token.is_cancelled());
+        let outcome =
+            embassy_futures::block_on(select(token.cancelled(), core::future::pending::<()>()));
+        assert!(matches!(outcome, Either::First(())));
+    }
+
+    #[test]
+    fn test_cancel_wakes_every_waiting_task() {
+        let token = ShutdownToken::new();
+        let cancel = async {
+            // Lets both waiters start before the token is cancelled
+            embassy_futures::yield_now().await;
+            token.cancel();
+        };
+        embassy_futures::block_on(join(join(token.cancelled(), token.cancelled()), cancel));
+        assert!(token.is_cancelled());
+    }
+}
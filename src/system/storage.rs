@@ -1,26 +1,62 @@
 //! NVS (Non-Volatile Storage) persistence for brew settings and learning data.
 //! Uses dedicated custom partition for app settings separate from WiFi.
 
+use crate::system::nvs_store::{
+    EspNvsStore, MemoryStore, NvsStore, StoreError, MEMORY_STORE_CAPACITY_BYTES,
+};
+use crate::types::{
+    AutoTareParams, BrewConfig, OvershootProfiles, AUTO_TARE_BREWING_COOLDOWN_SECS,
+    AUTO_TARE_EMPTY_THRESHOLD_G, TARE_STABILITY_COUNT, TARE_STABILITY_THRESHOLD_G,
+};
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
-use embassy_time::Instant;
-use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsCustom};
+use embassy_time::{Duration, Instant, Timer};
 use esp_idf_svc::sys::EspError;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 // Version for settings migration
 const SETTINGS_VERSION: u8 = 1;
 
-// NVS namespace for our application
-const NVS_NAMESPACE: &str = "gravel_brew";
+/// Layout of the blobs in `NVS_NAMESPACE`, stored under `LAYOUT_VERSION_KEY`.
+/// 1: `settings`, `statistics` and (once saved) `config`, before the key existed.
+/// 2: same blobs, key written.
+pub const NVS_LAYOUT_VERSION: u8 = 2;
 
+/// Firmware that predates the version key wrote layout 1
+const UNVERSIONED_LAYOUT: u8 = 1;
+
+const LAYOUT_VERSION_KEY: &str = "layout";
+
+/// Scratch key the self-test writes, reads back and removes
+const SELF_TEST_KEY: &str = "self_test";
+
+/// Overshoot profiles blob - optional in any layout, older firmware just never reads it
+const PROFILES_KEY: &str = "profiles";
+
+/// Quiet period after the last change to a blob before it is written - protects flash from slider drags
+pub const NVS_WRITE_DEBOUNCE_MS: u64 = 2000;
+
+/// A blob that keeps changing is still written this long after its first queued change
+pub const NVS_MAX_WRITE_DELAY_MS: u64 = 10_000;
+
+/// Pause before the one retry of a failed NVS init - rides out a flash still busy after reset
+const NVS_INIT_RETRY_DELAY_MS: u64 = 500;
+
+/// Overshoot learning state; the config fields are only read to migrate pre-`BrewConfig` installs
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BrewSettings {
     pub version: u8,
     pub target_weight_g: f32,
     pub auto_tare: bool,
     pub predictive_stop: bool,
+    pub auto_tare_empty_threshold_g: f32,
+    pub auto_tare_stability_threshold_g: f32,
+    pub auto_tare_stable_readings: usize,
+    pub auto_tare_cooldown_secs: u32,
 
     // Overshoot learning data
     pub overshoot_delay_ms: i32,
@@ -40,6 +76,10 @@ impl Default for BrewSettings {
             target_weight_g: 36.0,
             auto_tare: true,
             predictive_stop: true,
+            auto_tare_empty_threshold_g: AUTO_TARE_EMPTY_THRESHOLD_G,
+            auto_tare_stability_threshold_g: TARE_STABILITY_THRESHOLD_G,
+            auto_tare_stable_readings: TARE_STABILITY_COUNT,
+            auto_tare_cooldown_secs: AUTO_TARE_BREWING_COOLDOWN_SECS,
             overshoot_delay_ms: 500,  // Start with 500ms like Python
             overshoot_ewma: 0.0,      // No learned bias initially
             learning_confidence: 0.0, // No confidence initially
@@ -50,6 +90,7 @@ impl Default for BrewSettings {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BrewStatistics {
     pub version: u8,
     pub total_brews: u32,
@@ -60,6 +101,10 @@ pub struct BrewStatistics {
     pub worst_overshoot_g: f32,
     pub total_brewing_time_ms: u64,
     pub last_brew_timestamp: u64,
+
+    // Relay wear counters
+    pub relay_activations: u64,
+    pub relay_on_time_ms: u64,
 }
 
 impl Default for BrewStatistics {
@@ -74,90 +119,343 @@ impl Default for BrewStatistics {
             worst_overshoot_g: 0.0,
             total_brewing_time_ms: 0,
             last_brew_timestamp: 0,
+            relay_activations: 0,
+            relay_on_time_ms: 0,
         }
     }
 }
 
+impl BrewSettings {
+    /// Config as stored by firmware that predates the `config` blob
+    fn legacy_config(&self) -> BrewConfig {
+        let mut config = BrewConfig {
+            target_weight_g: self.target_weight_g,
+            auto_tare: self.auto_tare,
+            predictive_stop: self.predictive_stop,
+            auto_tare_cooldown_secs: self.auto_tare_cooldown_secs,
+            ..BrewConfig::default()
+        };
+        config.set_auto_tare_params(AutoTareParams {
+            empty_threshold_g: self.auto_tare_empty_threshold_g,
+            stability_threshold_g: self.auto_tare_stability_threshold_g,
+            stable_readings: self.auto_tare_stable_readings,
+        });
+        config
+    }
+}
+
+/// Serialized blob waiting out the debounce window
+struct PendingWrite {
+    data: Vec<u8>,
+    queued_at: Instant,
+    changed_at: Instant,
+}
+
+impl PendingWrite {
+    fn is_due(&self, now: Instant) -> bool {
+        write_due(self.queued_at, self.changed_at, now)
+    }
+}
+
 pub struct NvsStorage {
-    nvs: Option<Arc<Mutex<CriticalSectionRawMutex, EspNvs<NvsCustom>>>>,
+    store: Mutex<CriticalSectionRawMutex, Box<dyn NvsStore + Send>>,
     cached_settings: Arc<Mutex<CriticalSectionRawMutex, BrewSettings>>,
     cached_stats: Arc<Mutex<CriticalSectionRawMutex, BrewStatistics>>,
-    mock_mode: bool,
+    cached_config: Arc<Mutex<CriticalSectionRawMutex, BrewConfig>>,
+    cached_profiles: Arc<Mutex<CriticalSectionRawMutex, OvershootProfiles>>,
+    /// Blobs queued for writing, keyed by NVS key
+    pending_writes: Mutex<CriticalSectionRawMutex, HashMap<&'static str, PendingWrite>>,
+    /// What each key holds in flash, so unchanged values are never rewritten
+    last_written: Mutex<CriticalSectionRawMutex, HashMap<&'static str, Vec<u8>>>,
+    /// Flash writes since boot - writes to a memory store don't count
+    write_count: AtomicU32,
+    /// Backed by flash rather than the in-memory fallback
+    persistent: bool,
 }
 
 impl NvsStorage {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
         info!("🗄️ Initializing NVS storage for brew settings");
 
-        // Try to initialize real NVS with custom partition
-        let (nvs, mock_mode) = match Self::init_nvs() {
-            Ok(nvs) => {
+        let store: Box<dyn NvsStore + Send> = match Self::open_flash().await {
+            Ok(store) => {
                 info!("✅ Real NVS storage initialized successfully");
-                (Some(Arc::new(Mutex::new(nvs))), false)
+                Box::new(store)
             }
             Err(e) => {
                 warn!(
                     "⚠️ NVS initialization failed: {:?} - using in-memory storage",
                     e
                 );
-                (None, true)
+                Box::new(MemoryStore::new(MEMORY_STORE_CAPACITY_BYTES))
             }
         };
+        Ok(Self::with_store(store).await)
+    }
 
+    /// Storage over any backend - a `MemoryStore` runs the same load, save and flush paths
+    pub async fn with_store(store: Box<dyn NvsStore + Send>) -> Self {
+        let persistent = store.is_persistent();
         let mut storage = Self {
-            nvs,
+            store: Mutex::new(store),
             cached_settings: Arc::new(Mutex::new(BrewSettings::default())),
             cached_stats: Arc::new(Mutex::new(BrewStatistics::default())),
-            mock_mode,
+            cached_config: Arc::new(Mutex::new(BrewConfig::default())),
+            cached_profiles: Arc::new(Mutex::new(OvershootProfiles::default())),
+            pending_writes: Mutex::new(HashMap::new()),
+            last_written: Mutex::new(HashMap::new()),
+            write_count: AtomicU32::new(0),
+            persistent,
         };
 
-        // Load existing data if NVS is available
-        if !mock_mode {
-            if let Err(e) = storage.load_from_nvs().await {
-                warn!("Failed to load from NVS: {:?} - using defaults", e);
-            }
+        if let Err(e) = storage.load_from_nvs().await {
+            warn!("Failed to load from NVS: {:?} - using defaults", e);
         }
 
-        info!("✅ NVS storage initialized (mock_mode: {})", mock_mode);
-        Ok(storage)
+        info!("✅ NVS storage initialized (persistent: {})", persistent);
+        storage
     }
 
-    fn init_nvs() -> Result<EspNvs<NvsCustom>, EspError> {
-        // Try to use a custom NVS partition (separate from WiFi)
-        // If custom partition doesn't exist, fall back to default
-        let partition = EspNvsPartition::<NvsCustom>::take("nvs_custom").or_else(|_| {
-            info!("Custom NVS partition not found, using default NVS");
-            EspNvsPartition::<NvsCustom>::take("nvs")
-        })?;
-        let nvs = EspNvs::new(partition, NVS_NAMESPACE, true)?;
-        Ok(nvs)
+    /// Open the flash store, once more after a pause if the first attempt fails
+    async fn open_flash() -> Result<EspNvsStore, EspError> {
+        match EspNvsStore::new() {
+            Ok(store) => Ok(store),
+            Err(e) => {
+                warn!(
+                    "⚠️ NVS initialization failed: {:?} - retrying in {}ms",
+                    e, NVS_INIT_RETRY_DELAY_MS
+                );
+                Timer::after(Duration::from_millis(NVS_INIT_RETRY_DELAY_MS)).await;
+                EspNvsStore::new()
+            }
+        }
     }
 
     async fn load_from_nvs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(ref nvs_arc) = self.nvs {
-            let nvs = nvs_arc.lock().await;
+        let mut nvs = self.store.lock().await;
+        let mut last_written = self.last_written.lock().await;
+
+        let layout = match nvs.get_u8(LAYOUT_VERSION_KEY) {
+            Ok(Some(layout)) => layout,
+            _ => UNVERSIONED_LAYOUT,
+        };
+        if layout > NVS_LAYOUT_VERSION {
+            warn!(
+                "NVS layout {} is newer than this firmware ({}) - using defaults",
+                layout, NVS_LAYOUT_VERSION
+            );
+        }
 
-            // Load settings
-            let mut buffer = vec![0u8; 1024]; // Buffer for reading
-            if let Ok(Some(data)) = nvs.get_blob("settings", &mut buffer) {
-                if let Ok(settings) = serde_json::from_slice::<BrewSettings>(data) {
+        // Load settings
+        let mut has_settings = false;
+        if let Some(data) = read_blob(&**nvs, "settings") {
+            match serde_json::from_slice::<BrewSettings>(&data) {
+                Ok(settings) if layout <= NVS_LAYOUT_VERSION => {
                     *self.cached_settings.lock().await = settings;
+                    has_settings = true;
                     info!("📂 Loaded brew settings from NVS");
                 }
+                Ok(_) => {}
+                Err(e) => warn!("Stored brew settings are unreadable: {} - using defaults", e),
             }
+            last_written.insert("settings", data);
+        }
 
-            // Load statistics
-            let mut buffer = vec![0u8; 1024]; // Buffer for reading
-            if let Ok(Some(data)) = nvs.get_blob("statistics", &mut buffer) {
-                if let Ok(stats) = serde_json::from_slice::<BrewStatistics>(data) {
+        // Load statistics
+        if let Some(data) = read_blob(&**nvs, "statistics") {
+            match serde_json::from_slice::<BrewStatistics>(&data) {
+                Ok(stats) if layout <= NVS_LAYOUT_VERSION => {
                     *self.cached_stats.lock().await = stats;
                     info!("📊 Loaded brew statistics from NVS");
                 }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Stored brew statistics are unreadable: {} - using defaults", e);
+                }
+            }
+            last_written.insert("statistics", data);
+        }
+
+        // Load config, migrating from the settings blob on first boot after upgrade
+        let config_blob = read_blob(&**nvs, "config");
+        if let Some(ref data) = config_blob {
+            last_written.insert("config", data.clone());
+        }
+        let legacy_settings = self.cached_settings.lock().await.clone();
+        let config = migrate_config(
+            layout,
+            config_blob.as_deref(),
+            has_settings.then_some(&legacy_settings),
+        );
+        *self.cached_config.lock().await = config;
+
+        // Load overshoot profiles
+        if let Some(data) = read_blob(&**nvs, PROFILES_KEY) {
+            match serde_json::from_slice::<OvershootProfiles>(&data) {
+                Ok(profiles) if layout <= NVS_LAYOUT_VERSION => {
+                    info!("📂 Loaded {} overshoot profiles from NVS", profiles.models.len());
+                    *self.cached_profiles.lock().await = profiles;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Stored overshoot profiles are unreadable: {} - starting fresh", e);
+                }
             }
+            last_written.insert(PROFILES_KEY, data);
+        }
+
+        // Blobs are rewritten in this firmware's format from here on
+        if layout != NVS_LAYOUT_VERSION {
+            nvs.set_u8(LAYOUT_VERSION_KEY, NVS_LAYOUT_VERSION)?;
+            self.note_write();
+            info!("📂 NVS layout {} -> {}", layout, NVS_LAYOUT_VERSION);
         }
         Ok(())
     }
 
+    /// Persisted brew config, or defaults when nothing has been stored yet
+    pub async fn load_config(&self) -> BrewConfig {
+        self.cached_config.lock().await.clone()
+    }
+
+    /// Queue the config for persistence; the write happens once changes settle (see `flush`)
+    pub async fn save_config(&self, config: &BrewConfig) {
+        *self.cached_config.lock().await = config.clone();
+        match serde_json::to_vec(config) {
+            Ok(data) => self.queue_write("config", data).await,
+            Err(e) => warn!("Failed to serialize brew config: {}", e),
+        }
+    }
+
+    /// Persisted overshoot profiles, or a single fresh default profile
+    pub async fn load_overshoot_profiles(&self) -> OvershootProfiles {
+        self.cached_profiles.lock().await.clone()
+    }
+
+    /// Queue the overshoot profiles for persistence - debounced like the config
+    pub async fn save_overshoot_profiles(&self, profiles: &OvershootProfiles) {
+        *self.cached_profiles.lock().await = profiles.clone();
+        match serde_json::to_vec(profiles) {
+            Ok(data) => self.queue_write(PROFILES_KEY, data).await,
+            Err(e) => warn!("Failed to serialize overshoot profiles: {}", e),
+        }
+    }
+
+    /// Queue a blob for writing, dropping it if flash already holds exactly these bytes
+    async fn queue_write(&self, key: &'static str, data: Vec<u8>) {
+        let unchanged = self.last_written.lock().await.get(key) == Some(&data);
+        let mut pending = self.pending_writes.lock().await;
+        if unchanged {
+            // Also covers a value changed and changed back before it was written
+            pending.remove(key);
+            return;
+        }
+
+        let now = Instant::now();
+        match pending.get_mut(key) {
+            Some(write) => {
+                write.data = data;
+                write.changed_at = now;
+            }
+            None => {
+                pending.insert(
+                    key,
+                    PendingWrite {
+                        data,
+                        queued_at: now,
+                        changed_at: now,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Write queued blobs that have settled for `NVS_WRITE_DEBOUNCE_MS` (or waited `NVS_MAX_WRITE_DELAY_MS`)
+    pub async fn flush(&self) -> Result<(), StoreError> {
+        let now = Instant::now();
+        self.write_pending(|write| write.is_due(now)).await
+    }
+
+    /// Write everything still queued, regardless of debounce - call before a clean shutdown
+    pub async fn flush_all(&self) -> Result<(), StoreError> {
+        self.write_pending(|_| true).await
+    }
+
+    async fn write_pending(
+        &self,
+        ready: impl Fn(&PendingWrite) -> bool,
+    ) -> Result<(), StoreError> {
+        let writes: Vec<(&'static str, Vec<u8>)> = {
+            let mut pending = self.pending_writes.lock().await;
+            let keys: Vec<&'static str> = pending
+                .iter()
+                .filter(|(_, write)| ready(write))
+                .map(|(key, _)| *key)
+                .collect();
+            keys.into_iter()
+                .filter_map(|key| pending.remove(key).map(|write| (key, write.data)))
+                .collect()
+        };
+
+        let mut result = Ok(());
+        for (key, data) in writes {
+            if let Err(e) = self.store.lock().await.set_blob(key, &data) {
+                // Keep it queued for the next flush unless a newer value has replaced it
+                let now = Instant::now();
+                self.pending_writes.lock().await.entry(key).or_insert(PendingWrite {
+                    data,
+                    queued_at: now,
+                    changed_at: now,
+                });
+                result = Err(e);
+                continue;
+            }
+            self.note_write();
+            debug!(
+                "💾 Saved {} to {} ({} bytes)",
+                key,
+                if self.persistent { "NVS" } else { "memory" },
+                data.len()
+            );
+            self.last_written.lock().await.insert(key, data);
+        }
+
+        result
+    }
+
+    /// False when NVS never came up - settings then live in memory and are lost on reboot
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    fn note_write(&self) {
+        if self.persistent {
+            self.write_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Flash writes since boot - a steadily climbing count means something is thrashing NVS
+    pub fn write_count(&self) -> u32 {
+        self.write_count.load(Ordering::Relaxed)
+    }
+
+    /// Self-test: write a scratch value, read it back and remove it again
+    pub async fn self_test(&self) -> Result<String, String> {
+        if !self.persistent {
+            return Err("NVS unavailable - settings are only kept in memory".to_string());
+        }
+        let mut nvs = self.store.lock().await;
+        let probe = (Instant::now().as_millis() % 255) as u8 + 1;
+        nvs.set_u8(SELF_TEST_KEY, probe).map_err(|e| format!("write failed: {}", e))?;
+        self.note_write();
+        let read = nvs.get_u8(SELF_TEST_KEY).map_err(|e| format!("read failed: {}", e))?;
+        let _ = nvs.remove(SELF_TEST_KEY);
+        match read {
+            Some(value) if value == probe => Ok("read back what was written".to_string()),
+            other => Err(format!("wrote {} but read back {:?}", probe, other)),
+        }
+    }
+
     /// Get current settings (from cache)
     pub async fn get_settings(&self) -> BrewSettings {
         self.cached_settings.lock().await.clone()
@@ -168,7 +466,7 @@ impl NvsStorage {
         self.cached_stats.lock().await.clone()
     }
 
-    /// Update settings in cache and persist to NVS
+    /// Update settings in cache and queue them for persistence
     pub async fn update_settings(
         &self,
         settings: BrewSettings,
@@ -179,21 +477,11 @@ impl NvsStorage {
             *cached = settings.clone();
         }
 
-        // Persist to NVS if available
-        if let Some(ref nvs_arc) = self.nvs {
-            let mut nvs = nvs_arc.lock().await;
-            let data = serde_json::to_vec(&settings)?;
-            nvs.set_blob("settings", &data)?;
-            debug!(
-                "💾 Saved settings to NVS: target={:.1}g, delay={}ms, ewma={:.2}g",
-                settings.target_weight_g, settings.overshoot_delay_ms, settings.overshoot_ewma
-            );
-        } else {
-            debug!(
-                "📝 [MOCK] Would save settings to NVS: target={:.1}g, delay={}ms, ewma={:.2}g",
-                settings.target_weight_g, settings.overshoot_delay_ms, settings.overshoot_ewma
-            );
-        }
+        debug!(
+            "💾 Queued settings: target={:.1}g, delay={}ms, ewma={:.2}g",
+            settings.target_weight_g, settings.overshoot_delay_ms, settings.overshoot_ewma
+        );
+        self.queue_write("settings", serde_json::to_vec(&settings)?).await;
 
         Ok(())
     }
@@ -219,7 +507,7 @@ impl NvsStorage {
         self.update_settings(settings).await
     }
 
-    /// Update brewing statistics in cache and persist to NVS
+    /// Update brewing statistics in cache and queue them for persistence
     pub async fn update_statistics(
         &self,
         stats: BrewStatistics,
@@ -230,25 +518,30 @@ impl NvsStorage {
             *cached = stats.clone();
         }
 
-        // Persist to NVS if available
-        if let Some(ref nvs_arc) = self.nvs {
-            let mut nvs = nvs_arc.lock().await;
-            let data = serde_json::to_vec(&stats)?;
-            nvs.set_blob("statistics", &data)?;
-            debug!(
-                "💾 Saved statistics to NVS: {} brews, {}/{} predictions successful",
-                stats.total_brews, stats.successful_predictions, stats.total_predictions
-            );
-        } else {
-            debug!(
-                "📊 [MOCK] Would save statistics to NVS: {} brews, {}/{} predictions successful",
-                stats.total_brews, stats.successful_predictions, stats.total_predictions
-            );
-        }
+        debug!(
+            "📊 Queued statistics: {} brews, {}/{} predictions successful",
+            stats.total_brews, stats.successful_predictions, stats.total_predictions
+        );
+        self.queue_write("statistics", serde_json::to_vec(&stats)?).await;
 
         Ok(())
     }
 
+    /// Persist relay wear counters (skipped when unchanged)
+    pub async fn update_relay_stats(
+        &self,
+        activations: u64,
+        on_time_ms: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut stats = self.get_statistics().await;
+        if stats.relay_activations == activations && stats.relay_on_time_ms == on_time_ms {
+            return Ok(());
+        }
+        stats.relay_activations = activations;
+        stats.relay_on_time_ms = on_time_ms;
+        self.update_statistics(stats).await
+    }
+
     /// Record a completed brew with overshoot data
     pub async fn record_brew(
         &self,
@@ -322,3 +615,218 @@ impl NvsStorage {
         Ok(())
     }
 }
+
+/// A whole blob, read into a buffer of its stored size - a failed read is logged and treated as
+/// missing, so the caller falls back to defaults
+fn read_blob(nvs: &dyn NvsStore, key: &str) -> Option<Vec<u8>> {
+    let read = nvs.blob_len(key).and_then(|len| match len {
+        Some(len) => {
+            let mut buffer = vec![0u8; len];
+            Ok(nvs.get_blob(key, &mut buffer)?.map(<[u8]>::to_vec))
+        }
+        None => Ok(None),
+    });
+    read.unwrap_or_else(|e| {
+        warn!("Failed to read '{}' from NVS: {} - using defaults", key, e);
+        None
+    })
+}
+
+/// Config from the stored blobs as written by `layout`, upgraded to the current layout.
+/// Unreadable blobs and layouts from newer firmware fall back to defaults instead of failing.
+fn migrate_config(
+    layout: u8,
+    config_blob: Option<&[u8]>,
+    legacy_settings: Option<&BrewSettings>,
+) -> BrewConfig {
+    if layout > NVS_LAYOUT_VERSION {
+        return BrewConfig::default();
+    }
+
+    // Layouts 1 and 2 share the blob format; missing fields take their defaults
+    let stored = config_blob.and_then(|data| match serde_json::from_slice::<BrewConfig>(data) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            warn!("Stored config is unreadable: {} - using defaults", e);
+            None
+        }
+    });
+
+    match (stored, legacy_settings) {
+        (Some(config), _) => {
+            info!("📂 Loaded brew config from NVS");
+            config
+        }
+        (None, Some(settings)) => {
+            info!("📂 No stored config - migrating from legacy brew settings");
+            settings.legacy_config()
+        }
+        (None, None) => {
+            info!("📂 No stored config - using defaults");
+            BrewConfig::default()
+        }
+    }
+}
+
+/// A queued write is due once it has been quiet for the debounce window, or has waited too long
+fn write_due(queued_at: Instant, changed_at: Instant, now: Instant) -> bool {
+    now.saturating_duration_since(changed_at) >= Duration::from_millis(NVS_WRITE_DEBOUNCE_MS)
+        || now.saturating_duration_since(queued_at) >= Duration::from_millis(NVS_MAX_WRITE_DELAY_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brewing::profile::{FlowProfile, PidGains, ProfilePoint};
+    use crate::types::{
+        AutoTareProfile, QuietHours, MAX_FLOW_PROFILE_POINTS, MAX_SCALE_NAME_PREFIX_LEN,
+    };
+
+    fn at(ms: u64) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    fn as_json(config: &BrewConfig) -> serde_json::Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_unversioned_config_migrates_with_new_fields_at_defaults() {
+        // Written before stop modes, dose weighing and the startup window existed
+        let old = br#"{"target_weight_g":40.0,"cups":2,"auto_tare":false,"buzzer_enabled":false}"#;
+        let config = migrate_config(UNVERSIONED_LAYOUT, Some(old), None);
+
+        let expected = BrewConfig {
+            target_weight_g: 40.0,
+            cups: 2,
+            auto_tare: false,
+            buzzer_enabled: false,
+            ..BrewConfig::default()
+        };
+        assert_eq!(as_json(&config), as_json(&expected));
+    }
+
+    #[test]
+    fn test_missing_config_migrates_from_legacy_settings() {
+        let settings = BrewSettings {
+            target_weight_g: 42.0,
+            predictive_stop: false,
+            ..BrewSettings::default()
+        };
+        let config = migrate_config(UNVERSIONED_LAYOUT, None, Some(&settings));
+        assert_eq!(config.target_weight_g, 42.0);
+        assert!(!config.predictive_stop);
+        assert_eq!(config.stop_mode, BrewConfig::default().stop_mode);
+    }
+
+    #[test]
+    fn test_unreadable_or_future_config_falls_back_to_defaults() {
+        let defaults = as_json(&BrewConfig::default());
+        let garbage = br#"{"target_weight_g":"forty"}"#;
+        assert_eq!(as_json(&migrate_config(NVS_LAYOUT_VERSION, Some(garbage), None)), defaults);
+
+        let current = serde_json::to_vec(&BrewConfig {
+            target_weight_g: 18.0,
+            ..BrewConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            as_json(&migrate_config(NVS_LAYOUT_VERSION + 1, Some(&current), None)),
+            defaults
+        );
+    }
+
+    #[test]
+    fn test_memory_store_runs_the_same_load_and_save_paths() {
+        let mut store = MemoryStore::new(MEMORY_STORE_CAPACITY_BYTES);
+        let stored = BrewConfig {
+            target_weight_g: 40.0,
+            ..BrewConfig::default()
+        };
+        store.set_u8(LAYOUT_VERSION_KEY, NVS_LAYOUT_VERSION).unwrap();
+        store.set_blob("config", &serde_json::to_vec(&stored).unwrap()).unwrap();
+
+        embassy_futures::block_on(async {
+            let storage = NvsStorage::with_store(Box::new(store)).await;
+            assert!(!storage.is_persistent());
+            let mut config = storage.load_config().await;
+            assert_eq!(config.target_weight_g, 40.0);
+
+            config.cups = 3;
+            storage.save_config(&config).await;
+            storage.flush_all().await.unwrap();
+            // Memory writes are no flash wear
+            assert_eq!(storage.write_count(), 0);
+
+            let store = storage.store.lock().await;
+            let data = read_blob(&**store, "config").unwrap();
+            assert_eq!(serde_json::from_slice::<BrewConfig>(&data).unwrap().cups, 3);
+        });
+    }
+
+    #[test]
+    fn test_fully_populated_config_survives_a_reload() {
+        let mut points = heapless::Vec::new();
+        for i in 0..MAX_FLOW_PROFILE_POINTS {
+            let point = ProfilePoint {
+                time_ms: i as u32 * 1000,
+                flow_g_per_s: 1.5,
+            };
+            points.push(point).unwrap();
+        }
+        let full = BrewConfig {
+            target_weight_g: 40.5,
+            auto_tare_profile: Some(AutoTareProfile::Relaxed),
+            quiet_hours: Some(QuietHours {
+                start_hour: 22,
+                end_hour: 6,
+                utc_offset_minutes: -300,
+            }),
+            flow_profile: Some(FlowProfile {
+                points,
+                gains: PidGains::default(),
+            }),
+            dose_g: Some(18.25),
+            brew_ratio: Some(2.25),
+            scale_name_prefix: "B".repeat(MAX_SCALE_NAME_PREFIX_LEN),
+            ..BrewConfig::default()
+        };
+        let blob = serde_json::to_vec(&full).unwrap();
+        // The old fixed read buffer was 1KB
+        assert!(blob.len() > 1024, "config is only {} bytes", blob.len());
+
+        let mut store = MemoryStore::new(MEMORY_STORE_CAPACITY_BYTES);
+        store.set_u8(LAYOUT_VERSION_KEY, NVS_LAYOUT_VERSION).unwrap();
+        store.set_blob("config", &blob).unwrap();
+        embassy_futures::block_on(async {
+            let storage = NvsStorage::with_store(Box::new(store)).await;
+            assert_eq!(as_json(&storage.load_config().await), as_json(&full));
+        });
+    }
+
+    #[test]
+    fn test_full_memory_store_keeps_writes_queued() {
+        embassy_futures::block_on(async {
+            // Room for the layout byte and nothing else
+            let storage = NvsStorage::with_store(Box::new(MemoryStore::new(1))).await;
+            storage.save_config(&BrewConfig::default()).await;
+            assert!(storage.flush_all().await.is_err());
+            assert!(storage.pending_writes.lock().await.contains_key("config"));
+            assert!(storage.self_test().await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_write_waits_for_changes_to_settle() {
+        assert!(!write_due(at(0), at(0), at(NVS_WRITE_DEBOUNCE_MS - 1)));
+        assert!(write_due(at(0), at(0), at(NVS_WRITE_DEBOUNCE_MS)));
+        // A fresh change restarts the quiet period
+        assert!(!write_due(at(0), at(1500), at(NVS_WRITE_DEBOUNCE_MS)));
+    }
+
+    #[test]
+    fn test_constant_changes_still_get_written() {
+        let now = at(NVS_MAX_WRITE_DELAY_MS);
+        assert!(write_due(at(0), at(NVS_MAX_WRITE_DELAY_MS - 100), now));
+    }
+}
@@ -0,0 +1,57 @@
+//! ESP-IDF task watchdog (TWDT) integration
+//! The control loop subscribes itself and feeds every iteration - a hang resets the chip
+
+use esp_idf_svc::sys::{
+    esp, esp_task_wdt_add, esp_task_wdt_config_t, esp_task_wdt_delete, esp_task_wdt_reconfigure,
+    esp_task_wdt_reset, EspError,
+};
+use log::{info, warn};
+use std::ptr;
+
+/// Default hang detection window for the control loop
+pub const CONTROL_LOOP_WATCHDOG_TIMEOUT_MS: u32 = 8000;
+
+/// Subscription of the calling FreeRTOS task to the task watchdog
+pub struct TaskWatchdog {
+    timeout_ms: u32,
+}
+
+impl TaskWatchdog {
+    /// Configure the TWDT to panic (and therefore reboot) on timeout, then subscribe the current task
+    pub fn subscribe_current_task(timeout_ms: u32) -> Result<Self, EspError> {
+        let config = esp_task_wdt_config_t {
+            timeout_ms,
+            idle_core_mask: 1, // Keep watching the idle task on core 0 like the default config
+            trigger_panic: true,
+        };
+
+        unsafe {
+            esp!(esp_task_wdt_reconfigure(&config))?;
+            // NULL subscribes the calling task
+            esp!(esp_task_wdt_add(ptr::null_mut()))?;
+        }
+
+        info!("🐕 Task watchdog armed for control loop ({}ms timeout)", timeout_ms);
+        Ok(Self { timeout_ms })
+    }
+
+    /// Feed the watchdog - must be called from the subscribed task
+    pub fn feed(&self) {
+        unsafe {
+            esp_task_wdt_reset();
+        }
+    }
+
+    pub fn timeout_ms(&self) -> u32 {
+        self.timeout_ms
+    }
+}
+
+impl Drop for TaskWatchdog {
+    fn drop(&mut self) {
+        let result = unsafe { esp!(esp_task_wdt_delete(ptr::null_mut())) };
+        if let Err(e) = result {
+            warn!("Failed to unsubscribe from task watchdog: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Fixtures shared by the unit tests of more than one module
+
+use crate::brewing::states::SystemState as MachineState;
+use crate::brewing::{BrewController, BrewInput};
+use crate::system::events::UserEvent;
+use crate::types::{BrewState, ScaleData, StopMode, SystemState, TimerState};
+use embassy_time::Instant;
+
+/// State machine with the scale connected and nothing brewing
+pub fn idle_controller() -> BrewController {
+    let mut controller = BrewController::new();
+    for input in [BrewInput::BleEnabled, BrewInput::BleScanning, BrewInput::ScaleConnected] {
+        controller.handle_input(input);
+    }
+    assert_eq!(controller.get_system_state(), MachineState::Idle);
+    controller
+}
+
+/// State machine partway into a shot that stops by `mode`
+pub fn brewing_controller(mode: StopMode) -> BrewController {
+    let mut controller = idle_controller();
+    controller.set_stop_mode(mode);
+    controller.handle_input(BrewInput::UserCommand(UserEvent::StartBrewing));
+    assert_eq!(controller.get_system_state(), MachineState::Brewing);
+    controller
+}
+
+/// What the UI sees mid-shot - relay on, timer running, aiming for 36g
+pub fn brewing_state(weight_g: f32, flow_rate_g_per_s: f32) -> SystemState {
+    let mut state = SystemState {
+        scale_data: Some(ScaleData {
+            timestamp_ms: 20_000,
+            weight_g,
+            flow_rate_g_per_s,
+            battery_percent: 80,
+            timer_running: true,
+            received_at: Instant::now(),
+        }),
+        timer_state: TimerState::Running,
+        brew_state: BrewState::Brewing,
+        relay_enabled: true,
+        ble_connected: true,
+        ..SystemState::default()
+    };
+    state.config.target_weight_g = 36.0;
+    state
+}
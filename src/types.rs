@@ -1,5 +1,16 @@
+use crate::brewing::profile::FlowProfile;
+use crate::brewing::states::{
+    BrewSnapshot, DoseStatus, OvershootStatus, SystemState as MachineState,
+};
+use crate::ble::Device;
+use crate::hardware::relay::{RelayBootCheck, RelayStats};
+use crate::scales::link_quality::LinkQualityReport;
+use crate::scales::traits::ScaleInfo;
+use crate::system::safety::SafetyStatus;
+use crate::system::self_test::SelfTestReport;
 use embassy_time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimerState {
@@ -15,53 +26,1009 @@ pub enum BrewState {
     BrewSettling,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AutoTareState {
+    #[default]
     Empty,
     Loading,
     StableObject,
     Unloading,
 }
 
+/// Why the auto-tare state machine changed state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoTareReason {
+    /// Stable object placed on an empty scale
+    ObjectDetected,
+    /// Weight appeared but hasn't settled yet
+    WeightUnsettled,
+    /// Weight settled back to empty before anything was placed
+    SettledEmpty,
+    /// Weight that was still settling came to rest with an object on the scale
+    ObjectStabilized,
+    ObjectRemoved,
+    /// Large stable weight jump - treated as a cup swap, redetected from empty
+    CupSwap,
+    /// Weight moving away from the stable object
+    WeightShifting,
+    /// Weight settled at a different object after shifting
+    ObjectChanged,
+    BrewFinishedWithObject,
+    BrewFinishedEmpty,
+}
+
+impl AutoTareReason {
+    /// Whether this transition tares the scale
+    pub fn triggers_tare(&self) -> bool {
+        matches!(
+            self,
+            AutoTareReason::ObjectDetected
+                | AutoTareReason::ObjectStabilized
+                | AutoTareReason::ObjectChanged
+        )
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutoTareReason::ObjectDetected => "Object detected",
+            AutoTareReason::WeightUnsettled => "Weight not yet stable",
+            AutoTareReason::SettledEmpty => "Settled empty",
+            AutoTareReason::ObjectStabilized => "Object stabilized",
+            AutoTareReason::ObjectRemoved => "Object removed",
+            AutoTareReason::CupSwap => "Cup swap",
+            AutoTareReason::WeightShifting => "Weight changing",
+            AutoTareReason::ObjectChanged => "Object changed",
+            AutoTareReason::BrewFinishedWithObject => "Brew finished, cup kept",
+            AutoTareReason::BrewFinishedEmpty => "Brew finished, scale empty",
+        }
+    }
+}
+
+impl std::fmt::Display for AutoTareReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which radio gets the airtime on the shared 2.4GHz antenna
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RadioPriority {
+    /// WiFi updates go out as fast as state changes
+    #[default]
+    Balanced,
+    /// A shot is running - UI broadcasts are throttled so scale notifications aren't delayed
+    Ble,
+}
+
+/// Auto-tare sensitivity presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoTareProfile {
+    /// Light cups and precise scales - tares quickly on small objects
+    Sensitive,
+    Normal,
+    /// Heavy or wobbly vessels and noisy readings - waits for a clearly settled weight
+    Relaxed,
+}
+
+impl AutoTareProfile {
+    pub fn params(&self) -> AutoTareParams {
+        match self {
+            AutoTareProfile::Sensitive => AutoTareParams {
+                empty_threshold_g: 1.0,
+                stability_threshold_g: 0.3,
+                stable_readings: 3,
+            },
+            AutoTareProfile::Normal => AutoTareParams::default(),
+            AutoTareProfile::Relaxed => AutoTareParams {
+                empty_threshold_g: 3.0,
+                stability_threshold_g: 1.0,
+                stable_readings: 7,
+            },
+        }
+    }
+
+    /// The profile whose bundle matches these parameters exactly, if any
+    pub fn matching(params: &AutoTareParams) -> Option<Self> {
+        [
+            AutoTareProfile::Sensitive,
+            AutoTareProfile::Normal,
+            AutoTareProfile::Relaxed,
+        ]
+        .into_iter()
+        .find(|profile| profile.params() == *params)
+    }
+}
+
+/// Unit the web API shows weights in - everything internal stays in grams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightUnit {
+    #[default]
+    Grams,
+    Ounces,
+}
+
+impl WeightUnit {
+    pub fn from_grams(&self, grams: f32) -> f32 {
+        match self {
+            WeightUnit::Grams => grams,
+            WeightUnit::Ounces => grams / GRAMS_PER_OUNCE,
+        }
+    }
+
+    pub fn to_grams(&self, value: f32) -> f32 {
+        match self {
+            WeightUnit::Grams => value,
+            WeightUnit::Ounces => value * GRAMS_PER_OUNCE,
+        }
+    }
+
+    /// Convert from grams and round to what the scale can meaningfully resolve (0.1g, 0.01oz)
+    pub fn display(&self, grams: f32) -> f32 {
+        let per_unit = match self {
+            WeightUnit::Grams => 10.0,
+            WeightUnit::Ounces => 100.0,
+        };
+        (self.from_grams(grams) * per_unit).round() / per_unit
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            WeightUnit::Grams => "g",
+            WeightUnit::Ounces => "oz",
+        }
+    }
+}
+
+/// Web commands that can be set to need a second press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmableCommand {
+    Start,
+    Stop,
+}
+
+impl ConfirmableCommand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfirmableCommand::Start => "start",
+            ConfirmableCommand::Stop => "stop",
+        }
+    }
+}
+
+/// What ends a shot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopMode {
+    /// Stop at the target weight (espresso)
+    #[default]
+    Weight,
+    /// Stop once flow has ceased for `flow_stop_hold_ms` (drip, pour-over)
+    FlowStopped,
+    /// Stop after `brew_time_ms`
+    Time,
+}
+
+/// Auto-tare detection parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoTareParams {
+    /// Weight at or below which the scale counts as empty
+    pub empty_threshold_g: f32,
+    /// Max spread across the stability window for weight to count as stable
+    pub stability_threshold_g: f32,
+    /// Consecutive readings in the stability window
+    pub stable_readings: usize,
+}
+
+impl Default for AutoTareParams {
+    fn default() -> Self {
+        Self {
+            empty_threshold_g: AUTO_TARE_EMPTY_THRESHOLD_G,
+            stability_threshold_g: TARE_STABILITY_THRESHOLD_G,
+            stable_readings: TARE_STABILITY_COUNT,
+        }
+    }
+}
+
+impl AutoTareParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.empty_threshold_g.is_finite()
+            || self.empty_threshold_g <= 0.0
+            || self.empty_threshold_g > AUTO_TARE_MAX_EMPTY_THRESHOLD_G
+        {
+            return Err(format!(
+                "Empty threshold must be between 0 and {:.0}g, got {}",
+                AUTO_TARE_MAX_EMPTY_THRESHOLD_G, self.empty_threshold_g
+            ));
+        }
+        if !self.stability_threshold_g.is_finite()
+            || self.stability_threshold_g <= 0.0
+            || self.stability_threshold_g > AUTO_TARE_MAX_STABILITY_THRESHOLD_G
+        {
+            return Err(format!(
+                "Stability threshold must be between 0 and {:.0}g, got {}",
+                AUTO_TARE_MAX_STABILITY_THRESHOLD_G, self.stability_threshold_g
+            ));
+        }
+        // A single reading is trivially "stable"; more than the history holds can never be
+        if !(2..=AUTO_TARE_HISTORY_SIZE).contains(&self.stable_readings) {
+            return Err(format!(
+                "Stable readings must be between 2 and {}, got {}",
+                AUTO_TARE_HISTORY_SIZE, self.stable_readings
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScaleData {
+    /// The scale's own timer - resets whenever the user resets it on the scale
     pub timestamp_ms: u32,
     pub weight_g: f32,
     pub flow_rate_g_per_s: f32,
     pub battery_percent: u8,
     pub timer_running: bool,
+    /// Controller capture time - monotonic, so samples order correctly across scale timer resets
     pub received_at: Instant,
 }
 
+impl ScaleData {
+    /// Capture time as milliseconds since boot
+    pub fn received_at_ms(&self) -> u64 {
+        self.received_at.as_millis()
+    }
+}
+
+/// What the scale event detector is tracking, independent of the brew state machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ScaleDetectorStatus {
+    /// Scale timer is counting, judged from its timestamps advancing
+    pub timer_running: bool,
+    /// Last weight the detector saw settle, None until one has
+    pub stable_weight_g: Option<f32>,
+}
+
+/// How far a brew has got towards its total target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BrewProgress {
+    /// Fraction of the target reached, 0.0 to 1.0
+    pub fraction: f32,
+    /// Time to target at the current flow, None while nothing is flowing
+    pub eta_ms: Option<u32>,
+}
+
+impl BrewProgress {
+    pub fn new(weight_g: f32, flow_rate_g_per_s: f32, target_weight_g: f32) -> Self {
+        let fraction = if target_weight_g > 0.0 {
+            (weight_g / target_weight_g).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let remaining_g = (target_weight_g - weight_g).max(0.0);
+        let eta_ms = (flow_rate_g_per_s > 0.0)
+            .then(|| (remaining_g / flow_rate_g_per_s * 1000.0) as u32);
+        Self { fraction, eta_ms }
+    }
+
+    pub fn percent(&self) -> f32 {
+        self.fraction * 100.0
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BrewConfig {
+    /// Per-cup target - the brew stops at `total_target_weight_g`
     pub target_weight_g: f32,
+    /// Doses pulled into one shared vessel (batch brewing)
+    pub cups: u8,
     pub auto_tare: bool,
+    /// Starting a brew tares first and waits for tare verification to read zero
+    pub tare_before_brew: bool,
+    /// A web start only goes ahead when sent twice within `COMMAND_CONFIRM_WINDOW_MS`
+    pub confirm_start: bool,
+    /// A web stop only goes ahead when sent twice within `COMMAND_CONFIRM_WINDOW_MS`
+    pub confirm_stop: bool,
     pub predictive_stop: bool,
+    /// Predictive stop follows overshoot learning instead of `predictive_stop` being set by hand:
+    /// on once learning is ready, off again if its confidence collapses
+    pub predictive_stop_auto: bool,
+    pub auto_tare_empty_threshold_g: f32,
+    pub auto_tare_stability_threshold_g: f32,
+    pub auto_tare_stable_readings: usize,
+    /// Active preset, or None when the parameters were tuned individually
+    pub auto_tare_profile: Option<AutoTareProfile>,
+    /// Seconds after a brew finishes before auto-tare may fire again
+    pub auto_tare_cooldown_secs: u32,
+    /// How long a placed object must stay within the stability band before it is tared
+    pub auto_tare_hold_ms: u32,
+    pub buzzer_enabled: bool,
+    pub quiet_hours: Option<QuietHours>,
+    /// Safety cutoff - any reading above this while the pump runs triggers an emergency stop
+    pub max_weight_g: f32,
+    /// Longest gap between scale samples tolerated while brewing before an emergency stop
+    pub stale_data_timeout_ms: u64,
+    /// How long the scale has to read near zero after a tare before an alert is raised (0 disables)
+    pub tare_verify_timeout_ms: u64,
+    /// Largest reading (either sign) that counts as zeroed when verifying a tare
+    pub tare_zero_threshold_g: f32,
+    /// A timer start reporting more flow than this (g/s) is the scale shutting down, not a brew
+    pub shutdown_flow_rate_g_per_s: f32,
+    /// Same for a weight jump this large between two samples - some shutdowns report zero flow
+    pub shutdown_weight_jump_g: f32,
+    /// Scales that power off when idle are poked this often to stay connected (0 disables)
+    pub scale_keep_alive_ms: u32,
+    /// A weight moving faster than this (g/s) is a corrupt frame unless the next frame repeats it
+    pub weight_rate_limit_g_per_s: f32,
+    /// Brew trace keeps one sample per this interval - longer brews fit the buffer at coarser steps
+    pub brew_trace_interval_ms: u32,
+    /// Slowest UI broadcast interval while brewing, leaving airtime to the scale (0 disables)
+    pub ble_priority_broadcast_ms: u32,
+    /// Shortest gap between OLED redraws - samples arriving sooner are coalesced
+    pub display_refresh_ms: u32,
+    /// Shortest gap between UI broadcasts carrying only new weight (0 for every sample) - the
+    /// control loop still sees every sample
+    pub weight_stream_ms: u32,
+    /// Weight is ignored for this long after a brew starts, so the spike from pressing the
+    /// scale's timer button can't read as the target being reached
+    pub brew_startup_ignore_ms: u32,
+    /// Added to the learned stop delay to give the earliest time-to-target a prediction may act on
+    pub prediction_safety_margin_ms: u32,
+    /// The prediction window closes at this multiple of its start - larger values predict further ahead
+    pub prediction_window_multiplier: f32,
+    /// Predictions only run while flow is above this (g/s)
+    pub min_flow_for_prediction: f32,
+    /// Flow below this (g/s) after a predicted stop counts as stopped and the overshoot is recorded
+    pub flow_stopped_threshold: f32,
+    /// Scale timer time before predictions start
+    pub prediction_start_delay_ms: u32,
+    /// Brews after an overshoot reset that stop early on purpose while learning has little data
+    pub overshoot_warmup_brews: u32,
+    /// Where the settled weight should land relative to the target - negative leaves room for
+    /// drips that fall after the cup is taken away
+    pub overshoot_target_g: f32,
+    /// Confidence-to-learning-rate mapping for the overshoot learner
+    pub overshoot_learning: OvershootLearningSchedule,
+    pub stop_mode: StopMode,
+    /// Flow must stay below `flow_stopped_threshold` this long to count as stopped - ends
+    /// settling early, and the shot itself in FlowStopped mode
+    pub flow_stop_hold_ms: u32,
+    /// A scale that drops mid-brew and is back within this long resumes the shot, 0 to always
+    /// end it
+    pub scale_reconnect_grace_ms: u32,
+    /// Settling also waits for the last readings to lie within this many grams, so the final
+    /// weight is taken from a still cup
+    pub settle_stability_g: f32,
+    /// Time mode: shot length
+    pub brew_time_ms: u32,
+    /// Flow curve the pump power follows while brewing - None runs at full power. Needs a PWM relay
+    pub flow_profile: Option<FlowProfile>,
+    /// Last dry dose confirmed on the scale
+    pub dose_g: Option<f32>,
+    /// Beverage-to-dose ratio - when set, confirming a dose makes the per-cup target dose × ratio
+    pub brew_ratio: Option<f32>,
+    /// Advertised name the scale scan looks for before falling back to the service UUID
+    pub scale_name_prefix: String,
+    /// Unit weights are shown and entered in on the web UI
+    pub weight_unit: WeightUnit,
+}
+
+impl BrewConfig {
+    /// Effective brew target across all cups - what predictive stop and overshoot learning aim for
+    pub fn total_target_weight_g(&self) -> f32 {
+        self.target_weight_g * self.cups.max(1) as f32
+    }
+
+    /// Set the per-cup target, rejecting weights outside the supported range or past the cutoff
+    pub fn set_target_weight(&mut self, weight_g: f32) -> Result<(), String> {
+        check_target_weight(weight_g)?;
+        let total = weight_g * self.cups.max(1) as f32;
+        if total > self.max_weight_g {
+            return Err(format!(
+                "{} cups of {:.1}g ({:.1}g) exceeds the {:.0}g safety cutoff",
+                self.cups, weight_g, total, self.max_weight_g
+            ));
+        }
+        self.target_weight_g = weight_g;
+        Ok(())
+    }
+
+    /// Set the cup count, rejecting values that are zero or would exceed the safety cutoff
+    pub fn set_cups(&mut self, cups: u8) -> Result<(), String> {
+        if cups == 0 {
+            return Err("Cups must be at least 1".to_string());
+        }
+        let total = self.target_weight_g * cups as f32;
+        if total > self.max_weight_g {
+            return Err(format!(
+                "{} cups of {:.1}g ({:.1}g) exceeds the {:.0}g safety cutoff",
+                cups, self.target_weight_g, total, self.max_weight_g
+            ));
+        }
+        self.cups = cups;
+        Ok(())
+    }
+
+    /// Set the brew ratio (None for a fixed target), rejecting ratios outside the supported range
+    pub fn set_brew_ratio(&mut self, ratio: Option<f32>) -> Result<(), String> {
+        if let Some(ratio) = ratio {
+            if !(MIN_BREW_RATIO..=MAX_BREW_RATIO).contains(&ratio) {
+                return Err(format!(
+                    "Brew ratio must be 1:{}-1:{}, got 1:{}",
+                    MIN_BREW_RATIO, MAX_BREW_RATIO, ratio
+                ));
+            }
+        }
+        self.brew_ratio = ratio;
+        Ok(())
+    }
+
+    /// Record a confirmed dose and, with a ratio set, derive the per-cup target from it
+    pub fn apply_dose(&mut self, dose_g: f32) -> Result<(), String> {
+        check_dose(dose_g)?;
+        if let Some(ratio) = self.brew_ratio {
+            let target = dose_g * ratio;
+            check_target_weight(target)?;
+            let total = target * self.cups.max(1) as f32;
+            if total > self.max_weight_g {
+                return Err(format!(
+                    "{:.1}g dose at 1:{} ({:.1}g) exceeds the {:.0}g safety cutoff",
+                    dose_g, ratio, total, self.max_weight_g
+                ));
+            }
+            self.target_weight_g = target;
+        }
+        self.dose_g = Some(dose_g);
+        Ok(())
+    }
+
+    /// Set the scan name prefix, rejecting empty, overlong or non-printable names
+    pub fn set_scale_name_prefix(&mut self, prefix: &str) -> Result<(), String> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Err("Scale name prefix must not be empty".to_string());
+        }
+        if prefix.len() > MAX_SCALE_NAME_PREFIX_LEN {
+            return Err(format!(
+                "Scale name prefix is {} bytes, advertised names hold at most {}",
+                prefix.len(),
+                MAX_SCALE_NAME_PREFIX_LEN
+            ));
+        }
+        if !prefix.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+            return Err("Scale name prefix must be printable ASCII".to_string());
+        }
+        self.scale_name_prefix = prefix.to_string();
+        Ok(())
+    }
+
+    /// Set the keep-alive interval for scales with auto-off - 0 turns it off
+    pub fn set_scale_keep_alive_ms(&mut self, interval_ms: u32) -> Result<(), String> {
+        if interval_ms != 0
+            && !(MIN_SCALE_KEEP_ALIVE_MS..=MAX_SCALE_KEEP_ALIVE_MS).contains(&interval_ms)
+        {
+            return Err(format!(
+                "Scale keep-alive must be 0 or {}-{}ms, got {}ms",
+                MIN_SCALE_KEEP_ALIVE_MS, MAX_SCALE_KEEP_ALIVE_MS, interval_ms
+            ));
+        }
+        self.scale_keep_alive_ms = interval_ms;
+        Ok(())
+    }
+
+    /// Set the weight rate beyond which a frame is treated as corrupt
+    pub fn set_weight_rate_limit(&mut self, limit_g_per_s: f32) -> Result<(), String> {
+        let range = MIN_WEIGHT_RATE_LIMIT_G_PER_S..=MAX_WEIGHT_RATE_LIMIT_G_PER_S;
+        if !range.contains(&limit_g_per_s) {
+            return Err(format!(
+                "Weight rate limit must be {}-{}g/s, got {}g/s",
+                MIN_WEIGHT_RATE_LIMIT_G_PER_S, MAX_WEIGHT_RATE_LIMIT_G_PER_S, limit_g_per_s
+            ));
+        }
+        self.weight_rate_limit_g_per_s = limit_g_per_s;
+        Ok(())
+    }
+
+    /// Set how often the brew trace keeps a sample
+    pub fn set_brew_trace_interval_ms(&mut self, interval_ms: u32) -> Result<(), String> {
+        if !(MIN_BREW_TRACE_INTERVAL_MS..=MAX_BREW_TRACE_INTERVAL_MS).contains(&interval_ms) {
+            return Err(format!(
+                "Brew trace interval must be {}-{}ms, got {}ms",
+                MIN_BREW_TRACE_INTERVAL_MS, MAX_BREW_TRACE_INTERVAL_MS, interval_ms
+            ));
+        }
+        self.brew_trace_interval_ms = interval_ms;
+        Ok(())
+    }
+
+    /// Set the UI broadcast interval used while brewing, 0 to keep WiFi at full rate
+    pub fn set_ble_priority_broadcast_ms(&mut self, interval_ms: u32) -> Result<(), String> {
+        let range = MIN_BLE_PRIORITY_BROADCAST_MS..=MAX_BLE_PRIORITY_BROADCAST_MS;
+        if interval_ms != 0 && !range.contains(&interval_ms) {
+            return Err(format!(
+                "BLE priority broadcast interval must be 0 or {}-{}ms, got {}ms",
+                MIN_BLE_PRIORITY_BROADCAST_MS, MAX_BLE_PRIORITY_BROADCAST_MS, interval_ms
+            ));
+        }
+        self.ble_priority_broadcast_ms = interval_ms;
+        Ok(())
+    }
+
+    /// Set the shortest gap between OLED redraws
+    pub fn set_display_refresh_ms(&mut self, interval_ms: u32) -> Result<(), String> {
+        if !(MIN_DISPLAY_REFRESH_MS..=MAX_DISPLAY_REFRESH_MS).contains(&interval_ms) {
+            return Err(format!(
+                "Display refresh interval must be {}-{}ms, got {}ms",
+                MIN_DISPLAY_REFRESH_MS, MAX_DISPLAY_REFRESH_MS, interval_ms
+            ));
+        }
+        self.display_refresh_ms = interval_ms;
+        Ok(())
+    }
+
+    pub fn requires_confirmation(&self, command: ConfirmableCommand) -> bool {
+        match command {
+            ConfirmableCommand::Start => self.confirm_start,
+            ConfirmableCommand::Stop => self.confirm_stop,
+        }
+    }
+
+    pub fn set_requires_confirmation(&mut self, command: ConfirmableCommand, enabled: bool) {
+        match command {
+            ConfirmableCommand::Start => self.confirm_start = enabled,
+            ConfirmableCommand::Stop => self.confirm_stop = enabled,
+        }
+    }
+
+    /// Set the shortest gap between weight-only UI broadcasts, 0 to stream every sample
+    pub fn set_weight_stream_ms(&mut self, interval_ms: u32) -> Result<(), String> {
+        let range = MIN_WEIGHT_STREAM_MS..=MAX_WEIGHT_STREAM_MS;
+        if interval_ms != 0 && !range.contains(&interval_ms) {
+            return Err(format!(
+                "Weight stream interval must be 0 or {}-{}ms, got {}ms",
+                MIN_WEIGHT_STREAM_MS, MAX_WEIGHT_STREAM_MS, interval_ms
+            ));
+        }
+        self.weight_stream_ms = interval_ms;
+        Ok(())
+    }
+
+    /// Check a replacement for the whole config the way the individual setters would, and return
+    /// it normalised. Safety limits have no setter and must come back unchanged
+    pub fn validate_replacement(&self, current: &BrewConfig) -> Result<BrewConfig, String> {
+        let fixed = [
+            ("max_weight_g", self.max_weight_g == current.max_weight_g),
+            ("stale_data_timeout_ms", self.stale_data_timeout_ms == current.stale_data_timeout_ms),
+            (
+                "tare_verify_timeout_ms",
+                self.tare_verify_timeout_ms == current.tare_verify_timeout_ms,
+            ),
+            ("tare_zero_threshold_g", self.tare_zero_threshold_g == current.tare_zero_threshold_g),
+            (
+                "shutdown_flow_rate_g_per_s",
+                self.shutdown_flow_rate_g_per_s == current.shutdown_flow_rate_g_per_s,
+            ),
+            (
+                "shutdown_weight_jump_g",
+                self.shutdown_weight_jump_g == current.shutdown_weight_jump_g,
+            ),
+            (
+                "brew_startup_ignore_ms",
+                self.brew_startup_ignore_ms == current.brew_startup_ignore_ms,
+            ),
+        ];
+        if let Some((name, _)) = fixed.iter().find(|(_, unchanged)| !unchanged) {
+            return Err(format!("{} is a safety limit and can't be changed remotely", name));
+        }
+        self.validate()?;
+
+        let mut config = self.clone();
+        // Setters trim and normalise - run them so the stored config matches what they'd store
+        config.set_scale_name_prefix(&self.scale_name_prefix)?;
+        // The preset follows from the parameters, whatever the client sent
+        config.set_auto_tare_params(self.auto_tare_params());
+        Ok(config)
+    }
+
+    /// Check every user-settable field against the range its setter enforces, so a config that
+    /// reaches the brewing math can't carry a value no command would have accepted
+    pub fn validate(&self) -> Result<(), String> {
+        let mut config = self.clone();
+        config.set_target_weight(self.target_weight_g)?;
+        config.set_cups(self.cups)?;
+        config.set_brew_ratio(self.brew_ratio)?;
+        if let Some(dose_g) = self.dose_g {
+            check_dose(dose_g)?;
+        }
+        config.set_scale_name_prefix(&self.scale_name_prefix)?;
+        config.set_scale_keep_alive_ms(self.scale_keep_alive_ms)?;
+        config.set_weight_rate_limit(self.weight_rate_limit_g_per_s)?;
+        config.set_brew_trace_interval_ms(self.brew_trace_interval_ms)?;
+        config.set_ble_priority_broadcast_ms(self.ble_priority_broadcast_ms)?;
+        config.set_display_refresh_ms(self.display_refresh_ms)?;
+        config.set_weight_stream_ms(self.weight_stream_ms)?;
+        self.auto_tare_params().validate()?;
+        self.overshoot_learning.validate()?;
+        if self.auto_tare_cooldown_secs > AUTO_TARE_MAX_BREWING_COOLDOWN_SECS {
+            return Err(format!(
+                "Auto-tare cooldown must be at most {}s, got {}s",
+                AUTO_TARE_MAX_BREWING_COOLDOWN_SECS, self.auto_tare_cooldown_secs
+            ));
+        }
+        if self.auto_tare_hold_ms > AUTO_TARE_MAX_HOLD_MS {
+            return Err(format!(
+                "Auto-tare hold must be at most {}ms, got {}ms",
+                AUTO_TARE_MAX_HOLD_MS, self.auto_tare_hold_ms
+            ));
+        }
+        if !(-OVERSHOOT_MAX_TARGET_G..=OVERSHOOT_MAX_TARGET_G).contains(&self.overshoot_target_g) {
+            return Err(format!(
+                "Overshoot setpoint must be within ±{}g, got {}g",
+                OVERSHOOT_MAX_TARGET_G, self.overshoot_target_g
+            ));
+        }
+        if self.flow_stop_hold_ms > MAX_FLOW_STOP_HOLD_MS {
+            return Err(format!(
+                "Flow stop hold must be at most {}ms, got {}ms",
+                MAX_FLOW_STOP_HOLD_MS, self.flow_stop_hold_ms
+            ));
+        }
+        if self.scale_reconnect_grace_ms > MAX_SCALE_RECONNECT_GRACE_MS {
+            return Err(format!(
+                "Scale reconnect grace must be at most {}ms, got {}ms",
+                MAX_SCALE_RECONNECT_GRACE_MS, self.scale_reconnect_grace_ms
+            ));
+        }
+        if !(MIN_BREW_TIME_MS..=MAX_BREW_TIME_MS).contains(&self.brew_time_ms) {
+            return Err(format!(
+                "Brew time must be {}-{}ms, got {}ms",
+                MIN_BREW_TIME_MS, MAX_BREW_TIME_MS, self.brew_time_ms
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn auto_tare_params(&self) -> AutoTareParams {
+        AutoTareParams {
+            empty_threshold_g: self.auto_tare_empty_threshold_g,
+            stability_threshold_g: self.auto_tare_stability_threshold_g,
+            stable_readings: self.auto_tare_stable_readings,
+        }
+    }
+
+    /// Store parameters and derive which preset (if any) they correspond to
+    pub fn set_auto_tare_params(&mut self, params: AutoTareParams) {
+        self.auto_tare_empty_threshold_g = params.empty_threshold_g;
+        self.auto_tare_stability_threshold_g = params.stability_threshold_g;
+        self.auto_tare_stable_readings = params.stable_readings;
+        self.auto_tare_profile = AutoTareProfile::matching(&params);
+    }
+}
+
+/// Per-cup target range - NaN fails it too
+fn check_target_weight(weight_g: f32) -> Result<(), String> {
+    if !(MIN_TARGET_WEIGHT_G..=MAX_TARGET_WEIGHT_G).contains(&weight_g) {
+        return Err(format!(
+            "Target weight must be {}-{}g per cup, got {}g",
+            MIN_TARGET_WEIGHT_G, MAX_TARGET_WEIGHT_G, weight_g
+        ));
+    }
+    Ok(())
+}
+
+fn check_dose(dose_g: f32) -> Result<(), String> {
+    if !(MIN_DOSE_G..=MAX_DOSE_G).contains(&dose_g) {
+        return Err(format!("Dose must be {}-{}g, got {}g", MIN_DOSE_G, MAX_DOSE_G, dose_g));
+    }
+    Ok(())
 }
 
 impl Default for BrewConfig {
     fn default() -> Self {
         Self {
             target_weight_g: 36.0,
+            cups: 1,
             auto_tare: true,
+            tare_before_brew: false,
+            confirm_start: false,
+            confirm_stop: false,
             predictive_stop: true,
+            predictive_stop_auto: false,
+            auto_tare_empty_threshold_g: AUTO_TARE_EMPTY_THRESHOLD_G,
+            auto_tare_stability_threshold_g: TARE_STABILITY_THRESHOLD_G,
+            auto_tare_stable_readings: TARE_STABILITY_COUNT,
+            auto_tare_profile: Some(AutoTareProfile::Normal),
+            auto_tare_cooldown_secs: AUTO_TARE_BREWING_COOLDOWN_SECS,
+            auto_tare_hold_ms: AUTO_TARE_HOLD_MS,
+            buzzer_enabled: true,
+            quiet_hours: None,
+            max_weight_g: MAX_BREW_WEIGHT_G,
+            stale_data_timeout_ms: STALE_DATA_TIMEOUT_MS,
+            tare_verify_timeout_ms: TARE_VERIFY_TIMEOUT_MS,
+            tare_zero_threshold_g: TARE_ZERO_THRESHOLD_G,
+            shutdown_flow_rate_g_per_s: SHUTDOWN_FLOW_RATE_G_PER_S,
+            shutdown_weight_jump_g: SHUTDOWN_WEIGHT_JUMP_G,
+            scale_keep_alive_ms: SCALE_KEEP_ALIVE_MS,
+            weight_rate_limit_g_per_s: WEIGHT_RATE_LIMIT_G_PER_S,
+            brew_trace_interval_ms: BREW_TRACE_INTERVAL_MS,
+            ble_priority_broadcast_ms: BLE_PRIORITY_BROADCAST_MS,
+            display_refresh_ms: DISPLAY_REFRESH_MS,
+            weight_stream_ms: WEIGHT_STREAM_MS,
+            brew_startup_ignore_ms: BREW_STARTUP_IGNORE_MS,
+            prediction_safety_margin_ms: PREDICTION_SAFETY_MARGIN_MS,
+            prediction_window_multiplier: PREDICTION_WINDOW_MULTIPLIER,
+            min_flow_for_prediction: PREDICTION_MIN_FLOW_G_PER_S,
+            flow_stopped_threshold: FLOW_STOPPED_THRESHOLD_G_PER_S,
+            prediction_start_delay_ms: PREDICTION_START_DELAY_MS,
+            overshoot_warmup_brews: OVERSHOOT_WARMUP_BREWS,
+            overshoot_target_g: 0.0,
+            overshoot_learning: OvershootLearningSchedule::default(),
+            stop_mode: StopMode::Weight,
+            flow_stop_hold_ms: FLOW_STOP_HOLD_MS,
+            scale_reconnect_grace_ms: SCALE_RECONNECT_GRACE_MS,
+            settle_stability_g: SETTLE_STABILITY_G,
+            brew_time_ms: BREW_TIME_MS,
+            flow_profile: None,
+            dose_g: None,
+            brew_ratio: None,
+            scale_name_prefix: DEFAULT_SCALE_NAME_PREFIX.to_string(),
+            weight_unit: WeightUnit::Grams,
+        }
+    }
+}
+
+/// Daily window (local hours, wrapping past midnight) during which audible alerts are muted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_hour: u8,
+    pub end_hour: u8,
+    pub utc_offset_minutes: i16,
+}
+
+impl QuietHours {
+    /// Check whether a local hour (0-23) falls inside the window
+    pub fn contains_hour(&self, hour: u8) -> bool {
+        if self.start_hour == self.end_hour {
+            return false;
+        }
+        if self.start_hour < self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            // Window wraps midnight, e.g. 22 -> 7
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+
+    /// Check against the wall clock; inactive until SNTP has set a plausible time
+    pub fn is_active_now(&self) -> bool {
+        let unix_secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        // Clock still at boot epoch (no SNTP sync yet) - can't know the hour
+        if unix_secs < 1_600_000_000 {
+            return false;
+        }
+
+        let local_secs = unix_secs + self.utc_offset_minutes as i64 * 60;
+        let hour = local_secs.rem_euclid(86_400) / 3_600;
+        self.contains_hour(hour as u8)
+    }
+}
+
+/// How quickly the overshoot learner adapts as it grows confident.
+///
+/// Confidence is the consistency of recent overshoots scaled by experience, which ramps
+/// linearly to 1.0 over `full_experience_brews`. Each new overshoot then enters the EWMA
+/// with weight `confident_rate` above `high_confidence`, `medium_rate` above
+/// `medium_confidence` and `uncertain_rate` otherwise. Higher rates and fewer brews converge
+/// faster on a consistent machine; lower rates ride out the odd channeling shot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OvershootLearningSchedule {
+    pub uncertain_rate: f32,
+    pub medium_rate: f32,
+    pub confident_rate: f32,
+    pub medium_confidence: f32,
+    pub high_confidence: f32,
+    pub full_experience_brews: u32,
+}
+
+impl Default for OvershootLearningSchedule {
+    fn default() -> Self {
+        Self {
+            uncertain_rate: OVERSHOOT_UNCERTAIN_LEARNING_RATE,
+            medium_rate: OVERSHOOT_MEDIUM_LEARNING_RATE,
+            confident_rate: OVERSHOOT_CONFIDENT_LEARNING_RATE,
+            medium_confidence: OVERSHOOT_MEDIUM_CONFIDENCE,
+            high_confidence: OVERSHOOT_HIGH_CONFIDENCE,
+            full_experience_brews: OVERSHOOT_FULL_EXPERIENCE_BREWS,
         }
     }
 }
 
+impl OvershootLearningSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("Uncertain", self.uncertain_rate),
+            ("Medium", self.medium_rate),
+            ("Confident", self.confident_rate),
+        ] {
+            if !rate.is_finite() || rate <= 0.0 || rate > 1.0 {
+                return Err(format!(
+                    "{} learning rate must be above 0 and at most 1, got {}",
+                    name, rate
+                ));
+            }
+        }
+        if !self.medium_confidence.is_finite()
+            || !self.high_confidence.is_finite()
+            || self.medium_confidence <= 0.0
+            || self.medium_confidence > self.high_confidence
+            || self.high_confidence >= 1.0
+        {
+            return Err(format!(
+                "Confidence thresholds must satisfy 0 < medium <= high < 1, got {} and {}",
+                self.medium_confidence, self.high_confidence
+            ));
+        }
+        if self.full_experience_brews == 0
+            || self.full_experience_brews > OVERSHOOT_MAX_FULL_EXPERIENCE_BREWS
+        {
+            return Err(format!(
+                "Full experience must take between 1 and {} brews, got {}",
+                OVERSHOOT_MAX_FULL_EXPERIENCE_BREWS, self.full_experience_brews
+            ));
+        }
+        Ok(())
+    }
+
+    /// EWMA weight given to a new overshoot at this confidence
+    pub fn learning_rate(&self, confidence: f32) -> f32 {
+        if confidence > self.high_confidence {
+            self.confident_rate
+        } else if confidence > self.medium_confidence {
+            self.medium_rate
+        } else {
+            self.uncertain_rate
+        }
+    }
+
+    /// 0.0-1.0 share of full confidence that `brew_count` brews of experience allow
+    pub fn experience_factor(&self, brew_count: u32) -> f32 {
+        (brew_count as f32 / self.full_experience_brews as f32).min(1.0)
+    }
+}
+
+/// Learned overshoot compensation for one coffee setup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OvershootModel {
+    /// How far ahead of the predicted target the relay is cut
+    pub stop_delay_ms: i32,
+    /// Smoothed overshoot past the target (negative = stopping short)
+    pub ewma_g: f32,
+    /// 0.0-1.0, recomputed from `recent_overshoots_g` after every shot
+    pub confidence: f32,
+    pub brew_count: u32,
+    /// Latest measured overshoots, oldest first, at most `OVERSHOOT_HISTORY_SIZE`
+    pub recent_overshoots_g: Vec<f32>,
+}
+
+impl Default for OvershootModel {
+    fn default() -> Self {
+        Self {
+            stop_delay_ms: OVERSHOOT_INITIAL_STOP_DELAY_MS,
+            ewma_g: 0.0,
+            confidence: 0.0,
+            brew_count: 0,
+            recent_overshoots_g: Vec::new(),
+        }
+    }
+}
+
+/// Named overshoot models - grind and dose change how far a shot runs on, so espresso and
+/// filter each learn their own. Only the active one is updated by brews.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OvershootProfiles {
+    pub active: String,
+    /// A profile without an entry yet starts from `OvershootModel::default()`
+    pub models: BTreeMap<String, OvershootModel>,
+}
+
+impl Default for OvershootProfiles {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_OVERSHOOT_PROFILE.to_string(),
+            models: BTreeMap::new(),
+        }
+    }
+}
+
+impl OvershootProfiles {
+    /// Trimmed profile name, or why it can't be used
+    pub fn validate_name(name: &str) -> Result<&str, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        if name.len() > MAX_OVERSHOOT_PROFILE_NAME_LEN {
+            return Err(format!(
+                "Profile name is {} bytes, at most {} allowed",
+                name.len(),
+                MAX_OVERSHOOT_PROFILE_NAME_LEN
+            ));
+        }
+        if !name.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+            return Err("Profile name must be printable ASCII".to_string());
+        }
+        Ok(name)
+    }
+
+    /// Every profile name, the active one included even before it has learned anything
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.models.keys().cloned().collect();
+        if !self.models.contains_key(&self.active) {
+            names.push(self.active.clone());
+            names.sort();
+        }
+        names
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SystemState {
     pub scale_data: Option<ScaleData>,
     pub timer_state: TimerState,
     pub brew_state: BrewState,
+    /// Hierarchical state machine state - richer than the legacy `brew_state`
+    pub machine_state: MachineState,
     pub auto_tare_state: AutoTareState,
+    pub last_auto_tare_reason: Option<AutoTareReason>,
     pub config: BrewConfig,
     pub relay_enabled: bool,
     pub ble_connected: bool,
+    /// Last step of the scale connection - scanning, connecting, connected, discovering or
+    /// subscribed. None while disconnected
+    pub ble_stage: Option<&'static str>,
+    /// Connected scale as reported by the device, None while disconnected
+    pub scale_info: Option<ScaleInfo>,
+    /// Latest link quality score, None while disconnected
+    pub link_quality: Option<LinkQualityReport>,
+    /// Devices found by the last user-requested scan
+    pub nearby_devices: Vec<Device>,
     pub wifi_connected: bool,
+    /// Which radio currently has priority - BLE while a shot runs, so the UI updates slower
+    pub radio_priority: RadioPriority,
+    /// Killswitch state - false means the brew state machine is disabled
+    pub system_enabled: bool,
     pub last_error: Option<String>,
-    pub log_messages: heapless::Vec<String, 100>,
+    pub log_entries: heapless::Vec<LogEntry, LOG_BUFFER_SIZE>,
+    pub safety: SafetyStatus,
+    pub relay_stats: RelayStats,
+    /// Read-back of the relay pins at boot - a stuck pin is a hardware fault
+    pub relay_boot_check: RelayBootCheck,
+    pub overshoot: OvershootStatus,
+    /// The state machine's view of the current brew, refreshed on every sample and tick
+    pub brew: BrewSnapshot,
+    pub dose: DoseStatus,
+    pub scale_detector: ScaleDetectorStatus,
+    /// Flash writes since boot
+    pub nvs_writes: u32,
+    /// False when NVS failed to initialize - settings changes won't survive a reboot
+    pub storage_available: bool,
+    /// Overshoot profile brews currently learn into
+    pub overshoot_profile: String,
+    pub overshoot_profiles: Vec<String>,
+    /// Result of the last `run_self_test`, None until one has run
+    pub self_test: Option<SelfTestReport>,
 }
 
 impl Default for SystemState {
@@ -70,17 +1037,85 @@ impl Default for SystemState {
             scale_data: None,
             timer_state: TimerState::Idle,
             brew_state: BrewState::Idle,
+            machine_state: MachineState::default(),
             auto_tare_state: AutoTareState::Empty,
+            last_auto_tare_reason: None,
             config: BrewConfig::default(),
             relay_enabled: false,
             ble_connected: false,
+            ble_stage: None,
+            scale_info: None,
+            link_quality: None,
+            nearby_devices: Vec::new(),
             wifi_connected: false,
+            radio_priority: RadioPriority::Balanced,
+            system_enabled: false,
             last_error: None,
-            log_messages: heapless::Vec::new(),
+            log_entries: heapless::Vec::new(),
+            safety: SafetyStatus::default(),
+            relay_stats: RelayStats::default(),
+            relay_boot_check: RelayBootCheck::default(),
+            overshoot: OvershootStatus::default(),
+            brew: BrewSnapshot::default(),
+            dose: DoseStatus::default(),
+            scale_detector: ScaleDetectorStatus::default(),
+            nvs_writes: 0,
+            storage_available: true,
+            overshoot_profile: DEFAULT_OVERSHOOT_PROFILE.to_string(),
+            overshoot_profiles: vec![DEFAULT_OVERSHOOT_PROFILE.to_string()],
+            self_test: None,
         }
     }
 }
 
+impl SystemState {
+    /// Progress of the shot in flight, None unless brewing
+    pub fn brew_progress(&self) -> Option<BrewProgress> {
+        if self.brew_state != BrewState::Brewing {
+            return None;
+        }
+        self.scale_data.as_ref().map(|data| {
+            BrewProgress::new(
+                data.weight_g,
+                data.flow_rate_g_per_s,
+                self.config.total_target_weight_g(),
+            )
+        })
+    }
+
+    /// Minimum gap between UI broadcasts under the current radio priority, 0 for no limit
+    pub fn broadcast_interval_ms(&self) -> u32 {
+        match self.radio_priority {
+            RadioPriority::Balanced => 0,
+            RadioPriority::Ble => self.config.ble_priority_broadcast_ms,
+        }
+    }
+
+    /// Minimum gap before a broadcast that only carries new weight - the slower of the weight
+    /// stream rate and the radio priority limit
+    pub fn weight_broadcast_interval_ms(&self) -> u32 {
+        self.config.weight_stream_ms.max(self.broadcast_interval_ms())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Timestamped entry in the user-visible event log
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// Milliseconds since boot
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    pub message: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketMessage {
     pub message_type: String,
@@ -89,7 +1124,121 @@ pub struct WebSocketMessage {
 
 pub const TARE_STABILITY_THRESHOLD_G: f32 = 0.5; // Match Python implementation for faster cup removal detection
 pub const TARE_STABILITY_COUNT: usize = 5;
+pub const AUTO_TARE_HISTORY_SIZE: usize = 10; // Upper bound for the stability window
+pub const AUTO_TARE_EMPTY_THRESHOLD_G: f32 = 2.0;
+pub const AUTO_TARE_MAX_EMPTY_THRESHOLD_G: f32 = 50.0;
+pub const AUTO_TARE_MAX_STABILITY_THRESHOLD_G: f32 = 5.0;
+pub const AUTO_TARE_BREWING_COOLDOWN_SECS: u32 = 10; // No auto-tare right after a brew
+pub const AUTO_TARE_MAX_BREWING_COOLDOWN_SECS: u32 = 600;
+pub const AUTO_TARE_HOLD_MS: u32 = 500; // Outlasts the pause while a portafilter is being lowered
+pub const AUTO_TARE_MAX_HOLD_MS: u32 = 5000;
 pub const TARE_COOLDOWN_MS: u64 = 2000;
+pub const TARE_VERIFY_TIMEOUT_MS: u64 = 3000; // A few notifications past the scale's own settle time
+pub const TARE_ZERO_THRESHOLD_G: f32 = 0.5;
 pub const BREW_SETTLING_TIMEOUT_MS: u64 = 2000; // 2 seconds settling time
 pub const OVERSHOOT_HISTORY_SIZE: usize = 5;
+pub const OVERSHOOT_INITIAL_STOP_DELAY_MS: i32 = 500;
+pub const OVERSHOOT_OUTLIER_STD_DEVS: f32 = 3.0;
+pub const OVERSHOOT_OUTLIER_MIN_SPREAD_G: f32 = 1.0; // A tight history mustn't reject ordinary shots
+pub const OVERSHOOT_OUTLIER_MAX_STREAK: u32 = 2; // A third miss in a row is a real change, not a fluke
+pub const DEFAULT_OVERSHOOT_PROFILE: &str = "default";
+pub const MAX_OVERSHOOT_PROFILES: usize = 8; // All profiles share one NVS blob
+pub const MAX_OVERSHOOT_PROFILE_NAME_LEN: usize = 24;
+pub const OVERSHOOT_WARMUP_BREWS: u32 = 3; // Matches the brew count needed for learning to be ready
+pub const OVERSHOOT_MAX_WARMUP_BREWS: u32 = 20;
+pub const OVERSHOOT_MAX_TARGET_G: f32 = 10.0; // Either way - a drip tray's worth, not a cup size
+pub const OVERSHOOT_WARMUP_EXTRA_DELAY_MS: f32 = 300.0; // Extra stop delay on the very first shot
+pub const OVERSHOOT_UNCERTAIN_LEARNING_RATE: f32 = 0.3; // 30% new data, 70% historical
+pub const OVERSHOOT_MEDIUM_LEARNING_RATE: f32 = 0.2;
+pub const OVERSHOOT_CONFIDENT_LEARNING_RATE: f32 = 0.1; // One odd shot barely moves a settled delay
+pub const OVERSHOOT_MEDIUM_CONFIDENCE: f32 = 0.5;
+pub const OVERSHOOT_HIGH_CONFIDENCE: f32 = 0.8;
+pub const OVERSHOOT_FULL_EXPERIENCE_BREWS: u32 = 20; // About a week of daily shots
+pub const OVERSHOOT_MAX_FULL_EXPERIENCE_BREWS: u32 = 200;
+pub const OVERSHOOT_READY_BREWS: u32 = 3;
+pub const OVERSHOOT_READY_CONFIDENCE: f32 = 0.2;
+pub const PREDICTIVE_AUTO_DISABLE_CONFIDENCE: f32 = 0.1; // Below the ready bar so one noisy shot can't flap it
+pub const LOG_BUFFER_SIZE: usize = 100;
+pub const EVENT_STATS_LOG_KINDS: usize = 5; // A flood tops the list, one log line holds the rest
+pub const DEFAULT_SCALE_NAME_PREFIX: &str = "BOOKOO_SC"; // Bookoo Themis Mini advertised name
+pub const MAX_SCALE_NAME_PREFIX_LEN: usize = 29; // Longest name a legacy advertisement can carry
+pub const MIN_DEVICE_SCAN_MS: u32 = 1000;
+pub const SCALE_KEEP_ALIVE_MS: u32 = 60_000; // Well inside the shortest idle auto-off timers
+pub const MIN_SCALE_KEEP_ALIVE_MS: u32 = 5000;
+pub const MAX_SCALE_KEEP_ALIVE_MS: u32 = 600_000;
+pub const MAX_DEVICE_SCAN_MS: u32 = 30_000; // Scale data stalls while a scan runs
+pub const SCALE_DISCONNECT_TIMEOUT_MS: u64 = 2000; // A connected scale lets go well within this
+pub const RELAY_TEST_MS: u32 = 100; // Long enough to hear the relay click
+pub const MIN_RELAY_TEST_MS: u32 = 50;
+pub const MAX_RELAY_TEST_MS: u32 = 5000; // A pump running dry for longer risks damage
+pub const SHUTDOWN_FLOW_RATE_G_PER_S: f32 = 25.0; // Readings go wild as a Bookoo powers off
+pub const SHUTDOWN_WEIGHT_JUMP_G: f32 = 10.0; // 100g/s at 10Hz - no pour gets near this
+pub const WEIGHT_RATE_LIMIT_G_PER_S: f32 = 200.0; // Only a cup set down is faster, and it repeats
+pub const MIN_WEIGHT_RATE_LIMIT_G_PER_S: f32 = 20.0; // Above the fastest kettle pour
+pub const MAX_WEIGHT_RATE_LIMIT_G_PER_S: f32 = 5000.0;
+pub const WEIGHT_RATE_MIN_INTERVAL_MS: u64 = 100; // 10Hz frames - bunched ones get a full interval
+pub const BREW_STARTUP_IGNORE_MS: u32 = 2000; // Button press spike has settled well before this
+pub const BREW_TRACE_MAX_SAMPLES: usize = 600; // ~7KB of RAM, a minute at the full 10Hz
+pub const BREW_TRACE_INTERVAL_MS: u32 = 100; // Every frame - a shot fits with room to spare
+pub const MIN_BREW_TRACE_INTERVAL_MS: u32 = 100;
+pub const MAX_BREW_TRACE_INTERVAL_MS: u32 = 5000; // Still fits a ten minute batch brew many times
+pub const BLE_PRIORITY_BROADCAST_MS: u32 = 500; // 2Hz keeps the UI alive mid-shot
+pub const MIN_BLE_PRIORITY_BROADCAST_MS: u32 = 100;
+pub const MAX_BLE_PRIORITY_BROADCAST_MS: u32 = 5000;
+pub const DISPLAY_REFRESH_MS: u32 = 250; // 4Hz reads smoothly and leaves I2C mostly idle
+pub const MIN_DISPLAY_REFRESH_MS: u32 = 50; // A full 128x64 frame at 400kHz takes ~25ms
+pub const MAX_DISPLAY_REFRESH_MS: u32 = 2000;
+pub const COMMAND_CONFIRM_WINDOW_MS: u64 = 3000; // Long enough to find the button again
+pub const WEIGHT_STREAM_MS: u32 = 250; // 4Hz reads as live and leaves WiFi to the scale
+pub const MIN_WEIGHT_STREAM_MS: u32 = 50;
+pub const MAX_WEIGHT_STREAM_MS: u32 = 2000;
+pub const LINK_QUALITY_FRAME_WINDOW: usize = 200; // 20 seconds of frames at 10Hz
+pub const LINK_QUALITY_MAX_FAILED_FRACTION: f32 = 0.2; // One frame in five corrupt scores zero
+pub const LINK_QUALITY_MAX_RECONNECTS: usize = 4; // Four drops in the window scores zero
+pub const LINK_QUALITY_RECONNECT_WINDOW_MS: u64 = 3_600_000;
+pub const LINK_QUALITY_RSSI_GOOD_DBM: i8 = -55; // Scale on the drip tray next to the controller
+pub const LINK_QUALITY_RSSI_BAD_DBM: i8 = -90; // Around where BLE notifications start going missing
+pub const LINK_QUALITY_REPORT_INTERVAL_MS: u64 = 5000;
+pub const LINK_QUALITY_WARN_SCORE: u8 = 50;
+pub const LINK_QUALITY_RECOVER_SCORE: u8 = 65; // Hysteresis - a score hovering at 50 warns once
+pub const BREW_PROGRESS_INTERVAL_MS: u64 = 200; // ~5Hz is smooth enough for a progress bar
+pub const TIMER_STOP_FROZEN_SAMPLES: u8 = 2; // One duplicated BLE frame must not read as a stop
+pub const STALE_DATA_TIMEOUT_MS: u64 = 2000; // 20 missed samples at 10Hz - brewing blind beyond this
+pub const GRAMS_PER_OUNCE: f32 = 28.349_523;
+pub const MIN_BREW_RATIO: f32 = 1.0;
+pub const MAX_BREW_RATIO: f32 = 5.0; // Ristretto to lungo - drip ratios are out of range
+pub const MIN_TARGET_WEIGHT_G: f32 = 1.0;
+pub const MAX_TARGET_WEIGHT_G: f32 = 200.0; // Per cup - a batch brew multiplies it up to the cutoff
+pub const MIN_DOSE_G: f32 = 1.0;
+pub const MAX_DOSE_G: f32 = 50.0; // Past the largest basket and most pour-over beds
+pub const MAX_BREW_WEIGHT_G: f32 = 500.0; // Well above any shot, well below an overflowing cup
 pub const PREDICTION_SAFETY_MARGIN_G: f32 = 2.0; // Increased from 0.5g to prevent early stops
+pub const PREDICTION_SAFETY_MARGIN_MS: u32 = 200; // Reaction headroom on top of the learned stop delay
+pub const PREDICTION_MAX_SAFETY_MARGIN_MS: u32 = 2000;
+pub const PREDICTION_WINDOW_MULTIPLIER: f32 = 3.0; // Don't predict too far ahead
+pub const PREDICTION_MAX_WINDOW_MULTIPLIER: f32 = 10.0;
+pub const PREDICTION_MIN_FLOW_G_PER_S: f32 = 0.0; // Predict on any positive flow
+pub const PREDICTION_MAX_MIN_FLOW_G_PER_S: f32 = 10.0;
+pub const FLOW_STOPPED_THRESHOLD_G_PER_S: f32 = 0.5; // Below this the shot has stopped dripping
+pub const MAX_FLOW_STOPPED_THRESHOLD_G_PER_S: f32 = 5.0;
+pub const FLOW_DROP_CONFIRM_SAMPLES: u32 = 3; // One noisy reading can't end a shot early
+pub const FLOW_STOP_HOLD_MS: u32 = 1500; // Longer than the gap between drips at the end of a pour
+pub const MAX_FLOW_STOP_HOLD_MS: u32 = 30_000;
+pub const SCALE_RECONNECT_GRACE_MS: u32 = 0; // A lost scale ends the shot unless asked otherwise
+pub const MAX_SCALE_RECONNECT_GRACE_MS: u32 = 15_000; // Room for a rescan and reconnect
+pub const RESUME_MAX_WEIGHT_DRIFT_G: f32 = 3.0; // Drips with the pump off, not a moved cup
+pub const SETTLE_STABILITY_G: f32 = 0.3; // A cup still taking drips moves more than this
+pub const MAX_SETTLE_STABILITY_G: f32 = 5.0;
+pub const SETTLE_STABLE_READINGS: usize = 3; // Weight range is checked over this many samples
+pub const BREW_TIME_MS: u32 = 30_000;
+pub const MIN_BREW_TIME_MS: u32 = 1000;
+pub const MAX_BREW_TIME_MS: u32 = 600_000; // Ten minutes covers any batch brew
+pub const RELAY_MAX_ON_MARGIN_MS: u32 = 30_000; // Headroom past a time mode shot before the relay cap trips
+pub const MAX_FLOW_PROFILE_POINTS: usize = 8; // Pre-infusion, ramp, hold and decline, with room
+pub const MAX_PROFILE_FLOW_G_PER_S: f32 = 10.0; // Far beyond any espresso or pour-over flow
+pub const FLOW_PID_KP: f32 = 0.15; // 1g/s short of target adds 15% power straight away
+pub const FLOW_PID_KI: f32 = 0.05; // Each g/s of lasting shortfall adds 5% power per second
+pub const FLOW_PID_KD: f32 = 0.0; // Scale flow is too noisy at 10Hz for a useful derivative
+pub const FLOW_PROFILE_MIN_POWER_STEP: f32 = 0.01; // Smaller changes aren't worth a relay update
+pub const PREDICTION_START_DELAY_MS: u32 = 2000; // Ignore startup artifacts on the scale timer
+pub const PREDICTION_MAX_START_DELAY_MS: u32 = 30_000;
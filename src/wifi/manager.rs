@@ -1,5 +1,6 @@
 //! WiFi management for both provisioning and normal station operation
 
+use crate::ble::BleClient;
 use crate::wifi::provisioning::WifiProvisioning;
 use embassy_time::{Duration, Instant, Timer};
 use esp_idf_svc::eventloop::EspSystemEventLoop;
@@ -98,6 +99,7 @@ impl WifiManager {
                     let device_name = WifiProvisioning::generate_device_name("GravelScale");
                     let pop = Some("gravel123");
 
+                    Self::release_ble().await;
                     info!("🚀 Starting BLE provisioning as '{}'", device_name);
                     provisioning.start_provisioning(&device_name, pop, None)?;
 
@@ -322,6 +324,13 @@ impl WifiManager {
         }
     }
 
+    /// Provisioning brings up its own BLE stack - ours must be fully down first
+    async fn release_ble() {
+        if let Err(e) = BleClient::shutdown().await {
+            warn!("🔀 BLE shutdown before provisioning failed: {} - provisioning may not start", e);
+        }
+    }
+
     /// Start BLE provisioning mode
     async fn start_provisioning_mode(&mut self) -> Result<(), EspError> {
         if let Some(ref provisioning) = self.provisioning {
@@ -338,6 +347,7 @@ impl WifiManager {
             // Start provisioning with security (you can customize the POP)
             let pop = Some("gravel123"); // Proof of Possession - customize this
 
+            Self::release_ble().await;
             info!("🚀 Starting BLE provisioning as '{}'", device_name);
             provisioning.start_provisioning(&device_name, pop, None)?;
 
@@ -5,22 +5,75 @@ class EspressoWebClient {
     constructor() {
         this.pollingInterval = null;
         this.pollingRate = 200; // 5Hz (200ms)
+        this.heartbeatInterval = null;
+        this.heartbeatRate = 5000; // Well inside the device's 30s idle session timeout
+        this.heartbeatNonce = 0;
+        this.missedHeartbeats = 0;
+        this.socket = null;
         this.state = {
             scale_weight: 0.0,
             target_weight: 36.0,
+            cups: 1,
+            total_target_weight: 36.0,
             flow_rate: 0.0,
+            weight_unit: 'grams',
             timer_state: 'Idle',
             ble_connected: false,
             wifi_connected: true,
+            radio_priority: 'balanced',
             relay_enabled: false,
             brew_state: 'Idle',
             battery_percent: 0,
             auto_tare_enabled: true,
+            tare_before_brew: false,
             predictive_stop_enabled: true,
+            predictive_stop_auto: false,
+            system_enabled: true,
+            storage_available: true,
             overshoot_info: 'No data',
             error: null
         };
         this.initPolling();
+        this.connectSocket();
+    }
+
+    // Push updates shared by every open tab; polling only runs while the socket is down
+    connectSocket() {
+        this.socket = new WebSocket(`ws://${window.location.host}/ws`);
+
+        this.socket.onopen = () => {
+            addLogMessage('🌐 Live updates connected');
+            this.pausePolling();
+        };
+
+        this.socket.onmessage = (event) => {
+            try {
+                this.handleServerMessage(JSON.parse(event.data));
+            } catch (error) {
+                console.warn(`Bad live update: ${error.message}`);
+            }
+        };
+
+        this.socket.onclose = () => {
+            this.socket = null;
+            this.resumePolling();
+            setTimeout(() => this.connectSocket(), 5000);
+        };
+    }
+
+    pausePolling() {
+        if (this.pollingInterval) {
+            clearInterval(this.pollingInterval);
+            this.pollingInterval = null;
+        }
+    }
+
+    resumePolling() {
+        if (!this.pollingInterval) {
+            this.pollingInterval = setInterval(() => {
+                this.pollServer();
+            }, this.pollingRate);
+        }
     }
 
     initPolling() {
@@ -35,6 +88,38 @@ class EspressoWebClient {
         }, this.pollingRate);
         
         addLogMessage('✅ HTTP polling started - real-time data active');
+
+        this.heartbeatInterval = setInterval(() => {
+            this.sendHeartbeat();
+        }, this.heartbeatRate);
+    }
+
+    async sendHeartbeat() {
+        const nonce = ++this.heartbeatNonce;
+        const sentAt = performance.now();
+        try {
+            const response = await fetch('/command', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ type: 'ping', nonce: nonce })
+            });
+            const pong = await response.json();
+            if (pong.nonce !== nonce) {
+                throw new Error('stale pong');
+            }
+            const latency = Math.round(performance.now() - sentAt);
+            document.getElementById('latency').textContent = `${latency} ms`;
+            if (this.missedHeartbeats >= 3) {
+                addLogMessage('✅ Connection to device restored');
+            }
+            this.missedHeartbeats = 0;
+        } catch (error) {
+            this.missedHeartbeats++;
+            if (this.missedHeartbeats === 3) {
+                document.getElementById('latency').textContent = 'Offline';
+                addLogMessage('⚠️ Device not answering heartbeats');
+            }
+        }
     }
 
     async pollServer() {
@@ -60,6 +145,15 @@ class EspressoWebClient {
     }
 
     stopPolling() {
+        if (this.heartbeatInterval) {
+            clearInterval(this.heartbeatInterval);
+            this.heartbeatInterval = null;
+        }
+        if (this.socket) {
+            this.socket.onclose = null;
+            this.socket.close();
+            this.socket = null;
+        }
         if (this.pollingInterval) {
             clearInterval(this.pollingInterval);
             this.pollingInterval = null;
@@ -72,8 +166,8 @@ class EspressoWebClient {
         
         // Update scale data if present
         if (data.scale_data) {
-            this.state.scale_weight = data.scale_data.weight_g;
-            this.state.flow_rate = data.scale_data.flow_rate_g_per_s;
+            this.state.scale_weight = data.scale_data.weight;
+            this.state.flow_rate = data.scale_data.flow_rate;
             this.state.battery_percent = data.scale_data.battery_percent;
         }
 
@@ -82,15 +176,28 @@ class EspressoWebClient {
             const sys = data.system_state;
             this.state.timer_state = sys.timer_state;
             this.state.brew_state = sys.brew_state;
-            this.state.target_weight = sys.target_weight_g;
+            this.state.weight_unit = sys.weight_unit;
+            this.state.target_weight = sys.target_weight;
+            this.state.cups = sys.cups;
+            this.state.total_target_weight = sys.total_target_weight;
             this.state.ble_connected = sys.ble_connected;
+            this.state.ble_stage = sys.ble_stage;
+            this.state.radio_priority = sys.radio_priority;
             this.state.relay_enabled = sys.relay_enabled;
             this.state.auto_tare_enabled = sys.auto_tare_enabled;
+            this.state.tare_before_brew = sys.tare_before_brew;
             this.state.predictive_stop_enabled = sys.predictive_stop_enabled;
+            this.state.predictive_stop_auto = sys.predictive_stop_auto;
+            this.state.system_enabled = sys.system_enabled;
+            this.state.storage_available = sys.storage_available !== false;
             this.state.overshoot_info = sys.overshoot_info;
             this.state.error = sys.error;
         }
 
+        if (data.nearby_devices) {
+            this.state.nearby_devices = data.nearby_devices;
+        }
+
         this.updateUI();
     }
 
@@ -115,26 +222,54 @@ class EspressoWebClient {
     }
 
     updateUI() {
+        const ounces = this.state.weight_unit === 'ounces';
+        const unit = ounces ? 'oz' : 'g';
+        const digits = ounces ? 2 : 1;
         document.getElementById('scale-weight').textContent = this.state.scale_weight.toFixed(2);
-        document.getElementById('target-weight').textContent = this.state.target_weight.toFixed(1);
+        document.getElementById('weight-label').textContent = `Weight (${unit})`;
+        document.getElementById('target-weight').textContent = this.state.total_target_weight.toFixed(digits);
+        const unitName = ounces ? 'Ounces' : 'Grams';
+        document.getElementById('target-breakdown').textContent = this.state.cups > 1
+            ? `${unitName} (${this.state.target_weight.toFixed(digits)}${unit} × ${this.state.cups} cups)`
+            : unitName;
         document.getElementById('flow-rate').textContent = this.state.flow_rate.toFixed(2);
+        document.getElementById('flow-label').textContent = `${unit}/s`;
+        document.getElementById('target-weight-label').textContent = `Target Weight per Cup (${unit}):`;
+        const unitSelect = document.getElementById('weight-unit-select');
+        if (document.activeElement !== unitSelect) {
+            unitSelect.value = this.state.weight_unit;
+        }
         document.getElementById('timer-state').textContent = this.state.timer_state;
         document.getElementById('battery-level').textContent = this.state.battery_percent + '%';
-        document.getElementById('ble-status').textContent = this.state.ble_connected ? 'Connected' : 'Disconnected';
-        document.getElementById('wifi-status').textContent = 'Connected'; // We're getting data, so WiFi works
+        document.getElementById('ble-status').textContent = this.bleStatusText();
+        // We're getting data, so WiFi works - mid-shot the scale gets the airtime and updates slow down
+        document.getElementById('wifi-status').textContent = this.state.radio_priority === 'ble'
+            ? 'Connected (BLE priority - updates slowed)'
+            : 'Connected';
         document.getElementById('relay-status').textContent = this.state.relay_enabled ? 'ON' : 'OFF';
         document.getElementById('brew-state').textContent = this.state.brew_state;
         document.getElementById('overshoot-info').textContent = this.state.overshoot_info;
+        document.getElementById('system-status-value').textContent = this.state.system_enabled ? 'Enabled' : 'Disabled (killswitch)';
+        document.getElementById('storage-warning').hidden = this.state.storage_available;
 
         // Update checkboxes to match server state
         document.getElementById('auto-tare-checkbox').checked = this.state.auto_tare_enabled;
+        document.getElementById('tare-before-brew-checkbox').checked = this.state.tare_before_brew;
         document.getElementById('predictive-stop-checkbox').checked = this.state.predictive_stop_enabled;
+        document.getElementById('predictive-auto-checkbox').checked = this.state.predictive_stop_auto;
+        document.getElementById('system-enabled-checkbox').checked = this.state.system_enabled;
         
         // Only update target weight input if it's not currently focused (user isn't typing)
         const targetInput = document.getElementById('target-weight-input');
         if (document.activeElement !== targetInput) {
             targetInput.value = this.state.target_weight;
         }
+        const cupsInput = document.getElementById('cups-input');
+        if (document.activeElement !== cupsInput) {
+            cupsInput.value = this.state.cups;
+        }
+
+        this.updateNearbyDevices();
 
         // Add visual indicators for connection status
         this.updateStatusColors();
@@ -145,6 +280,38 @@ class EspressoWebClient {
         }
     }
 
+    updateNearbyDevices() {
+        const list = document.getElementById('nearby-devices');
+        const devices = this.state.nearby_devices || [];
+        const key = JSON.stringify(devices);
+        if (list.dataset.key === key) {
+            return; // Unchanged - keep the buttons stable between polls
+        }
+        list.dataset.key = key;
+        list.replaceChildren(...devices.map(device => {
+            const item = document.createElement('li');
+            item.textContent = `${device.name || 'Unnamed'} (${device.address}, ${device.rssi} dBm) `;
+            const button = document.createElement('button');
+            button.textContent = 'Connect';
+            button.onclick = () => connectToDevice(device.address);
+            item.appendChild(button);
+            return item;
+        }));
+    }
+
+    bleStatusText() {
+        const stages = {
+            scanning: 'Scanning...',
+            connecting: 'Connecting...',
+            connected: 'Connecting (link up)...',
+            discovering: 'Discovering services...',
+        };
+        if (this.state.ble_stage in stages) {
+            return stages[this.state.ble_stage];
+        }
+        return this.state.ble_connected ? 'Connected' : 'Disconnected';
+    }
+
     updateStatusColors() {
         // Color-code BLE status
         const bleStatus = document.getElementById('ble-status');
@@ -195,13 +362,34 @@ function setTargetWeight() {
     
     client.sendCommand({
         type: 'set_target_weight',
-        weight: weight
+        weight: weight,
+        unit: client.state.weight_unit
+    });
+}
+
+function setCups() {
+    const cups = parseInt(document.getElementById('cups-input').value, 10);
+    if (isNaN(cups) || cups < 1) {
+        addLogMessage('❌ Cups must be at least 1');
+        return;
+    }
+
+    client.sendCommand({
+        type: 'set_cups',
+        cups: cups
     });
 }
 
 function testRelay() {
+    const durationMs = parseInt(document.getElementById('relay-test-input').value, 10);
+    if (isNaN(durationMs) || durationMs < 1) {
+        addLogMessage('❌ Relay test duration must be a positive number of milliseconds');
+        return;
+    }
+
     client.sendCommand({
-        type: 'test_relay'
+        type: 'test_relay',
+        duration_ms: durationMs
     });
 }
 
@@ -229,6 +417,28 @@ function resetTimer() {
     });
 }
 
+function scanDevices() {
+    addLogMessage('📡 Scanning for nearby devices...');
+    client.sendCommand({
+        type: 'scan_devices',
+        duration_ms: 5000
+    });
+}
+
+function connectToDevice(address) {
+    client.sendCommand({
+        type: 'connect_to_device',
+        address: address
+    });
+}
+
+function runSelfTest() {
+    addLogMessage('🩺 Running self-test - the full report is in /api/status');
+    client.sendCommand({
+        type: 'run_self_test'
+    });
+}
+
 function resetOvershoot() {
     client.sendCommand({
         type: 'reset_overshoot'
@@ -243,6 +453,13 @@ document.getElementById('auto-tare-checkbox').addEventListener('change', functio
     });
 });
 
+document.getElementById('tare-before-brew-checkbox').addEventListener('change', function() {
+    client.sendCommand({
+        type: 'set_tare_before_brew',
+        enabled: this.checked
+    });
+});
+
 document.getElementById('predictive-stop-checkbox').addEventListener('change', function() {
     client.sendCommand({
         type: 'set_predictive_stop',
@@ -250,6 +467,27 @@ document.getElementById('predictive-stop-checkbox').addEventListener('change', f
     });
 });
 
+document.getElementById('predictive-auto-checkbox').addEventListener('change', function() {
+    client.sendCommand({
+        type: 'set_predictive_stop_auto',
+        enabled: this.checked
+    });
+});
+
+document.getElementById('weight-unit-select').addEventListener('change', function() {
+    client.sendCommand({
+        type: 'set_weight_unit',
+        unit: this.value
+    });
+});
+
+document.getElementById('system-enabled-checkbox').addEventListener('change', function() {
+    client.sendCommand({
+        type: 'set_system_enabled',
+        enabled: this.checked
+    });
+});
+
 // Initialize HTTP polling client on page load
 document.addEventListener('DOMContentLoaded', function() {
     client = new EspressoWebClient();